@@ -0,0 +1,60 @@
+//! Compare decoding and encoding of native- and non-native-endian chunks with the bytes codec.
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use zarr3::codecs::ab::{
+    bytes_codec::{BytesCodec, Endian, NATIVE_ENDIAN},
+    ABCodec,
+};
+use zarr3::codecs::ArrayRepr;
+use zarr3::ArcArrayD;
+
+const SHAPE: [u64; 3] = [64, 128, 128];
+
+fn other_endian() -> Endian {
+    match NATIVE_ENDIAN {
+        Endian::Big => Endian::Little,
+        Endian::Little => Endian::Big,
+    }
+}
+
+fn bench_type<T>(c: &mut Criterion, name: &str, make: fn(usize) -> T)
+where
+    T: zarr3::prelude::ReflectedType,
+{
+    let shape: Vec<usize> = SHAPE.iter().map(|s| *s as usize).collect();
+    let numel: usize = shape.iter().product();
+    let arr = ArcArrayD::from_shape_vec(shape, (0..numel).map(make).collect()).unwrap();
+    let nbytes = (numel * std::mem::size_of::<T>()) as u64;
+
+    let mut group = c.benchmark_group(format!("bytes_codec/{name}"));
+    group.throughput(Throughput::Bytes(nbytes));
+    for (label, endian) in [("native", NATIVE_ENDIAN), ("swapped", other_endian())] {
+        let codec = BytesCodec::new(Some(endian));
+        let mut encoded = Vec::default();
+        codec.encode(arr.clone(), &mut encoded).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("encode", label), &arr, |b, arr| {
+            b.iter(|| {
+                let mut buf = Vec::with_capacity(encoded.len());
+                codec.encode(black_box(arr.clone()), &mut buf).unwrap();
+                buf
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("decode", label), &encoded, |b, enc| {
+            b.iter(|| {
+                let repr = ArrayRepr::new(&SHAPE, T::default());
+                codec.decode(black_box(enc.as_slice()), repr).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn endian(c: &mut Criterion) {
+    bench_type(c, "u8", |i| i as u8);
+    bench_type(c, "u16", |i| i as u16);
+    bench_type(c, "f32", |i| i as f32);
+    bench_type(c, "f64", |i| i as f64);
+}
+
+criterion_group!(benches, endian);
+criterion_main!(benches);
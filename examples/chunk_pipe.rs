@@ -0,0 +1,62 @@
+//! Decode or encode a single chunk between stdin and stdout.
+//!
+//! ```sh
+//! # print the raw values of an encoded chunk
+//! curl https://example.com/data.zarr/my_array/c/1/0 \
+//!     | cargo run --example chunk_pipe -- decode --meta zarr.json --chunk 1,0 > chunk.bin
+//! # and encode them again
+//! cargo run --example chunk_pipe -- encode --meta zarr.json --chunk 1,0 < chunk.bin > encoded
+//! ```
+//!
+//! Raw values are little-endian unless `--big-endian` is given.
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+
+use anyhow::{anyhow, bail, Context};
+use zarr3::codecs::ab::bytes_codec::Endian;
+use zarr3::node::chunk_stream::{decode_chunk_stream, encode_chunk_stream};
+use zarr3::prelude::ArrayMetadata;
+use zarr3::{GridCoord, Ndim};
+
+const USAGE: &str =
+    "Usage: chunk_pipe (decode|encode) --meta <zarr.json> [--chunk <i,j,...>] [--big-endian]";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let command = args.next().ok_or_else(|| anyhow!(USAGE))?;
+    let mut meta_path = None;
+    let mut chunk = None;
+    let mut endian = Endian::Little;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--meta" => meta_path = args.next(),
+            "--chunk" => {
+                let s = args.next().ok_or_else(|| anyhow!(USAGE))?;
+                let idx = s
+                    .split(',')
+                    .map(|i| i.trim().parse())
+                    .collect::<Result<GridCoord, _>>()
+                    .with_context(|| format!("Invalid chunk index {s:?}"))?;
+                chunk = Some(idx);
+            }
+            "--big-endian" => endian = Endian::Big,
+            _ => bail!(USAGE),
+        }
+    }
+
+    let meta_path = meta_path.ok_or_else(|| anyhow!(USAGE))?;
+    let meta: ArrayMetadata = serde_json::from_reader(BufReader::new(File::open(&meta_path)?))
+        .with_context(|| format!("Could not read array metadata from {meta_path}"))?;
+    // default to the first chunk
+    let chunk = chunk.unwrap_or_else(|| (0..meta.ndim()).map(|_| 0).collect());
+
+    let stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    match command.as_str() {
+        "decode" => decode_chunk_stream(&meta, &chunk, stdin, &mut stdout, endian)?,
+        "encode" => encode_chunk_stream(&meta, &chunk, stdin, &mut stdout, endian)?,
+        _ => bail!(USAGE),
+    }
+    stdout.flush()?;
+    Ok(())
+}
@@ -22,7 +22,7 @@ fn main() -> anyhow::Result<()> {
         .into();
 
     // Create the (empty) array with this metadata below the group created above
-    let arr = root_group.create_array::<i32>("my_array".parse()?, arr_meta)?;
+    let arr = root_group.create_array::<i32>("my_array".parse()?, arr_meta, false)?;
 
     // Write some data into the middle of the array
     let data = ArcArrayD::from_shape_vec(vec![10, 6], (10..70).collect())?;
@@ -0,0 +1,230 @@
+//! Heuristics for choosing chunk (and shard) shapes.
+//!
+//! ```
+//! # use zarr3::advice::{suggest_chunks, AccessPattern};
+//! # use zarr3::prelude::*;
+//! // a 16-bit image stack which will mostly be viewed one z-slice at a time
+//! let shape = [500, 20_000, 20_000];
+//! let advice = suggest_chunks::<u16>(&shape, 1 << 20, AccessPattern::Planes { normal: 0 });
+//! assert_eq!(advice.chunk_shape.as_slice(), &[1, 512, 1024]);
+//! assert!(advice.shard_shape.is_some());
+//!
+//! let meta: ArrayMetadata = advice
+//!     .apply(ArrayMetadataBuilder::<u16>::new(&shape))
+//!     .unwrap()
+//!     .into();
+//! ```
+use crate::{
+    codecs::ab::sharding_indexed::ShardingIndexedCodec,
+    data_type::{NBytes, ReflectedType},
+    node::ArrayMetadataBuilder,
+    GridCoord,
+};
+
+/// Arrays with more chunks than this are sharded,
+/// as many small objects are slow to list, copy and delete in most stores.
+pub const MAX_UNSHARDED_CHUNKS: u64 = 10_000;
+/// Shards are grown towards this size (in uncompressed bytes).
+pub const TARGET_SHARD_BYTES: usize = 256 << 20;
+
+/// How an array is expected to be read, which determines the chunks' proportions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessPattern {
+    /// Arbitrary blocks, e.g. for processing in tiles: chunks are as close to cubic as possible.
+    #[default]
+    Blocks,
+    /// Whole planes perpendicular to the `normal` axis, e.g. XY slices of a ZYX volume (`normal: 0`):
+    /// chunks are 1 element thick along that axis.
+    Planes { normal: usize },
+    /// Lines along the given axis, e.g. Z columns or per-pixel time series:
+    /// chunks extend along that axis as far as possible before growing in the others.
+    Lines { axis: usize },
+}
+
+/// Suggested layout for an array; see [suggest_chunks].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkAdvice {
+    /// The unit of reading and decoding:
+    /// the chunk grid's shape if unsharded, otherwise the shards' sub-chunk shape.
+    pub chunk_shape: GridCoord,
+    /// The chunk grid's shape, if chunks should be grouped into shards.
+    pub shard_shape: Option<GridCoord>,
+}
+
+impl ChunkAdvice {
+    /// The shape of the array's chunk grid (shards, if any; otherwise chunks).
+    pub fn grid_shape(&self) -> &[u64] {
+        self.shard_shape.as_ref().unwrap_or(&self.chunk_shape)
+    }
+
+    /// Set the builder's chunk grid, and its array->bytes codec to sharding if needed.
+    ///
+    /// Any other array->bytes codec is replaced by sharding;
+    /// set codecs within the shards on the sharding codec afterwards if needed.
+    pub fn apply<T: ReflectedType>(
+        &self,
+        builder: ArrayMetadataBuilder<T>,
+    ) -> Result<ArrayMetadataBuilder<T>, &'static str> {
+        let builder = builder.chunk_grid(self.grid_shape())?;
+        match self.shard_shape {
+            Some(_) => builder.ab_codec(ShardingIndexedCodec::new(self.chunk_shape.clone())),
+            None => Ok(builder),
+        }
+    }
+}
+
+/// Suggest a chunk shape for an array of `T` with the given shape,
+/// with up to `target_chunk_bytes` (uncompressed) per chunk and proportions suited to the access pattern.
+///
+/// Typical targets are 100KiB-1MiB for interactive viewing and 1-10MiB for bulk processing;
+/// smaller chunks waste requests, larger ones read more unwanted data.
+/// Chunk lengths are powers of two (or the whole axis), grown from the last axis first.
+///
+/// If the chunk grid would have more than [MAX_UNSHARDED_CHUNKS] chunks,
+/// a shard shape is also suggested, grown in whole chunks towards [TARGET_SHARD_BYTES]
+/// with the same proportions.
+///
+/// An axis given by the access pattern which is out of range for the shape is ignored,
+/// treating the pattern as [AccessPattern::Blocks].
+pub fn suggest_chunks<T: ReflectedType>(
+    shape: &[u64],
+    target_chunk_bytes: usize,
+    access: AccessPattern,
+) -> ChunkAdvice {
+    let item_size = T::ZARR_TYPE.nbytes().max(1);
+    let max_elements = (target_chunk_bytes / item_size).max(1) as u64;
+    let access = match access {
+        AccessPattern::Planes { normal: a } | AccessPattern::Lines { axis: a }
+            if a >= shape.len() =>
+        {
+            AccessPattern::Blocks
+        }
+        a => a,
+    };
+
+    let extent: GridCoord = shape.iter().map(|s| (*s).max(1)).collect();
+    let chunk_shape = grow(&extent, max_elements, access);
+
+    let grid_shape: GridCoord = extent
+        .iter()
+        .zip(chunk_shape.iter())
+        .map(|(e, c)| e.div_ceil(*c))
+        .collect();
+    let n_chunks = grid_shape
+        .iter()
+        .fold(1u64, |acc, n| acc.saturating_mul(*n));
+    let shard_shape = (n_chunks > MAX_UNSHARDED_CHUNKS).then(|| {
+        let chunk_elements: u64 = chunk_shape.iter().product();
+        let max_chunks = (TARGET_SHARD_BYTES as u64 / (chunk_elements * item_size as u64)).max(1);
+        grow(&grid_shape, max_chunks, access)
+            .iter()
+            .zip(chunk_shape.iter())
+            .map(|(n, c)| n * c)
+            .collect()
+    });
+
+    ChunkAdvice {
+        chunk_shape,
+        shard_shape,
+    }
+}
+
+/// Grow a block within `extent`, starting at 1 in every axis, by doubling the shortest growable axis
+/// (the last, if tied) until no axis can grow without exceeding `max_elements`.
+fn grow(extent: &[u64], max_elements: u64, access: AccessPattern) -> GridCoord {
+    let mut block: GridCoord = extent.iter().map(|_| 1).collect();
+    let mut elements = 1;
+
+    let phases: Vec<Vec<usize>> = match access {
+        AccessPattern::Blocks => vec![(0..extent.len()).collect()],
+        AccessPattern::Planes { normal } => {
+            vec![(0..extent.len()).filter(|d| *d != normal).collect()]
+        }
+        AccessPattern::Lines { axis } => {
+            vec![
+                vec![axis],
+                (0..extent.len()).filter(|d| *d != axis).collect(),
+            ]
+        }
+    };
+
+    for axes in phases {
+        loop {
+            let candidate = axes
+                .iter()
+                .rev()
+                .filter_map(|d| {
+                    let next = (block[*d] * 2).min(extent[*d]);
+                    let next_elements = elements / block[*d] * next;
+                    (next > block[*d] && next_elements <= max_elements).then_some((*d, next))
+                })
+                .min_by_key(|(d, _)| block[*d]);
+            let Some((d, next)) = candidate else {
+                break;
+            };
+            elements = elements / block[d] * next;
+            block[d] = next;
+        }
+    }
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_chunks() {
+        // small arrays are a single chunk
+        let advice = suggest_chunks::<u8>(&[10, 20], 1 << 20, AccessPattern::Blocks);
+        assert_eq!(advice.chunk_shape.as_slice(), &[10, 20]);
+        assert_eq!(advice.shard_shape, None);
+
+        let advice = suggest_chunks::<f32>(&[1000, 1000, 1000], 1 << 20, AccessPattern::Blocks);
+        assert_eq!(advice.chunk_shape.as_slice(), &[64, 64, 64]);
+        assert_eq!(advice.shard_shape, None);
+
+        let advice = suggest_chunks::<u8>(&[1000, 1000], 1 << 20, AccessPattern::Blocks);
+        assert_eq!(advice.chunk_shape.as_slice(), &[1000, 1000]);
+
+        let advice = suggest_chunks::<u16>(
+            &[100, 2000, 2000],
+            1 << 20,
+            AccessPattern::Planes { normal: 0 },
+        );
+        assert_eq!(advice.chunk_shape.as_slice(), &[1, 512, 1024]);
+
+        let advice = suggest_chunks::<f64>(
+            &[100, 2000, 2000],
+            1 << 20,
+            AccessPattern::Lines { axis: 0 },
+        );
+        assert_eq!(advice.chunk_shape.as_slice(), &[100, 32, 32]);
+
+        // out-of-range axis
+        let advice = suggest_chunks::<f32>(
+            &[1000, 1000, 1000],
+            1 << 20,
+            AccessPattern::Lines { axis: 3 },
+        );
+        assert_eq!(advice.chunk_shape.as_slice(), &[64, 64, 64]);
+
+        // tiny targets still give at least one element
+        let advice = suggest_chunks::<u64>(&[0, 10], 1, AccessPattern::Blocks);
+        assert_eq!(advice.chunk_shape.as_slice(), &[1, 1]);
+    }
+
+    #[test]
+    fn suggests_shards() {
+        let shape = [20_000, 20_000, 20_000];
+        let advice = suggest_chunks::<u8>(&shape, 1 << 18, AccessPattern::Blocks);
+        assert_eq!(advice.chunk_shape.as_slice(), &[64, 64, 64]);
+        let shard = advice.shard_shape.clone().unwrap();
+        assert_eq!(shard.as_slice(), &[512, 512, 1024]);
+        assert_eq!(advice.grid_shape(), shard.as_slice());
+
+        assert!(advice
+            .apply(ArrayMetadataBuilder::<u8>::new(&shape))
+            .is_ok());
+    }
+}
@@ -1,13 +1,32 @@
-use crate::{
-    chunk_grid::{ArrayRegion, ArraySlice, PartialChunk},
-    CoordVec, GridCoord, Ndim,
-};
+//! Iteration over N-dimensional indices and chunk grids.
+//!
+//! These are the building blocks used to map array regions to chunks,
+//! exposed for tools which schedule or tile work by chunk.
+//! All iterators visit indices in C order (last axis fastest) by default;
+//! see [Order], and the `with_step` methods to visit only every nth index along each axis.
+pub use crate::chunk_grid::{ArrayRegion, ArraySlice, PartialChunk};
+use crate::{util::DimensionMismatch, CoordVec, GridCoord, Ndim};
 use ndarray::{IxDyn, SliceInfo, SliceInfoElem};
 use smallvec::smallvec;
 
-/// Iterate N dimensional indices in C order.
-pub(crate) struct CIter {
+/// Order in which N dimensional indices are visited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// Last axis varies fastest.
+    #[default]
+    C,
+    /// First axis varies fastest.
+    F,
+}
+
+/// Iterate N dimensional indices within a shape, in C order by default.
+#[derive(Debug, Clone)]
+pub struct CIter {
     shape: GridCoord,
+    step: GridCoord,
+    order: Order,
+    /// Number of steps along each axis.
+    counts: GridCoord,
     next: Option<GridCoord>,
     total_size: usize,
     count: usize,
@@ -15,19 +34,74 @@ pub(crate) struct CIter {
 
 impl CIter {
     pub fn new(shape: GridCoord) -> Self {
-        let next = if shape.is_empty() || shape.iter().any(|s| 0 == *s) {
+        let step = smallvec![1; shape.len()];
+        Self::new_stepped(shape, step, Order::C)
+    }
+
+    fn new_stepped(shape: GridCoord, step: GridCoord, order: Order) -> Self {
+        let counts: GridCoord = shape
+            .iter()
+            .zip(step.iter())
+            .map(|(sh, st)| sh.div_ceil(*st))
+            .collect();
+        let next = if counts.is_empty() || counts.contains(&0) {
             None
         } else {
-            Some(smallvec![0; shape.len()])
+            Some(smallvec![0; counts.len()])
         };
-        let total_size = shape.iter().product::<u64>() as usize;
+        let total_size = counts.iter().product::<u64>() as usize;
         Self {
             shape,
+            step,
+            order,
+            counts,
             next,
             total_size,
             count: 0,
         }
     }
+
+    /// Visit indices in the given order.
+    ///
+    /// Restarts iteration.
+    pub fn with_order(self, order: Order) -> Self {
+        Self::new_stepped(self.shape, self.step, order)
+    }
+
+    /// Only visit every `step[d]`th index along each axis `d`, starting at 0.
+    ///
+    /// Restarts iteration.
+    /// Fails if any step is 0.
+    pub fn with_step(self, step: &[u64]) -> Result<Self, &'static str> {
+        DimensionMismatch::check_coords(step.len(), self.ndim())
+            .map_err(|_| "Step has wrong dimensionality")?;
+        if step.contains(&0) {
+            return Err("Step must be nonzero");
+        }
+        Ok(Self::new_stepped(
+            self.shape,
+            step.iter().cloned().collect(),
+            self.order,
+        ))
+    }
+
+    /// Increment the counter in place; false if iteration is finished.
+    fn increment(&self, c: &mut GridCoord) -> bool {
+        let ndim = c.len();
+        for n in 0..ndim {
+            let idx = match self.order {
+                Order::C => ndim - 1 - n,
+                Order::F => n,
+            };
+            if c[idx] + 1 == self.counts[idx] {
+                c[idx] = 0;
+            } else {
+                c[idx] += 1;
+                return true;
+            }
+        }
+        false
+    }
 }
 
 impl Ndim for CIter {
@@ -45,37 +119,18 @@ impl Iterator for CIter {
     }
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.next.as_ref()?;
+        let mut c = self.next.take()?;
         self.count += 1;
 
-        let curr = self.next.clone();
-        let mut finished = false;
-
-        // this scope ends the mutable borrow of self.next
-        {
-            let c = self.next.as_mut().unwrap();
-
-            for idx in (0..c.len()).rev() {
-                if c[idx] + 1 == self.shape[idx] {
-                    if idx == 0 {
-                        finished = true;
-                        break;
-                    }
-                    c[idx] = 0;
-                } else {
-                    c[idx] += 1;
-                    break;
-                }
-            }
+        let curr = c.iter().zip(self.step.iter()).map(|(i, s)| i * s).collect();
+        if self.increment(&mut c) {
+            self.next = Some(c);
         }
-        if finished {
-            self.next = None;
-        }
-
-        curr
+        Some(curr)
     }
 }
 
+/// A chunk of a [ChunkIter], with its offset and (possibly truncated) shape within the array.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ChunkIterOutput {
     pub chunk_idx: GridCoord,
@@ -83,7 +138,9 @@ pub struct ChunkIterOutput {
     pub shape: GridCoord,
 }
 
-pub(crate) struct ChunkIter {
+/// Iterate over the chunks of a regular grid which cover an array,
+/// including partial chunks at the array's far edges.
+pub struct ChunkIter {
     arr_shape: GridCoord,
     chunk_shape: GridCoord,
     c_iter: CIter,
@@ -133,6 +190,18 @@ impl ChunkIter {
         }
     }
 
+    /// Visit chunks in the given order; restarts iteration.
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.c_iter = self.c_iter.with_order(order);
+        self
+    }
+
+    /// Only visit every `step[d]`th chunk along each axis `d`; restarts iteration.
+    pub fn with_step(mut self, step: &[u64]) -> Result<Self, &'static str> {
+        self.c_iter = self.c_iter.with_step(step)?;
+        Ok(self)
+    }
+
     /// Checks that chunks exactly subdivide the array.
     pub fn new_strict(chunk_shape: GridCoord, arr_shape: GridCoord) -> Result<Self, &'static str> {
         if arr_shape
@@ -175,6 +244,9 @@ impl ChunkIter {
     }
 }
 
+/// Iterate over the chunks of a regular grid which intersect a region,
+/// with the part of each chunk inside the region
+/// and where that part lies relative to the region's origin.
 pub struct PartialChunkIter {
     min_chunk: GridCoord,
     min_chunk_offset: GridCoord,
@@ -208,6 +280,65 @@ impl PartialChunkIter {
             c_iter,
         }
     }
+
+    /// Fails if the chunk shape and region have different dimensionalities.
+    pub fn from_region(
+        chunk_shape: GridCoord,
+        region: &ArrayRegion,
+    ) -> Result<Self, DimensionMismatch> {
+        DimensionMismatch::check_coords(region.ndim(), chunk_shape.len())?;
+        Ok(Self::from_region_unchecked(chunk_shape, region))
+    }
+
+    pub fn from_region_unchecked(chunk_shape: GridCoord, region: &ArrayRegion) -> Self {
+        let ndim = chunk_shape.len();
+        let mut min_chunk = GridCoord::with_capacity(ndim);
+        let mut min_chunk_offset = GridCoord::with_capacity(ndim);
+        let mut max_chunk = GridCoord::with_capacity(ndim);
+        let mut max_chunk_offset = GridCoord::with_capacity(ndim);
+        let mut is_empty = false;
+
+        for ((o, sh), cs) in region
+            .offset()
+            .iter()
+            .zip(region.shape().iter())
+            .zip(chunk_shape.iter())
+        {
+            min_chunk.push(o / cs);
+            min_chunk_offset.push(o % cs);
+            // use the last voxel so that a region ending on a chunk boundary
+            // does not include an empty chunk beyond it
+            let last = (o + sh).max(o + 1) - 1;
+            max_chunk.push(last / cs);
+            max_chunk_offset.push(last % cs + 1);
+            is_empty |= *sh == 0;
+        }
+
+        let mut it = Self::new(
+            min_chunk,
+            min_chunk_offset,
+            max_chunk,
+            max_chunk_offset,
+            chunk_shape,
+        );
+        if is_empty {
+            it.c_iter = CIter::new(smallvec![0; ndim]);
+        }
+        it
+    }
+
+    /// Visit chunks in the given order; restarts iteration.
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.c_iter = self.c_iter.with_order(order);
+        self
+    }
+
+    /// Only visit every `step[d]`th chunk along each axis `d`, starting from the first;
+    /// restarts iteration.
+    pub fn with_step(mut self, step: &[u64]) -> Result<Self, &'static str> {
+        self.c_iter = self.c_iter.with_step(step)?;
+        Ok(self)
+    }
 }
 
 impl Ndim for PartialChunkIter {
@@ -337,4 +468,53 @@ mod tests {
         ];
         assert_eq!(v, expected)
     }
+
+    #[test]
+    fn c_iter_order_step() {
+        let v: Vec<_> = CIter::new(smallvec![2, 3]).with_order(Order::F).collect();
+        let expected: Vec<GridCoord> = vec![
+            smallvec![0, 0],
+            smallvec![1, 0],
+            smallvec![0, 1],
+            smallvec![1, 1],
+            smallvec![0, 2],
+            smallvec![1, 2],
+        ];
+        assert_eq!(v, expected);
+
+        let it = CIter::new(smallvec![3, 5]).with_step(&[2, 2]).unwrap();
+        assert_eq!(it.size_hint(), (6, Some(6)));
+        let v: Vec<_> = it.collect();
+        assert_eq!(v[1].as_slice(), &[0, 2]);
+        assert_eq!(v[5].as_slice(), &[2, 4]);
+        assert!(CIter::new(smallvec![3]).with_step(&[0]).is_err());
+    }
+
+    #[test]
+    fn partial_chunk_iter_from_region() {
+        // ends on a chunk boundary
+        let region = ArrayRegion::from_offset_shape(&[2, 0], &[6, 4]);
+        let v: Vec<_> = PartialChunkIter::from_region(smallvec![4, 4], &region)
+            .unwrap()
+            .collect();
+        assert_eq!(v.len(), 2);
+        assert_eq!(v[0].chunk_idx.as_slice(), &[0, 0]);
+        assert_eq!(
+            v[0].chunk_region,
+            ArrayRegion::from_offset_shape(&[2, 0], &[2, 4])
+        );
+        assert_eq!(
+            v[1].out_region,
+            ArrayRegion::from_offset_shape(&[2, 0], &[4, 4])
+        );
+
+        let empty = ArrayRegion::from_offset_shape(&[2, 0], &[0, 4]);
+        assert_eq!(
+            PartialChunkIter::from_region(smallvec![4, 4], &empty)
+                .unwrap()
+                .count(),
+            0
+        );
+        assert!(PartialChunkIter::from_region(smallvec![4], &region).is_err());
+    }
 }
@@ -65,9 +65,9 @@ impl ArrayRegion {
     }
 
     /// If this region has origin 0 and the given shape.
-    pub fn is_whole(&self, shape: &[u64]) -> bool {
-        DimensionMismatch::check_coords(shape.len(), self.ndim()).unwrap();
-        self.is_whole_unchecked(shape)
+    pub fn is_whole(&self, shape: &[u64]) -> Result<bool, DimensionMismatch> {
+        DimensionMismatch::check_coords(shape.len(), self.ndim())?;
+        Ok(self.is_whole_unchecked(shape))
     }
 
     pub fn from_offset_shape_unchecked(offset: &[u64], shape: &[u64]) -> Self {
@@ -111,10 +111,12 @@ impl ArrayRegion {
         self.0.iter().map(|s| s.shape).reduce(|a, b| a * b)
     }
 
-    /// Panics if max has incorrect dimensionality.
-    pub fn limit_extent(&self, max: &[u64]) -> Option<Self> {
-        DimensionMismatch::check_coords(max.len(), self.ndim()).unwrap();
-        self.limit_extent_unchecked(max)
+    /// Restrict the region so that it does not extend beyond `max`.
+    ///
+    /// `Ok(None)` if the region starts beyond `max`.
+    pub fn limit_extent(&self, max: &[u64]) -> Result<Option<Self>, DimensionMismatch> {
+        DimensionMismatch::check_coords(max.len(), self.ndim())?;
+        Ok(self.limit_extent_unchecked(max))
     }
 
     pub fn limit_extent_unchecked(&self, max: &[u64]) -> Option<Self> {
@@ -163,6 +165,7 @@ pub struct PartialChunk {
 }
 
 impl PartialChunk {
+    #[allow(dead_code)]
     pub fn new(chunk_idx: GridCoord, chunk_region: ArrayRegion, out_region: ArrayRegion) -> Self {
         DimensionMismatch::check_many(chunk_idx.len(), &[chunk_region.ndim(), out_region.ndim()])
             .unwrap();
@@ -184,43 +187,46 @@ impl PartialChunk {
 
 pub trait ChunkGrid: MaybeNdim {
     /// Calculate the chunk index where the voxel exists, and its offset within that chunk.
-    ///
-    /// Panics if dimensions mismatch.
-    fn voxel_chunk(&self, idx: &[u64]) -> (GridCoord, GridCoord) {
+    #[allow(dead_code)]
+    fn voxel_chunk(&self, idx: &[u64]) -> Result<(GridCoord, GridCoord), DimensionMismatch> {
         if let Some(d) = self.maybe_ndim() {
-            DimensionMismatch::check_coords(idx.len(), d).unwrap()
+            DimensionMismatch::check_coords(idx.len(), d)?;
         }
-        self.voxel_chunk_unchecked(idx)
+        Ok(self.voxel_chunk_unchecked(idx))
     }
 
     fn voxel_chunk_unchecked(&self, idx: &[u64]) -> (GridCoord, GridCoord);
 
     /// Calculate the shape of a given chunk.
-    ///
-    /// Panics if dimensions mismatch.
-    fn chunk_shape(&self, idx: &[u64]) -> GridCoord {
+    fn chunk_shape(&self, idx: &[u64]) -> Result<GridCoord, DimensionMismatch> {
         if let Some(d) = self.maybe_ndim() {
-            DimensionMismatch::check_coords(idx.len(), d).unwrap();
+            DimensionMismatch::check_coords(idx.len(), d)?;
         }
-        self.chunk_shape_unchecked(idx)
+        Ok(self.chunk_shape_unchecked(idx))
     }
 
     fn chunk_shape_unchecked(&self, idx: &[u64]) -> GridCoord;
 
     /// Calculate how regions of chunks map into a given array region.
-    ///
-    /// Panics if dimensions mismatch.
-    fn chunks_in_region(&self, region: &ArrayRegion) -> PartialChunkIter {
+    fn chunks_in_region(
+        &self,
+        region: &ArrayRegion,
+    ) -> Result<PartialChunkIter, DimensionMismatch> {
         if let Some(d) = self.maybe_ndim() {
-            DimensionMismatch::check_coords(region.ndim(), d).unwrap();
+            DimensionMismatch::check_coords(region.ndim(), d)?;
         }
-        self.chunks_in_region_unchecked(region)
+        Ok(self.chunks_in_region_unchecked(region))
     }
 
     fn chunks_in_region_unchecked(&self, region: &ArrayRegion) -> PartialChunkIter;
+
+    /// Calculate the number of chunks along each dimension needed to cover an array.
+    ///
+    /// The array shape must have the grid's dimensionality.
+    fn grid_shape_unchecked(&self, array_shape: &[u64]) -> GridCoord;
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct RegularChunkGrid {
     chunk_shape: GridCoord,
 }
@@ -255,20 +261,21 @@ impl ChunkGrid for RegularChunkGrid {
     }
 
     fn chunks_in_region_unchecked(&self, region: &ArrayRegion) -> PartialChunkIter {
-        let (min_chunk, min_offset) = self.voxel_chunk(region.offset().as_slice());
-        let (max_chunk, max_offset) = self.voxel_chunk(region.end().as_slice());
+        PartialChunkIter::from_region_unchecked(self.chunk_shape.clone(), region)
+    }
 
-        PartialChunkIter::new(
-            min_chunk,
-            min_offset,
-            max_chunk,
-            max_offset,
-            self.chunk_shape.clone(),
-        )
+    fn grid_shape_unchecked(&self, array_shape: &[u64]) -> GridCoord {
+        array_shape
+            .iter()
+            .zip(self.chunk_shape.iter())
+            .map(|(a, c)| a.div_ceil(*c))
+            .collect()
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Unlike codecs, an unsupported chunk grid is an error even if it sets `"must_understand": false`,
+/// as there is nothing to fall back to when locating chunks.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(tag = "name", content = "configuration", rename_all = "lowercase")]
 #[enum_delegate::implement(MaybeNdim)]
 pub enum ChunkGridType {
@@ -293,6 +300,12 @@ impl ChunkGrid for ChunkGridType {
             Self::Regular(g) => g.chunks_in_region_unchecked(region),
         }
     }
+
+    fn grid_shape_unchecked(&self, array_shape: &[u64]) -> GridCoord {
+        match self {
+            Self::Regular(g) => g.grid_shape_unchecked(array_shape),
+        }
+    }
 }
 
 impl From<&[u64]> for ChunkGridType {
@@ -301,11 +314,3 @@ impl From<&[u64]> for ChunkGridType {
         Self::Regular(RegularChunkGrid::new(cs))
     }
 }
-
-impl ChunkGridType {
-    pub fn chunk_shape(&self, _idx: &GridCoord) -> GridCoord {
-        match self {
-            Self::Regular(g) => g.chunk_shape.clone(),
-        }
-    }
-}
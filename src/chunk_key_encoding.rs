@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 use smallvec::smallvec;
 
 use crate::store::NodeName;
-use crate::{store::NodeKey, CoordVec};
+use crate::{store::NodeKey, CoordVec, GridCoord};
 
 #[enum_delegate::register]
 pub trait ChunkKeyEncoder {
@@ -16,6 +16,12 @@ pub trait ChunkKeyEncoder {
     /// the output will have a single item.
     fn components(&self, coord: &[u64]) -> CoordVec<NodeName>;
 
+    /// The inverse of [ChunkKeyEncoder::components]:
+    /// parse the coordinates of a chunk with the given dimensionality.
+    ///
+    /// `None` if the components do not represent such a chunk.
+    fn decode_components(&self, components: &[NodeName], ndim: usize) -> Option<GridCoord>;
+
     /// Get the key for a chunk below the given (array) node with the given coordinates.
     fn chunk_key(&self, node: &NodeKey, coord: &[u64]) -> NodeKey {
         let mut n = node.clone();
@@ -24,6 +30,33 @@ pub trait ChunkKeyEncoder {
         }
         n
     }
+
+    /// Get the coordinates of the chunk with the given key below the given (array) node.
+    ///
+    /// `None` if the key is not a chunk key for this node.
+    fn chunk_coord(&self, node: &NodeKey, key: &NodeKey, ndim: usize) -> Option<GridCoord> {
+        if !node.is_ancestor_of(key) {
+            return None;
+        }
+        self.decode_components(&key.as_slice()[node.len()..], ndim)
+    }
+}
+
+/// Parse a single name of separated coordinates.
+fn parse_joined(s: &str, sep: char, ndim: usize) -> Option<GridCoord> {
+    let coord = s
+        .split(sep)
+        .map(|c| c.parse().ok())
+        .collect::<Option<GridCoord>>()?;
+    (coord.len() == ndim).then_some(coord)
+}
+
+/// Parse one coordinate per name.
+fn parse_each(components: &[NodeName], ndim: usize) -> Option<GridCoord> {
+    if components.len() != ndim {
+        return None;
+    }
+    components.iter().map(|c| c.as_ref().parse().ok()).collect()
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -62,7 +95,7 @@ impl ChunkKeyEncoder for DefaultChunkKeyEncoding {
         let mut out = CoordVec::default();
         match self.separator {
             Separator::Slash => {
-                out.push("c".parse().unwrap());
+                out.push(NodeName::new_unchecked("c".to_owned()));
                 for n in coord.iter() {
                     out.push(NodeName::new_unchecked(n.to_string()));
                 }
@@ -78,6 +111,27 @@ impl ChunkKeyEncoder for DefaultChunkKeyEncoding {
         }
         out
     }
+
+    fn decode_components(&self, components: &[NodeName], ndim: usize) -> Option<GridCoord> {
+        let (first, rest) = components.split_first()?;
+        match self.separator {
+            Separator::Slash => {
+                if first.as_ref() != "c" {
+                    return None;
+                }
+                parse_each(rest, ndim)
+            }
+            Separator::Dot => {
+                if !rest.is_empty() {
+                    return None;
+                }
+                match first.as_ref().strip_prefix('c')? {
+                    "" if ndim == 0 => Some(GridCoord::default()),
+                    s => parse_joined(s.strip_prefix('.')?, '.', ndim),
+                }
+            }
+        }
+    }
 }
 
 impl Default for DefaultChunkKeyEncoding {
@@ -97,7 +151,7 @@ pub struct V2ChunkKeyEncoding {
 impl ChunkKeyEncoder for V2ChunkKeyEncoding {
     fn components(&self, coord: &[u64]) -> CoordVec<NodeName> {
         if coord.is_empty() {
-            return smallvec!["0".parse().unwrap()];
+            return smallvec![NodeName::new_unchecked("0".to_owned())];
         }
         let mut out = CoordVec::default();
         match self.separator {
@@ -118,6 +172,20 @@ impl ChunkKeyEncoder for V2ChunkKeyEncoding {
         }
         out
     }
+
+    fn decode_components(&self, components: &[NodeName], ndim: usize) -> Option<GridCoord> {
+        if ndim == 0 {
+            return (components.len() == 1 && components[0].as_ref() == "0")
+                .then(GridCoord::default);
+        }
+        match self.separator {
+            Separator::Slash => parse_each(components, ndim),
+            Separator::Dot => match components {
+                [name] => parse_joined(name.as_ref(), '.', ndim),
+                _ => None,
+            },
+        }
+    }
 }
 
 impl Default for V2ChunkKeyEncoding {
@@ -128,6 +196,8 @@ impl Default for V2ChunkKeyEncoding {
     }
 }
 
+/// Unlike codecs, an unsupported chunk key encoding is an error even if it sets `"must_understand": false`,
+/// as there is nothing to fall back to when locating chunks.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(tag = "name", content = "configuration", rename_all = "lowercase")]
 #[enum_delegate::implement(ChunkKeyEncoder)]
@@ -200,6 +270,36 @@ mod tests {
         assert_eq!(strs, expected);
     }
 
+    #[test]
+    fn decode_chunk_keys() {
+        let node: NodeKey = "arr".parse().unwrap();
+        let encodings = vec![
+            ChunkKeyEncoding::Default(DefaultChunkKeyEncoding::default()),
+            ChunkKeyEncoding::Default(DefaultChunkKeyEncoding {
+                separator: Separator::Dot,
+            }),
+            ChunkKeyEncoding::V2(V2ChunkKeyEncoding::default()),
+            ChunkKeyEncoding::V2(V2ChunkKeyEncoding {
+                separator: Separator::Slash,
+            }),
+        ];
+        for cke in encodings {
+            for coord in [vec![], vec![4], vec![1, 20, 300]] {
+                let key = cke.chunk_key(&node, &coord);
+                assert_eq!(
+                    cke.chunk_coord(&node, &key, coord.len())
+                        .unwrap()
+                        .as_slice(),
+                    coord.as_slice(),
+                    "{cke:?}"
+                );
+            }
+            let mut meta = node.clone();
+            meta.with_metadata();
+            assert!(cke.chunk_coord(&node, &meta, 3).is_none());
+        }
+    }
+
     #[test]
     fn v2_chunk_key_encoding() {
         let cke = ChunkKeyEncoding::V2(V2ChunkKeyEncoding::default());
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use crate::{data_type::ReflectedType, ArcArrayD, MaybeNdim};
 mod transpose;
 pub use transpose::TransposeCodec;
+pub(crate) use transpose::{reverse_permutation, validate_permutation};
 
 use super::ArrayRepr;
 
@@ -11,7 +11,7 @@ pub struct TransposeCodec {
     pub order: CoordVec<usize>,
 }
 
-fn validate_permutation(perm: &[usize]) -> Result<(), &'static str> {
+pub(crate) fn validate_permutation(perm: &[usize]) -> Result<(), &'static str> {
     let max = perm.len();
     let mut elems = HashSet::with_capacity(max);
     for item in perm.iter() {
@@ -81,7 +81,7 @@ impl AACodec for TransposeCodec {
     }
 }
 
-fn reverse_permutation(p: &[usize]) -> CoordVec<usize> {
+pub(crate) fn reverse_permutation(p: &[usize]) -> CoordVec<usize> {
     let mut pos_idx: HashMap<_, _> = p.iter().enumerate().map(|(idx, pos)| (*pos, idx)).collect();
     (0..pos_idx.len())
         .map(|pos| pos_idx.remove(&pos).unwrap())
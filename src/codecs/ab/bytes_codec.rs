@@ -1,7 +1,7 @@
 use crate::{codecs::ArrayRepr, ArcArrayD, CoordVec, MaybeNdim};
 use serde::{Deserialize, Serialize};
 
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 
 use super::ABCodec;
 use crate::data_type::ReflectedType;
@@ -69,16 +69,24 @@ impl BytesCodec {
 }
 
 impl ABCodec for BytesCodec {
-    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) {
-        let endian = self.valid_endian::<T>().unwrap();
-        T::write_array_to(decoded, w, endian).unwrap();
-    }
-
-    fn decode<T: ReflectedType, R: Read>(&self, r: R, decoded_repr: ArrayRepr<T>) -> ArcArrayD<T> {
+    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) -> io::Result<()> {
+        let endian = self
+            .valid_endian::<T>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        T::write_array_to(decoded, w, endian)
+    }
+
+    fn decode<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+    ) -> io::Result<ArcArrayD<T>> {
         if &T::ZARR_TYPE != decoded_repr.data_type() {
             panic!("Decoded array is not of the reflected type");
         }
-        let endian = self.valid_endian::<T>().unwrap();
+        let endian = self
+            .valid_endian::<T>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
         let shape: CoordVec<_> = decoded_repr.shape.iter().map(|s| *s as usize).collect();
         T::read_array_from(r, endian, shape.as_slice())
     }
@@ -1,4 +1,4 @@
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 
 use crate::{
     data_type::{NBytes, ReflectedType},
@@ -9,21 +9,58 @@ use serde::{Deserialize, Serialize};
 
 pub mod bytes_codec;
 use bytes_codec::BytesCodec;
-// pub mod sharding_indexed;
-// use sharding_indexed::ShardingIndexedCodec;
+pub mod sharding_indexed;
+use sharding_indexed::ShardingIndexedCodec;
 
 use self::bytes_codec::Endian;
 
-use super::ArrayRepr;
+use super::{ArrayRepr, DecodeOptions};
 
 // enum_delegate doesn't work here because of type annotations?
 // #[enum_delegate::register]
 pub trait ABCodec {
     /// Write the given array to the given [Write]r, via the configured codecs.
-    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W);
+    ///
+    /// `Err` if writing fails, or the array cannot be represented by the codecs
+    /// (e.g. it encodes larger than a padding envelope).
+    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) -> io::Result<()>;
+
+    /// As [ABCodec::encode], but using the given fill value for any padding added by the codecs,
+    /// e.g. for sharding sub-chunks which overhang the edge of the shard.
+    ///
+    /// By default, ignores the fill value.
+    fn encode_with_fill<T: ReflectedType, W: Write>(
+        &self,
+        decoded: ArcArrayD<T>,
+        fill_value: T,
+        w: W,
+    ) -> io::Result<()> {
+        let _ = fill_value;
+        self.encode(decoded, w)
+    }
 
     /// Read an array from the given [Read]er, via the configured codecs.
-    fn decode<T: ReflectedType, R: Read>(&self, r: R, decoded_repr: ArrayRepr<T>) -> ArcArrayD<T>;
+    ///
+    /// `Err` if the encoded data is truncated or malformed.
+    fn decode<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+    ) -> io::Result<ArcArrayD<T>>;
+
+    /// As [ABCodec::decode], with options controlling validation of the encoded data
+    /// by any codecs nested within this one.
+    ///
+    /// By default, ignores the options.
+    fn decode_with<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
+        let _ = options;
+        self.decode(r, decoded_repr)
+    }
 
     fn compute_encoded_size<T: ReflectedType>(&self, decoded_repr: ArrayRepr<T>) -> Option<usize>;
 
@@ -39,16 +76,38 @@ pub trait ABCodec {
 }
 
 impl<C: ABCodec + ?Sized> ABCodec for Box<C> {
-    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) {
+    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) -> io::Result<()> {
         (**self).encode(decoded, w)
         // ABCodec::encode::<T, W>(self, decoded, w)
     }
 
-    fn decode<T: ReflectedType, R: Read>(&self, r: R, decoded_repr: ArrayRepr<T>) -> ArcArrayD<T> {
+    fn encode_with_fill<T: ReflectedType, W: Write>(
+        &self,
+        decoded: ArcArrayD<T>,
+        fill_value: T,
+        w: W,
+    ) -> io::Result<()> {
+        (**self).encode_with_fill(decoded, fill_value, w)
+    }
+
+    fn decode<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+    ) -> io::Result<ArcArrayD<T>> {
         (**self).decode(r, decoded_repr)
         // ABCodec::decode::<T, R>(self, r, decoded_repr)
     }
 
+    fn decode_with<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
+        (**self).decode_with(r, decoded_repr, options)
+    }
+
     fn endian(&self) -> Option<Endian> {
         (**self).endian()
     }
@@ -65,35 +124,63 @@ pub enum ABCodecType {
     Bytes(BytesCodec),
     // box is necessary as sharding codec contains codecs,
     // so it's a recursive enum of potentially infinite size
-    // ShardingIndexed(Box<ShardingIndexedCodec>),
+    ShardingIndexed(Box<ShardingIndexedCodec>),
 }
 
 impl ABCodec for ABCodecType {
-    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) {
+    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) -> io::Result<()> {
         match self {
             Self::Bytes(c) => c.encode(decoded, w),
-            // Self::ShardingIndexed(c) => c.encode(decoded, w),
+            Self::ShardingIndexed(c) => c.encode(decoded, w),
         }
     }
 
-    fn decode<T: ReflectedType, R: Read>(&self, r: R, decoded_repr: ArrayRepr<T>) -> ArcArrayD<T> {
+    fn encode_with_fill<T: ReflectedType, W: Write>(
+        &self,
+        decoded: ArcArrayD<T>,
+        fill_value: T,
+        w: W,
+    ) -> io::Result<()> {
+        match self {
+            Self::Bytes(c) => c.encode_with_fill(decoded, fill_value, w),
+            Self::ShardingIndexed(c) => c.encode_with_fill(decoded, fill_value, w),
+        }
+    }
+
+    fn decode<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+    ) -> io::Result<ArcArrayD<T>> {
         match self {
             Self::Bytes(c) => c.decode(r, decoded_repr),
-            // Self::ShardingIndexed(c) => c.decode(r, decoded_repr),
+            Self::ShardingIndexed(c) => c.decode(r, decoded_repr),
+        }
+    }
+
+    fn decode_with<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
+        match self {
+            Self::Bytes(c) => c.decode_with(r, decoded_repr, options),
+            Self::ShardingIndexed(c) => c.decode_with(r, decoded_repr, options),
         }
     }
 
     fn endian(&self) -> Option<Endian> {
         match self {
             Self::Bytes(c) => c.endian(),
-            // Self::ShardingIndexed(c) => c.endian(),
+            Self::ShardingIndexed(c) => c.endian(),
         }
     }
 
     fn compute_encoded_size<T: ReflectedType>(&self, decoded_repr: ArrayRepr<T>) -> Option<usize> {
         match self {
             Self::Bytes(c) => c.compute_encoded_size(decoded_repr),
-            // Self::ShardingIndexed(c) => c.compute_encoded_size(decoded_repr),
+            Self::ShardingIndexed(c) => c.compute_encoded_size(decoded_repr),
         }
     }
 }
@@ -102,7 +189,7 @@ impl MaybeNdim for ABCodecType {
     fn maybe_ndim(&self) -> Option<usize> {
         match self {
             Self::Bytes(c) => c.maybe_ndim(),
-            // Self::ShardingIndexed(c) => c.maybe_ndim(),
+            Self::ShardingIndexed(c) => c.maybe_ndim(),
         }
     }
 }
@@ -115,11 +202,11 @@ impl Default for ABCodecType {
 
 variant_from_data!(ABCodecType, Bytes, BytesCodec);
 
-// impl From<ShardingIndexedCodec> for ABCodecType {
-//     fn from(c: ShardingIndexedCodec) -> Self {
-//         Self::ShardingIndexed(Box::new(c))
-//     }
-// }
+impl From<ShardingIndexedCodec> for ABCodecType {
+    fn from(c: ShardingIndexedCodec) -> Self {
+        Self::ShardingIndexed(Box::new(c))
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -1,24 +1,60 @@
 use crc32c::crc32c;
 use serde::{Deserialize, Serialize};
 
-use std::io::{BufWriter, Cursor, Read, Seek};
+use std::io::{self, Cursor, Read, Seek};
 use thiserror::Error;
 
 use crate::chunk_arr::{offset_shape_to_slice_info, ChunkIter};
 use crate::codecs::aa::AACodecType;
-use crate::codecs::bb::BBCodecType;
-use crate::codecs::{ArrayRepr, CodecChain};
+use crate::codecs::bb::{crc32c_codec::Crc32cCodec, BBCodecType};
+use crate::codecs::{ArrayRepr, CodecChain, DecodeOptions};
 use crate::data_type::ReflectedType;
-use crate::{ArcArrayD, GridCoord, MaybeNdim, Ndim};
+use crate::{ArcArrayD, DimensionMismatch, GridCoord, MaybeNdim, Ndim};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{SeekFrom, Write};
 
-use super::{ABCodec, ABCodecType};
+use super::{
+    bytes_codec::{BytesCodec, Endian},
+    ABCodec, ABCodecType,
+};
+
+/// Where the index of sub-chunk addresses is stored within a shard.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexLocation {
+    Start,
+    #[default]
+    End,
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct ShardingIndexedCodec {
     pub chunk_shape: GridCoord,
     pub codecs: CodecChain,
+    /// Codecs for the index, which must have a fixed encoded size.
+    ///
+    /// Required by the spec, but defaults to little-endian `bytes` followed by `crc32c`
+    /// (the layout used before it was configurable) if missing from metadata.
+    #[serde(default = "default_index_codecs")]
+    pub index_codecs: CodecChain,
+    #[serde(default)]
+    pub index_location: IndexLocation,
+}
+
+fn default_index_codecs() -> CodecChain {
+    CodecChain::new(
+        Vec::default(),
+        BytesCodec::new_little().into(),
+        vec![BBCodecType::Crc32c(Some(Crc32cCodec::default()))],
+    )
+}
+
+fn invalid_input<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, e)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
 }
 
 impl Ndim for ShardingIndexedCodec {
@@ -32,20 +68,79 @@ impl ShardingIndexedCodec {
         Self {
             chunk_shape: chunk_shape.into(),
             codecs: CodecChain::default(),
+            index_codecs: default_index_codecs(),
+            index_location: IndexLocation::default(),
         }
     }
 
+    /// Number of sub-chunks along each axis of a shard,
+    /// including partial sub-chunks overhanging its far edges.
     pub fn n_chunks(&self, shard_shape: &[u64]) -> Result<Vec<u64>, &'static str> {
-        self.chunk_shape
+        self.check_overhanging_shard_shape(shard_shape)
+            .map_err(|_| "Shard shape does not match sub-chunks")?;
+        Ok(self
+            .chunk_shape
             .iter()
             .zip(shard_shape.iter())
-            .map(|(c, s)| {
-                if s % c != 0 {
-                    return Err("Shard shape does not match sub-chunks");
-                }
-                Ok(s / c)
-            })
-            .collect()
+            .map(|(c, s)| s.div_ceil(*c))
+            .collect())
+    }
+
+    /// Check that the shard shape is a whole number of sub-chunks on every axis,
+    /// including for any nested sharding codecs, as the spec requires.
+    pub fn check_shard_shape(&self, shard_shape: &[u64]) -> Result<(), ShardShapeError> {
+        self.check_shard_shape_with(shard_shape, false)
+    }
+
+    /// As [ShardingIndexedCodec::check_shard_shape],
+    /// but allowing sub-chunks which overhang the shard's far edges.
+    ///
+    /// Such sub-chunks are stored padded to the full sub-chunk shape,
+    /// but other implementations may not read them (see [ShardingIndexedCodec::overhangs]).
+    pub fn check_overhanging_shard_shape(
+        &self,
+        shard_shape: &[u64],
+    ) -> Result<(), ShardShapeError> {
+        self.check_shard_shape_with(shard_shape, true)
+    }
+
+    fn check_shard_shape_with(
+        &self,
+        shard_shape: &[u64],
+        allow_overhang: bool,
+    ) -> Result<(), ShardShapeError> {
+        if shard_shape.len() != self.chunk_shape.len()
+            || self
+                .chunk_shape
+                .iter()
+                .zip(shard_shape.iter())
+                .any(|(c, s)| *c == 0 || (!allow_overhang && s % c != 0))
+        {
+            return Err(ShardShapeError {
+                shard_shape: shard_shape.to_vec(),
+                chunk_shape: self.chunk_shape.to_vec(),
+            });
+        }
+        if let ABCodecType::ShardingIndexed(inner) = self.codecs.ab_codec() {
+            inner.check_shard_shape_with(&self.chunk_shape, allow_overhang)?;
+        }
+        Ok(())
+    }
+
+    /// Whether sub-chunks overhang the far edges of a shard of the given shape,
+    /// including for any nested sharding codecs.
+    pub fn overhangs(&self, shard_shape: &[u64]) -> bool {
+        if shard_shape
+            .iter()
+            .zip(self.chunk_shape.iter())
+            .any(|(s, c)| *c != 0 && s % c != 0)
+        {
+            return true;
+        }
+        match self.codecs.ab_codec() {
+            ABCodecType::ShardingIndexed(inner) => inner.overhangs(&self.chunk_shape),
+            _ => false,
+        }
     }
 
     /// Set the array->bytes codec.
@@ -57,7 +152,7 @@ impl ShardingIndexedCodec {
     pub fn ab_codec<T: Into<ABCodecType>>(mut self, codec: T) -> Result<Self, &'static str> {
         let c = codec.into();
         self.union_ndim(&c)?;
-        self.codecs.replace_ab_codec(Some(c));
+        self.codecs.replace_ab_codec(c);
         Ok(self)
     }
 
@@ -82,9 +177,246 @@ impl ShardingIndexedCodec {
         self.codecs.bb_codecs_mut().push(c);
         self
     }
+
+    /// Replace the codecs used for the shard index.
+    ///
+    /// Fails if the codecs do not have a fixed encoded size (e.g. compression or sharding).
+    pub fn index_codecs(mut self, codecs: CodecChain) -> Result<Self, &'static str> {
+        if codecs
+            .compute_encoded_size(Self::index_repr(&self.chunk_shape))
+            .is_none()
+        {
+            return Err("Sharding index codecs must have a fixed encoded size");
+        }
+        self.index_codecs = codecs;
+        Ok(self)
+    }
+
+    /// Set whether the index is stored at the start or end of each shard.
+    pub fn index_location(mut self, location: IndexLocation) -> Self {
+        self.index_location = location;
+        self
+    }
+
+    /// Representation of the index: an address (offset, nbytes) for each sub-chunk.
+    fn index_repr(n_chunks: &GridCoord) -> ArrayRepr<u64> {
+        let mut shape = n_chunks.clone();
+        shape.push(2);
+        ArrayRepr {
+            shape,
+            fill_value: u64::MAX,
+        }
+    }
+
+    /// Encoded size of the index of a shard with the given number of sub-chunks along each axis.
+    pub fn index_nbytes(&self, n_chunks: &GridCoord) -> io::Result<usize> {
+        self.index_codecs
+            .compute_encoded_size(Self::index_repr(n_chunks))
+            .ok_or_else(|| invalid_input("Sharding index codecs must have a fixed encoded size"))
+    }
+
+    /// Range of a shard's bytes which is not occupied by its index.
+    fn data_bounds(&self, shard_len: usize, n_chunks: &GridCoord) -> io::Result<(usize, usize)> {
+        let index_nbytes = self.index_nbytes(n_chunks)?;
+        if shard_len < index_nbytes {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Shard is shorter than its index",
+            ));
+        }
+        Ok(match self.index_location {
+            IndexLocation::Start => (index_nbytes, shard_len),
+            IndexLocation::End => (0, shard_len - index_nbytes),
+        })
+    }
+
+    /// Decode the index of an encoded shard.
+    pub fn read_index(
+        &self,
+        shard: &[u8],
+        n_chunks: GridCoord,
+        options: &DecodeOptions,
+    ) -> io::Result<ChunkSpec> {
+        let (start, end) = self.data_bounds(shard.len(), &n_chunks)?;
+        let encoded = match self.index_location {
+            IndexLocation::Start => &shard[..start],
+            IndexLocation::End => &shard[end..],
+        };
+        let index = self
+            .index_codecs
+            .decode_with(encoded, Self::index_repr(&n_chunks), options)?;
+        let values: Vec<u64> = index.iter().cloned().collect();
+        let addrs = values
+            .chunks_exact(2)
+            .map(|a| ChunkAddress {
+                offset: a[0],
+                nbytes: a[1],
+            })
+            .collect();
+        ChunkSpec::new(addrs, n_chunks).map_err(invalid_data)
+    }
+
+    /// Write a shard from its concatenated encoded sub-chunks
+    /// and their index, whose offsets are relative to the start of the data.
+    fn write_shard<W: Write>(&self, spec: ChunkSpec, data: &[u8], mut w: W) -> io::Result<()> {
+        let index_nbytes = self.index_nbytes(&spec.shape)?;
+        let shift = match self.index_location {
+            IndexLocation::Start => index_nbytes as u64,
+            IndexLocation::End => 0,
+        };
+        let mut values = Vec::with_capacity(spec.n_subchunks() * 2);
+        for addr in spec.chunk_idxs.iter() {
+            if addr.is_empty() {
+                values.extend([u64::MAX, u64::MAX]);
+            } else {
+                values.extend([addr.offset + shift, addr.nbytes]);
+            }
+        }
+        let shape: Vec<usize> = Self::index_repr(&spec.shape)
+            .shape
+            .iter()
+            .map(|s| *s as usize)
+            .collect();
+        let index = ArcArrayD::from_shape_vec(shape, values).map_err(invalid_input)?;
+        let mut encoded = Vec::with_capacity(index_nbytes);
+        self.index_codecs.encode(index, &mut encoded)?;
+
+        match self.index_location {
+            IndexLocation::Start => {
+                w.write_all(&encoded)?;
+                w.write_all(data)?;
+            }
+            IndexLocation::End => {
+                w.write_all(data)?;
+                w.write_all(&encoded)?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Re-encode a shard so that only the region of the given shape (from the shard's origin) remains,
+    /// without decoding sub-chunks which lie entirely within it.
+    ///
+    /// Sub-chunks entirely outside the region are marked as empty in the index;
+    /// sub-chunks straddling its edge have values outside it reset to the fill value.
+    /// `None` if no sub-chunks remain.
+    pub fn truncate_shard<T: ReflectedType>(
+        &self,
+        shard: &[u8],
+        decoded_repr: ArrayRepr<T>,
+        keep: &[u64],
+        options: &DecodeOptions,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let n_chunks: GridCoord = self
+            .n_chunks(&decoded_repr.shape)
+            .map_err(invalid_input)?
+            .into();
+        let old_spec = self.read_index(shard, n_chunks.clone(), options)?;
+        let bounds = self.data_bounds(shard.len(), &n_chunks)?;
+        let mut spec = ChunkSpec::new_unchecked(
+            vec![ChunkAddress::empty(); old_spec.n_subchunks()],
+            n_chunks,
+        );
+
+        let mut data = Vec::default();
+        let mut any_kept = false;
+        for c_info in ChunkIter::new(self.chunk_shape.clone(), decoded_repr.shape.clone())
+            .expect("Shard shape already checked")
+        {
+            let addr = *old_spec.get_idx(&c_info.chunk_idx)?.unwrap();
+            if addr.is_empty() || c_info.offset.iter().zip(keep.iter()).any(|(o, k)| o >= k) {
+                continue;
+            }
+            let encoded = subchunk_bytes(shard, &addr, bounds)?;
+
+            let within = c_info
+                .offset
+                .iter()
+                .zip(c_info.shape.iter())
+                .zip(keep.iter())
+                .all(|((o, s), k)| o + s <= *k);
+            let offset = data.len() as u64;
+            if within {
+                data.extend_from_slice(encoded);
+            } else {
+                let repr = ArrayRepr {
+                    shape: self.chunk_shape.clone(),
+                    fill_value: decoded_repr.fill_value,
+                };
+                let sub_arr = self.codecs.decode_with(encoded, repr.clone(), options)?;
+                let keep_shape: GridCoord = c_info
+                    .offset
+                    .iter()
+                    .zip(c_info.shape.iter())
+                    .zip(keep.iter())
+                    .map(|((o, s), k)| (*s).min(k - o))
+                    .collect();
+                let sl = offset_shape_to_slice_info(
+                    &keep_shape.iter().map(|_| 0).collect::<GridCoord>(),
+                    &keep_shape,
+                );
+                let mut truncated = repr.empty_array();
+                truncated.slice_mut(&sl).assign(&sub_arr.slice(&sl));
+                if truncated
+                    .iter()
+                    .all(|v| v.bitwise_eq(&decoded_repr.fill_value))
+                {
+                    continue;
+                }
+                self.codecs
+                    .encode_with_fill(truncated, decoded_repr.fill_value, &mut data)?;
+            }
+            spec.set_idx(
+                &c_info.chunk_idx,
+                ChunkAddress {
+                    offset,
+                    nbytes: data.len() as u64 - offset,
+                },
+            )
+            .expect("Sub-chunk index is within shard");
+            any_kept = true;
+        }
+
+        if !any_kept {
+            return Ok(None);
+        }
+        let mut out = Vec::default();
+        self.write_shard(spec, &data, &mut out)?;
+        Ok(Some(out))
+    }
+}
+
+/// The encoded bytes of a sub-chunk,
+/// or an error if its address is not within the given bounds of the shard's data.
+fn subchunk_bytes<'a>(
+    shard: &'a [u8],
+    addr: &ChunkAddress,
+    (start, end): (usize, usize),
+) -> io::Result<&'a [u8]> {
+    match addr.offset.checked_add(addr.nbytes) {
+        Some(addr_end) if addr.offset >= start as u64 && addr_end <= end as u64 => {
+            Ok(&shard[addr.offset as usize..addr_end as usize])
+        }
+        _ => Err(invalid_data("Sub-chunk address is beyond shard data")),
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Shard shape {shard_shape:?} is not a multiple of sub-chunk shape {chunk_shape:?}")]
+#[non_exhaustive]
+pub struct ShardShapeError {
+    pub shard_shape: Vec<u64>,
+    pub chunk_shape: Vec<u64>,
+}
+
+impl From<ShardShapeError> for std::io::Error {
+    fn from(value: ShardShapeError) -> Self {
+        Self::new(std::io::ErrorKind::InvalidData, value)
+    }
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ChunkReadError {
     #[error("Index dimension does not match array dimension")]
     DimensionMismatch(#[from] DimensionMismatch),
@@ -109,106 +441,120 @@ impl From<ReadChunk> for Option<Vec<u8>> {
 }
 
 impl ABCodec for ShardingIndexedCodec {
-    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) {
-        let mut bw = BufWriter::new(w);
-        let mut curs = Cursor::new(Vec::default());
+    /// Sub-chunks overhanging the shard are padded with the default value of `T`;
+    /// see [ABCodec::encode_with_fill].
+    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) -> io::Result<()> {
+        self.encode_with_fill(decoded, T::default(), w)
+    }
 
+    fn encode_with_fill<T: ReflectedType, W: Write>(
+        &self,
+        decoded: ArcArrayD<T>,
+        fill_value: T,
+        w: W,
+    ) -> io::Result<()> {
         let dec_shape: GridCoord = decoded.shape().iter().map(|s| *s as u64).collect();
-        let mut offset: u64 = 0;
+        let n_chunks = self.n_chunks(&dec_shape).map_err(invalid_input)?.into();
+        let sub_repr = ArrayRepr {
+            shape: self.chunk_shape.clone(),
+            fill_value,
+        };
 
+        let mut data = Vec::default();
         let mut addrs = Vec::default();
-        for c_info in ChunkIter::new_strict(self.chunk_shape.clone(), dec_shape).unwrap() {
+        for c_info in
+            ChunkIter::new(self.chunk_shape.clone(), dec_shape.clone()).map_err(invalid_input)?
+        {
             let sl = offset_shape_to_slice_info(&c_info.offset, &c_info.shape);
-            // todo: is this a clone which can be avoided?
-            let sub_arr = decoded.slice(sl).to_shared();
-            self.codecs.encode(sub_arr, &mut curs);
-            let nbytes = curs.position();
-            bw.write(&curs.get_ref()[..(nbytes as usize)])
-                .expect("Could not write sub-chunk");
+            let sub_arr = if c_info.shape == self.chunk_shape {
+                // todo: is this a clone which can be avoided?
+                decoded.slice(sl).to_shared()
+            } else {
+                // overhanging sub-chunks are stored at full size, like edge chunks of an array
+                let mut padded = sub_repr.empty_array();
+                padded
+                    .slice_mut(offset_shape_to_slice_info(
+                        &c_info.offset.iter().map(|_| 0).collect::<GridCoord>(),
+                        &c_info.shape,
+                    ))
+                    .assign(&decoded.slice(sl));
+                padded
+            };
+            let offset = data.len() as u64;
+            self.codecs
+                .encode_with_fill(sub_arr, fill_value, &mut data)?;
             addrs.push(ChunkAddress {
-                offset: offset.clone(),
-                nbytes: nbytes.clone(),
+                offset,
+                nbytes: data.len() as u64 - offset,
             });
-            offset += nbytes;
-            curs.set_position(0);
-        }
-
-        for addr in addrs {
-            addr.write_to(&mut bw).expect("Could not write chunk addr");
         }
 
-        bw.flush()
-            .expect("Could not write shard to underlying buffer");
+        self.write_shard(ChunkSpec::new_unchecked(addrs, n_chunks), &data, w)
     }
 
     fn decode<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+    ) -> io::Result<ArcArrayD<T>> {
+        self.decode_with(r, decoded_repr, &Default::default())
+    }
+
+    fn decode_with<T: ReflectedType, R: Read>(
         &self,
         mut r: R,
         decoded_repr: ArrayRepr<T>,
-    ) -> ArcArrayD<T> {
-        let shape: Vec<_> = decoded_repr.shape.iter().map(|s| *s as usize).collect();
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
         let mut arr = decoded_repr.empty_array();
-        let mut chunk_buf = Vec::default();
-        r.read_to_end(&mut chunk_buf).expect("Could not read");
-        let chunk_len = chunk_buf.len();
-        let mut curs = Cursor::new(chunk_buf);
-
-        let n_chunks = shape
-            .iter()
-            .zip(self.chunk_shape.iter())
-            .map(|(a_s, c_s)| *a_s as u64 / c_s)
-            .collect();
-        let cspec =
-            ChunkSpec::from_shard(&mut curs, n_chunks).expect("Could not construct chunk spec");
-
-        let total_chunks = cspec.n_subchunks();
-
-        let mut subchunk_buf: Vec<u8> = Vec::default();
-
-        for c_info in ChunkIter::new_strict(self.chunk_shape.clone(), decoded_repr.shape)
-            .expect("Could not iterate shard chunks")
+        let mut shard = Vec::default();
+        r.read_to_end(&mut shard)?;
+
+        let n_chunks: GridCoord = self
+            .n_chunks(&decoded_repr.shape)
+            .map_err(invalid_input)?
+            .into();
+        let bounds = self.data_bounds(shard.len(), &n_chunks)?;
+        let cspec = self.read_index(&shard, n_chunks, options)?;
+
+        for c_info in
+            ChunkIter::new(self.chunk_shape.clone(), decoded_repr.shape).map_err(invalid_input)?
         {
-            let addr = cspec.get_idx(&c_info.chunk_idx).unwrap().unwrap();
+            let addr = cspec
+                .get_idx(&c_info.chunk_idx)
+                .map_err(invalid_input)?
+                .expect("Sub-chunk index is within shard");
 
             if addr.is_empty() {
                 continue;
             }
 
-            // this prevents a bad chunk address trying to allocate all our RAM
-            let nbytes = (addr.nbytes as usize).min(
-                chunk_len
-                    - total_chunks * std::mem::size_of::<ChunkAddress>()
-                    - addr.offset as usize,
-            );
-
-            if subchunk_buf.len() < nbytes {
-                // safety factor of 2 to reduce repeated resizes.
-                // Resize is usually fast but might have to re-allocate
-                subchunk_buf.resize(nbytes * 2, 0);
-            }
-            curs.seek(SeekFrom::Start(addr.offset))
-                .expect("Could not seek");
-            curs.read_exact(&mut subchunk_buf[..nbytes])
-                .expect("Could not read sub-chunk");
-
-            let sub_arr = self.codecs.decode::<T, _>(
-                &subchunk_buf[..nbytes],
+            let sub_arr = self.codecs.decode_with::<T, _>(
+                subchunk_bytes(&shard, addr, bounds)?,
                 ArrayRepr {
-                    shape: c_info.shape.clone(),
+                    shape: self.chunk_shape.clone(),
                     fill_value: decoded_repr.fill_value,
                 },
-            );
+                options,
+            )?;
 
             let sl = offset_shape_to_slice_info(&c_info.offset, &c_info.shape);
             let mut view = arr.slice_mut(sl);
-            view.assign(&sub_arr);
+            view.assign(&sub_arr.slice(offset_shape_to_slice_info(
+                &c_info.offset.iter().map(|_| 0).collect::<GridCoord>(),
+                &c_info.shape,
+            )));
         }
-        arr
+        Ok(arr)
     }
 
     fn endian(&self) -> Option<Endian> {
         self.codecs.endian()
     }
+
+    fn compute_encoded_size<T: ReflectedType>(&self, _decoded_repr: ArrayRepr<T>) -> Option<usize> {
+        None
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -280,6 +626,7 @@ impl Ord for ChunkAddress {
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ChunkSpecError {
     #[error("Scalar datasets (i.e. empty chunk shape array) cannot be sharded")]
     EmptyChunkShape,
@@ -309,6 +656,7 @@ impl ChunkSpecError {
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ChunkSpecConstructionError {
     #[error("Chunk spec is malformed")]
     MalformedSpec(#[from] ChunkSpecError),
@@ -329,15 +677,16 @@ fn to_linear_idx(coord: &GridCoord, shape: &GridCoord) -> Result<Option<usize>,
             return Ok(None);
         }
         total += *i as usize * prev_s;
-        prev_s = *s as usize;
+        prev_s *= *s as usize;
     }
     Ok(Some(total))
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ChunkSpecModificationError {
     #[error("Index {coord:?} is out of bounds of shape {shape:?}")]
-    OutOfBounds { coord: GridCoord, shape: GridCoord },
+    OutOfBounds { coord: Vec<u64>, shape: Vec<u64> },
     #[error("Dimension mismatch")]
     DimensionMismatch(#[from] DimensionMismatch),
 }
@@ -359,7 +708,7 @@ impl ChunkSpec {
         r: &mut R,
         shape: GridCoord,
     ) -> Result<Self, ChunkSpecConstructionError> {
-        let prod = shape.iter().fold(1, |a, b| a * b);
+        let prod: u64 = shape.iter().product();
         if prod == 0 {
             Ok(Self::new_unchecked(vec![], shape))
         } else {
@@ -391,7 +740,7 @@ impl ChunkSpec {
         }
 
         let chksum_read = curs.read_u32::<LittleEndian>()?;
-        if chksum_calc != chksum_read {
+        if chksum_calc == chksum_read {
             Self::new(c_idxs, shape).map_err(|e| e.into())
         } else {
             Err(ChunkSpecConstructionError::ChecksumFailure)
@@ -431,8 +780,8 @@ impl ChunkSpec {
     ) -> Result<ChunkAddress, ChunkSpecModificationError> {
         let lin_idx = to_linear_idx(idx, &self.shape)?.ok_or_else(|| {
             ChunkSpecModificationError::OutOfBounds {
-                coord: idx.clone(),
-                shape: self.shape.clone(),
+                coord: idx.to_vec(),
+                shape: self.shape.to_vec(),
             }
         })?;
         Ok(std::mem::replace(&mut self.chunk_idxs[lin_idx], chunk_idx))
@@ -468,7 +817,9 @@ impl ChunkSpec {
 
 #[cfg(test)]
 mod tests {
-    use crate::codecs::{aa::TransposeCodec, ab::endian::EndianCodec};
+    #[cfg(feature = "gzip")]
+    use crate::codecs::aa::TransposeCodec;
+    use crate::codecs::ab::bytes_codec::BytesCodec;
 
     use super::*;
     use smallvec::smallvec;
@@ -483,33 +834,208 @@ mod tests {
         let arr = make_arr();
         let arr1 = arr.clone();
         let mut buf = Cursor::new(Vec::<u8>::default());
-        codec.encode(arr, &mut buf);
+        codec.encode(arr, &mut buf).unwrap();
 
         buf.set_position(0);
-        let arr2 = codec.decode::<i32, _>(&mut buf, ArrayRepr::new(vec![50, 60].as_slice(), 0i32));
+        let arr2 = codec
+            .decode::<i32, _>(&mut buf, ArrayRepr::new(vec![50, 60].as_slice(), 0i32))
+            .unwrap();
 
         assert_eq!(arr1, arr2);
     }
 
+    #[test]
+    fn check_shard_shape() {
+        let codec = ShardingIndexedCodec::new(smallvec![10, 20]);
+        codec.check_shard_shape(&[50, 60]).unwrap();
+        let err = codec.check_shard_shape(&[50, 50]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Shard shape [50, 50] is not a multiple of sub-chunk shape [10, 20]"
+        );
+        assert!(codec.check_shard_shape(&[50]).is_err());
+
+        let nested = ShardingIndexedCodec::new(smallvec![10, 20])
+            .ab_codec(ShardingIndexedCodec::new(smallvec![5, 8]))
+            .unwrap();
+        let err = nested.check_shard_shape(&[50, 60]).unwrap_err();
+        assert_eq!(err.shard_shape, vec![10, 20]);
+        assert_eq!(err.chunk_shape, vec![5, 8]);
+
+        // overhanging sub-chunks are only allowed when asked for
+        codec.check_overhanging_shard_shape(&[50, 50]).unwrap();
+        nested.check_overhanging_shard_shape(&[50, 60]).unwrap();
+        assert!(codec.check_overhanging_shard_shape(&[50]).is_err());
+        assert!(ShardingIndexedCodec::new(smallvec![10, 0])
+            .check_overhanging_shard_shape(&[50, 60])
+            .is_err());
+    }
+
+    #[test]
+    fn roundtrip_shard_overhanging() {
+        // overhanging sub-chunks are stored at full size, padded with the fill value
+        let inner = ShardingIndexedCodec::new(smallvec![4, 10]);
+        assert!(inner.overhangs(&[15, 25]));
+        assert!(!inner.overhangs(&[16, 20]));
+        let arr = ArcArrayD::from_shape_fn(vec![15, 25], |idx| (idx[0] * 25 + idx[1]) as i32);
+        let mut buf = Vec::default();
+        inner.encode_with_fill(arr.clone(), -1, &mut buf).unwrap();
+        assert_eq!(buf.len(), 12 * 4 * 10 * 4 + 12 * 16 + 4);
+        let spec = inner
+            .read_index(&buf, smallvec![4, 3], &Default::default())
+            .unwrap();
+        let addr = spec.get_idx(&smallvec![3, 2]).unwrap().unwrap();
+        assert_eq!(addr.nbytes, 4 * 10 * 4);
+        let corner = BytesCodec::default()
+            .decode(
+                &buf[addr.offset as usize..addr.end_offset().unwrap() as usize],
+                ArrayRepr::new(&[4, 10], 0i32),
+            )
+            .unwrap();
+        let expected = ArcArrayD::from_shape_fn(vec![4, 10], |idx| {
+            if idx[0] < 3 && idx[1] < 5 {
+                ((12 + idx[0]) * 25 + 20 + idx[1]) as i32
+            } else {
+                -1
+            }
+        });
+        assert_eq!(corner, expected);
+        let arr2 = inner
+            .decode::<i32, _>(buf.as_slice(), ArrayRepr::new(&[15, 25], -1))
+            .unwrap();
+        assert_eq!(arr, arr2);
+
+        let codec = ShardingIndexedCodec::new(smallvec![15, 25])
+            .ab_codec(ShardingIndexedCodec::new(smallvec![4, 10]))
+            .unwrap();
+        assert!(codec.overhangs(&[30, 50]));
+        assert_eq!(codec.n_chunks(&[50, 60]).unwrap(), vec![4, 3]);
+        let arr = make_arr();
+        let mut buf = Cursor::new(Vec::<u8>::default());
+        codec.encode(arr.clone(), &mut buf).unwrap();
+
+        buf.set_position(0);
+        let arr2 = codec
+            .decode::<i32, _>(&mut buf, ArrayRepr::new(vec![50, 60].as_slice(), 0i32))
+            .unwrap();
+        assert_eq!(arr, arr2);
+
+        let truncated = codec
+            .truncate_shard(
+                buf.get_ref(),
+                ArrayRepr::new(vec![50, 60].as_slice(), 0i32),
+                &[47, 33],
+                &Default::default(),
+            )
+            .unwrap()
+            .unwrap();
+        let arr3 = codec
+            .decode::<i32, _>(
+                truncated.as_slice(),
+                ArrayRepr::new(vec![50, 60].as_slice(), 0i32),
+            )
+            .unwrap();
+        let mut expected = ArcArrayD::from_elem(vec![50, 60], 0);
+        expected
+            .slice_mut(ndarray::s![..47, ..33])
+            .assign(&arr.slice(ndarray::s![..47, ..33]));
+        assert_eq!(arr3, expected);
+    }
+
+    #[test]
+    fn index_config_serde() {
+        let s = r#"{
+            "chunk_shape": [10, 20],
+            "codecs": [{"name": "bytes", "configuration": {"endian": "little"}}],
+            "index_codecs": [{"name": "bytes", "configuration": {"endian": "big"}}],
+            "index_location": "start"
+        }"#;
+        let codec: ShardingIndexedCodec = serde_json::from_str(s).unwrap();
+        assert_eq!(codec.index_location, IndexLocation::Start);
+        assert_eq!(codec.index_codecs.ab_codec(), &BytesCodec::new_big().into());
+        assert_eq!(codec.index_nbytes(&smallvec![5, 3]).unwrap(), 5 * 3 * 16);
+
+        let value = serde_json::to_value(ShardingIndexedCodec::new(smallvec![10, 20])).unwrap();
+        assert_eq!(value["index_location"], "end");
+        assert_eq!(value["index_codecs"][0]["name"], "bytes");
+        assert_eq!(value["index_codecs"][1]["name"], "crc32c");
+    }
+
+    #[test]
+    fn roundtrip_shard_index_start() {
+        let index_codecs = CodecChain::new(vec![], BytesCodec::new_big().into(), vec![]);
+        let codec = ShardingIndexedCodec::new(smallvec![10, 20])
+            .index_codecs(index_codecs)
+            .unwrap()
+            .index_location(IndexLocation::Start);
+        let arr = make_arr();
+        let mut buf = Vec::default();
+        codec.encode(arr.clone(), &mut buf).unwrap();
+
+        // the first sub-chunk directly follows the big-endian index
+        let index_nbytes = 5 * 3 * 16;
+        assert_eq!(&buf[..8], &(index_nbytes as u64).to_be_bytes());
+        assert_eq!(buf.len(), index_nbytes + 50 * 60 * 4);
+
+        let arr2 = codec
+            .decode::<i32, _>(buf.as_slice(), ArrayRepr::new(&[50, 60], 0i32))
+            .unwrap();
+        assert_eq!(arr, arr2);
+    }
+
+    #[test]
+    fn decode_corrupt_shard() {
+        let codec = ShardingIndexedCodec::new(smallvec![10, 20])
+            .index_codecs(CodecChain::default())
+            .unwrap();
+        let mut buf = Vec::default();
+        codec.encode(make_arr(), &mut buf).unwrap();
+        let repr = ArrayRepr::new(&[50, 60], 0i32);
+
+        let err = codec.decode(&buf[..10], repr.clone()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        // point the last sub-chunk's data into the index
+        let n = buf.len();
+        let index_start = n - 5 * 3 * 16;
+        buf[n - 16..n - 8].copy_from_slice(&(index_start as u64).to_le_bytes());
+        let err = codec.decode(buf.as_slice(), repr.clone()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        buf[n - 16..n - 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let err = codec.decode(buf.as_slice(), repr).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        assert!(ShardingIndexedCodec::new(smallvec![10, 20])
+            .index_codecs(CodecChain::new(
+                vec![],
+                ShardingIndexedCodec::new(smallvec![1, 1, 1]).into(),
+                vec![]
+            ))
+            .is_err());
+    }
+
     #[cfg(feature = "gzip")]
     #[test]
     fn roundtrip_shard_complex() {
         use crate::codecs::bb::gzip_codec::GzipCodec;
 
         let codec = ShardingIndexedCodec::new(smallvec![10, 20])
-            .push_aa_codec(TransposeCodec::new_f())
+            .push_aa_codec(TransposeCodec::new_transpose(2))
             .unwrap()
-            .ab_codec(EndianCodec::new_big())
+            .ab_codec(BytesCodec::new_big())
             .unwrap()
             .push_bb_codec(GzipCodec::default());
 
         let arr = make_arr();
         let arr1 = arr.clone();
         let mut buf = Cursor::new(Vec::<u8>::default());
-        codec.encode(arr, &mut buf);
+        codec.encode(arr, &mut buf).unwrap();
 
         buf.set_position(0);
-        let arr2 = codec.decode::<i32, _>(&mut buf, ArrayRepr::new(vec![50, 60].as_slice(), 0i32));
+        let arr2 = codec
+            .decode::<i32, _>(&mut buf, ArrayRepr::new(vec![50, 60].as_slice(), 0i32))
+            .unwrap();
 
         assert_eq!(arr1, arr2);
     }
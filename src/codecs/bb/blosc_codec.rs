@@ -2,7 +2,10 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::io::{self, Cursor, Read, Write};
 use thiserror::Error;
 
-use crate::{codecs::bb::BBCodec, data_type::ReflectedType};
+use crate::{
+    codecs::{bb::BBCodec, DecodeOptions, TrailingDataPolicy},
+    data_type::ReflectedType,
+};
 use blosc::{decompress_bytes, Context};
 pub use blosc::{Clevel, Compressor, ShuffleMode};
 
@@ -109,6 +112,7 @@ mod shuffle {
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum BloscBuildError {
     #[error("`typesize` must not be None if blosc codec shuffling is active (here `{0:?}`)")]
     TypesizeNeeded(ShuffleMode),
@@ -216,11 +220,16 @@ impl Default for BloscCodec {
 struct BloscReader<R: Read> {
     r: R,
     buf: Option<Cursor<Vec<u8>>>,
+    trailing_data: TrailingDataPolicy,
 }
 
 impl<R: Read> BloscReader<R> {
-    fn new(r: R) -> Self {
-        Self { r, buf: None }
+    fn new(r: R, trailing_data: TrailingDataPolicy) -> Self {
+        Self {
+            r,
+            buf: None,
+            trailing_data,
+        }
     }
 
     /// This wraps an unsafe decompression of blosc-encoded bytes.
@@ -237,6 +246,15 @@ impl<R: Read> BloscReader<R> {
             let mut compressed = Vec::default();
             self.r.read_to_end(&mut compressed)?;
 
+            // total compressed size, including the 16-byte header
+            if let Some(cbytes) = compressed.get(12..16) {
+                let cbytes = u32::from_le_bytes(cbytes.try_into().unwrap()) as usize;
+                if cbytes < compressed.len() {
+                    self.trailing_data.check(&compressed[cbytes..], "blosc")?;
+                    compressed.truncate(cbytes);
+                }
+            }
+
             let decomp: Vec<u8> = Self::unsafe_decompress(&compressed)?;
             self.buf = Some(Cursor::new(decomp));
         }
@@ -287,8 +305,8 @@ impl BBCodec for BloscCodec {
         Box::new(FinalWriter(BloscWriter::new(self, w)))
     }
 
-    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
-        Box::new(BloscReader::new(r))
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
+        Box::new(BloscReader::new(r, options.trailing_data))
     }
 
     fn compute_encoded_size(&self, _input_size: Option<usize>) -> Option<usize> {
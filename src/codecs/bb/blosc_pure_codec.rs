@@ -0,0 +1,433 @@
+//! Decode-only implementation of the blosc codec which does not depend on the C library.
+//!
+//! Supports blosc1 containers whose blocks are stored uncompressed or compressed with
+//! lz4, lz4hc, or zstd, with no shuffle or byte shuffle.
+//! Other internal compressors and bit shuffling produce an [io::ErrorKind::Unsupported] error on read,
+//! as does any attempt to encode.
+use serde::{Deserialize, Serialize};
+use std::io::{self, Cursor, Read, Write};
+
+use crate::codecs::fwrite::{FinalWrite, FinalWriter};
+use crate::codecs::{bb::BBCodec, DecodeOptions, TrailingDataPolicy};
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Compressor {
+    BloscLZ,
+    LZ4,
+    LZ4HC,
+    Snappy,
+    Zlib,
+    Zstd,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ShuffleMode {
+    #[serde(rename = "noshuffle")]
+    None,
+    #[serde(rename = "shuffle")]
+    Byte,
+    #[serde(rename = "bitshuffle")]
+    Bit,
+}
+
+/// Blosc codec configuration.
+///
+/// The configuration is only used to round-trip metadata:
+/// everything needed for decoding is stored in the blosc container header.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct BloscCodec {
+    pub cname: Compressor,
+    pub clevel: u8,
+    pub shuffle: ShuffleMode,
+    pub blocksize: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typesize: Option<usize>,
+}
+
+const HEADER_LEN: usize = 16;
+const MAX_SPLITS: usize = 16;
+const MIN_BUFFERSIZE: usize = 128;
+
+const FLAG_SHUFFLE: u8 = 0x01;
+const FLAG_MEMCPYED: u8 = 0x02;
+const FLAG_BITSHUFFLE: u8 = 0x04;
+const FLAG_DONT_SPLIT: u8 = 0x10;
+
+const COMPCODE_LZ4: u8 = 1;
+const COMPCODE_ZSTD: u8 = 4;
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn unsupported(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, msg)
+}
+
+fn read_u32(b: &[u8], offset: usize) -> io::Result<usize> {
+    b.get(offset..offset + 4)
+        .map(|s| u32::from_le_bytes(s.try_into().unwrap()) as usize)
+        .ok_or_else(|| invalid("Truncated blosc container"))
+}
+
+/// Upper bound on the size of the output of an lz4 block of the given length:
+/// each byte of a sequence extends its match by at most 255 bytes.
+fn lz4_max_decompressed_len(src_len: usize) -> usize {
+    src_len.saturating_mul(255).saturating_add(64)
+}
+
+/// Decompress a stream which must decode to exactly `len` bytes.
+///
+/// The output grows with the decoded data rather than being allocated from `len` up front,
+/// so that a corrupt header cannot cause a huge allocation.
+fn decompress_stream(compcode: u8, src: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    let out = match compcode {
+        COMPCODE_LZ4 => {
+            if len > lz4_max_decompressed_len(src.len()) {
+                return Err(invalid("Blosc stream decompressed to unexpected length"));
+            }
+            lz4_flex::block::decompress(src, len)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+        COMPCODE_ZSTD => {
+            let dec = ruzstd::StreamingDecoder::new(src)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let mut out = Vec::default();
+            dec.take(len as u64).read_to_end(&mut out)?;
+            out
+        }
+        _ => {
+            return Err(unsupported(
+                "Blosc internal compressor not supported without the C library",
+            ))
+        }
+    };
+    if out.len() != len {
+        return Err(invalid("Blosc stream decompressed to unexpected length"));
+    }
+    Ok(out)
+}
+
+fn unshuffle(src: Vec<u8>, typesize: usize) -> Vec<u8> {
+    let n_elements = src.len() / typesize;
+    // blocks smaller than a single element are not shuffled
+    if n_elements == 0 {
+        return src;
+    }
+    let mut out = vec![0; src.len()];
+    for (byte_idx, plane) in src.chunks_exact(n_elements).take(typesize).enumerate() {
+        for (el_idx, b) in plane.iter().enumerate() {
+            out[el_idx * typesize + byte_idx] = *b;
+        }
+    }
+    // trailing bytes which don't make up a whole element are not shuffled
+    let shuffled_len = n_elements * typesize;
+    out[shuffled_len..].copy_from_slice(&src[shuffled_len..]);
+    out
+}
+
+/// Decode a complete blosc1 container, applying the policy to any data after it.
+fn decompress(b: &[u8], trailing_data: TrailingDataPolicy) -> io::Result<Vec<u8>> {
+    if b.len() < HEADER_LEN {
+        return Err(invalid("Truncated blosc header"));
+    }
+    let flags = b[2];
+    let typesize = b[3] as usize;
+    let nbytes = read_u32(b, 4)?;
+    let blocksize = read_u32(b, 8)?;
+    let cbytes = read_u32(b, 12)?;
+    if cbytes > b.len() {
+        return Err(invalid("Truncated blosc container"));
+    }
+    if cbytes < b.len() {
+        trailing_data.check(&b[cbytes..], "blosc")?;
+    }
+
+    if flags & FLAG_MEMCPYED != 0 {
+        return b
+            .get(HEADER_LEN..HEADER_LEN + nbytes)
+            .map(|s| s.to_vec())
+            .ok_or_else(|| invalid("Truncated blosc container"));
+    }
+    if nbytes == 0 {
+        return Ok(Vec::default());
+    }
+    if typesize == 0 || blocksize == 0 {
+        return Err(invalid("Invalid blosc header"));
+    }
+    let do_shuffle = flags & FLAG_SHUFFLE != 0 && typesize > 1;
+    if flags & FLAG_BITSHUFFLE != 0 && blocksize >= typesize {
+        return Err(unsupported(
+            "Blosc bit shuffle not supported without the C library",
+        ));
+    }
+    let compcode = (flags & 0xe0) >> 5;
+    let dont_split = flags & FLAG_DONT_SPLIT != 0;
+
+    let n_blocks = nbytes.div_ceil(blocksize);
+    // every block needs an offset in the container, which bounds the declared size by the input
+    if n_blocks
+        .checked_mul(4)
+        .and_then(|n| n.checked_add(HEADER_LEN))
+        .is_none_or(|n| n > cbytes)
+    {
+        return Err(invalid("Truncated blosc container"));
+    }
+    let mut out = Vec::default();
+
+    for block_idx in 0..n_blocks {
+        let leftover = block_idx == n_blocks - 1 && nbytes % blocksize != 0;
+        let bsize = if leftover {
+            nbytes % blocksize
+        } else {
+            blocksize
+        };
+        let n_splits = if !dont_split
+            && typesize <= MAX_SPLITS
+            && bsize / typesize >= MIN_BUFFERSIZE
+            && !leftover
+        {
+            typesize
+        } else {
+            1
+        };
+        // C-blosc only splits blocks made of whole elements
+        if bsize % n_splits != 0 {
+            return Err(invalid(
+                "Blosc block size is not a multiple of the type size",
+            ));
+        }
+        let split_size = bsize / n_splits;
+
+        let mut block = Vec::default();
+        let mut offset = read_u32(b, HEADER_LEN + 4 * block_idx)?;
+        for _ in 0..n_splits {
+            let csize = read_u32(b, offset)?;
+            offset += 4;
+            let src = b
+                .get(offset..offset.saturating_add(csize))
+                .ok_or_else(|| invalid("Truncated blosc container"))?;
+            if csize == split_size {
+                block.extend_from_slice(src);
+            } else {
+                block.extend(decompress_stream(compcode, src, split_size)?);
+            }
+            offset += csize;
+        }
+
+        if do_shuffle {
+            block = unshuffle(block, typesize);
+        }
+        out.extend_from_slice(&block);
+    }
+
+    Ok(out)
+}
+
+struct BloscReader<R: Read> {
+    r: R,
+    buf: Option<Cursor<Vec<u8>>>,
+    trailing_data: TrailingDataPolicy,
+}
+
+impl<R: Read> BloscReader<R> {
+    fn new(r: R, trailing_data: TrailingDataPolicy) -> Self {
+        Self {
+            r,
+            buf: None,
+            trailing_data,
+        }
+    }
+
+    fn buffer(&mut self) -> io::Result<&mut Cursor<Vec<u8>>> {
+        if self.buf.is_none() {
+            let mut compressed = Vec::default();
+            self.r.read_to_end(&mut compressed)?;
+            self.buf = Some(Cursor::new(decompress(&compressed, self.trailing_data)?));
+        }
+        Ok(self.buf.as_mut().unwrap())
+    }
+}
+
+impl<R: Read> Read for BloscReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(&mut self.buffer()?, buf)
+    }
+}
+
+/// Writer which fails on any write.
+struct UnsupportedWriter;
+
+impl Write for UnsupportedWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(unsupported(
+            "Blosc encoding requires the `blosc` feature (C library)",
+        ))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl BBCodec for BloscCodec {
+    fn encoder<'a, W: Write + 'a>(&self, _w: W) -> Box<dyn FinalWrite + 'a> {
+        Box::new(FinalWriter(UnsupportedWriter))
+    }
+
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
+        Box::new(BloscReader::new(r, options.trailing_data))
+    }
+
+    fn compute_encoded_size(&self, _input_size: Option<usize>) -> Option<usize> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(flags: u8, typesize: u8, nbytes: usize, blocksize: usize, cbytes: usize) -> Vec<u8> {
+        let mut out = vec![2, 1, flags, typesize];
+        for n in [nbytes, blocksize, cbytes] {
+            out.extend_from_slice(&(n as u32).to_le_bytes());
+        }
+        out
+    }
+
+    /// Build a single-block, single-split container.
+    fn container(flags: u8, typesize: u8, nbytes: usize, stream: &[u8]) -> Vec<u8> {
+        let cbytes = HEADER_LEN + 4 + 4 + stream.len();
+        let mut out = header(flags | FLAG_DONT_SPLIT, typesize, nbytes, nbytes, cbytes);
+        out.extend_from_slice(&((HEADER_LEN + 4) as u32).to_le_bytes());
+        out.extend_from_slice(&(stream.len() as u32).to_le_bytes());
+        out.extend_from_slice(stream);
+        out
+    }
+
+    fn data() -> Vec<u8> {
+        (0..100u16).flat_map(|n| (n % 7).to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn deser_config() {
+        let s =
+            r#"{"cname": "lz4", "clevel": 5, "shuffle": "shuffle", "blocksize": 0, "typesize": 2}"#;
+        let codec: BloscCodec = serde_json::from_str(s).unwrap();
+        assert_eq!(codec.cname, Compressor::LZ4);
+        assert_eq!(codec.shuffle, ShuffleMode::Byte);
+    }
+
+    #[test]
+    fn decode_memcpyed() {
+        let data = data();
+        let mut c = header(
+            FLAG_MEMCPYED,
+            2,
+            data.len(),
+            data.len(),
+            HEADER_LEN + data.len(),
+        );
+        c.extend_from_slice(&data);
+        assert_eq!(decompress(&c, TrailingDataPolicy::Error).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_lz4_shuffled() {
+        let data = data();
+        let shuffled: Vec<u8> = (0..2)
+            .flat_map(|b| data.iter().skip(b).step_by(2).copied())
+            .collect();
+        let stream = lz4_flex::block::compress(&shuffled);
+        let c = container(FLAG_SHUFFLE | (COMPCODE_LZ4 << 5), 2, data.len(), &stream);
+        assert_eq!(decompress(&c, TrailingDataPolicy::Error).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_split_raw() {
+        // blocks >= MIN_BUFFERSIZE elements are split into one stream per byte of the type
+        let data: Vec<u8> = (0..=255).collect();
+        let mut c = header(0, 2, data.len(), data.len(), 0);
+        c.extend_from_slice(&((HEADER_LEN + 4) as u32).to_le_bytes());
+        for split in data.chunks(128) {
+            c.extend_from_slice(&128u32.to_le_bytes());
+            c.extend_from_slice(split);
+        }
+        let cbytes = (c.len() as u32).to_le_bytes();
+        c[12..16].copy_from_slice(&cbytes);
+        assert_eq!(decompress(&c, TrailingDataPolicy::Error).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_trailing() {
+        let data = data();
+        let mut c = header(
+            FLAG_MEMCPYED,
+            2,
+            data.len(),
+            data.len(),
+            HEADER_LEN + data.len(),
+        );
+        c.extend_from_slice(&data);
+        c.push(0);
+        assert!(decompress(&c, TrailingDataPolicy::Error).is_err());
+        assert_eq!(decompress(&c, TrailingDataPolicy::Warn).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_block_shorter_than_type() {
+        let data = [1, 2, 3];
+        let mut c = header(FLAG_SHUFFLE, 4, data.len(), 4096, HEADER_LEN + 4 + 4 + 3);
+        c.extend_from_slice(&((HEADER_LEN + 4) as u32).to_le_bytes());
+        c.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        c.extend_from_slice(&data);
+        assert_eq!(decompress(&c, TrailingDataPolicy::Error).unwrap(), data);
+    }
+
+    #[test]
+    fn decode_split_partial_element_errors() {
+        // 257 bytes of a 2-byte type would be split, leaving a byte over
+        let data: Vec<u8> = (0..=255).chain([0]).collect();
+        let mut c = header(0, 2, data.len(), data.len(), 0);
+        c.extend_from_slice(&((HEADER_LEN + 4) as u32).to_le_bytes());
+        for split in data.chunks(128) {
+            c.extend_from_slice(&(split.len() as u32).to_le_bytes());
+            c.extend_from_slice(split);
+        }
+        let cbytes = (c.len() as u32).to_le_bytes();
+        c[12..16].copy_from_slice(&cbytes);
+        let err = decompress(&c, TrailingDataPolicy::Error).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn decode_oversized_header_errors() {
+        let max = u32::MAX as usize;
+        // more blocks than there are offsets in the container
+        let c = header(0, 1, max, 1, HEADER_LEN);
+        let err = decompress(&c, TrailingDataPolicy::Error).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // a single block larger than its stream could decompress to
+        let c = container(COMPCODE_LZ4 << 5, 1, max, &[0]);
+        let err = decompress(&c, TrailingDataPolicy::Error).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encode_unsupported() {
+        let mut buf = Vec::default();
+        let mut w = BloscCodec {
+            cname: Compressor::LZ4,
+            clevel: 5,
+            shuffle: ShuffleMode::None,
+            blocksize: 0,
+            typesize: None,
+        }
+        .encoder(&mut buf);
+        let err = w.write_all(&[1, 2, 3]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
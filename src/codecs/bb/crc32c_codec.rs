@@ -1,5 +1,5 @@
 use super::BBCodec;
-use crate::codecs::fwrite::FinalWrite;
+use crate::codecs::{fwrite::FinalWrite, DecodeOptions};
 use byteorder::{LittleEndian, WriteBytesExt};
 use crc32c::{crc32c, crc32c_append};
 use serde::{Deserialize, Serialize};
@@ -8,8 +8,8 @@ use std::io::{Read, Write};
 
 /// Read the entire stream as if it were a payload with a u32le crc32c checksum suffix.
 ///
-/// Return the payload, or an error if the checksum does not match.
-fn validate_crc32c<R: Read>(mut r: R) -> io::Result<Vec<u8>> {
+/// Return the payload, or an error if `verify` is true and the checksum does not match.
+fn read_crc32c<R: Read>(mut r: R, verify: bool) -> io::Result<Vec<u8>> {
     let mut buf = Vec::default();
     let end = r.read_to_end(&mut buf)?;
     if end < 4 {
@@ -24,6 +24,9 @@ fn validate_crc32c<R: Read>(mut r: R) -> io::Result<Vec<u8>> {
         buf.pop().unwrap(),
         buf.pop().unwrap(),
     ];
+    if !verify {
+        return Ok(buf);
+    }
     // big-endian because the pops above guarantee reverse order
     let expected = u32::from_be_bytes(suff_be);
     let actual = crc32c(&buf);
@@ -42,14 +45,28 @@ fn validate_crc32c<R: Read>(mut r: R) -> io::Result<Vec<u8>> {
 ///
 /// The payload is cached so that subsequent calls to `.read()` seem to progress through the wrapped reader normally.
 /// In practice, these subsequent reads are infallible.
+///
+/// If not `verify`ing, the checksum is discarded without being checked.
 struct Crc32cReader<R: Read> {
     r: R,
     content: Option<Cursor<Vec<u8>>>,
+    verify: bool,
 }
 
 impl<R: Read> Crc32cReader<R> {
     pub fn new(r: R) -> Self {
-        Self { r, content: None }
+        Self {
+            r,
+            content: None,
+            verify: true,
+        }
+    }
+
+    pub fn new_unverified(r: R) -> Self {
+        Self {
+            verify: false,
+            ..Self::new(r)
+        }
     }
 }
 
@@ -57,7 +74,7 @@ impl<R: Read> Read for Crc32cReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         if self.content.is_none() {
             // no reads have happened yet
-            let content = validate_crc32c(&mut self.r)?;
+            let content = read_crc32c(&mut self.r, self.verify)?;
             self.content = Some(Cursor::new(content));
         }
 
@@ -102,8 +119,14 @@ impl<W: Write> FinalWrite for Crc32cWriter<W> {
 pub struct Crc32cCodec {}
 
 impl BBCodec for Crc32cCodec {
-    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
-        Box::new(Crc32cReader::new(r))
+    /// The checksum is always the last 4 bytes of the stream,
+    /// so trailing data cannot be distinguished from the payload and will fail verification.
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
+        if options.verify_checksums {
+            Box::new(Crc32cReader::new(r))
+        } else {
+            Box::new(Crc32cReader::new_unverified(r))
+        }
     }
 
     fn encoder<'a, W: Write + 'a>(&self, w: W) -> Box<dyn FinalWrite + 'a> {
@@ -131,14 +154,14 @@ mod tests {
         buf.extend_from_slice(TEST_STRING);
         buf.extend_from_slice(&CHECKSUM);
 
-        let out = validate_crc32c(buf.as_slice()).unwrap();
+        let out = read_crc32c(buf.as_slice(), true).unwrap();
 
         assert_eq!(out, TEST_STRING);
 
         // change the checksum value
         let last = buf.pop().unwrap();
         buf.push(last.wrapping_add(1));
-        assert!(validate_crc32c(buf.as_slice()).is_err())
+        assert!(read_crc32c(buf.as_slice(), true).is_err())
     }
 
     #[test]
@@ -168,6 +191,18 @@ mod tests {
         assert!(res.is_err())
     }
 
+    #[test]
+    fn can_skip_verification() {
+        let mut buf = Vec::default();
+        buf.extend_from_slice(TEST_STRING);
+        buf.extend_from_slice(&[0, 0, 0, 0]);
+
+        let mut r = Crc32cReader::new_unverified(&buf[..]);
+        let mut out = Vec::default();
+        r.read_to_end(&mut out).unwrap();
+        assert_eq!(out, TEST_STRING);
+    }
+
     #[test]
     fn can_write() {
         let mut buf = Vec::<u8>::default();
@@ -1,12 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
+use std::io::{self, BufReader, Read, Write};
 use thiserror::Error;
 
-use flate2::read::GzDecoder;
+use flate2::bufread::GzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression as GzCompression;
 
-use crate::codecs::bb::BBCodec;
+use crate::codecs::{bb::BBCodec, DecodeOptions, TrailingDataPolicy};
 
 use crate::codecs::fwrite::{FinalWrite, FinalWriter};
 
@@ -114,6 +114,34 @@ impl Default for GzipCodec {
     }
 }
 
+/// Decodes a single gzip member, then applies the [TrailingDataPolicy] to anything after it.
+struct GzipReader<R: Read> {
+    decoder: GzDecoder<BufReader<R>>,
+    trailing_data: TrailingDataPolicy,
+    finished: bool,
+}
+
+impl<R: Read> GzipReader<R> {
+    fn new(r: R, trailing_data: TrailingDataPolicy) -> Self {
+        Self {
+            decoder: GzDecoder::new(BufReader::new(r)),
+            trailing_data,
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> Read for GzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.decoder.read(buf)?;
+        if n == 0 && !buf.is_empty() && !self.finished {
+            self.finished = true;
+            self.trailing_data.check(self.decoder.get_mut(), "gzip")?;
+        }
+        Ok(n)
+    }
+}
+
 impl BBCodec for GzipCodec {
     fn encoder<'a, W: Write + 'a>(&self, w: W) -> Box<dyn FinalWrite + 'a> {
         Box::new(FinalWriter(GzEncoder::new(
@@ -122,8 +150,8 @@ impl BBCodec for GzipCodec {
         )))
     }
 
-    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
-        Box::new(GzDecoder::new(r))
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
+        Box::new(GzipReader::new(r, options.trailing_data))
     }
 
     fn compute_encoded_size(&self, _input_size: Option<usize>) -> Option<usize> {
@@ -140,4 +168,40 @@ mod tests {
         let s = r#"{"level": 1}"#;
         let _codec: GzipCodec = serde_json::from_str(s).unwrap();
     }
+
+    fn encoded_with_trailing() -> Vec<u8> {
+        let mut buf = Vec::default();
+        let mut w = GzipCodec::default().encoder(&mut buf);
+        w.write_all(b"payload").unwrap();
+        w.finalize().unwrap();
+        drop(w);
+        buf.extend_from_slice(b"garbage");
+        buf
+    }
+
+    #[test]
+    fn trailing_data_error() {
+        let buf = encoded_with_trailing();
+        let mut out = Vec::default();
+        let err = GzipCodec::default()
+            .decoder(buf.as_slice())
+            .read_to_end(&mut out)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn trailing_data_warn() {
+        let buf = encoded_with_trailing();
+        let options = DecodeOptions {
+            trailing_data: TrailingDataPolicy::Warn,
+            ..Default::default()
+        };
+        let mut out = Vec::default();
+        GzipCodec::default()
+            .decoder_with(buf.as_slice(), &options)
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, b"payload");
+    }
 }
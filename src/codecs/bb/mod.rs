@@ -6,17 +6,32 @@ use crate::{variant_from_data, MaybeNdim};
 
 #[cfg(feature = "blosc")]
 pub mod blosc_codec;
+#[cfg(all(feature = "blosc_pure", not(feature = "blosc")))]
+pub mod blosc_pure_codec;
 #[cfg(feature = "gzip")]
 pub mod gzip_codec;
 
 pub mod crc32c_codec;
+pub mod padding_codec;
 
-use super::fwrite::{FinalWrite, FinalWriter};
+use super::{
+    fwrite::{FinalWrite, FinalWriter},
+    DecodeOptions,
+};
 
 /// Common interface for compressing writers and decompressing readers.
 pub trait BBCodec {
     /// Create a [Read]er which decodes data from the given [Read]er
-    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a>;
+    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
+        self.decoder_with(r, &Default::default())
+    }
+
+    /// Create a [Read]er which decodes data from the given [Read]er,
+    /// with options controlling how strictly the encoded data is validated.
+    ///
+    /// Codecs which can detect trailing data after their payload should apply the
+    /// [TrailingDataPolicy](super::TrailingDataPolicy) when they reach the end of their payload.
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a>;
 
     /// Create a [FinalWrite]r which encodes data and writes it to the given [Write]r.
     fn encoder<'a, W: Write + 'a>(&self, w: W) -> Box<dyn FinalWrite + 'a>;
@@ -31,10 +46,13 @@ pub trait BBCodec {
 pub enum BBCodecType {
     #[cfg(feature = "blosc")]
     Blosc(blosc_codec::BloscCodec),
+    #[cfg(all(feature = "blosc_pure", not(feature = "blosc")))]
+    Blosc(blosc_pure_codec::BloscCodec),
     #[cfg(feature = "gzip")]
     Gzip(gzip_codec::GzipCodec),
     // Option because configuration could be missing or null (there is nothing to configure)
     Crc32c(Option<crc32c_codec::Crc32cCodec>),
+    Padding(padding_codec::PaddingCodec),
 }
 
 impl MaybeNdim for BBCodecType {
@@ -49,20 +67,22 @@ impl BBCodec for BBCodecType {
             #[cfg(feature = "gzip")]
             Self::Gzip(c) => c.encoder(w),
 
-            #[cfg(feature = "blosc")]
+            #[cfg(any(feature = "blosc", feature = "blosc_pure"))]
             Self::Blosc(c) => c.encoder(w),
             Self::Crc32c(c) => c.unwrap_or_default().encoder(w),
+            Self::Padding(c) => c.encoder(w),
         }
     }
 
-    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
         match self {
             #[cfg(feature = "gzip")]
-            Self::Gzip(c) => c.decoder(r),
+            Self::Gzip(c) => c.decoder_with(r, options),
 
-            #[cfg(feature = "blosc")]
-            Self::Blosc(c) => c.decoder(r),
-            Self::Crc32c(c) => c.unwrap_or_default().decoder(r),
+            #[cfg(any(feature = "blosc", feature = "blosc_pure"))]
+            Self::Blosc(c) => c.decoder_with(r, options),
+            Self::Crc32c(c) => c.unwrap_or_default().decoder_with(r, options),
+            Self::Padding(c) => c.decoder_with(r, options),
         }
     }
 
@@ -71,9 +91,10 @@ impl BBCodec for BBCodecType {
             #[cfg(feature = "gzip")]
             Self::Gzip(c) => c.compute_encoded_size(input_size),
 
-            #[cfg(feature = "blosc")]
+            #[cfg(any(feature = "blosc", feature = "blosc_pure"))]
             Self::Blosc(c) => c.compute_encoded_size(input_size),
             Self::Crc32c(c) => c.unwrap_or_default().compute_encoded_size(input_size),
+            Self::Padding(c) => c.compute_encoded_size(input_size),
         }
     }
 }
@@ -98,19 +119,19 @@ impl BBCodec for &[BBCodecType] {
         out
     }
 
-    fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
         let mut it = self.iter().rev();
 
         let mut out;
 
         if let Some(c) = it.next() {
-            out = c.decoder(r);
+            out = c.decoder_with(r, options);
         } else {
             return Box::new(r);
         }
 
         for c in it {
-            out = c.decoder(out);
+            out = c.decoder_with(out, options);
         }
 
         out
@@ -125,12 +146,18 @@ impl BBCodec for &[BBCodecType] {
 #[cfg(feature = "gzip")]
 variant_from_data!(BBCodecType, Gzip, gzip_codec::GzipCodec);
 
+variant_from_data!(BBCodecType, Padding, padding_codec::PaddingCodec);
+
 #[cfg(feature = "blosc")]
 variant_from_data!(BBCodecType, Blosc, blosc_codec::BloscCodec);
 
+#[cfg(all(feature = "blosc_pure", not(feature = "blosc")))]
+variant_from_data!(BBCodecType, Blosc, blosc_pure_codec::BloscCodec);
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "gzip")]
     use gzip_codec::GzipLevel;
 
     #[cfg(feature = "gzip")]
@@ -0,0 +1,226 @@
+use super::BBCodec;
+use crate::codecs::{fwrite::FinalWrite, DecodeOptions, TrailingDataPolicy};
+use byteorder::{LittleEndian, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Cursor, Read, Write};
+
+const LEN_SIZE: usize = std::mem::size_of::<u32>();
+
+/// Read an entire padded envelope and return its payload.
+fn read_padded<R: Read>(
+    mut r: R,
+    size: usize,
+    trailing_data: TrailingDataPolicy,
+) -> io::Result<Vec<u8>> {
+    if size < LEN_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Padding envelope is too small",
+        ));
+    }
+    let mut buf = Vec::with_capacity(size);
+    (&mut r).take(size as u64).read_to_end(&mut buf)?;
+    if buf.len() < size {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Padded payload was shorter than the envelope size",
+        ));
+    }
+    trailing_data.check(r, "padding")?;
+
+    let mut len_bytes = [0; LEN_SIZE];
+    len_bytes.copy_from_slice(&buf[size - LEN_SIZE..]);
+    let len = u32::from_le_bytes(len_bytes) as usize;
+    if len > size - LEN_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Padded payload length exceeds envelope size",
+        ));
+    }
+    buf.truncate(len);
+    Ok(buf)
+}
+
+/// [Read]er wrapper which, on first call to `.read()`, reads the entire envelope
+/// and then yields only the payload.
+struct PaddingReader<R: Read> {
+    r: R,
+    size: usize,
+    trailing_data: TrailingDataPolicy,
+    content: Option<Cursor<Vec<u8>>>,
+}
+
+impl<R: Read> Read for PaddingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.content.is_none() {
+            let content = read_padded(&mut self.r, self.size, self.trailing_data)?;
+            self.content = Some(Cursor::new(content));
+        }
+        self.content.as_mut().unwrap().read(buf)
+    }
+}
+
+struct PaddingWriter<W: Write> {
+    w: W,
+    size: usize,
+    written: usize,
+    finished: bool,
+}
+
+impl<W: Write> Write for PaddingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() > self.size.saturating_sub(LEN_SIZE) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Encoded chunk does not fit in padding envelope",
+            ));
+        }
+        let n = self.w.write(buf)?;
+        self.written += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+
+impl<W: Write> FinalWrite for PaddingWriter<W> {
+    fn finalize(&mut self) -> io::Result<usize> {
+        if self.finished {
+            return Ok(0);
+        }
+        let padding = self
+            .size
+            .checked_sub(LEN_SIZE + self.written)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Padding envelope is too small")
+            })?;
+        io::copy(&mut io::repeat(0).take(padding as u64), &mut self.w)?;
+        self.w.write_u32::<LittleEndian>(self.written as u32)?;
+        self.finished = true;
+        Ok(padding + LEN_SIZE)
+    }
+}
+
+/// Codec which pads each encoded payload to a constant size,
+/// so that chunks can be addressed by offset and updated in place.
+///
+/// The envelope is the payload, followed by zeros,
+/// followed by the payload length as a little-endian u32 (4 bytes);
+/// `size` is the total length of the envelope.
+/// Encoding fails if the payload is larger than `size - 4` bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
+#[serde(try_from = "PaddingConfig")]
+pub struct PaddingCodec {
+    pub size: usize,
+}
+
+/// Unvalidated configuration of a [PaddingCodec].
+#[derive(Deserialize)]
+struct PaddingConfig {
+    size: usize,
+}
+
+impl TryFrom<PaddingConfig> for PaddingCodec {
+    type Error = &'static str;
+
+    fn try_from(value: PaddingConfig) -> Result<Self, Self::Error> {
+        Self::new(value.size)
+    }
+}
+
+impl PaddingCodec {
+    pub fn new(size: usize) -> Result<Self, &'static str> {
+        if size < LEN_SIZE || size - LEN_SIZE > u32::MAX as usize {
+            return Err("Padding size must be between 4 bytes and 4GiB");
+        }
+        Ok(Self { size })
+    }
+}
+
+impl BBCodec for PaddingCodec {
+    fn decoder_with<'a, R: Read + 'a>(&self, r: R, options: &DecodeOptions) -> Box<dyn Read + 'a> {
+        Box::new(PaddingReader {
+            r,
+            size: self.size,
+            trailing_data: options.trailing_data,
+            content: None,
+        })
+    }
+
+    fn encoder<'a, W: Write + 'a>(&self, w: W) -> Box<dyn FinalWrite + 'a> {
+        Box::new(PaddingWriter {
+            w,
+            size: self.size,
+            written: 0,
+            finished: false,
+        })
+    }
+
+    fn compute_encoded_size(&self, _input_size: Option<usize>) -> Option<usize> {
+        Some(self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &[u8] = b"some payload";
+
+    fn encode(codec: &PaddingCodec, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::default();
+        {
+            let mut w = codec.encoder(&mut buf);
+            w.write_all(payload)?;
+            w.finalize()?;
+        }
+        Ok(buf)
+    }
+
+    #[test]
+    fn roundtrip() {
+        let codec = PaddingCodec::new(32).unwrap();
+        let buf = encode(&codec, PAYLOAD).unwrap();
+        assert_eq!(buf.len(), 32);
+        assert_eq!(codec.compute_encoded_size(None), Some(32));
+
+        let mut out = Vec::default();
+        codec.decoder(buf.as_slice()).read_to_end(&mut out).unwrap();
+        assert_eq!(out, PAYLOAD);
+    }
+
+    #[test]
+    fn too_large() {
+        let codec = PaddingCodec::new(PAYLOAD.len() + 3).unwrap();
+        assert!(encode(&codec, PAYLOAD).is_err());
+        let codec = PaddingCodec::new(PAYLOAD.len() + 4).unwrap();
+        assert_eq!(encode(&codec, PAYLOAD).unwrap().len(), PAYLOAD.len() + 4);
+    }
+
+    #[test]
+    fn trailing_and_truncated() {
+        let codec = PaddingCodec::new(32).unwrap();
+        let mut buf = encode(&codec, PAYLOAD).unwrap();
+        buf.push(0);
+        let mut out = Vec::default();
+        assert!(codec.decoder(buf.as_slice()).read_to_end(&mut out).is_err());
+
+        buf.truncate(31);
+        assert!(codec.decoder(buf.as_slice()).read_to_end(&mut out).is_err());
+    }
+
+    #[test]
+    fn deser() {
+        let s = r#"{"name": "padding", "configuration": {"size": 1024}}"#;
+        let codec: crate::codecs::bb::BBCodecType = serde_json::from_str(s).unwrap();
+        assert_eq!(
+            codec,
+            crate::codecs::bb::BBCodecType::Padding(PaddingCodec::new(1024).unwrap())
+        );
+
+        let s = r#"{"name": "padding", "configuration": {"size": 2}}"#;
+        assert!(serde_json::from_str::<crate::codecs::bb::BBCodecType>(s).is_err());
+    }
+}
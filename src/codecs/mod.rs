@@ -1,8 +1,10 @@
 use std::{
     collections::HashSet,
-    io::{Read, Write},
+    io::{self, Read, Write},
 };
 
+use log::warn;
+
 use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 
@@ -18,9 +20,59 @@ pub(super) mod fwrite;
 
 use crate::{
     data_type::{DataType, NBytes, ReflectedType},
+    util::must_understand,
     ArcArrayD, GridCoord, MaybeNdim,
 };
 
+/// What to do when stored data continues after the payload expected by a codec.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TrailingDataPolicy {
+    /// Fail with an [io::ErrorKind::InvalidData] error.
+    #[default]
+    Error,
+    /// Log a warning and ignore the trailing data.
+    Warn,
+}
+
+impl TrailingDataPolicy {
+    /// Apply the policy if the given reader, which should have been exhausted, still has data.
+    pub(crate) fn check<R: Read>(&self, mut r: R, codec: &'static str) -> io::Result<()> {
+        let mut buf = [0u8; 1];
+        if r.read(&mut buf)? == 0 {
+            return Ok(());
+        }
+        match self {
+            Self::Error => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Trailing data after {codec} payload"),
+            )),
+            Self::Warn => {
+                warn!("Ignoring trailing data after {codec} payload");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Options controlling how strictly encoded data is validated while decoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Whether to verify checksums, e.g. from the `crc32c` codec.
+    ///
+    /// Should only be disabled for performance-critical reads of trusted data.
+    pub verify_checksums: bool,
+    pub trailing_data: TrailingDataPolicy,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            verify_checksums: true,
+            trailing_data: Default::default(),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct CodecChain {
     pub aa_codecs: Vec<AACodecType>,
@@ -63,6 +115,39 @@ impl CodecChain {
         self.len() == 0
     }
 
+    /// As [ABCodec::decode], with options controlling validation of the encoded data.
+    ///
+    /// Trailing data is detected for bytes->bytes codecs which support it,
+    /// and for array->bytes codecs whose encoded size is known.
+    pub fn decode_with<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
+        let ab_repr = self
+            .aa_codecs
+            .as_slice()
+            .compute_encoded_representation_type(decoded_repr);
+        let mut bb_r = self.bb_codecs.as_slice().decoder_with(r, options);
+        let arr = match self.ab_codec.compute_encoded_size(ab_repr.clone()) {
+            Some(n) => {
+                // limit the array->bytes codec's reads so that trailing data can be checked
+                let arr = self.ab_codec().decode_with::<T, _>(
+                    (&mut bb_r).take(n as u64),
+                    ab_repr,
+                    options,
+                )?;
+                options.trailing_data.check(bb_r, "array->bytes")?;
+                arr
+            }
+            None => self
+                .ab_codec()
+                .decode_with::<T, _>(bb_r, ab_repr, options)?,
+        };
+        Ok(self.aa_codecs.as_slice().decode(arr))
+    }
+
     pub fn len(&self) -> usize {
         self.aa_codecs.len() + self.bb_codecs.len() + 1
     }
@@ -126,12 +211,30 @@ impl Serialize for CodecChain {
     }
 }
 
+/// Codecs which are not supported by this crate are skipped (with a warning)
+/// if they are marked `"must_understand": false`.
 impl<'de> Deserialize<'de> for CodecChain {
     fn deserialize<D>(deserializer: D) -> Result<CodecChain, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let codecs: Vec<CodecType> = Vec::<CodecType>::deserialize(deserializer)?;
+        let values = Vec::<serde_json::Value>::deserialize(deserializer)?;
+        let mut codecs = Vec::with_capacity(values.len());
+        for value in values {
+            match parse_codec(&value).map_err(de::Error::custom)? {
+                Some(c) => codecs.push(c),
+                None if must_understand(&value) => {
+                    return Err(de::Error::custom(format!(
+                        "Unsupported codec {}",
+                        value.get("name").unwrap_or(&value)
+                    )))
+                }
+                None => warn!(
+                    "Ignoring unsupported codec {} which does not need to be understood",
+                    value.get("name").unwrap_or(&value)
+                ),
+            }
+        }
         let chain = codecs
             .into_iter()
             .collect::<Result<CodecChain, CodecChainConstructionError>>()
@@ -141,21 +244,40 @@ impl<'de> Deserialize<'de> for CodecChain {
 }
 
 impl ABCodec for CodecChain {
-    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) {
+    fn encode<T: ReflectedType, W: Write>(&self, decoded: ArcArrayD<T>, w: W) -> io::Result<()> {
+        self.encode_with_fill(decoded, T::default(), w)
+    }
+
+    fn encode_with_fill<T: ReflectedType, W: Write>(
+        &self,
+        decoded: ArcArrayD<T>,
+        fill_value: T,
+        w: W,
+    ) -> io::Result<()> {
         let mut bb_w = self.bb_codecs.as_slice().encoder(w);
         let arr = self.aa_codecs.as_slice().encode(decoded);
-        self.ab_codec().encode::<T, _>(arr, &mut bb_w);
-        bb_w.finalize().unwrap();
+        self.ab_codec()
+            .encode_with_fill::<T, _>(arr, fill_value, &mut bb_w)?;
+        bb_w.finalize()?;
+        Ok(())
     }
 
-    fn decode<T: ReflectedType, R: Read>(&self, r: R, decoded_repr: ArrayRepr<T>) -> ArcArrayD<T> {
-        let ab_repr = self
-            .aa_codecs
-            .as_slice()
-            .compute_encoded_representation_type(decoded_repr);
-        let bb_r = self.bb_codecs.as_slice().decoder(r);
-        let arr = self.ab_codec().decode::<T, _>(bb_r, ab_repr);
-        self.aa_codecs.as_slice().decode(arr)
+    /// As [CodecChain::decode_with] with the default (strict) [DecodeOptions].
+    fn decode<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+    ) -> io::Result<ArcArrayD<T>> {
+        self.decode_with(r, decoded_repr, &Default::default())
+    }
+
+    fn decode_with<T: ReflectedType, R: Read>(
+        &self,
+        r: R,
+        decoded_repr: ArrayRepr<T>,
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
+        CodecChain::decode_with(self, r, decoded_repr, options)
     }
 
     fn endian(&self) -> Option<ab::bytes_codec::Endian> {
@@ -170,6 +292,7 @@ impl ABCodec for CodecChain {
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum CodecChainConstructionError {
     #[error("More than one array->bytes codec found")]
     MultipleAB,
@@ -217,6 +340,38 @@ impl FromIterator<CodecType> for Result<CodecChain, CodecChainConstructionError>
     }
 }
 
+/// Names of the array->array codecs supported by this crate.
+const AA_CODEC_NAMES: &[&str] = &["transpose"];
+/// Names of the array->bytes codecs supported by this crate.
+const AB_CODEC_NAMES: &[&str] = &["bytes", "sharding_indexed"];
+/// Names of the bytes->bytes codecs supported by this crate, with its enabled features.
+const BB_CODEC_NAMES: &[&str] = &[
+    #[cfg(any(feature = "blosc", feature = "blosc_pure"))]
+    "blosc",
+    #[cfg(feature = "gzip")]
+    "gzip",
+    "crc32c",
+    "padding",
+];
+
+/// `Ok(None)` if the codec's name is not supported;
+/// `Err` if the name is supported but the configuration is invalid.
+fn parse_codec(value: &serde_json::Value) -> Result<Option<CodecType>, serde_json::Error> {
+    let Some(name) = value.get("name").and_then(serde_json::Value::as_str) else {
+        return Err(de::Error::missing_field("name"));
+    };
+    let codec = if AA_CODEC_NAMES.contains(&name) {
+        CodecType::AA(serde_json::from_value(value.clone())?)
+    } else if AB_CODEC_NAMES.contains(&name) {
+        CodecType::AB(serde_json::from_value(value.clone())?)
+    } else if BB_CODEC_NAMES.contains(&name) {
+        CodecType::BB(serde_json::from_value(value.clone())?)
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(codec))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[enum_delegate::implement(MaybeNdim)]
 #[serde(untagged)]
@@ -266,8 +421,8 @@ impl<T: ReflectedType> From<GridCoord> for ArrayRepr<T> {
 
 #[cfg(test)]
 mod tests {
-    use crate::codecs::ab::bytes_codec::BytesCodec;
-    use crate::codecs::bb::gzip_codec::GzipCodec;
+    #[cfg(feature = "gzip")]
+    use crate::codecs::{ab::bytes_codec::BytesCodec, bb::gzip_codec::GzipCodec};
     use crate::ArcArrayD;
 
     use super::*;
@@ -284,7 +439,7 @@ mod tests {
         let chain = CodecChain::default();
         let mut buf: Vec<u8> = Vec::default();
 
-        chain.encode(arr.clone(), &mut buf);
+        chain.encode(arr.clone(), &mut buf).unwrap();
         assert_ne!(buf.len(), 0);
 
         let repr = ArrayRepr {
@@ -292,7 +447,7 @@ mod tests {
             fill_value: 0.0f64,
         };
 
-        let arr2 = chain.decode::<f64, _>(buf.as_slice(), repr);
+        let arr2 = chain.decode::<f64, _>(buf.as_slice(), repr).unwrap();
 
         assert_eq!(&arr, &arr2);
     }
@@ -317,7 +472,7 @@ mod tests {
         );
         let mut buf: Vec<u8> = Vec::default();
 
-        chain.encode(arr.clone(), &mut buf);
+        chain.encode(arr.clone(), &mut buf).unwrap();
         assert_ne!(buf.len(), 0);
 
         let repr = ArrayRepr {
@@ -325,8 +480,74 @@ mod tests {
             fill_value: 0.0f64,
         };
 
-        let arr2 = chain.decode::<f64, _>(buf.as_slice(), repr);
+        let arr2 = chain.decode::<f64, _>(buf.as_slice(), repr).unwrap();
+
+        assert_eq!(&arr, &arr2);
+    }
 
+    #[test]
+    fn parse_codec_names() {
+        let parse = |v: serde_json::Value| parse_codec(&v);
+
+        assert!(matches!(
+            parse(serde_json::json!({"name": "bytes", "configuration": {"endian": "big"}})),
+            Ok(Some(CodecType::AB(_)))
+        ));
+        assert!(matches!(
+            parse(serde_json::json!({"name": "crc32c"})),
+            Ok(Some(CodecType::BB(_)))
+        ));
+        assert!(matches!(
+            parse(serde_json::json!({"name": "future_codec", "configuration": {}})),
+            Ok(None)
+        ));
+        // a known codec with a bad configuration is not treated as unknown
+        assert!(parse(serde_json::json!({
+            "name": "bytes",
+            "configuration": {"endian": "middle"},
+            "must_understand": false
+        }))
+        .is_err());
+        assert!(parse(serde_json::json!({"configuration": {}})).is_err());
+
+        let chain: Result<CodecChain, _> = serde_json::from_value(serde_json::json!([
+            {"name": "bytes"},
+            {"name": "padding", "configuration": {"size": 2}, "must_understand": false}
+        ]));
+        assert!(chain.is_err());
+    }
+
+    #[test]
+    fn array_trailing_data() {
+        let arr = make_arr();
+        let chain = CodecChain::default();
+        let mut buf: Vec<u8> = Vec::default();
+        chain.encode(arr.clone(), &mut buf).unwrap();
+        buf.extend_from_slice(b"extra");
+
+        let repr = ArrayRepr {
+            shape: SHAPE.iter().map(|s| *s as u64).collect(),
+            fill_value: 0.0f64,
+        };
+
+        let err = chain
+            .decode_with(buf.as_slice(), repr.clone(), &DecodeOptions::default())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = chain.decode(buf.as_slice(), repr.clone()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = chain
+            .decode(&buf[..buf.len() - 20], repr.clone())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let options = DecodeOptions {
+            trailing_data: TrailingDataPolicy::Warn,
+            ..Default::default()
+        };
+        let arr2 = chain
+            .decode_with::<f64, _>(buf.as_slice(), repr, &options)
+            .unwrap();
         assert_eq!(&arr, &arr2);
     }
 }
@@ -0,0 +1,109 @@
+//! Bulk conversion between arrays of primitives and their encoded bytes.
+//!
+//! Values are read and written directly from/to the array's memory,
+//! and non-native-endian data is byte-swapped in place in a single pass over an integer view,
+//! which the compiler vectorises (e.g. to `pshufb`/`rev` on x86/ARM).
+use std::io::{self, Read, Write};
+
+use bytemuck::Pod;
+
+use crate::{
+    codecs::ab::bytes_codec::{Endian, NATIVE_ENDIAN},
+    ArcArrayD,
+};
+
+/// Unsigned integers whose bytes can be reversed, used as a view of same-sized primitives.
+pub(crate) trait SwapBytes: Pod {
+    fn swap(self) -> Self;
+}
+
+macro_rules! swap_bytes {
+    ($($t:ty),*) => {
+        $(
+            impl SwapBytes for $t {
+                #[inline(always)]
+                fn swap(self) -> Self {
+                    self.swap_bytes()
+                }
+            }
+        )*
+    };
+}
+
+swap_bytes!(u16, u32, u64);
+
+/// Reverse the bytes of every value in place.
+pub(crate) fn swap_all<U: SwapBytes>(values: &mut [U]) {
+    for v in values.iter_mut() {
+        *v = v.swap();
+    }
+}
+
+/// Read an array of `T`, which is the same size as `U`, from bytes in the given endianness.
+pub(crate) fn read_pod_array<T: Pod, U: SwapBytes, R: Read>(
+    mut r: R,
+    endian: Endian,
+    shape: &[usize],
+) -> io::Result<ArcArrayD<T>> {
+    let numel = shape.iter().product();
+    let mut data = vec![T::zeroed(); numel];
+    r.read_exact(bytemuck::cast_slice_mut(data.as_mut_slice()))?;
+    if endian != NATIVE_ENDIAN {
+        swap_all::<U>(bytemuck::cast_slice_mut(data.as_mut_slice()));
+    }
+    ArcArrayD::from_shape_vec(shape.to_vec(), data)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+/// Write an array of `T`, which is the same size as `U`, as bytes in the given endianness (in C order).
+pub(crate) fn write_pod_array<T: Pod, U: SwapBytes, W: Write>(
+    array: ArcArrayD<T>,
+    mut w: W,
+    endian: Endian,
+) -> io::Result<()> {
+    let mut data = match array.as_slice() {
+        Some(sl) if endian == NATIVE_ENDIAN => return w.write_all(bytemuck::cast_slice(sl)),
+        Some(sl) => sl.to_vec(),
+        None => array.iter().cloned().collect(),
+    };
+    if endian != NATIVE_ENDIAN {
+        swap_all::<U>(bytemuck::cast_slice_mut(data.as_mut_slice()));
+    }
+    w.write_all(bytemuck::cast_slice(data.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pod_roundtrip() {
+        let bytes = [0x01, 0x02, 0x03, 0x04];
+        let be = read_pod_array::<u16, u16, _>(&bytes[..], Endian::Big, &[2]).unwrap();
+        assert_eq!(be.as_slice().unwrap(), &[0x0102, 0x0304]);
+        let le = read_pod_array::<u16, u16, _>(&bytes[..], Endian::Little, &[2]).unwrap();
+        assert_eq!(le.as_slice().unwrap(), &[0x0201, 0x0403]);
+
+        let vals = ArcArrayD::from_shape_vec(vec![2, 2], vec![1.5f32, -2.0, 0.25, 8.0]).unwrap();
+        for endian in [Endian::Big, Endian::Little] {
+            let mut buf = Vec::default();
+            write_pod_array::<f32, u32, _>(vals.clone(), &mut buf, endian).unwrap();
+            let expected: Vec<u8> = vals
+                .iter()
+                .flat_map(|v| match endian {
+                    Endian::Big => v.to_be_bytes(),
+                    Endian::Little => v.to_le_bytes(),
+                })
+                .collect();
+            assert_eq!(buf, expected);
+
+            // non-contiguous input is written in C order
+            let mut buf_t = Vec::default();
+            write_pod_array::<f32, u32, _>(vals.t().to_shared(), &mut buf_t, endian).unwrap();
+            let back = read_pod_array::<f32, u32, _>(buf_t.as_slice(), endian, &[2, 2]).unwrap();
+            assert_eq!(back, vals.t());
+        }
+
+        assert!(read_pod_array::<u16, u16, _>(&bytes[..3], Endian::Big, &[2]).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
@@ -48,66 +48,46 @@ impl NBytes for ComplexSize {
     }
 }
 
-impl ReflectedType for c64 {
-    const ZARR_TYPE: DataType = DataType::Complex(ComplexSize::b64);
+macro_rules! reflected_complex {
+    ($d_type:ty, $d_size:expr, $part_nbytes:expr, $bo_read_fn:ident, $bo_write_fn:ident) => {
+        impl ReflectedType for $d_type {
+            const ZARR_TYPE: DataType = DataType::Complex($d_size);
 
-    fn encoder(endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
-        Box::new(match endian {
-            Endian::Big => |v: Self, mut buf: &mut [u8]| {
-                buf.write_f32::<BigEndian>(v.re).unwrap();
-                buf.write_f32::<BigEndian>(v.im).unwrap();
-            },
-            Endian::Little => |v, mut buf| {
-                buf.write_f32::<LittleEndian>(v.re).unwrap();
-                buf.write_f32::<LittleEndian>(v.im).unwrap();
-            },
-        })
-    }
+            fn bitwise_eq(&self, other: &Self) -> bool {
+                self.re.to_bits() == other.re.to_bits() && self.im.to_bits() == other.im.to_bits()
+            }
 
-    fn decoder(endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
-        Box::new(match endian {
-            Endian::Big => |buf| {
-                let re = BigEndian::read_f32(buf);
-                let im = BigEndian::read_f32(buf);
-                Self::new(re, im)
-            },
-            Endian::Little => |buf| {
-                let re = LittleEndian::read_f32(buf);
-                let im = LittleEndian::read_f32(buf);
-                Self::new(re, im)
-            },
-        })
-    }
-}
+            /// Writes the real part, then the imaginary part.
+            #[inline]
+            fn encode_into(self, buf: &mut [u8], endian: Endian) {
+                let (re, im) = buf.split_at_mut($part_nbytes);
+                match endian {
+                    Endian::Big => {
+                        BigEndian::$bo_write_fn(re, self.re);
+                        BigEndian::$bo_write_fn(im, self.im);
+                    }
+                    Endian::Little => {
+                        LittleEndian::$bo_write_fn(re, self.re);
+                        LittleEndian::$bo_write_fn(im, self.im);
+                    }
+                }
+            }
 
-impl ReflectedType for c128 {
-    const ZARR_TYPE: DataType = DataType::Complex(ComplexSize::b64);
-
-    fn encoder(endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
-        Box::new(match endian {
-            Endian::Big => |v: Self, mut buf: &mut [u8]| {
-                buf.write_f64::<BigEndian>(v.re).unwrap();
-                buf.write_f64::<BigEndian>(v.im).unwrap();
-            },
-            Endian::Little => |v, mut buf| {
-                buf.write_f64::<LittleEndian>(v.re).unwrap();
-                buf.write_f64::<LittleEndian>(v.im).unwrap();
-            },
-        })
-    }
-
-    fn decoder(endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
-        Box::new(match endian {
-            Endian::Big => |buf| {
-                let re = BigEndian::read_f64(buf);
-                let im = BigEndian::read_f64(buf);
-                Self::new(re, im)
-            },
-            Endian::Little => |buf| {
-                let re = LittleEndian::read_f64(buf);
-                let im = LittleEndian::read_f64(buf);
-                Self::new(re, im)
-            },
-        })
-    }
+            #[inline]
+            fn decode_from(buf: &[u8], endian: Endian) -> Self {
+                let (re, im) = buf.split_at($part_nbytes);
+                match endian {
+                    Endian::Big => {
+                        Self::new(BigEndian::$bo_read_fn(re), BigEndian::$bo_read_fn(im))
+                    }
+                    Endian::Little => {
+                        Self::new(LittleEndian::$bo_read_fn(re), LittleEndian::$bo_read_fn(im))
+                    }
+                }
+            }
+        }
+    };
 }
+
+reflected_complex!(c64, ComplexSize::b64, 4, read_f32, write_f32);
+reflected_complex!(c128, ComplexSize::b128, 8, read_f64, write_f64);
@@ -1,12 +1,11 @@
 use std::fmt::Display;
 
-use byteorder::WriteBytesExt;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 use crate::codecs::ab::bytes_codec::Endian;
 
-use super::{DataType, NBytes, ReflectedType};
+use super::{partial_min_max, DataType, NBytes, ReflectedType};
 
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
@@ -52,24 +51,35 @@ impl NBytes for IntSize {
 impl ReflectedType for u8 {
     const ZARR_TYPE: DataType = DataType::UInt(IntSize::b8);
 
-    fn encoder(_endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
-        Box::new(|v: Self, buf: &mut [u8]| buf[0] = v)
+    #[inline]
+    fn encode_into(self, buf: &mut [u8], _endian: Endian) {
+        buf[0] = self;
     }
 
-    fn decoder(_endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
-        Box::new(|buf: &mut [u8]| buf[0])
+    #[inline]
+    fn decode_from(buf: &[u8], _endian: Endian) -> Self {
+        buf[0]
+    }
+
+    fn min_max<'a, I: IntoIterator<Item = &'a Self>>(values: I) -> Option<(Self, Self)> {
+        partial_min_max(values)
     }
 }
 
 impl ReflectedType for i8 {
     const ZARR_TYPE: DataType = DataType::UInt(IntSize::b8);
 
-    fn encoder(_endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
-        Box::new(|v: Self, mut buf: &mut [u8]| buf.write_i8(v).unwrap())
+    #[inline]
+    fn encode_into(self, buf: &mut [u8], _endian: Endian) {
+        buf[0] = self.to_le_bytes()[0];
+    }
+
+    #[inline]
+    fn decode_from(buf: &[u8], _endian: Endian) -> Self {
+        Self::from_le_bytes([buf[0]])
     }
 
-    fn decoder(_endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
-        // todo: kludge to get type bounds to work, should be a better way
-        Box::new(|buf: &mut [u8]| Self::from_le_bytes([buf[0]]))
+    fn min_max<'a, I: IntoIterator<Item = &'a Self>>(values: I) -> Option<(Self, Self)> {
+        partial_min_max(values)
     }
 }
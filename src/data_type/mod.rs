@@ -1,6 +1,6 @@
 use std::{
     fmt::{Debug, Display},
-    io::{self, BufReader, BufWriter, Read, Write},
+    io::{self, Read, Write},
     str::FromStr,
 };
 
@@ -8,6 +8,7 @@ use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::serde_as;
 
 use crate::{codecs::ab::bytes_codec::Endian, ArcArrayD};
+mod byteswap;
 mod complex;
 mod raw;
 
@@ -127,23 +128,19 @@ impl DataType {
                 }
             },
             DataType::Float(s) => match s {
-                // todo: check for NaN, +-Inf
-                FloatSize::b32 => {
-                    serde_json::from_value::<f32>(v)?;
-                }
-                FloatSize::b64 => {
-                    serde_json::from_value::<f64>(v)?;
-                }
+                FloatSize::b32 => validate_float_json_value(&v, 4)?,
+                FloatSize::b64 => validate_float_json_value(&v, 8)?,
             },
-            DataType::Complex(s) => match s {
-                // todo: check for NaN, +-Inf
-                ComplexSize::b64 => {
-                    serde_json::from_value::<c64>(v)?;
+            DataType::Complex(s) => {
+                let nbytes = match s {
+                    ComplexSize::b64 => 4,
+                    ComplexSize::b128 => 8,
+                };
+                let parts = serde_json::from_value::<[serde_json::Value; 2]>(v)?;
+                for p in parts.iter() {
+                    validate_float_json_value(p, nbytes)?;
                 }
-                ComplexSize::b128 => {
-                    serde_json::from_value::<c128>(v)?;
-                }
-            },
+            }
             DataType::Raw(s) => {
                 let b = serde_json::from_value::<Vec<u8>>(v)?;
                 if b.len() != *s {
@@ -155,6 +152,37 @@ impl DataType {
     }
 }
 
+/// Check a JSON float fill value (or one component of a complex fill value) of the given byte width.
+///
+/// As well as numbers, the spec allows the strings `"NaN"`, `"Infinity"` and `"-Infinity"`,
+/// and the raw bytes as a `"0x"`-prefixed big-endian hex string.
+fn validate_float_json_value(
+    value: &serde_json::Value,
+    nbytes: usize,
+) -> Result<(), serde_json::Error> {
+    match value {
+        serde_json::Value::Number(_) => Ok(()),
+        serde_json::Value::String(s) => match s.as_str() {
+            "NaN" | "Infinity" | "-Infinity" => Ok(()),
+            _ => match s.strip_prefix("0x") {
+                Some(hex)
+                    if hex.len() == nbytes * 2 && hex.chars().all(|c| c.is_ascii_hexdigit()) =>
+                {
+                    Ok(())
+                }
+                _ => Err(de::Error::invalid_value(
+                    de::Unexpected::Str(s),
+                    &"a number, \"NaN\", \"Infinity\", \"-Infinity\" or a hex-encoded float",
+                )),
+            },
+        },
+        _ => Err(de::Error::invalid_type(
+            de::Unexpected::Other("non-numeric JSON value"),
+            &"a float",
+        )),
+    }
+}
+
 // todo: as extension dtypes are added, we can either separate by
 // known/unknown or core/ extension.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -251,7 +279,7 @@ impl FromStr for DataType {
                 _ => Err("Unknown data type"),
             }
         } else if s == "bool" {
-            return Ok(Self::Bool);
+            Ok(Self::Bool)
         } else {
             Err("Could not parse data type")
         }
@@ -260,9 +288,6 @@ impl FromStr for DataType {
     type Err = &'static str;
 }
 
-type PrimitiveEncoder<T> = Box<dyn Fn(T, &mut [u8])>;
-type PrimitiveDecoder<T> = Box<dyn Fn(&mut [u8]) -> T>;
-
 /// Trait implemented by primitive types that are reflected in Zarr.
 ///
 /// The supertraits are not necessary for this trait, but are used to
@@ -285,44 +310,60 @@ pub trait ReflectedType:
 {
     const ZARR_TYPE: DataType;
 
-    // this is to avoid a match in a hot loop but the Box deref might be slower anyway?
-    /// Produce a routine which writes the bytes of a self-typed value
-    /// into the given buffer.
-    fn encoder(endian: Endian) -> PrimitiveEncoder<Self>;
-
-    /// Produce a routine which reads a self-typed value from
-    /// the given byte buffer.
-    fn decoder(endian: Endian) -> PrimitiveDecoder<Self>;
-
-    // todo: replace array reading/writing with these
-    // use bufreader & bufwriter, read however many bytes we need for a single item, use std (to|from)_[lb]e_bytes
-    fn write_array_to<W: Write>(array: ArcArrayD<Self>, w: W, endian: Endian) -> io::Result<()> {
-        let mut bw = BufWriter::new(w);
-        let mut buf = vec![0u8; Self::ZARR_TYPE.nbytes()];
-        let encoder = Self::encoder(endian);
-
-        for val in array.into_iter() {
-            encoder(val, buf.as_mut());
-            bw.write_all(buf.as_mut()).unwrap();
-        }
-        bw.flush()
-    }
+    /// Write the bytes of the value into the start of the given buffer,
+    /// which must be at least `nbytes` long.
+    fn encode_into(self, buf: &mut [u8], endian: Endian);
 
-    fn read_array_from<R: Read>(r: R, endian: Endian, shape: &[usize]) -> ArcArrayD<Self> {
-        let mut br = BufReader::new(r);
-        let mut buf = vec![0u8; Self::ZARR_TYPE.nbytes()];
-        let decoder = Self::decoder(endian);
+    /// Read a value from the start of the given buffer,
+    /// which must be at least `nbytes` long.
+    fn decode_from(buf: &[u8], endian: Endian) -> Self;
 
-        let numel = shape.iter().cloned().reduce(|a, b| a * b).unwrap_or(1);
+    /// Whether the values have identical bit patterns,
+    /// e.g. so that a NaN fill value matches NaN values, and `-0.0` does not match `0.0`.
+    ///
+    /// By default, this is the same as [PartialEq].
+    fn bitwise_eq(&self, other: &Self) -> bool {
+        self == other
+    }
 
-        let mut data = Vec::with_capacity(numel);
+    /// Find the smallest and largest of the given values.
+    ///
+    /// `None` if there are no comparable values,
+    /// or if the type has no meaningful ordering (the default).
+    fn min_max<'a, I: IntoIterator<Item = &'a Self>>(values: I) -> Option<(Self, Self)> {
+        let _ = values;
+        None
+    }
 
-        for _ in 0..numel {
-            br.read_exact(buf.as_mut()).unwrap();
-            data.push(decoder(buf.as_mut()));
+    /// Write the values in C order.
+    fn write_array_to<W: Write>(
+        array: ArcArrayD<Self>,
+        mut w: W,
+        endian: Endian,
+    ) -> io::Result<()> {
+        let nbytes = Self::ZARR_TYPE.nbytes();
+        let mut buf = vec![0u8; array.len() * nbytes];
+        for (val, b) in array.iter().zip(buf.chunks_exact_mut(nbytes)) {
+            val.encode_into(b, endian);
         }
+        w.write_all(&buf)
+    }
 
-        ArcArrayD::from_shape_vec(shape.to_vec(), data).unwrap()
+    fn read_array_from<R: Read>(
+        mut r: R,
+        endian: Endian,
+        shape: &[usize],
+    ) -> io::Result<ArcArrayD<Self>> {
+        let nbytes = Self::ZARR_TYPE.nbytes();
+        let numel: usize = shape.iter().product();
+        let mut buf = vec![0u8; numel * nbytes];
+        r.read_exact(&mut buf)?;
+        let data = buf
+            .chunks_exact(nbytes)
+            .map(|b| Self::decode_from(b, endian))
+            .collect();
+        ArcArrayD::from_shape_vec(shape.to_vec(), data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
     }
 
     // fn create_data_chunk(grid_position: &GridCoord, num_el: u32) -> VecDataChunk<Self> {
@@ -333,58 +374,110 @@ pub trait ReflectedType:
     // }
 }
 
+/// Implementation of [ReflectedType::min_max] for ordered types.
+///
+/// Incomparable values (e.g. NaN) are skipped.
+pub(crate) fn partial_min_max<'a, T: PartialOrd + Copy + 'a, I: IntoIterator<Item = &'a T>>(
+    values: I,
+) -> Option<(T, T)> {
+    let mut it = values.into_iter().filter(|v| v.partial_cmp(v).is_some());
+    let first = *it.next()?;
+    Some(it.fold((first, first), |(min, max), v| {
+        (
+            if *v < min { *v } else { min },
+            if *v > max { *v } else { max },
+        )
+    }))
+}
+
 macro_rules! reflected_primitive {
-    ($d_name:expr, $d_type:ty, $bo_read_fn:ident, $bo_write_fn:ident) => {
+    ($d_name:expr, $d_type:ty, $swap_type:ty, $bo_read_fn:ident, $bo_write_fn:ident) => {
         impl ReflectedType for $d_type {
             const ZARR_TYPE: DataType = $d_name;
 
-            fn encoder(endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
+            #[inline]
+            fn encode_into(self, buf: &mut [u8], endian: Endian) {
                 use byteorder::ByteOrder;
-                Box::new(match endian {
-                    Endian::Big => {
-                        |v: Self, buf: &mut [u8]| byteorder::BigEndian::$bo_write_fn(buf, v)
-                    }
-                    Endian::Little => {
-                        |v: Self, buf: &mut [u8]| byteorder::LittleEndian::$bo_write_fn(buf, v)
-                    }
-                })
+                match endian {
+                    Endian::Big => byteorder::BigEndian::$bo_write_fn(buf, self),
+                    Endian::Little => byteorder::LittleEndian::$bo_write_fn(buf, self),
+                }
             }
 
-            /// Produce a routine which reads a self-typed value from
-            /// the given byte buffer.
-            fn decoder(endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
+            #[inline]
+            fn decode_from(buf: &[u8], endian: Endian) -> Self {
                 use byteorder::ByteOrder;
-                Box::new(match endian {
-                    Endian::Big => |buf: &mut [u8]| byteorder::BigEndian::$bo_read_fn(buf),
-                    Endian::Little => |buf: &mut [u8]| byteorder::LittleEndian::$bo_read_fn(buf),
-                })
+                match endian {
+                    Endian::Big => byteorder::BigEndian::$bo_read_fn(buf),
+                    Endian::Little => byteorder::LittleEndian::$bo_read_fn(buf),
+                }
+            }
+
+            fn bitwise_eq(&self, other: &Self) -> bool {
+                self.to_ne_bytes() == other.to_ne_bytes()
+            }
+
+            fn min_max<'a, I: IntoIterator<Item = &'a Self>>(values: I) -> Option<(Self, Self)> {
+                partial_min_max(values)
+            }
+
+            fn write_array_to<W: Write>(
+                array: ArcArrayD<Self>,
+                w: W,
+                endian: Endian,
+            ) -> io::Result<()> {
+                byteswap::write_pod_array::<Self, $swap_type, _>(array, w, endian)
+            }
+
+            fn read_array_from<R: Read>(
+                r: R,
+                endian: Endian,
+                shape: &[usize],
+            ) -> io::Result<ArcArrayD<Self>> {
+                byteswap::read_pod_array::<Self, $swap_type, _>(r, endian, shape)
             }
         }
     };
 }
 
-pub(crate) use reflected_primitive;
-
 impl ReflectedType for bool {
     const ZARR_TYPE: DataType = DataType::Bool;
 
-    fn encoder(_endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
-        Box::new(|v: Self, buf: &mut [u8]| buf[0] = if v { 1 } else { 0 })
+    #[inline]
+    fn encode_into(self, buf: &mut [u8], _endian: Endian) {
+        buf[0] = self as u8;
     }
 
-    fn decoder(_endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
-        Box::new(|buf: &mut [u8]| buf[0] != 0)
+    #[inline]
+    fn decode_from(buf: &[u8], _endian: Endian) -> Self {
+        buf[0] != 0
+    }
+
+    fn min_max<'a, I: IntoIterator<Item = &'a Self>>(values: I) -> Option<(Self, Self)> {
+        partial_min_max(values)
     }
 }
 
-reflected_primitive!(DataType::Float(FloatSize::b32), f32, read_f32, write_f32);
-reflected_primitive!(DataType::Float(FloatSize::b64), f64, read_f64, write_f64);
-reflected_primitive!(DataType::UInt(IntSize::b16), u16, read_u16, write_u16);
-reflected_primitive!(DataType::UInt(IntSize::b32), u32, read_u32, write_u32);
-reflected_primitive!(DataType::UInt(IntSize::b64), u64, read_u64, write_u64);
-reflected_primitive!(DataType::Int(IntSize::b16), i16, read_i16, write_i16);
-reflected_primitive!(DataType::Int(IntSize::b32), i32, read_i32, write_i32);
-reflected_primitive!(DataType::Int(IntSize::b64), i64, read_i64, write_i64);
+reflected_primitive!(
+    DataType::Float(FloatSize::b32),
+    f32,
+    u32,
+    read_f32,
+    write_f32
+);
+reflected_primitive!(
+    DataType::Float(FloatSize::b64),
+    f64,
+    u64,
+    read_f64,
+    write_f64
+);
+reflected_primitive!(DataType::UInt(IntSize::b16), u16, u16, read_u16, write_u16);
+reflected_primitive!(DataType::UInt(IntSize::b32), u32, u32, read_u32, write_u32);
+reflected_primitive!(DataType::UInt(IntSize::b64), u64, u64, read_u64, write_u64);
+reflected_primitive!(DataType::Int(IntSize::b16), i16, u16, read_i16, write_i16);
+reflected_primitive!(DataType::Int(IntSize::b32), i32, u32, read_i32, write_i32);
+reflected_primitive!(DataType::Int(IntSize::b64), i64, u64, read_i64, write_i64);
 
 #[cfg(test)]
 mod tests {
@@ -436,6 +529,51 @@ mod tests {
         };
     }
 
+    #[test]
+    fn elementwise_roundtrip() {
+        let vals = ArcArrayD::from_shape_vec(
+            vec![3],
+            vec![c64::new(1.0, -2.0), c64::new(0.5, 4.0), c64::default()],
+        )
+        .unwrap();
+        for endian in [Endian::Big, Endian::Little] {
+            let mut buf = Vec::default();
+            c64::write_array_to(vals.clone(), &mut buf, endian).unwrap();
+            assert_eq!(buf.len(), 24);
+            let (re, im) = match endian {
+                Endian::Big => (1.0f32.to_be_bytes(), (-2.0f32).to_be_bytes()),
+                Endian::Little => (1.0f32.to_le_bytes(), (-2.0f32).to_le_bytes()),
+            };
+            assert_eq!(&buf[..4], &re);
+            assert_eq!(&buf[4..8], &im);
+            assert_eq!(
+                c64::read_array_from(buf.as_slice(), endian, &[3]).unwrap(),
+                vals
+            );
+        }
+
+        // c128 is 16 bytes: a pair of f64s
+        assert_eq!(c128::ZARR_TYPE, DataType::Complex(ComplexSize::b128));
+        assert_eq!(c128::ZARR_TYPE.nbytes(), 16);
+        let vals = ArcArrayD::from_shape_vec(vec![2], vec![c128::new(1.0, -2.0), c128::default()])
+            .unwrap();
+        let mut buf = Vec::default();
+        c128::write_array_to(vals.clone(), &mut buf, Endian::Little).unwrap();
+        assert_eq!(buf.len(), 32);
+        assert_eq!(&buf[8..16], &(-2.0f64).to_le_bytes());
+        assert_eq!(
+            c128::read_array_from(buf.as_slice(), Endian::Little, &[2]).unwrap(),
+            vals
+        );
+
+        let bools = ArcArrayD::from_shape_vec(vec![2, 2], vec![true, false, false, true]).unwrap();
+        let mut buf = Vec::default();
+        bool::write_array_to(bools.t().to_shared(), &mut buf, Endian::Little).unwrap();
+        assert_eq!(buf, [1, 0, 0, 1]);
+        let raw = <[u8; 2]>::read_array_from(&[1u8, 2, 3, 4][..], Endian::Big, &[2]).unwrap();
+        assert_eq!(raw.as_slice().unwrap(), &[[1, 2], [3, 4]]);
+    }
+
     #[test]
     /// Ensure that DataType's default fill value is reflected type default value
     fn reflected_defaults() {
@@ -520,7 +658,7 @@ mod tests {
 
     #[test]
     fn can_validate_endian() {
-        for dt in vec![
+        for dt in [
             DataType::Bool,
             DataType::UInt(IntSize::b8),
             DataType::Int(IntSize::b8),
@@ -528,22 +666,59 @@ mod tests {
             DataType::Raw(2),
             DataType::Raw(4),
         ] {
-            for e in vec![Endian::Little, Endian::Big] {
+            for e in [Endian::Little, Endian::Big] {
                 dt.valid_endian(Some(e)).unwrap();
             }
             dt.valid_endian(None).unwrap();
         }
 
-        for dt in vec![
+        for dt in [
             DataType::UInt(IntSize::b16),
             DataType::Int(IntSize::b32),
             DataType::Float(FloatSize::b32),
             DataType::Complex(ComplexSize::b64),
         ] {
-            for e in vec![Endian::Little, Endian::Big] {
+            for e in [Endian::Little, Endian::Big] {
                 dt.valid_endian(Some(e)).unwrap();
             }
             assert!(dt.valid_endian(None).is_err());
         }
     }
+
+    #[test]
+    fn validate_float_fill_values() {
+        use serde_json::json;
+
+        let f32_ = DataType::Float(FloatSize::b32);
+        let c128_ = DataType::Complex(ComplexSize::b128);
+        for v in [
+            json!(1.5),
+            json!(2),
+            json!("NaN"),
+            json!("Infinity"),
+            json!("-Infinity"),
+            json!("0x7fc00000"),
+        ] {
+            f32_.validate_json_value(&v).unwrap();
+        }
+        for v in [
+            json!("nan"),
+            json!("0x7ff8000000000000"),
+            json!("0xnothex!"),
+            json!(null),
+        ] {
+            assert!(f32_.validate_json_value(&v).is_err());
+        }
+
+        for v in [
+            json!([1.0, 0]),
+            json!(["NaN", "-Infinity"]),
+            json!(["0x7ff8000000000000", 1]),
+        ] {
+            c128_.validate_json_value(&v).unwrap();
+        }
+        for v in [json!(1.0), json!(["0x7fc00000", 0]), json!([1, 2, 3])] {
+            assert!(c128_.validate_json_value(&v).is_err());
+        }
+    }
 }
@@ -7,22 +7,17 @@ macro_rules! reflected_raw {
             const ZARR_TYPE: DataType = DataType::Raw($nbytes * 8);
 
             /// Endianness is ignored for raw types.
-            fn encoder(_endian: Endian) -> Box<dyn Fn(Self, &mut [u8])> {
-                Box::new(|v: Self, buf: &mut[u8]| {
-                    buf.copy_from_slice(&v);
-                })
+            #[inline]
+            fn encode_into(self, buf: &mut [u8], _endian: Endian) {
+                buf[..$nbytes].copy_from_slice(&self);
             }
 
-            /// Produce a routine which reads a self-typed value from
-            /// the given byte buffer.
-            ///
             /// Endianness is ignored for raw types.
-            fn decoder(_endian: Endian) -> Box<dyn Fn(&mut [u8]) -> Self> {
-                Box::new(|buf: &mut[u8]| {
-                    let mut out = [0; $nbytes];
-                    out.as_mut().copy_from_slice(buf);
-                    out
-                })
+            #[inline]
+            fn decode_from(buf: &[u8], _endian: Endian) -> Self {
+                let mut out = [0; $nbytes];
+                out.copy_from_slice(&buf[..$nbytes]);
+                out
             }
         }
     )*
@@ -0,0 +1,24 @@
+//! All public error types, re-exported from one stable location.
+//!
+//! Prefer these paths over the modules in which the errors are defined,
+//! which may be reorganised between releases.
+//! Error enums are `#[non_exhaustive]`, so matches on them need a wildcard arm;
+//! new variants are not breaking changes.
+//!
+//! Store operations, and encoding/decoding through codecs, report [std::io::Error]s.
+pub use crate::codecs::ab::sharding_indexed::{
+    ChunkReadError, ChunkSpecConstructionError, ChunkSpecError, ChunkSpecModificationError,
+    ShardShapeError,
+};
+#[cfg(feature = "blosc")]
+pub use crate::codecs::bb::blosc_codec::BloscBuildError;
+#[cfg(feature = "gzip")]
+pub use crate::codecs::bb::gzip_codec::InvalidGzipLevel;
+pub use crate::codecs::CodecChainConstructionError;
+pub use crate::metadata::MetadataError;
+pub use crate::node::attributes::AttributeError;
+pub use crate::node::ExtensionError;
+pub use crate::schema::SchemaError;
+pub use crate::store::config::ConfigError;
+pub use crate::store::InvalidNodeName;
+pub use crate::util::DimensionMismatch;
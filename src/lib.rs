@@ -3,15 +3,21 @@ use std::{fmt::Display, ops::Range};
 use ndarray::{ArcArray, IxDyn};
 use smallvec::SmallVec;
 
-mod chunk_arr;
+pub mod advice;
+pub mod chunk_arr;
 mod chunk_grid;
 pub mod chunk_key_encoding;
 pub mod codecs;
 mod data_type;
+pub mod errors;
+pub mod metadata;
+pub mod migrate;
 pub mod node;
 pub mod prelude;
+pub mod schema;
 pub mod store;
 mod util;
+pub use util::DimensionMismatch;
 
 const COORD_SMALLVEC_SIZE: usize = 6;
 pub const ZARR_FORMAT: usize = 3;
@@ -144,3 +150,35 @@ impl Display for RangeRequest {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+    /// Compile-time check that nodes and stores can be shared between threads.
+    #[test]
+    fn send_sync() {
+        use node::{Array, Group};
+        use store::{BufferedStore, CachingStore, HashMapStore, KeyTranslatingStore};
+
+        assert_send_sync::<HashMapStore>();
+        assert_send_sync::<KeyTranslatingStore<HashMapStore>>();
+        assert_send_sync::<CachingStore<HashMapStore>>();
+        assert_send_sync::<BufferedStore<HashMapStore>>();
+        assert_send_sync::<store::naming::ValidatingStore<HashMapStore>>();
+        #[cfg(feature = "filesystem")]
+        assert_send_sync::<store::filesystem::FileSystemStore>();
+        #[cfg(feature = "http")]
+        assert_send_sync::<store::http::HttpStore>();
+
+        assert_send_sync::<Array<'static, HashMapStore, f64>>();
+        assert_send_sync::<Group<'static, HashMapStore>>();
+
+        // type-erased stores must opt in
+        fn assert_shareable_store<S: store::WriteableStore + Send + Sync + ?Sized>() {}
+        assert_shareable_store::<dyn store::DynWriteableStore + Send + Sync>();
+        assert_send_sync::<Array<'static, dyn store::DynWriteableStore + Send + Sync, u8>>();
+    }
+}
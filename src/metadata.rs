@@ -0,0 +1,135 @@
+//! Parsing and serialising metadata documents without a store,
+//! e.g. for linting or converting `zarr.json` files.
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    codecs::ab::sharding_indexed::ShardShapeError,
+    node::{ArrayMetadata, GroupMetadata, Metadata, ReadableMetadata},
+    ZARR_FORMAT,
+};
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum MetadataError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported zarr_format {0}")]
+    UnsupportedFormat(usize),
+    #[error("Expected {0} metadata")]
+    WrongNodeType(&'static str),
+    #[error(transparent)]
+    ShardShape(#[from] ShardShapeError),
+    #[error("Invalid array metadata: {0}")]
+    Invalid(&'static str),
+}
+
+/// Parse a metadata document of either node type.
+///
+/// Documents without `node_type` (as written by earlier versions of this crate)
+/// are inferred to be arrays if they have a `data_type`.
+/// Array metadata is validated.
+pub fn parse_meta(bytes: &[u8]) -> Result<Metadata, MetadataError> {
+    let meta = parse_meta_unvalidated(bytes)?;
+    if let Metadata::Array(m) = &meta {
+        m.validate_sharding()?;
+        m.validate().map_err(MetadataError::Invalid)?;
+    }
+    Ok(meta)
+}
+
+/// As [parse_meta], but without validating array metadata,
+/// e.g. so that a store can check it against its own extension policy.
+pub(crate) fn parse_meta_unvalidated(bytes: &[u8]) -> Result<Metadata, MetadataError> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    if let Some(obj) = value.as_object_mut() {
+        if !obj.contains_key("node_type") {
+            let node_type = if obj.contains_key("data_type") {
+                "array"
+            } else {
+                "group"
+            };
+            obj.insert("node_type".to_owned(), node_type.into());
+        }
+    }
+    let meta: Metadata = serde_json::from_value(value)?;
+    if meta.get_zarr_format() != ZARR_FORMAT {
+        return Err(MetadataError::UnsupportedFormat(meta.get_zarr_format()));
+    }
+    Ok(meta)
+}
+
+/// Parse and validate an array metadata document.
+pub fn parse_array_meta(bytes: &[u8]) -> Result<ArrayMetadata, MetadataError> {
+    match parse_meta(bytes)? {
+        Metadata::Array(m) => Ok(m),
+        Metadata::Group(_) => Err(MetadataError::WrongNodeType("array")),
+    }
+}
+
+/// As [parse_array_meta], but without validation.
+pub(crate) fn parse_array_meta_unvalidated(bytes: &[u8]) -> Result<ArrayMetadata, MetadataError> {
+    match parse_meta_unvalidated(bytes)? {
+        Metadata::Array(m) => Ok(m),
+        Metadata::Group(_) => Err(MetadataError::WrongNodeType("array")),
+    }
+}
+
+/// Parse a group metadata document.
+pub fn parse_group_meta(bytes: &[u8]) -> Result<GroupMetadata, MetadataError> {
+    match parse_meta(bytes)? {
+        Metadata::Group(m) => Ok(m),
+        Metadata::Array(_) => Err(MetadataError::WrongNodeType("group")),
+    }
+}
+
+/// Serialise a metadata document in a canonical form:
+/// including `node_type`, with object keys sorted, pretty-printed with a trailing newline.
+///
+/// Equivalent documents produce identical bytes, so the output can be compared or hashed.
+pub fn to_canonical_bytes<M: Into<Metadata>>(meta: M) -> Vec<u8> {
+    // serde_json's Map is ordered by key
+    let value = serde_json::to_value(meta.into()).expect("could not serialise metadata");
+    let mut out = serde_json::to_vec_pretty(&value).expect("could not serialise metadata");
+    out.push(b'\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::ArrayMetadataBuilder;
+
+    #[test]
+    fn array_roundtrip() {
+        let meta: ArrayMetadata = ArrayMetadataBuilder::<u16>::new(&[10, 20]).into();
+        let bytes = to_canonical_bytes(meta);
+        let parsed = parse_array_meta(&bytes).unwrap();
+        assert_eq!(to_canonical_bytes(parsed), bytes);
+
+        let value: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["node_type"], "array");
+        assert!(matches!(
+            parse_group_meta(&bytes),
+            Err(MetadataError::WrongNodeType("group"))
+        ));
+    }
+
+    #[test]
+    fn group_inferred() {
+        let meta =
+            parse_group_meta(br#"{"zarr_format": 3, "attributes": {"b": 1, "a": 2}}"#).unwrap();
+        assert_eq!(meta.get_attributes().len(), 2);
+        let s = String::from_utf8(to_canonical_bytes(meta)).unwrap();
+        assert!(s.find("\"a\"").unwrap() < s.find("\"b\"").unwrap());
+        assert!(s.contains("\"node_type\": \"group\""));
+    }
+
+    #[test]
+    fn wrong_format() {
+        assert!(matches!(
+            parse_group_meta(br#"{"zarr_format": 2, "node_type": "group"}"#),
+            Err(MetadataError::UnsupportedFormat(2))
+        ));
+    }
+}
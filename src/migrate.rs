@@ -0,0 +1,339 @@
+//! Upgrading metadata documents written against pre-release drafts of the zarr v3 specification,
+//! which are otherwise rejected (or misread) by this crate.
+//!
+//! [migrate_document] detects the known [Quirk]s of a single document and rewrites it in its current form,
+//! and [migrate_store] does so for every node in a store.
+//! Both report what they found, so that a dry run can be used to check a hierarchy before changing it.
+use std::{
+    fmt::Display,
+    io::{self, ErrorKind, Read, Write},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{
+    metadata::{parse_meta, to_canonical_bytes, MetadataError},
+    node::Metadata,
+    store::{ListableStore, NodeKey, WriteableStore, METADATA_NAME},
+};
+
+/// A known difference between a draft and the current form of a metadata document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Quirk {
+    /// No `node_type` field.
+    MissingNodeType,
+    /// The `bytes` codec under its earlier name, `endian`.
+    EndianCodec,
+    /// A `transpose` codec whose `order` is `"C"` or `"F"` rather than a permutation.
+    TransposeOrderLayout,
+    /// A `blosc` codec whose `shuffle` is an integer rather than a name.
+    BloscShuffleInteger,
+    /// A `chunk_key_encoding` without a `configuration`.
+    ChunkKeyEncodingConfiguration,
+    /// A `chunk_memory_layout` field, which has been replaced by the `transpose` codec.
+    ChunkMemoryLayout,
+    /// An `extensions` list, which has been replaced by top-level fields.
+    ExtensionsList,
+}
+
+impl Display for Quirk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::MissingNodeType => "missing node_type",
+            Self::EndianCodec => "codec \"endian\" renamed to \"bytes\"",
+            Self::TransposeOrderLayout => "transpose order given as memory layout",
+            Self::BloscShuffleInteger => "blosc shuffle given as integer",
+            Self::ChunkKeyEncodingConfiguration => "chunk_key_encoding without configuration",
+            Self::ChunkMemoryLayout => "chunk_memory_layout instead of transpose codec",
+            Self::ExtensionsList => "extensions list",
+        };
+        f.write_str(s)
+    }
+}
+
+/// The result of migrating a metadata document.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    /// Quirks found in the original document; empty if it did not need migrating.
+    pub quirks: Vec<Quirk>,
+    pub metadata: Metadata,
+}
+
+impl Migration {
+    pub fn is_needed(&self) -> bool {
+        !self.quirks.is_empty()
+    }
+
+    /// The migrated document, as from [to_canonical_bytes].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        to_canonical_bytes(self.metadata.clone())
+    }
+}
+
+/// Detect and fix the known quirks of a metadata document.
+///
+/// The fixed document is parsed and validated as by [parse_meta],
+/// so `Err` if it is invalid for reasons other than known quirks.
+pub fn migrate_document(bytes: &[u8]) -> Result<Migration, MetadataError> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    let mut quirks = Vec::default();
+    if let Some(obj) = value.as_object_mut() {
+        fix_document(obj, &mut quirks);
+    }
+    let metadata = parse_meta(&serde_json::to_vec(&value)?)?;
+    Ok(Migration { quirks, metadata })
+}
+
+/// Migrate the metadata document of every node in the store.
+///
+/// Returns the key of each document which needed migrating, with its quirks, in key order.
+/// Documents are only rewritten if `apply` is `true`; otherwise this is a dry run.
+/// Documents which cannot be parsed even after migration are [ErrorKind::InvalidData] errors,
+/// in which case nothing is written.
+pub fn migrate_store<S: ListableStore + WriteableStore + ?Sized>(
+    store: &S,
+    apply: bool,
+) -> io::Result<Vec<(NodeKey, Vec<Quirk>)>> {
+    let mut migrations = Vec::default();
+    for key in store.list()? {
+        if key.as_slice().last().map(|n| n.as_ref()) != Some(METADATA_NAME) {
+            continue;
+        }
+        let Some(mut r) = store.get(&key)? else {
+            continue;
+        };
+        let mut buf = Vec::default();
+        r.read_to_end(&mut buf)?;
+        let migration = migrate_document(&buf).map_err(|e| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid metadata \"{key}\": {e}"),
+            )
+        })?;
+        if migration.is_needed() {
+            migrations.push((key, migration));
+        }
+    }
+    migrations.sort_by_key(|(key, _)| key.encode());
+
+    if apply {
+        for (key, migration) in migrations.iter() {
+            let bytes = migration.to_bytes();
+            store.set(key, |w| w.write_all(&bytes))?;
+        }
+    }
+    Ok(migrations
+        .into_iter()
+        .map(|(key, migration)| (key, migration.quirks))
+        .collect())
+}
+
+fn found(quirks: &mut Vec<Quirk>, quirk: Quirk) {
+    if !quirks.contains(&quirk) {
+        quirks.push(quirk);
+    }
+}
+
+fn fix_document(obj: &mut Map<String, Value>, quirks: &mut Vec<Quirk>) {
+    let is_array = obj.contains_key("data_type");
+    if !obj.contains_key("node_type") {
+        let node_type = if is_array { "array" } else { "group" };
+        obj.insert("node_type".to_owned(), node_type.into());
+        found(quirks, Quirk::MissingNodeType);
+    }
+    if obj.get("extensions").is_some_and(Value::is_array) {
+        obj.remove("extensions");
+        found(quirks, Quirk::ExtensionsList);
+    }
+    if !is_array {
+        return;
+    }
+
+    let ndim = obj.get("shape").and_then(Value::as_array).map(Vec::len);
+
+    if let Some(Value::Object(encoding)) = obj.get_mut("chunk_key_encoding") {
+        if !encoding.contains_key("configuration") {
+            let separator = match encoding.get("name").and_then(Value::as_str) {
+                Some("v2") => ".",
+                _ => "/",
+            };
+            encoding.insert(
+                "configuration".to_owned(),
+                serde_json::json!({ "separator": separator }),
+            );
+            found(quirks, Quirk::ChunkKeyEncodingConfiguration);
+        }
+    }
+
+    if let Some(layout) = obj.remove("chunk_memory_layout") {
+        found(quirks, Quirk::ChunkMemoryLayout);
+        if let (Some("F"), Some(n)) = (layout.as_str(), ndim) {
+            let transpose = serde_json::json!({
+                "name": "transpose",
+                "configuration": { "order": (0..n).rev().collect::<Vec<_>>() },
+            });
+            match obj.get_mut("codecs") {
+                Some(Value::Array(codecs)) => codecs.insert(0, transpose),
+                _ => {
+                    let bytes = serde_json::json!({
+                        "name": "bytes",
+                        "configuration": { "endian": "little" },
+                    });
+                    obj.insert("codecs".to_owned(), Value::Array(vec![transpose, bytes]));
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Array(codecs)) = obj.get_mut("codecs") {
+        fix_codecs(codecs, ndim, quirks);
+    }
+}
+
+/// Fix a list of codecs, including those nested within sharding codecs.
+fn fix_codecs(codecs: &mut [Value], ndim: Option<usize>, quirks: &mut Vec<Quirk>) {
+    for codec in codecs.iter_mut() {
+        let Some(codec) = codec.as_object_mut() else {
+            continue;
+        };
+        let name = codec
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        match name {
+            "endian" => {
+                codec.insert("name".to_owned(), "bytes".into());
+                found(quirks, Quirk::EndianCodec);
+            }
+            "transpose" => {
+                let Some(config) = codec.get_mut("configuration") else {
+                    continue;
+                };
+                let order = match (config.get("order").and_then(Value::as_str), ndim) {
+                    (Some("C"), Some(n)) => (0..n).collect::<Vec<_>>(),
+                    (Some("F"), Some(n)) => (0..n).rev().collect(),
+                    _ => continue,
+                };
+                config["order"] = order.into();
+                found(quirks, Quirk::TransposeOrderLayout);
+            }
+            "blosc" => {
+                let Some(config) = codec.get_mut("configuration") else {
+                    continue;
+                };
+                let shuffle = match config.get("shuffle").and_then(Value::as_u64) {
+                    Some(0) => "noshuffle",
+                    Some(1) => "shuffle",
+                    Some(2) => "bitshuffle",
+                    _ => continue,
+                };
+                config["shuffle"] = shuffle.into();
+                found(quirks, Quirk::BloscShuffleInteger);
+            }
+            "sharding_indexed" => {
+                let Some(Value::Object(config)) = codec.get_mut("configuration") else {
+                    continue;
+                };
+                for field in ["codecs", "index_codecs"] {
+                    if let Some(Value::Array(inner)) = config.get_mut(field) {
+                        fix_codecs(inner, ndim, quirks);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::ReadableMetadata,
+        store::{HashMapStore, ReadableStore},
+    };
+
+    const DRAFT_ARRAY: &str = r#"{
+        "zarr_format": 3,
+        "shape": [4, 6],
+        "data_type": "uint16",
+        "chunk_grid": {"name": "regular", "configuration": {"chunk_shape": [2, 3]}},
+        "chunk_key_encoding": {"name": "default"},
+        "chunk_memory_layout": "F",
+        "fill_value": 0,
+        "extensions": [],
+        "codecs": [
+            {"name": "endian", "configuration": {"endian": "little"}},
+            {"name": "crc32c"}
+        ]
+    }"#;
+
+    #[test]
+    fn migrates_draft_array() {
+        assert!(parse_meta(DRAFT_ARRAY.as_bytes()).is_err());
+
+        let migration = migrate_document(DRAFT_ARRAY.as_bytes()).unwrap();
+        assert_eq!(
+            migration.quirks,
+            vec![
+                Quirk::MissingNodeType,
+                Quirk::ExtensionsList,
+                Quirk::ChunkKeyEncodingConfiguration,
+                Quirk::ChunkMemoryLayout,
+                Quirk::EndianCodec,
+            ]
+        );
+        let value: Value = serde_json::from_slice(&migration.to_bytes()).unwrap();
+        assert_eq!(value["codecs"][0]["name"], "transpose");
+        assert_eq!(
+            value["codecs"][0]["configuration"]["order"],
+            serde_json::json!([1, 0])
+        );
+        assert_eq!(value["codecs"][1]["name"], "bytes");
+        assert_eq!(
+            value["chunk_key_encoding"]["configuration"]["separator"],
+            "/"
+        );
+
+        // migrating again is a no-op
+        let again = migrate_document(&migration.to_bytes()).unwrap();
+        assert!(!again.is_needed());
+        assert_eq!(again.to_bytes(), migration.to_bytes());
+    }
+
+    #[test]
+    fn migrates_store() {
+        let store = HashMapStore::default();
+        let root: NodeKey = "zarr.json".parse().unwrap();
+        let array: NodeKey = "arr/zarr.json".parse().unwrap();
+        let current = to_canonical_bytes(parse_meta(br#"{"zarr_format": 3}"#).unwrap());
+        store.set(&root, |w| w.write_all(&current)).unwrap();
+        store
+            .set(&array, |w| w.write_all(DRAFT_ARRAY.as_bytes()))
+            .unwrap();
+
+        let dry_run = migrate_store(&store, false).unwrap();
+        assert_eq!(dry_run.len(), 1);
+        assert_eq!(dry_run[0].0, array);
+        let mut buf = Vec::default();
+        store
+            .get(&array)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, DRAFT_ARRAY.as_bytes());
+
+        assert_eq!(migrate_store(&store, true).unwrap(), dry_run);
+        assert!(migrate_store(&store, false).unwrap().is_empty());
+        let mut buf = Vec::default();
+        store
+            .get(&array)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert!(parse_meta(&buf).unwrap().is_array());
+    }
+}
@@ -1,30 +1,61 @@
+#[cfg(feature = "parallel")]
+use std::sync::Arc;
 use std::{
-    collections::HashMap,
-    io::{self, ErrorKind},
+    collections::{HashMap, HashSet},
+    io::{self, ErrorKind, Read, Write},
+    ops::ControlFlow,
+    sync::Mutex,
 };
 
+#[cfg(feature = "parallel")]
+use rayon::{prelude::*, ThreadPool};
+
+use log::warn;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{
-    chunk_grid::{ArrayRegion, ChunkGrid, ChunkGridType},
+    chunk_arr::CIter,
+    chunk_grid::{ArrayRegion, ChunkGrid, ChunkGridType, PartialChunk},
     data_type::NBytes,
     to_usize,
     util::DimensionMismatch,
+    RangeRequest,
 };
 use crate::{
     chunk_key_encoding::{ChunkKeyEncoder, ChunkKeyEncoding},
     codecs::{
-        aa::AACodecType,
-        ab::{ABCodec, ABCodecType},
+        aa::{reverse_permutation, validate_permutation, AACodecType},
+        ab::{bytes_codec::Endian, sharding_indexed::ShardShapeError, ABCodec, ABCodecType},
         bb::BBCodecType,
-        ArrayRepr, CodecChain,
+        ArrayRepr, CodecChain, DecodeOptions, TrailingDataPolicy,
     },
     data_type::{DataType, ReflectedType},
-    store::{ListableStore, NodeKey, ReadableStore, Store, WriteableStore},
+    metadata::parse_array_meta_unvalidated,
+    store::{ListableStore, NodeKey, NodeName, ReadableStore, Store, VersionToken, WriteableStore},
     ArcArrayD, CoordVec, GridCoord, MaybeNdim, Ndim, ZARR_FORMAT,
 };
 
-use super::{JsonObject, ReadableMetadata, WriteableMetadata};
+use super::{
+    attributes::AttributeLimits,
+    buffer_pool::{BufferPool, PoolStats},
+    chunk_exchange::ChunkRecord,
+    chunk_stats::{
+        chunk_id, parse_chunk_id, ChunkStats, ChunkStatsDocument, CHUNK_STATS_EXTENSION,
+        CHUNK_STATS_NAME,
+    },
+    digest::{ContentDigest, DigestAlgorithm, DigestState, SegmentedDigest},
+    inline_chunks::{
+        decode_payload, encode_payload, InlineChunksConfig, InlineChunksDocument,
+        INLINE_CHUNKS_EXTENSION, INLINE_CHUNKS_NAME,
+    },
+    manifest::Manifest,
+    prefetch::StrideDetector,
+    JsonObject, ReadableMetadata, WriteableMetadata,
+};
+
+/// Extensions implemented by this crate, which are understood even if `must_understand`.
+const UNDERSTOOD_EXTENSIONS: [&str; 2] = [CHUNK_STATS_EXTENSION, INLINE_CHUNKS_EXTENSION];
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "name", content = "configuration")]
@@ -35,18 +66,75 @@ pub struct Extension(serde_json::Value);
 
 impl Extension {
     pub fn try_understand(&self) -> Result<(), &'static str> {
+        if self.must_understand()? {
+            Err("Extension must be understood")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn must_understand(&self) -> Result<bool, &'static str> {
         let mut map: JsonObject =
             serde_json::from_value(self.0.clone()).map_err(|_| "Extension is not an object")?;
         let mu_value = map
             .remove("must_understand")
             .ok_or("Extension does not define \"must_understand\"")?;
-        let mu: bool = serde_json::from_value(mu_value)
-            .map_err(|_| "Extension's \"must_understand\" is not a boolean")?;
-        if mu {
-            Err("Extension must be understood")
-        } else {
-            Ok(())
-        }
+        serde_json::from_value(mu_value)
+            .map_err(|_| "Extension's \"must_understand\" is not a boolean")
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExtensionError {
+    #[error("Extension `{0}` is denied")]
+    Denied(String),
+    #[error("Extension `{0}` must be understood, but is not implemented or allowed")]
+    MustUnderstand(String),
+    #[error("Extension `{0}` is malformed: {1}")]
+    Malformed(String, &'static str),
+}
+
+impl From<ExtensionError> for io::Error {
+    fn from(value: ExtensionError) -> Self {
+        Self::new(ErrorKind::InvalidData, value)
+    }
+}
+
+/// Which metadata extensions an array may use to be opened.
+///
+/// By default, an array is rejected if it has an extension which sets `must_understand`
+/// and is not implemented by this crate.
+/// Allowed extensions are accepted even if they must be understood,
+/// and are treated as opaque: they are preserved when metadata is written, but have no effect.
+/// Denied extensions are rejected even if they need not be understood.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionPolicy {
+    allow: HashSet<String>,
+    deny: HashSet<String>,
+}
+
+impl ExtensionPolicy {
+    pub fn allow<N: Into<String>>(mut self, name: N) -> Self {
+        let name = name.into();
+        self.deny.remove(&name);
+        self.allow.insert(name);
+        self
+    }
+
+    pub fn deny<N: Into<String>>(mut self, name: N) -> Self {
+        let name = name.into();
+        self.allow.remove(&name);
+        self.deny.insert(name);
+        self
+    }
+
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.allow.contains(name)
+    }
+
+    pub fn is_denied(&self, name: &str) -> bool {
+        self.deny.contains(name)
     }
 }
 
@@ -69,6 +157,9 @@ pub struct ArrayMetadata {
     dimension_names: Option<CoordVec<Option<String>>>,
     #[serde(flatten)]
     extensions: HashMap<String, Extension>,
+    /// Whether sharding sub-chunks may overhang the shards; never stored.
+    #[serde(skip)]
+    overhanging_sub_chunks: bool,
 }
 
 impl Ndim for ArrayMetadata {
@@ -105,14 +196,39 @@ impl ArrayMetadata {
         self.try_understand_extensions()?;
         self.validate_dimensions()?;
         self.validate_codecs()?;
+        self.validate_sharding()
+            .map_err(|_| "Shard shape is not a multiple of sub-chunk shape")?;
         Ok(())
     }
 
     /// Ensures that all unknown extensions do not require understanding.
     pub fn try_understand_extensions(&self) -> Result<(), &'static str> {
         self.extensions
-            .values()
-            .try_for_each(|config| config.try_understand())
+            .iter()
+            .filter(|(name, _)| !UNDERSTOOD_EXTENSIONS.contains(&name.as_str()))
+            .try_for_each(|(_, config)| config.try_understand())
+    }
+
+    /// Check the extensions against the given policy, naming the first offending extension.
+    ///
+    /// Extensions are checked in name order, so that the error is deterministic.
+    pub fn check_extensions(&self, policy: &ExtensionPolicy) -> Result<(), ExtensionError> {
+        let mut names: Vec<_> = self.extensions.keys().collect();
+        names.sort();
+        for name in names {
+            if policy.is_denied(name) {
+                return Err(ExtensionError::Denied(name.clone()));
+            }
+            if policy.is_allowed(name) || UNDERSTOOD_EXTENSIONS.contains(&name.as_str()) {
+                continue;
+            }
+            match self.extensions[name].must_understand() {
+                Ok(false) => (),
+                Ok(true) => return Err(ExtensionError::MustUnderstand(name.clone())),
+                Err(e) => return Err(ExtensionError::Malformed(name.clone(), e)),
+            }
+        }
+        Ok(())
     }
 
     /// Ensure that all dimensioned metadata is consistent.
@@ -135,6 +251,16 @@ impl ArrayMetadata {
         Ok(())
     }
 
+    /// If the array->bytes codec is sharding,
+    /// check that the chunk grid's chunks are a whole number of sub-chunks.
+    ///
+    /// The array shape need not be a multiple of the shard shape.
+    /// Sub-chunks may overhang the shards only if allowed by
+    /// [ArrayMetadataBuilder::overhanging_sub_chunks] or [Array::from_store_lenient].
+    pub fn validate_sharding(&self) -> Result<(), ShardShapeError> {
+        check_sharding(&self.chunk_grid, &self.codecs, self.overhanging_sub_chunks)
+    }
+
     pub fn get_effective_fill_value<T: ReflectedType>(&self) -> Result<T, &'static str> {
         if T::ZARR_TYPE != self.data_type {
             return Err("Reflected type mismatches array data type");
@@ -143,10 +269,118 @@ impl ArrayMetadata {
             .map_err(|_| "Could not deserialize fill value")
     }
 
-    /// Panics on dimension mismatch
-    pub fn chunk_should_exist(&self, chunk: &GridCoord) -> bool {
-        DimensionMismatch::check_coords(chunk.len(), self.ndim()).unwrap();
-        self.chunk_should_exist_unchecked(chunk)
+    /// Replace a fill value which is invalid for the data type,
+    /// but has an unambiguous interpretation, with its canonical form:
+    /// numeric `0` or `1` for `bool`, and integral floats (e.g. `3.0`) for integer types.
+    ///
+    /// Logs a warning when the fill value is changed, and returns whether it was.
+    pub fn coerce_fill_value(&mut self) -> bool {
+        if self.data_type.validate_json_value(&self.fill_value).is_ok() {
+            return false;
+        }
+        let Some(coerced) = coerce_json_value(&self.data_type, &self.fill_value) else {
+            return false;
+        };
+        if self.data_type.validate_json_value(&coerced).is_err() {
+            return false;
+        }
+        warn!(
+            "Coercing fill value {} to {} for data type {}",
+            self.fill_value, coerced, self.data_type
+        );
+        self.fill_value = coerced;
+        true
+    }
+
+    pub fn data_type(&self) -> &DataType {
+        &self.data_type
+    }
+
+    pub(crate) fn chunk_grid(&self) -> &ChunkGridType {
+        &self.chunk_grid
+    }
+
+    /// Decode a single chunk from its encoded bytes, e.g. as read directly from a store.
+    ///
+    /// `Err` with [ErrorKind::InvalidInput] if the chunk is not in the grid
+    /// or `T` is not the array's data type.
+    pub fn decode_chunk<T: ReflectedType, R: Read>(
+        &self,
+        chunk_idx: &GridCoord,
+        r: R,
+        options: &DecodeOptions,
+    ) -> io::Result<ArcArrayD<T>> {
+        let repr = self.standalone_chunk_repr(chunk_idx)?;
+        self.codecs.decode_with(r, repr, options)
+    }
+
+    /// Encode a single chunk, e.g. to be written directly to a store.
+    ///
+    /// `Err` with [ErrorKind::InvalidInput] if the chunk is not in the grid,
+    /// `T` is not the array's data type, or the chunk is the wrong shape.
+    pub fn encode_chunk<T: ReflectedType, W: Write>(
+        &self,
+        chunk_idx: &GridCoord,
+        chunk: ArcArrayD<T>,
+        mut w: W,
+    ) -> io::Result<()> {
+        let repr = self.standalone_chunk_repr::<T>(chunk_idx)?;
+        if chunk.shape() != to_usize(repr.shape.as_slice()).as_slice() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Chunk is the wrong shape",
+            ));
+        }
+        let mut buf = Vec::default();
+        self.codecs
+            .encode_with_fill(chunk, repr.fill_value, &mut buf)?;
+        w.write_all(&buf)
+    }
+
+    /// Shape of the chunk, including any padding beyond the edge of the array.
+    ///
+    /// `Err` with [ErrorKind::InvalidInput] if the chunk is not in the grid.
+    pub fn chunk_shape(&self, chunk_idx: &GridCoord) -> io::Result<GridCoord> {
+        DimensionMismatch::check_coords(chunk_idx.len(), self.ndim())?;
+        let grid_shape = self.chunk_grid.grid_shape_unchecked(&self.shape);
+        if chunk_idx.iter().zip(grid_shape.iter()).any(|(i, n)| i >= n) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Chunk index is out of bounds",
+            ));
+        }
+        Ok(self.chunk_grid.chunk_shape_unchecked(chunk_idx))
+    }
+
+    fn standalone_chunk_repr<T: ReflectedType>(
+        &self,
+        chunk_idx: &GridCoord,
+    ) -> io::Result<ArrayRepr<T>> {
+        let shape = self.chunk_shape(chunk_idx)?;
+        let fill_value = self
+            .get_effective_fill_value()
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        Ok(ArrayRepr::new(shape.as_slice(), fill_value))
+    }
+
+    /// Whether per-chunk statistics are recorded on write.
+    pub fn records_chunk_stats(&self) -> bool {
+        self.extensions.contains_key(CHUNK_STATS_EXTENSION)
+    }
+
+    /// Largest encoded chunk which is stored inline, if chunk inlining is enabled.
+    ///
+    /// `None` if the extension is absent or its configuration is invalid.
+    pub fn inline_chunk_max_size(&self) -> Option<usize> {
+        let ext = self.extensions.get(INLINE_CHUNKS_EXTENSION)?;
+        serde_json::from_value::<InlineChunksConfig>(ext.0.clone())
+            .ok()
+            .map(|c| c.max_size)
+    }
+
+    pub fn chunk_should_exist(&self, chunk: &GridCoord) -> Result<bool, DimensionMismatch> {
+        DimensionMismatch::check_coords(chunk.len(), self.ndim())?;
+        Ok(self.chunk_should_exist_unchecked(chunk))
     }
 
     pub fn chunk_should_exist_unchecked(&self, chunk: &GridCoord) -> bool {
@@ -156,6 +390,135 @@ impl ArrayMetadata {
             .0;
         max_chunk.iter().zip(chunk.iter()).all(|(ma, ch)| ch <= ma)
     }
+
+    /// Check for patterns which are valid but discouraged by the spec,
+    /// or which may be handled inconsistently by other implementations.
+    ///
+    /// An empty result does not guarantee that the metadata is valid;
+    /// see [ArrayMetadata::validate] for that.
+    pub fn lint(&self) -> Vec<MetadataLint> {
+        let mut out = Vec::default();
+
+        match &self.dimension_names {
+            None => out.push(MetadataLint::MissingDimensionNames),
+            Some(names) => {
+                let unnamed: Vec<_> = names
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx, n)| n.is_none().then_some(idx))
+                    .collect();
+                if !unnamed.is_empty() {
+                    out.push(MetadataLint::UnnamedDimensions(unnamed));
+                }
+                let mut seen = HashSet::with_capacity(names.len());
+                for n in names.iter().flatten() {
+                    if !seen.insert(n) {
+                        out.push(MetadataLint::DuplicateDimensionName(n.clone()));
+                    }
+                }
+            }
+        }
+
+        if self
+            .data_type
+            .validate_json_value(&self.fill_value)
+            .is_err()
+        {
+            out.push(MetadataLint::UnusualFillValue(self.fill_value.clone()));
+        }
+
+        if let ABCodecType::ShardingIndexed(c) = self.codecs.ab_codec() {
+            let origin: GridCoord = self.shape.iter().map(|_| 0).collect();
+            let shard_shape = self.chunk_grid.chunk_shape_unchecked(&origin);
+            if c.overhangs(&shard_shape) {
+                out.push(MetadataLint::OverhangingSubChunks(shard_shape.to_vec()));
+            }
+        }
+
+        #[cfg(feature = "blosc")]
+        for bb in self.codecs.bb_codecs.iter() {
+            if let BBCodecType::Blosc(c) = bb {
+                if let Some(ts) = c.typesize {
+                    if ts != self.data_type.nbytes() {
+                        out.push(MetadataLint::DeprecatedCodecConfig(
+                            "blosc codec typesize does not match the data type",
+                        ));
+                    }
+                }
+            }
+        }
+
+        // in name order, as for ArrayMetadata::check_extensions
+        let mut names: Vec<_> = self.extensions.keys().collect();
+        names.sort();
+        for name in names {
+            if !UNDERSTOOD_EXTENSIONS.contains(&name.as_str())
+                && self.extensions[name].try_understand().is_ok()
+            {
+                out.push(MetadataLint::IgnoredExtension(name.clone()));
+            }
+        }
+
+        out
+    }
+}
+
+/// Canonical form of a sloppily-encoded JSON value of the given data type, if it has one.
+fn coerce_json_value(data_type: &DataType, value: &serde_json::Value) -> Option<serde_json::Value> {
+    // largest magnitude at which every integer is exactly representable as an f64
+    const MAX_EXACT: f64 = (1u64 << f64::MANTISSA_DIGITS) as f64;
+
+    let f = value.as_f64()?;
+    match data_type {
+        DataType::Bool if f == 0.0 => Some(false.into()),
+        DataType::Bool if f == 1.0 => Some(true.into()),
+        DataType::Int(_) | DataType::UInt(_) if f.fract() == 0.0 && f.abs() <= MAX_EXACT => {
+            if f < 0.0 {
+                Some((f as i64).into())
+            } else {
+                Some((f as u64).into())
+            }
+        }
+        _ => None,
+    }
+}
+
+fn check_sharding(
+    chunk_grid: &ChunkGridType,
+    codecs: &CodecChain,
+    allow_overhang: bool,
+) -> Result<(), ShardShapeError> {
+    if let ABCodecType::ShardingIndexed(c) = codecs.ab_codec() {
+        let origin: GridCoord = (0..chunk_grid.maybe_ndim().unwrap_or(0))
+            .map(|_| 0)
+            .collect();
+        let shard_shape = chunk_grid.chunk_shape_unchecked(&origin);
+        if allow_overhang {
+            c.check_overhanging_shard_shape(&shard_shape)?;
+        } else {
+            c.check_shard_shape(&shard_shape)?;
+        }
+    }
+    Ok(())
+}
+
+/// Spec-discouraged patterns in array metadata, as found by [ArrayMetadata::lint].
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum MetadataLint {
+    #[error("Array does not define dimension names")]
+    MissingDimensionNames,
+    #[error("Dimensions at indices {0:?} are unnamed")]
+    UnnamedDimensions(Vec<usize>),
+    #[error("Dimension name \"{0}\" is used more than once")]
+    DuplicateDimensionName(String),
+    #[error("Fill value {0} is not a standard representation for the data type")]
+    UnusualFillValue(serde_json::Value),
+    #[error("Discouraged codec configuration: {0}")]
+    DeprecatedCodecConfig(&'static str),
+    #[error("Extension \"{0}\" is not understood and will be ignored")]
+    IgnoredExtension(String),
+    #[error("Sharding sub-chunks overhang the shard shape {0:?}, which other implementations may not read")]
+    OverhangingSubChunks(Vec<u64>),
 }
 
 pub struct ArrayMetadataBuilder<T: ReflectedType> {
@@ -169,6 +532,7 @@ pub struct ArrayMetadataBuilder<T: ReflectedType> {
     attributes: JsonObject,
     dimension_names: Option<CoordVec<Option<String>>>,
     extensions: HashMap<String, Extension>,
+    overhanging_sub_chunks: bool,
 }
 
 impl<T: ReflectedType> ArrayMetadataBuilder<T> {
@@ -189,6 +553,7 @@ impl<T: ReflectedType> ArrayMetadataBuilder<T> {
             attributes: HashMap::default(),
             dimension_names: None,
             extensions: HashMap::default(),
+            overhanging_sub_chunks: false,
         }
     }
 
@@ -203,6 +568,8 @@ impl<T: ReflectedType> ArrayMetadataBuilder<T> {
     ) -> Result<Self, &'static str> {
         let cg = chunk_grid.into();
         self.union_ndim(&cg)?;
+        check_sharding(&cg, &self.codecs, self.overhanging_sub_chunks)
+            .map_err(|_| "Chunk shape is not a multiple of sharding sub-chunk shape")?;
         self.chunk_grid = Some(cg);
         Ok(self)
     }
@@ -235,10 +602,10 @@ impl<T: ReflectedType> ArrayMetadataBuilder<T> {
         &mut self.storage_transformers
     }
 
-    /// Append a storage transformer to the list.
-    ///
-    /// N.B. this API is subject to change as there are no specified
-    /// storage transformers at time of writing.
+    // Append a storage transformer to the list.
+    //
+    // N.B. this API is subject to change as there are no specified
+    // storage transformers at time of writing.
     // pub fn push_storage_transformer<T: Into<StorageTransformer>>(
     //     mut self,
     //     storage_transformer: T,
@@ -252,12 +619,21 @@ impl<T: ReflectedType> ArrayMetadataBuilder<T> {
     /// By default, uses a little-endian [crate::codecs::ab::bytes_codec::BytesCodec].
     ///
     /// Replaces the existing AB codec.
-    /// Fails if the dimensions are not compatible with the array's shape.
+    /// Fails if the dimensions are not compatible with the array's shape,
+    /// or if a sharding codec's sub-chunks do not evenly divide the chunk grid's chunks.
     pub fn ab_codec<C: Into<ABCodecType>>(mut self, codec: C) -> Result<Self, &'static str> {
         let c = codec.into();
         self.union_ndim(&c)?;
         c.valid_endian::<T>()?;
-        self.codecs.replace_ab_codec(c);
+        let old = self.codecs.replace_ab_codec(c);
+        let chunk_grid = self
+            .chunk_grid
+            .clone()
+            .unwrap_or_else(|| ChunkGridType::from(self.shape.as_slice()));
+        if check_sharding(&chunk_grid, &self.codecs, self.overhanging_sub_chunks).is_err() {
+            self.codecs.replace_ab_codec(old);
+            return Err("Chunk shape is not a multiple of sharding sub-chunk shape");
+        }
         Ok(self)
     }
 
@@ -311,6 +687,136 @@ impl<T: ReflectedType> ArrayMetadataBuilder<T> {
     pub fn extensions_mut(&mut self) -> &mut HashMap<String, Extension> {
         &mut self.extensions
     }
+
+    /// Allow sharding sub-chunks which overhang the chunk grid's chunks,
+    /// which are stored padded to the full sub-chunk shape.
+    ///
+    /// Must be called before setting the chunk grid and sharding codec.
+    /// The spec forbids such shards, so other implementations may reject the array,
+    /// and it can only be re-opened with [Array::from_store_lenient].
+    pub fn overhanging_sub_chunks(mut self) -> Self {
+        self.overhanging_sub_chunks = true;
+        self
+    }
+
+    /// Record per-chunk statistics on write; see [chunk_stats](super::chunk_stats).
+    ///
+    /// Other implementations will ignore the extension and not update the statistics.
+    pub fn chunk_stats(mut self) -> Self {
+        self.extensions.insert(
+            CHUNK_STATS_EXTENSION.to_owned(),
+            Extension(serde_json::json!({"must_understand": false})),
+        );
+        self
+    }
+
+    /// Store encoded chunks of up to `max_size` bytes inline in a single document;
+    /// see [inline_chunks](super::inline_chunks).
+    ///
+    /// Experimental: other implementations will refuse to read the array.
+    pub fn inline_chunks(mut self, max_size: usize) -> Self {
+        self.extensions.insert(
+            INLINE_CHUNKS_EXTENSION.to_owned(),
+            Extension(serde_json::json!({"must_understand": true, "max_size": max_size})),
+        );
+        self
+    }
+}
+
+/// The compressor used by the [ArrayMetadataBuilder] presets.
+///
+/// Blosc, byte-shuffled for multi-byte types;
+/// zstd if `high_ratio`, otherwise lz4.
+#[cfg(feature = "blosc")]
+fn preset_compressor<T: ReflectedType>(
+    high_ratio: bool,
+) -> Result<Option<BBCodecType>, &'static str> {
+    use crate::codecs::bb::blosc_codec::{BloscCodec, Clevel, Compressor, ShuffleMode};
+
+    let cname = if high_ratio {
+        Compressor::Zstd
+    } else {
+        Compressor::LZ4
+    };
+    let shuffle = if T::ZARR_TYPE.nbytes() > 1 {
+        ShuffleMode::Byte
+    } else {
+        ShuffleMode::None
+    };
+    let codec = BloscCodec::for_type::<T>(cname, Clevel::L5, shuffle, 0)
+        .map_err(|_| "Blosc compressor not available")?;
+    Ok(Some(codec.into()))
+}
+
+/// The compressor used by the [ArrayMetadataBuilder] presets.
+///
+/// Gzip at the default level.
+#[cfg(all(not(feature = "blosc"), feature = "gzip"))]
+#[allow(clippy::extra_unused_type_parameters)]
+fn preset_compressor<T: ReflectedType>(
+    _high_ratio: bool,
+) -> Result<Option<BBCodecType>, &'static str> {
+    Ok(Some(
+        crate::codecs::bb::gzip_codec::GzipCodec::default().into(),
+    ))
+}
+
+/// The compressor used by the [ArrayMetadataBuilder] presets.
+///
+/// No compressors are enabled.
+#[cfg(not(any(feature = "blosc", feature = "gzip")))]
+#[allow(clippy::extra_unused_type_parameters)]
+fn preset_compressor<T: ReflectedType>(
+    _high_ratio: bool,
+) -> Result<Option<BBCodecType>, &'static str> {
+    Ok(None)
+}
+
+impl<T: ReflectedType> ArrayMetadataBuilder<T> {
+    fn preset(shape: &[u64], chunk_shape: &[u64], high_ratio: bool) -> Result<Self, &'static str> {
+        let mut builder = Self::new(shape).chunk_grid(chunk_shape)?;
+        if let Some(c) = preset_compressor::<T>(high_ratio)? {
+            builder = builder.push_bb_codec(c);
+        }
+        Ok(builder)
+    }
+}
+
+impl ArrayMetadataBuilder<u8> {
+    /// Preset for 8-bit intensity data, e.g. EM or greyscale light microscopy.
+    ///
+    /// Chunked as given, compressed with blosc (lz4) if the `blosc` feature is enabled,
+    /// otherwise with gzip if the `gzip` feature is enabled.
+    ///
+    /// Fails if the chunk shape's dimensionality does not match the array's.
+    pub fn u8_image(shape: &[u64], chunk_shape: &[u64]) -> Result<Self, &'static str> {
+        Self::preset(shape, chunk_shape, false)
+    }
+}
+
+impl ArrayMetadataBuilder<u64> {
+    /// Preset for 64-bit label (segmentation) volumes.
+    ///
+    /// Label data is highly repetitive, so this uses blosc (zstd, byte-shuffled)
+    /// if the `blosc` feature is enabled, otherwise gzip if the `gzip` feature is enabled.
+    /// The fill value is the default of `0`, conventionally the background label.
+    ///
+    /// Fails if the chunk shape's dimensionality does not match the array's.
+    pub fn label_volume(shape: &[u64], chunk_shape: &[u64]) -> Result<Self, &'static str> {
+        Self::preset(shape, chunk_shape, true)
+    }
+}
+
+impl ArrayMetadataBuilder<f32> {
+    /// Preset for 32-bit floating point images, e.g. processed or normalised intensities.
+    ///
+    /// Uses blosc (lz4, byte-shuffled) if the `blosc` feature is enabled,
+    /// otherwise gzip if the `gzip` feature is enabled.
+    ///
+    /// Fails if the chunk shape's dimensionality does not match the array's.
+    pub fn f32_image(shape: &[u64], chunk_shape: &[u64]) -> Result<Self, &'static str> {
+        Self::preset(shape, chunk_shape, false)
+    }
 }
 
 impl<T: ReflectedType> From<ArrayMetadataBuilder<T>> for ArrayMetadata {
@@ -334,6 +840,7 @@ impl<T: ReflectedType> From<ArrayMetadataBuilder<T>> for ArrayMetadata {
             attributes: value.attributes,
             dimension_names: value.dimension_names,
             extensions: value.extensions,
+            overhanging_sub_chunks: value.overhanging_sub_chunks,
         }
     }
 }
@@ -344,21 +851,153 @@ impl<T: ReflectedType> Ndim for ArrayMetadataBuilder<T> {
     }
 }
 
-pub struct Array<'s, S: Store, T: ReflectedType> {
+/// What to do when writing a region which extends beyond the array's shape.
+///
+/// To resize the array to contain the region instead, use [Array::write_region_growing].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfBoundsPolicy {
+    /// Write the part of the region within the array and discard the rest.
+    #[default]
+    Clip,
+    /// Fail without writing anything.
+    Error,
+}
+
+/// Summary of the chunk IO a call to [Array::write_region] would perform,
+/// as estimated by [Array::plan_write_region].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WritePlan {
+    /// Chunks which are entirely overwritten.
+    pub whole_chunks: u64,
+    /// Chunks which are partly overwritten, so must be read, modified and written back.
+    pub partial_chunks: u64,
+    /// Shards touched, if the array uses the sharding codec (otherwise 0).
+    ///
+    /// Shards are written in full, so every shard touched is also counted as a whole or partial chunk.
+    pub shards: u64,
+    /// Estimated bytes read from the store.
+    pub bytes_read: u64,
+    /// Estimated bytes written to the store.
+    pub bytes_written: u64,
+}
+
+impl WritePlan {
+    /// Total chunks written.
+    pub fn chunks(&self) -> u64 {
+        self.whole_chunks + self.partial_chunks
+    }
+}
+
+/// Progress of a call to [Array::write_region_with_progress].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WriteProgress {
+    /// Chunks written so far.
+    pub chunks_done: u64,
+    /// Chunks which the whole region will write.
+    pub chunks_total: u64,
+    /// Bytes of (decoded) region data written so far.
+    pub bytes_written: u64,
+}
+
+impl WriteProgress {
+    pub fn is_complete(&self) -> bool {
+        self.chunks_done == self.chunks_total
+    }
+}
+
+/// Pixel data with the channels interleaved, e.g. `RGBRGBRGB...`,
+/// as read by [Array::read_interleaved].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterleavedImage<T> {
+    /// Shape of the spatial axes, in the order they appear in the array.
+    pub shape: GridCoord,
+    /// Number of values per pixel: 3 for RGB, 4 for RGBA.
+    pub channels: usize,
+    /// Values in C order over the spatial axes, with the channel varying fastest.
+    pub data: Vec<T>,
+}
+
+/// A run of equal, non-fill values along the last axis,
+/// as read by [Array::read_region_sparse].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseRun<T> {
+    /// Coordinates of the first voxel in the run, relative to the array's origin.
+    pub start: GridCoord,
+    /// Number of voxels in the run (at least 1).
+    pub len: u64,
+    pub value: T,
+}
+
+impl<T: Copy> SparseRun<T> {
+    /// Every voxel in the run, with its value.
+    pub fn voxels(&self) -> impl Iterator<Item = (GridCoord, T)> + '_ {
+        (0..self.len).map(move |i| {
+            let mut coord = self.start.clone();
+            if let Some(last) = coord.last_mut() {
+                *last += i;
+            }
+            (coord, self.value)
+        })
+    }
+}
+
+/// Checksum and length of chunks known to be stored with a particular version,
+/// so that [Array::write_chunk] can skip identical writes without reading the stored chunk.
+type DedupCache = Mutex<HashMap<NodeKey, (u32, usize, VersionToken)>>;
+
+/// How many times to retry updating a shared document which other writers keep changing.
+const DOCUMENT_UPDATE_ATTEMPTS: usize = 16;
+
+/// Changes to the documents shared between chunks (inline chunks and chunk statistics),
+/// collected while writing a number of chunks so that each document is read and written once.
+struct DocumentUpdates<T> {
+    /// New payload, or `None` to remove the chunk from the document.
+    inline: HashMap<String, Option<String>>,
+    /// Chunk keys to erase once their chunks have been inlined.
+    inlined_keys: Vec<NodeKey>,
+    stats: HashMap<String, Option<ChunkStats<T>>>,
+}
+
+impl<T> Default for DocumentUpdates<T> {
+    fn default() -> Self {
+        Self {
+            inline: Default::default(),
+            inlined_keys: Default::default(),
+            stats: Default::default(),
+        }
+    }
+}
+
+/// Decode buffers kept by each array unless configured otherwise.
+const DEFAULT_POOLED_BUFFERS: usize = 4;
+
+/// Handle to an array in a store.
+///
+/// Arrays are [Send] and [Sync] if their store is [Sync],
+/// so that one handle can be shared between threads (all mutable state is behind locks).
+pub struct Array<'s, S: Store + ?Sized, T: ReflectedType> {
     store: &'s S,
     key: NodeKey,
     meta_key: NodeKey,
     metadata: ArrayMetadata,
     fill_value: T,
+    decode_options: DecodeOptions,
+    out_of_bounds: OutOfBoundsPolicy,
+    attribute_limits: Option<AttributeLimits>,
+    write_dedup: Option<DedupCache>,
+    buffer_pool: BufferPool,
+    prefetch: Option<Mutex<StrideDetector>>,
+    #[cfg(feature = "parallel")]
+    thread_pool: Option<Arc<ThreadPool>>,
 }
 
-impl<'s, S: Store, T: ReflectedType> Ndim for Array<'s, S, T> {
+impl<'s, S: Store + ?Sized, T: ReflectedType> Ndim for Array<'s, S, T> {
     fn ndim(&self) -> usize {
         self.metadata.ndim()
     }
 }
 
-impl<'s, S: Store, T: ReflectedType> ReadableMetadata for Array<'s, S, T> {
+impl<'s, S: Store + ?Sized, T: ReflectedType> ReadableMetadata for Array<'s, S, T> {
     fn get_zarr_format(&self) -> usize {
         self.metadata.get_zarr_format()
     }
@@ -372,19 +1011,17 @@ impl<'s, S: Store, T: ReflectedType> ReadableMetadata for Array<'s, S, T> {
     }
 }
 
-impl<'s, S: WriteableStore, T: ReflectedType> WriteableMetadata for Array<'s, S, T> {
+impl<'s, S: WriteableStore + ?Sized, T: ReflectedType> WriteableMetadata for Array<'s, S, T> {
     fn mutate_attributes<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut JsonObject) -> R,
     {
-        let result = self.metadata.mutate_attributes(f);
         // todo: fix this error
-        self.write_meta().expect("metadata io error");
-        result
+        self.try_mutate_attributes(f).expect("metadata io error")
     }
 }
 
-impl<'s, S: Store, T: ReflectedType> Array<'s, S, T> {
+impl<'s, S: Store + ?Sized, T: ReflectedType> Array<'s, S, T> {
     /// Does not write metadata
     pub(crate) fn new(
         store: &'s S,
@@ -404,116 +1041,1151 @@ impl<'s, S: Store, T: ReflectedType> Array<'s, S, T> {
             meta_key,
             metadata,
             fill_value,
+            decode_options: Default::default(),
+            out_of_bounds: Default::default(),
+            attribute_limits: None,
+            write_dedup: None,
+            buffer_pool: BufferPool::new(DEFAULT_POOLED_BUFFERS),
+            prefetch: None,
+            #[cfg(feature = "parallel")]
+            thread_pool: None,
         })
     }
 
-    pub fn key(&self) -> &NodeKey {
-        &self.key
+    /// Whether to verify checksums (e.g. from the `crc32c` codec) when reading chunks.
+    ///
+    /// Verification is on by default;
+    /// it should only be disabled for performance-critical reads of trusted data.
+    pub fn with_checksum_verification(mut self, verify: bool) -> Self {
+        self.decode_options.verify_checksums = verify;
+        self
     }
 
-    pub fn meta_key(&self) -> &NodeKey {
-        &self.meta_key
+    pub fn verifies_checksums(&self) -> bool {
+        self.decode_options.verify_checksums
     }
 
-    pub fn store(&self) -> &'s S {
-        self.store
+    /// How to handle stored chunks which contain more data than expected by the codecs.
+    ///
+    /// By default, this is an error.
+    pub fn with_trailing_data_policy(mut self, policy: TrailingDataPolicy) -> Self {
+        self.decode_options.trailing_data = policy;
+        self
     }
 
-    fn chunk_repr(&self, chunk_idx: &GridCoord) -> ArrayRepr<T> {
-        let shape = self.metadata.chunk_grid.chunk_shape(chunk_idx);
-        ArrayRepr::new(shape.as_slice(), self.fill_value)
+    pub fn decode_options(&self) -> &DecodeOptions {
+        &self.decode_options
     }
 
-    fn empty_chunk(&self, chunk_idx: &GridCoord) -> Result<ArcArrayD<T>, &'static str> {
-        let shape = self.metadata.chunk_grid.chunk_shape(chunk_idx);
-
-        let arr = ArcArrayD::from_elem(
-            shape.into_iter().map(|s| s as usize).collect::<Vec<_>>(),
-            self.fill_value,
-        );
-        Ok(arr)
+    /// How [Array::write_region] handles regions extending beyond the array.
+    ///
+    /// By default, such regions are clipped.
+    pub fn with_out_of_bounds_policy(mut self, policy: OutOfBoundsPolicy) -> Self {
+        self.out_of_bounds = policy;
+        self
     }
-}
 
-impl<'s, S: ReadableStore, T: ReflectedType> Array<'s, S, T> {
-    pub fn from_store(store: &'s S, key: NodeKey) -> io::Result<Self> {
-        let mut meta_key = key.clone();
-        meta_key.with_metadata();
-        if let Some(r) = store.get(&meta_key)? {
-            let meta: ArrayMetadata = serde_json::from_reader(r).expect("deser error");
-            Ok(Self::new(store, key, meta).unwrap())
-        } else {
-            Err(io::Error::new(
-                ErrorKind::NotFound,
-                "Group metadata not found",
-            ))
-        }
+    pub fn out_of_bounds_policy(&self) -> OutOfBoundsPolicy {
+        self.out_of_bounds
     }
 
-    /// Read a chunk from the array.
+    /// Whether [Array::write_chunk] skips writing chunks whose encoded bytes are identical to those already stored,
+    /// e.g. when re-running an ingest pipeline against a store which charges per write.
     ///
-    /// `Err` if IO problems; `Ok(None)` if out of bounds; panics if idx is the wrong dimensionality; `Ok(Some(array))` otherwise.
-    /// Fills in empty chunks with the fill value.
+    /// Each write is compared against the stored chunk by reading it;
+    /// for stores which support versioning ([ReadableStore::version]),
+    /// the checksum of chunks which this array has written or compared is cached,
+    /// so that later writes of the same chunk only need to fetch its version.
     ///
-    /// Includes padding values for chunks which overhang the array.
-    pub fn read_chunk(&self, chunk_idx: &GridCoord) -> io::Result<Option<ArcArrayD<T>>> {
-        if !(self.metadata.chunk_should_exist(chunk_idx)) {
-            return Ok(None);
-        }
+    /// Off by default.
+    pub fn with_write_dedup(mut self, dedup: bool) -> Self {
+        self.write_dedup = dedup.then(Default::default);
+        self
+    }
 
-        let key = self
-            .metadata
-            .chunk_key_encoding
-            .chunk_key(&self.key, chunk_idx);
-        if let Some(r) = self.store.get(&key)? {
-            let arr = self.metadata.codecs.decode(r, self.chunk_repr(chunk_idx));
-            Ok(Some(arr))
-        } else {
-            Ok(Some(self.empty_chunk(chunk_idx).expect("wrong data type")))
-        }
+    pub fn dedups_writes(&self) -> bool {
+        self.write_dedup.is_some()
     }
 
-    fn read_partial_chunk(
-        &self,
-        chunk_idx: &GridCoord,
-        chunk_region: &ArrayRegion,
-    ) -> io::Result<Option<ArcArrayD<T>>> {
-        // todo: check it fits in chunk?
-        if let Some(sub_arr) = self.read_chunk(chunk_idx)? {
-            let chunk_slice = chunk_region.slice_info();
-            Ok(Some(sub_arr.slice_move(chunk_slice)))
-        } else {
-            Ok(None)
-        }
+    /// Keep up to `max_buffers` buffers for staging chunks' stored bytes while they are decoded,
+    /// so that reading many chunks does not allocate a buffer for each.
+    ///
+    /// Buffers are sized for a whole chunk's decoded bytes (elements × data type size).
+    /// One buffer is in use per chunk being decoded, so parallel reads benefit from
+    /// as many buffers as threads. 0 disables pooling; the default is 4.
+    /// Replaces the existing pool, resetting its statistics.
+    pub fn with_buffer_pool(mut self, max_buffers: usize) -> Self {
+        self.buffer_pool = BufferPool::new(max_buffers);
+        self
     }
 
-    pub fn read_region(&self, region: ArrayRegion) -> io::Result<Option<ArcArrayD<T>>> {
-        if let Some(reg) = region.limit_extent(&self.metadata.shape) {
-            let mut out =
-                ArcArrayD::from_elem(to_usize(reg.shape().as_slice()).as_slice(), self.fill_value);
-            let it = self.metadata.chunk_grid.chunks_in_region(&reg);
-            for pc in it {
-                if let Some(sub_chunk) = self.read_partial_chunk(&pc.chunk_idx, &pc.chunk_region)? {
-                    let out_slice = pc.out_region.slice_info();
-                    sub_chunk.assign_to(out.slice_mut(out_slice));
-                }
-            }
-            Ok(Some(out))
-        } else {
-            Ok(None)
-        }
+    /// Statistics of the pool of decode buffers, e.g. to choose its size
+    /// (see [Array::with_buffer_pool]).
+    pub fn buffer_pool_stats(&self) -> PoolStats {
+        self.buffer_pool.stats()
     }
-}
 
-impl<'s, S: ListableStore, T: ReflectedType> Array<'s, S, T> {
-    pub fn child_keys(&self) -> io::Result<Vec<NodeKey>> {
+    /// Watch which chunks are read and, once they are being stepped through with a constant stride
+    /// (e.g. a sweep through the array for visualisation), hint the next `distance` chunks of the scan
+    /// to the store with [ReadableStore::prefetch].
+    ///
+    /// Hints only help with stores which act on them, such as a [CachingStore](crate::store::CachingStore);
+    /// with sharding, the hinted keys are those of shards.
+    /// 0 disables prefetching, which is the default.
+    /// Replaces the existing access history.
+    pub fn with_prefetch(mut self, distance: usize) -> Self {
+        self.prefetch = (distance > 0).then(|| Mutex::new(StrideDetector::new(distance)));
+        self
+    }
+
+    /// How many chunks ahead are hinted when a scan is detected, if prefetching is enabled
+    /// (see [Array::with_prefetch]).
+    pub fn prefetch_distance(&self) -> Option<usize> {
+        self.prefetch.as_ref().map(|d| d.lock().unwrap().distance())
+    }
+
+    /// Limits checked by [Array::validate_attributes] before every metadata write.
+    ///
+    /// By default, attributes are not checked.
+    pub fn with_attribute_limits(mut self, limits: AttributeLimits) -> Self {
+        self.attribute_limits = Some(limits);
+        self
+    }
+
+    pub fn attribute_limits(&self) -> Option<&AttributeLimits> {
+        self.attribute_limits.as_ref()
+    }
+
+    /// Check the attributes against this array's [AttributeLimits], if any.
+    ///
+    /// Violations are [ErrorKind::InvalidInput] errors.
+    pub fn validate_attributes(&self) -> io::Result<()> {
+        match &self.attribute_limits {
+            Some(limits) => limits
+                .validate_attributes(self.metadata.get_attributes())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Thread pool used by the parallel region methods
+    /// ([Array::par_read_region], [Array::par_write_region]).
+    ///
+    /// This crate never creates a pool of its own (including rayon's global pool):
+    /// without one, the parallel methods run on the calling thread.
+    #[cfg(feature = "parallel")]
+    pub fn with_thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(&self) -> Option<&Arc<ThreadPool>> {
+        self.thread_pool.as_ref()
+    }
+
+    pub fn key(&self) -> &NodeKey {
+        &self.key
+    }
+
+    pub fn meta_key(&self) -> &NodeKey {
+        &self.meta_key
+    }
+
+    pub(crate) fn metadata(&self) -> &ArrayMetadata {
+        &self.metadata
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.metadata.shape
+    }
+
+    /// Number of chunks needed to cover the whole array.
+    pub fn expected_chunk_count(&self) -> u64 {
+        self.metadata
+            .chunk_grid
+            .grid_shape_unchecked(&self.metadata.shape)
+            .iter()
+            .product()
+    }
+
+    pub fn store(&self) -> &'s S {
+        self.store
+    }
+
+    /// The same array in a different store with the same layout, e.g. a local mirror of a remote store.
+    ///
+    /// Re-uses this array's parsed metadata and options rather than reading the metadata again,
+    /// so that reads can fail over between mirrors cheaply.
+    /// The other store's metadata is not checked, and is not written by this method.
+    pub fn with_store<'s2, S2: Store + ?Sized>(&self, store: &'s2 S2) -> Array<'s2, S2, T> {
+        Array {
+            store,
+            key: self.key.clone(),
+            meta_key: self.meta_key.clone(),
+            metadata: self.metadata.clone(),
+            fill_value: self.fill_value,
+            decode_options: self.decode_options,
+            out_of_bounds: self.out_of_bounds,
+            attribute_limits: self.attribute_limits,
+            write_dedup: self.write_dedup.as_ref().map(|_| Default::default()),
+            buffer_pool: BufferPool::new(self.buffer_pool.max_buffers()),
+            prefetch: self
+                .prefetch_distance()
+                .map(|d| Mutex::new(StrideDetector::new(d))),
+            #[cfg(feature = "parallel")]
+            thread_pool: self.thread_pool.clone(),
+        }
+    }
+
+    /// Estimate the chunk IO which writing a region of the given shape at the given offset would need,
+    /// without touching the store; e.g. to choose tile sizes which align with chunks.
+    ///
+    /// The region is clipped to the array, as by [OutOfBoundsPolicy::Clip].
+    /// Byte counts use the encoded size of each chunk where the codecs can compute it,
+    /// and the decoded size otherwise (e.g. if compressed), so are an upper bound for compressible data.
+    pub fn plan_write_region(
+        &self,
+        offset: &GridCoord,
+        shape: &[u64],
+    ) -> Result<WritePlan, &'static str> {
+        if offset.len() != self.ndim() || shape.len() != self.ndim() {
+            return Err("Offset or shape has wrong dimensionality");
+        }
+        let mut plan = WritePlan::default();
+        let Some(region) = ArrayRegion::from_offset_shape_unchecked(offset, shape)
+            .limit_extent_unchecked(&self.metadata.shape)
+        else {
+            return Ok(plan);
+        };
+        let sharded = matches!(
+            self.metadata.codecs.ab_codec(),
+            ABCodecType::ShardingIndexed(_)
+        );
+
+        for pc in self.metadata.chunk_grid.chunks_in_region_unchecked(&region) {
+            let repr = self.chunk_repr(&pc.chunk_idx);
+            let decoded_size =
+                repr.shape.iter().product::<u64>() * self.metadata.data_type.nbytes() as u64;
+            let encoded_size = self
+                .metadata
+                .codecs
+                .compute_encoded_size(repr.clone())
+                .map_or(decoded_size, |n| n as u64);
+
+            if pc.chunk_region.is_whole_unchecked(&repr.shape) {
+                plan.whole_chunks += 1;
+            } else {
+                plan.partial_chunks += 1;
+                plan.bytes_read += encoded_size;
+            }
+            plan.bytes_written += encoded_size;
+            if sharded {
+                plan.shards += 1;
+            }
+        }
+        Ok(plan)
+    }
+
+    fn chunk_repr(&self, chunk_idx: &GridCoord) -> ArrayRepr<T> {
+        let shape = self.metadata.chunk_grid.chunk_shape_unchecked(chunk_idx);
+        ArrayRepr::new(shape.as_slice(), self.fill_value)
+    }
+
+    fn chunk_stats_key(&self) -> NodeKey {
+        let mut key = self.key.clone();
+        key.push(NodeName::new_unchecked(CHUNK_STATS_NAME.to_owned()));
+        key
+    }
+
+    fn inline_chunks_key(&self) -> NodeKey {
+        let mut key = self.key.clone();
+        key.push(NodeName::new_unchecked(INLINE_CHUNKS_NAME.to_owned()));
+        key
+    }
+
+    fn empty_chunk(&self, chunk_idx: &GridCoord) -> Result<ArcArrayD<T>, &'static str> {
+        let shape = self.metadata.chunk_grid.chunk_shape_unchecked(chunk_idx);
+
+        let arr = ArcArrayD::from_elem(
+            shape.into_iter().map(|s| s as usize).collect::<Vec<_>>(),
+            self.fill_value,
+        );
+        Ok(arr)
+    }
+}
+
+impl<'s, S: ReadableStore + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// Open an existing array, using the default [ExtensionPolicy].
+    pub fn from_store(store: &'s S, key: NodeKey) -> io::Result<Self> {
+        Self::from_store_with_extensions(store, key, &ExtensionPolicy::default())
+    }
+
+    /// Open an existing array whose extensions are permitted by the given policy.
+    ///
+    /// Extensions which are not permitted are an [ErrorKind::InvalidData] error
+    /// wrapping an [ExtensionError].
+    pub fn from_store_with_extensions(
+        store: &'s S,
+        key: NodeKey,
+        extensions: &ExtensionPolicy,
+    ) -> io::Result<Self> {
+        Self::open(store, key, extensions, false)
+    }
+
+    /// As [Array::from_store_with_extensions],
+    /// but coercing fill values which are sloppily encoded but unambiguous
+    /// (see [ArrayMetadata::coerce_fill_value]) rather than failing,
+    /// and allowing sharding sub-chunks which overhang the shards.
+    ///
+    /// The stored metadata is not modified.
+    pub fn from_store_lenient(
+        store: &'s S,
+        key: NodeKey,
+        extensions: &ExtensionPolicy,
+    ) -> io::Result<Self> {
+        Self::open(store, key, extensions, true)
+    }
+
+    fn open(
+        store: &'s S,
+        key: NodeKey,
+        extensions: &ExtensionPolicy,
+        lenient: bool,
+    ) -> io::Result<Self> {
+        let mut meta_key = key.clone();
+        meta_key.with_metadata();
+        if let Some(mut r) = store.get(&meta_key)? {
+            let mut bytes = Vec::default();
+            r.read_to_end(&mut bytes)?;
+            let mut meta = parse_array_meta_unvalidated(&bytes)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            meta.check_extensions(extensions)?;
+            meta.overhanging_sub_chunks = lenient;
+            meta.validate_sharding()?;
+            if lenient {
+                meta.coerce_fill_value();
+            }
+            Self::new(store, key, meta).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+        } else {
+            Err(io::Error::new(
+                ErrorKind::NotFound,
+                "Group metadata not found",
+            ))
+        }
+    }
+
+    /// Read a chunk from the array.
+    ///
+    /// `Err` if IO problems or if idx is the wrong dimensionality ([ErrorKind::InvalidInput]);
+    /// `Ok(None)` if out of bounds; `Ok(Some(array))` otherwise.
+    /// Fills in empty chunks with the fill value.
+    ///
+    /// Includes padding values for chunks which overhang the array.
+    pub fn read_chunk(&self, chunk_idx: &GridCoord) -> io::Result<Option<ArcArrayD<T>>> {
+        let inline = self.read_inline_chunks_document()?;
+        self.read_chunk_with(chunk_idx, inline.as_ref())
+    }
+
+    /// `None` if chunk inlining is not enabled.
+    fn read_inline_chunks_document(&self) -> io::Result<Option<InlineChunksDocument>> {
+        if self.metadata.inline_chunk_max_size().is_none() {
+            return Ok(None);
+        }
+        match self.store.get(&self.inline_chunks_key())? {
+            Some(r) => serde_json::from_reader(r)
+                .map(Some)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+            None => Ok(Some(Default::default())),
+        }
+    }
+
+    /// As [Array::read_chunk], using an already-read inline chunks document.
+    fn read_chunk_with(
+        &self,
+        chunk_idx: &GridCoord,
+        inline: Option<&InlineChunksDocument>,
+    ) -> io::Result<Option<ArcArrayD<T>>> {
+        if !(self.metadata.chunk_should_exist(chunk_idx)?) {
+            return Ok(None);
+        }
+
+        match self.read_present_chunk(chunk_idx, inline)? {
+            Some(arr) => Ok(Some(arr)),
+            None => Ok(Some(self.empty_chunk(chunk_idx).expect("wrong data type"))),
+        }
+    }
+
+    /// Decode a chunk from the inline chunks document or the store; `None` if it is in neither.
+    ///
+    /// Does not check that the chunk is in bounds.
+    fn read_present_chunk(
+        &self,
+        chunk_idx: &GridCoord,
+        inline: Option<&InlineChunksDocument>,
+    ) -> io::Result<Option<ArcArrayD<T>>> {
+        if let Some(payload) = inline.and_then(|doc| doc.get(&chunk_id(chunk_idx))) {
+            let bytes = decode_payload(payload)?;
+            let arr = self.metadata.codecs.decode_with(
+                bytes.as_slice(),
+                self.chunk_repr(chunk_idx),
+                &self.decode_options,
+            )?;
+            return Ok(Some(arr));
+        }
+        self.read_stored_chunk(chunk_idx)
+    }
+
+    /// Decode a chunk from the store (not the inline chunks document); `None` if it is not stored.
+    ///
+    /// The stored bytes are read into a buffer from the array's pool before decoding.
+    fn read_stored_chunk(&self, chunk_idx: &GridCoord) -> io::Result<Option<ArcArrayD<T>>> {
+        self.hint_prefetch(chunk_idx);
+        let key = self
+            .metadata
+            .chunk_key_encoding
+            .chunk_key(&self.key, chunk_idx);
+        let Some(mut r) = self.store.get(&key)? else {
+            return Ok(None);
+        };
+        let repr = self.chunk_repr(chunk_idx);
+        let mut buf = self.buffer_pool.acquire(repr.nbytes());
+        r.read_to_end(&mut buf)?;
+        drop(r);
+        self.metadata
+            .codecs
+            .decode_with(buf.as_slice(), repr, &self.decode_options)
+            .map(Some)
+    }
+
+    /// Record a chunk access and pass any predicted chunks within the grid to the store.
+    fn hint_prefetch(&self, chunk_idx: &GridCoord) {
+        let Some(detector) = self.prefetch.as_ref() else {
+            return;
+        };
+        let predicted = detector.lock().unwrap().record(chunk_idx);
+        if predicted.is_empty() {
+            return;
+        }
+        let grid_shape = self
+            .metadata
+            .chunk_grid
+            .grid_shape_unchecked(&self.metadata.shape);
+        let keys: Vec<_> = predicted
+            .iter()
+            .filter(|idx| idx.iter().zip(grid_shape.iter()).all(|(i, n)| i < n))
+            .map(|idx| self.metadata.chunk_key_encoding.chunk_key(&self.key, idx))
+            .collect();
+        if !keys.is_empty() {
+            self.store.prefetch(&keys);
+        }
+    }
+
+    /// Read the part of a chunk which is within a region; `None` if the chunk is not stored,
+    /// in which case that part of the region is the fill value.
+    fn read_partial_chunk(
+        &self,
+        pc: &PartialChunk,
+        inline: Option<&InlineChunksDocument>,
+    ) -> io::Result<Option<ArcArrayD<T>>> {
+        Ok(self
+            .read_present_chunk(&pc.chunk_idx, inline)?
+            .map(|arr| arr.slice_move(pc.chunk_region.slice_info())))
+    }
+
+    /// Write the part of a chunk which is within a region into that region's output.
+    ///
+    /// Every element of `out` is written, so it need not be initialised with the fill value.
+    fn copy_partial_chunk(
+        &self,
+        sub_chunk: Option<ArcArrayD<T>>,
+        mut out: ndarray::ArrayViewMutD<T>,
+    ) {
+        match sub_chunk {
+            Some(arr) => arr.assign_to(out),
+            None => out.fill(self.fill_value),
+        }
+    }
+
+    /// Read several sub-regions ("tiles") of a single chunk, decoding it only once.
+    ///
+    /// Regions are relative to the chunk's origin.
+    /// Each tile is an independent copy, so that the decoded chunk can be dropped.
+    ///
+    /// `Ok(None)` if the chunk is out of bounds;
+    /// `Err` with [ErrorKind::InvalidInput] if any region does not lie within the chunk.
+    pub fn read_chunk_tiles(
+        &self,
+        chunk_idx: &GridCoord,
+        regions: &[ArrayRegion],
+    ) -> io::Result<Option<Vec<ArcArrayD<T>>>> {
+        let chunk_shape = self.metadata.chunk_grid.chunk_shape(chunk_idx)?;
+        for reg in regions.iter() {
+            if reg.ndim() != chunk_shape.len()
+                || reg.end().iter().zip(chunk_shape.iter()).any(|(e, s)| e > s)
+            {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "Tile region is not within chunk",
+                ));
+            }
+        }
+
+        let Some(chunk) = self.read_chunk(chunk_idx)? else {
+            return Ok(None);
+        };
+        let tiles = regions
+            .iter()
+            .map(|reg| chunk.slice(reg.slice_info()).to_shared())
+            .collect();
+        Ok(Some(tiles))
+    }
+
+    /// URIs of every chunk in the array's grid, in C order of chunk index,
+    /// for handing to external transfer tools (see [ReadableStore::uri]).
+    ///
+    /// Not every chunk necessarily exists: chunks which only contain the fill value may not be stored,
+    /// and inlined chunks are stored in the inline chunks document.
+    /// For listable stores, [Array::stored_chunk_uris] only includes chunks which exist.
+    ///
+    /// `Err` with [ErrorKind::Unsupported] if the store cannot provide URIs.
+    pub fn chunk_uris(&self) -> io::Result<Vec<(GridCoord, String)>> {
+        let grid_shape = self
+            .metadata
+            .chunk_grid
+            .grid_shape_unchecked(&self.metadata.shape);
+        CIter::new(grid_shape)
+            .map(|idx| {
+                let uri = self.chunk_uri(&idx)?;
+                Ok((idx, uri))
+            })
+            .collect()
+    }
+
+    fn chunk_uri(&self, chunk_idx: &GridCoord) -> io::Result<String> {
+        let key = self
+            .metadata
+            .chunk_key_encoding
+            .chunk_key(&self.key, chunk_idx);
+        self.store.uri(&key)?.ok_or_else(|| {
+            io::Error::new(ErrorKind::Unsupported, "Store cannot provide URIs of keys")
+        })
+    }
+
+    fn read_chunk_stats_document(&self) -> io::Result<ChunkStatsDocument<T>> {
+        match self.store.get(&self.chunk_stats_key())? {
+            Some(r) => {
+                serde_json::from_reader(r).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+            }
+            None => Ok(Default::default()),
+        }
+    }
+
+    /// Read the recorded statistics for a single chunk.
+    ///
+    /// `Ok(None)` if no statistics were recorded, including for chunks which were never written
+    /// or which only contain the fill value.
+    pub fn chunk_stats(&self, chunk_idx: &GridCoord) -> io::Result<Option<ChunkStats<T>>> {
+        Ok(self
+            .read_chunk_stats_document()?
+            .remove(&chunk_id(chunk_idx)))
+    }
+
+    /// Read the recorded statistics for all chunks which have them.
+    pub fn all_chunk_stats(&self) -> io::Result<HashMap<GridCoord, ChunkStats<T>>> {
+        self.read_chunk_stats_document()?
+            .into_iter()
+            .map(|(id, stats)| {
+                parse_chunk_id(&id).map(|idx| (idx, stats)).ok_or_else(|| {
+                    io::Error::new(ErrorKind::InvalidData, "Invalid chunk index in statistics")
+                })
+            })
+            .collect()
+    }
+
+    /// Estimate the range of values in a region from recorded chunk statistics, without reading chunk data.
+    ///
+    /// Whole chunks are considered, so the range may be wider than the region's true range.
+    /// Chunks without statistics are assumed to contain only the fill value.
+    /// `Ok(None)` if the region is out of bounds or the data type is unordered.
+    pub fn region_stats(&self, region: &ArrayRegion) -> io::Result<Option<ChunkStats<T>>> {
+        let Some(reg) = region.limit_extent(&self.metadata.shape)? else {
+            return Ok(None);
+        };
+        let doc = self.read_chunk_stats_document()?;
+        let mut stats = Vec::default();
+        let mut has_fill = false;
+        for pc in self.metadata.chunk_grid.chunks_in_region(&reg)? {
+            match doc.get(&chunk_id(&pc.chunk_idx)) {
+                Some(s) => stats.push(*s),
+                None => has_fill = true,
+            }
+        }
+        if has_fill {
+            stats.push(ChunkStats {
+                min: self.fill_value,
+                max: self.fill_value,
+            });
+        }
+        Ok(ChunkStats::merge(stats))
+    }
+
+    pub fn read_region(&self, region: ArrayRegion) -> io::Result<Option<ArcArrayD<T>>> {
+        if let Some(reg) = region.limit_extent(&self.metadata.shape)? {
+            let mut out = ArcArrayD::default(to_usize(reg.shape().as_slice()).as_slice());
+            let it = self.metadata.chunk_grid.chunks_in_region(&reg)?;
+            let inline = self.read_inline_chunks_document()?;
+            for pc in it {
+                let sub_chunk = self.read_partial_chunk(&pc, inline.as_ref())?;
+                self.copy_partial_chunk(sub_chunk, out.slice_mut(pc.out_region.slice_info()));
+            }
+            Ok(Some(out))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Read a region with its axes permuted, as if the array had a transpose codec with the given order:
+    /// axis `i` of the output is axis `order[i]` of the array.
+    ///
+    /// The region is given in the array's axis order.
+    /// Chunks are copied directly into an output which is contiguous (in C order) in the permuted order,
+    /// rather than permuting the assembled region afterwards.
+    ///
+    /// `Ok(None)` if the region is out of bounds;
+    /// `Err` with [ErrorKind::InvalidInput] if `order` is not a permutation of the array's axes.
+    pub fn read_region_permuted(
+        &self,
+        region: ArrayRegion,
+        order: &[usize],
+    ) -> io::Result<Option<ArcArrayD<T>>> {
+        DimensionMismatch::check_coords(order.len(), self.ndim())?;
+        validate_permutation(order).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        let Some(reg) = region.limit_extent(&self.metadata.shape)? else {
+            return Ok(None);
+        };
+        let reg_shape = to_usize(reg.shape().as_slice());
+        let out_shape: Vec<usize> = order.iter().map(|ax| reg_shape[*ax]).collect();
+        let mut out = ArcArrayD::default(out_shape);
+
+        let inline = self.read_inline_chunks_document()?;
+        // the output, viewed in the array's axis order
+        let mut view = out
+            .view_mut()
+            .permuted_axes(reverse_permutation(order).as_slice());
+        for pc in self.metadata.chunk_grid.chunks_in_region(&reg)? {
+            let sub_chunk = self.read_partial_chunk(&pc, inline.as_ref())?;
+            self.copy_partial_chunk(sub_chunk, view.slice_mut(pc.out_region.slice_info()));
+        }
+        Ok(Some(out))
+    }
+
+    /// Read the non-fill values of a region as runs of equal values along the last axis,
+    /// without allocating the dense region.
+    ///
+    /// Chunks are read one at a time, and chunks which are not stored are skipped entirely.
+    /// Runs do not cross chunk boundaries, and are ordered by chunk
+    /// (in C order of chunk index), then in C order within each chunk.
+    ///
+    /// `Ok(None)` if the region is out of bounds.
+    pub fn read_region_sparse(&self, region: ArrayRegion) -> io::Result<Option<Vec<SparseRun<T>>>> {
+        let Some(reg) = region.limit_extent(&self.metadata.shape)? else {
+            return Ok(None);
+        };
+        let origin = reg.offset();
+        let inline = self.read_inline_chunks_document()?;
+        let mut runs: Vec<SparseRun<T>> = Vec::default();
+        for pc in self.metadata.chunk_grid.chunks_in_region(&reg)? {
+            let Some(chunk) = self.read_present_chunk(&pc.chunk_idx, inline.as_ref())? else {
+                continue;
+            };
+
+            let out_offset = pc.out_region.offset();
+            let first_run = runs.len();
+            for (idx, value) in chunk.slice(pc.chunk_region.slice_info()).indexed_iter() {
+                if value.bitwise_eq(&self.fill_value) {
+                    continue;
+                }
+                let coord: GridCoord = ndarray::Dimension::slice(&idx)
+                    .iter()
+                    .zip(out_offset.iter().zip(origin.iter()))
+                    .map(|(i, (o, r))| *i as u64 + o + r)
+                    .collect();
+                // extend the previous run if this voxel follows it on the same row
+                if let Some(run) = runs[first_run..].last_mut() {
+                    let n = coord.len();
+                    if run.value.bitwise_eq(value)
+                        && n > 0
+                        && run.start[..n - 1] == coord[..n - 1]
+                        && run.start[n - 1] + run.len == coord[n - 1]
+                    {
+                        run.len += 1;
+                        continue;
+                    }
+                }
+                runs.push(SparseRun {
+                    start: coord,
+                    len: 1,
+                    value: *value,
+                });
+            }
+        }
+        Ok(Some(runs))
+    }
+
+    /// Compute a deterministic digest of the array's contents, reading and decoding each chunk once.
+    ///
+    /// The decoded digest covers:
+    /// the data type's name as UTF-8, the number of dimensions and then the shape (as little-endian `u64`s),
+    /// and then the (little-endian `u64`) wrapping sum of the digests of every segment of the array's values.
+    /// Segments are the values of each row along the last axis,
+    /// split every 1024 elements from the start of the row;
+    /// each segment's digest covers the row's coordinates on the other axes and the segment's index along the row
+    /// (as little-endian `u64`s), then the values as little-endian bytes.
+    /// Chunks which are not stored contribute the fill value.
+    /// It therefore does not depend on the chunk grid, codecs, chunk key encoding or store;
+    /// only the segments which straddle chunks are held in memory beyond the chunk being read.
+    ///
+    /// If `encoded`, a second digest covers each chunk's stored bytes, with chunks in C order of their indices:
+    /// `0u8` for chunks which are not stored,
+    /// otherwise `1u8`, the length as a little-endian `u64`, and the bytes.
+    pub fn content_digest(
+        &self,
+        algorithm: DigestAlgorithm,
+        encoded: bool,
+    ) -> io::Result<ContentDigest> {
+        let mut decoded_state = DigestState::new(algorithm);
+
+        let dtype = serde_json::to_value(&self.metadata.data_type)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        match dtype.as_str() {
+            Some(name) => decoded_state.update(name.as_bytes()),
+            None => decoded_state.update(dtype.to_string().as_bytes()),
+        }
+        decoded_state.update(&(self.ndim() as u64).to_le_bytes());
+        for s in self.metadata.shape.iter() {
+            decoded_state.update(&s.to_le_bytes());
+        }
+
+        let shape = self.metadata.shape.clone();
+        let nbytes = T::ZARR_TYPE.nbytes();
+        let mut segments =
+            SegmentedDigest::new(algorithm, shape.last().copied().unwrap_or(1), nbytes);
+        let mut encoded_state = encoded.then(|| DigestState::new(algorithm));
+        if shape.iter().all(|s| *s > 0) {
+            let whole = ArrayRegion::from_offset_shape(&vec![0; self.ndim()], shape.as_slice());
+            let inline = self.read_inline_chunks_document()?;
+            let mut buf = Vec::default();
+            for pc in self.metadata.chunk_grid.chunks_in_region(&whole)? {
+                let bytes = self.read_encoded_chunk(&pc.chunk_idx, inline.as_ref())?;
+                if let Some(state) = encoded_state.as_mut() {
+                    match &bytes {
+                        Some(b) => {
+                            state.update(&[1]);
+                            state.update(&(b.len() as u64).to_le_bytes());
+                            state.update(b);
+                        }
+                        None => state.update(&[0]),
+                    }
+                }
+                let chunk = match bytes {
+                    Some(b) => self.metadata.codecs.decode_with(
+                        b.as_slice(),
+                        self.chunk_repr(&pc.chunk_idx),
+                        &self.decode_options,
+                    )?,
+                    None => self
+                        .empty_chunk(&pc.chunk_idx)
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?,
+                };
+                buf.clear();
+                T::write_array_to(
+                    chunk.slice(pc.chunk_region.slice_info()).to_shared(),
+                    &mut buf,
+                    Endian::Little,
+                )?;
+
+                let offset = pc.out_region.offset();
+                let region_shape = pc.chunk_region.shape();
+                let (leading_offset, start) = match offset.split_last() {
+                    Some((last, leading)) => (leading, *last),
+                    None => (&[][..], 0),
+                };
+                let leading_shape = &region_shape[..leading_offset.len()];
+                let row_nbytes = region_shape.last().copied().unwrap_or(1) as usize * nbytes;
+                let mut leading: GridCoord = leading_offset.into();
+                for row in buf.chunks_exact(row_nbytes) {
+                    segments.update_row(&leading, start, row);
+                    // next row in C order
+                    for (axis, c) in leading.iter_mut().enumerate().rev() {
+                        *c += 1;
+                        if *c < leading_offset[axis] + leading_shape[axis] {
+                            break;
+                        }
+                        *c = leading_offset[axis];
+                    }
+                }
+            }
+        }
+        decoded_state.update(&segments.finish().to_le_bytes());
+
+        Ok(ContentDigest {
+            algorithm,
+            decoded: decoded_state.finish(),
+            encoded: encoded_state.map(|s| s.finish()),
+        })
+    }
+
+    /// A chunk's encoded bytes, exactly as stored (inline or under its own key); `None` if it is not stored.
+    fn read_encoded_chunk(
+        &self,
+        idx: &GridCoord,
+        inline: Option<&InlineChunksDocument>,
+    ) -> io::Result<Option<Vec<u8>>> {
+        if let Some(payload) = inline.and_then(|doc| doc.get(&chunk_id(idx))) {
+            return decode_payload(payload).map(Some);
+        }
+        let key = self.metadata.chunk_key_encoding.chunk_key(&self.key, idx);
+        match self.store.get(&key)? {
+            Some(mut r) => {
+                let mut bytes = Vec::default();
+                r.read_to_end(&mut bytes)?;
+                Ok(Some(bytes))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Write the given chunks' encoded bytes as [chunk exchange records](super::chunk_exchange),
+    /// returning the indices of the chunks which were written.
+    ///
+    /// Chunks which are not stored (i.e. are entirely the fill value) are skipped.
+    /// `Err` with [ErrorKind::InvalidInput] if any index is outside the chunk grid.
+    pub fn export_chunks<W: Write>(
+        &self,
+        idxs: &[GridCoord],
+        w: &mut W,
+    ) -> io::Result<Vec<GridCoord>> {
+        let inline = self.read_inline_chunks_document()?;
+        let mut exported = Vec::default();
+        for idx in idxs {
+            if !self.chunk_in_grid(idx)? {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "Chunk index is outside the chunk grid",
+                ));
+            }
+            if let Some(data) = self.read_encoded_chunk(idx, inline.as_ref())? {
+                ChunkRecord::new(&self.metadata, idx, data).write_to(w)?;
+                exported.push(idx.clone());
+            }
+        }
+        Ok(exported)
+    }
+
+    /// Whether the chunk index is within the chunk grid; `Err` if it has the wrong dimensionality.
+    fn chunk_in_grid(&self, idx: &GridCoord) -> io::Result<bool> {
+        DimensionMismatch::check_coords(idx.len(), self.ndim())?;
+        let grid_shape = self
+            .metadata
+            .chunk_grid
+            .grid_shape_unchecked(&self.metadata.shape);
+        Ok(idx.iter().zip(grid_shape.iter()).all(|(i, n)| i < n))
+    }
+
+    /// Read individual voxels, returning their values in the order requested.
+    ///
+    /// Voxels are grouped by chunk so that each chunk is read at most once.
+    /// If the codecs allow it (i.e. only the `bytes` codec is used),
+    /// each value is fetched with a single byte-range request rather than decoding its chunk,
+    /// and all requests are made in one call to [ReadableStore::get_partial_values].
+    ///
+    /// `Err` with [ErrorKind::InvalidInput] if any voxel has the wrong dimensionality or is out of bounds.
+    pub fn read_values(&self, coords: &[GridCoord]) -> io::Result<Vec<T>> {
+        let mut by_chunk: HashMap<GridCoord, Vec<(usize, GridCoord)>> = HashMap::default();
+        for (idx, coord) in coords.iter().enumerate() {
+            DimensionMismatch::check_coords(coord.len(), self.ndim())?;
+            if coord
+                .iter()
+                .zip(self.metadata.shape.iter())
+                .any(|(c, s)| c >= s)
+            {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    "Voxel is out of bounds",
+                ));
+            }
+            let (chunk_idx, offset) = self.metadata.chunk_grid.voxel_chunk_unchecked(coord);
+            by_chunk.entry(chunk_idx).or_default().push((idx, offset));
+        }
+
+        let mut out = vec![self.fill_value; coords.len()];
+        let inline = self.read_inline_chunks_document()?;
+        let byte_ranges = match self.metadata.codecs.ab_codec() {
+            ABCodecType::Bytes(c)
+                if self.metadata.codecs.aa_codecs.is_empty()
+                    && self.metadata.codecs.bb_codecs.is_empty() =>
+            {
+                Some(
+                    self.metadata
+                        .data_type
+                        .valid_endian(c.endian())
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+                )
+            }
+            _ => None,
+        };
+
+        let nbytes = self.metadata.data_type.nbytes();
+        let mut key_ranges = Vec::default();
+        let mut range_idxs = Vec::default();
+        for (chunk_idx, voxels) in by_chunk.iter() {
+            let inlined = inline
+                .as_ref()
+                .is_some_and(|doc| doc.contains_key(&chunk_id(chunk_idx)));
+            if byte_ranges.is_none() || inlined {
+                let chunk = self
+                    .read_chunk_with(chunk_idx, inline.as_ref())?
+                    .expect("chunk is in bounds");
+                for (idx, offset) in voxels.iter() {
+                    out[*idx] = chunk[to_usize(offset).as_slice()];
+                }
+                continue;
+            }
+
+            let key = self
+                .metadata
+                .chunk_key_encoding
+                .chunk_key(&self.key, chunk_idx);
+            let chunk_shape = self.metadata.chunk_grid.chunk_shape_unchecked(chunk_idx);
+            for (idx, offset) in voxels.iter() {
+                // C order
+                let linear = offset
+                    .iter()
+                    .zip(chunk_shape.iter())
+                    .fold(0, |acc, (o, s)| acc * s + o);
+                key_ranges.push((
+                    key.clone(),
+                    RangeRequest::new_range(linear as usize * nbytes, Some(nbytes)),
+                ));
+                range_idxs.push(*idx);
+            }
+        }
+
+        if let Some(endian) = byte_ranges {
+            let mut buf = vec![0; nbytes];
+            let readers = self.store.get_partial_values(&key_ranges)?;
+            for (idx, r) in range_idxs.into_iter().zip(readers) {
+                // missing chunks are the fill value
+                if let Some(mut r) = r {
+                    r.read_exact(&mut buf)?;
+                    out[idx] = T::decode_from(&buf, endian);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'s, S: ReadableStore + Sync + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// As [Array::read_region], but reading and decoding chunks on the array's thread pool.
+    pub fn par_read_region(&self, region: ArrayRegion) -> io::Result<Option<ArcArrayD<T>>> {
+        let Some(pool) = self.thread_pool.as_ref() else {
+            return self.read_region(region);
+        };
+        let Some(reg) = region.limit_extent(&self.metadata.shape)? else {
+            return Ok(None);
+        };
+        let pcs: Vec<_> = self.metadata.chunk_grid.chunks_in_region(&reg)?.collect();
+        let inline = self.read_inline_chunks_document()?;
+        let sub_chunks = pool.install(|| {
+            pcs.into_par_iter()
+                .map(|pc| {
+                    self.read_partial_chunk(&pc, inline.as_ref())
+                        .map(|sc| (pc.out_region, sc))
+                })
+                .collect::<io::Result<Vec<_>>>()
+        })?;
+
+        let mut out = ArcArrayD::default(to_usize(reg.shape().as_slice()).as_slice());
+        for (out_region, sub_chunk) in sub_chunks {
+            self.copy_partial_chunk(sub_chunk, out.slice_mut(out_region.slice_info()));
+        }
+        Ok(Some(out))
+    }
+}
+
+impl<'s, S: ReadableStore + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// Read a spatial region across all channels, producing an interleaved (RGB or RGBA) buffer.
+    ///
+    /// `spatial_region` covers every axis except `channel_axis`,
+    /// which must have length 3 or 4.
+    /// Typically used with `u8` or `u16` arrays for passing to image libraries.
+    ///
+    /// `Ok(None)` if the region is out of bounds.
+    pub fn read_interleaved(
+        &self,
+        spatial_region: &ArrayRegion,
+        channel_axis: usize,
+    ) -> io::Result<Option<InterleavedImage<T>>> {
+        DimensionMismatch::check_coords(spatial_region.ndim() + 1, self.ndim())?;
+        let Some(channels) = self.metadata.shape.get(channel_axis).map(|c| *c as usize) else {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Channel axis out of range",
+            ));
+        };
+        if !(3..=4).contains(&channels) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Channel axis must have length 3 (RGB) or 4 (RGBA)",
+            ));
+        }
+
+        let mut offset = spatial_region.offset();
+        offset.insert(channel_axis, 0);
+        let mut shape = spatial_region.shape();
+        shape.insert(channel_axis, channels as u64);
+        let region = ArrayRegion::from_offset_shape_unchecked(&offset, &shape);
+        let Some(arr) = self.read_region(region)? else {
+            return Ok(None);
+        };
+
+        let mut shape: GridCoord = arr.shape().iter().map(|s| *s as u64).collect();
+        shape.remove(channel_axis);
+        // move the channel axis last so that C order interleaves channels
+        let mut axes: Vec<usize> = (0..arr.ndim()).filter(|a| *a != channel_axis).collect();
+        axes.push(channel_axis);
+        let data = arr.permuted_axes(axes).iter().cloned().collect();
+
+        Ok(Some(InterleavedImage {
+            shape,
+            channels,
+            data,
+        }))
+    }
+}
+
+impl<'s, S: ListableStore + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    pub fn child_keys(&self) -> io::Result<Vec<NodeKey>> {
         let (_, keys) = self.store.list_dir(&self.key)?;
         Ok(keys)
     }
 }
 
-impl<'s, S: WriteableStore, T: ReflectedType> Array<'s, S, T> {
+impl<'s, S: ReadableStore + ListableStore + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// Indices of all chunks which exist in the store, including any outside the array's grid.
+    ///
+    /// Found by listing (without reading) keys, and reading the inline chunks document if enabled.
+    fn stored_chunk_indices(&self) -> io::Result<Vec<GridCoord>> {
+        let ndim = self.ndim();
+        let mut out: Vec<_> = self
+            .store
+            .list_prefix(&self.key)?
+            .iter()
+            .filter_map(|k| {
+                self.metadata
+                    .chunk_key_encoding
+                    .chunk_coord(&self.key, k, ndim)
+            })
+            .collect();
+        if let Some(doc) = self.read_inline_chunks_document()? {
+            out.extend(doc.keys().filter_map(|id| parse_chunk_id(id)));
+        }
+        Ok(out)
+    }
+
+    /// Number of chunks which exist in the store, found by listing (without reading) keys.
+    ///
+    /// Chunks which only contain the fill value are not stored, and so are not counted.
+    pub fn stored_chunk_count(&self) -> io::Result<u64> {
+        let grid_shape = self
+            .metadata
+            .chunk_grid
+            .grid_shape_unchecked(&self.metadata.shape);
+        let count = self
+            .stored_chunk_indices()?
+            .iter()
+            .filter(|c| c.iter().zip(grid_shape.iter()).all(|(i, n)| i < n))
+            .count();
+        Ok(count as u64)
+    }
+
+    /// Fraction of the expected chunks which exist in the store, between 0 and 1.
+    ///
+    /// See [Array::stored_chunk_count] for caveats.
+    pub fn completeness(&self) -> io::Result<f64> {
+        let expected = self.expected_chunk_count();
+        if expected == 0 {
+            return Ok(1.0);
+        }
+        Ok(self.stored_chunk_count()? as f64 / expected as f64)
+    }
+
+    /// As [Array::chunk_uris], but only for chunks within the grid which exist in the store
+    /// as objects of their own (i.e. not inlined), sorted by chunk index.
+    pub fn stored_chunk_uris(&self) -> io::Result<Vec<(GridCoord, String)>> {
+        let grid_shape = self
+            .metadata
+            .chunk_grid
+            .grid_shape_unchecked(&self.metadata.shape);
+        let ndim = self.ndim();
+        let mut indices: Vec<_> = self
+            .store
+            .list_prefix(&self.key)?
+            .iter()
+            .filter_map(|k| {
+                self.metadata
+                    .chunk_key_encoding
+                    .chunk_coord(&self.key, k, ndim)
+            })
+            .filter(|c| c.iter().zip(grid_shape.iter()).all(|(i, n)| i < n))
+            .collect();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .map(|idx| {
+                let uri = self.chunk_uri(&idx)?;
+                Ok((idx, uri))
+            })
+            .collect()
+    }
+}
+
+impl<'s, S: ReadableStore + ListableStore + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// List the size (and optionally checksum) of every key in this array, including metadata.
+    pub fn manifest(&self, checksums: bool) -> io::Result<Manifest> {
+        Manifest::from_store(self.store, &self.key, checksums)
+    }
+}
+
+impl<'s, S: WriteableStore + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// Modify the attributes and write the metadata.
+    ///
+    /// If the new attributes violate the array's [AttributeLimits],
+    /// they are reverted and an [ErrorKind::InvalidInput] error is returned without writing.
+    /// [WriteableMetadata::mutate_attributes] panics instead.
+    pub fn try_mutate_attributes<F, R>(&mut self, f: F) -> io::Result<R>
+    where
+        F: FnOnce(&mut JsonObject) -> R,
+    {
+        let previous = self
+            .attribute_limits
+            .map(|_| self.metadata.get_attributes().clone());
+        let result = self.metadata.mutate_attributes(f);
+        if let Err(e) = self.validate_attributes() {
+            if let Some(prev) = previous {
+                self.metadata.mutate_attributes(|a| *a = prev);
+            }
+            return Err(e);
+        }
+        self.write_meta()?;
+        Ok(result)
+    }
+
     pub(crate) fn write_meta(&self) -> io::Result<()> {
+        self.validate_attributes()?;
         self.store.set(&self.meta_key, |w| {
             serde_json::to_writer_pretty(w, &self.metadata).unwrap();
             Ok(())
@@ -521,103 +2193,723 @@ impl<'s, S: WriteableStore, T: ReflectedType> Array<'s, S, T> {
         Ok(())
     }
 
+    /// Store chunks from [chunk exchange records](super::chunk_exchange) (e.g. from [Array::export_chunks]),
+    /// reading records until the end of the input, and returning the indices of the imported chunks.
+    ///
+    /// The encoded bytes are stored exactly as exported, under this array's chunk keys
+    /// (replacing any inline copy).
+    /// If the array records chunk statistics, each imported chunk is decoded to recompute them.
+    /// Each record's array must encode chunks identically to this one (same data type, chunk grid, codecs and fill value),
+    /// and its chunk must be within this array's grid; otherwise, an [ErrorKind::InvalidData] error is returned
+    /// and no further records are imported.
+    pub fn import_chunks<R: Read>(&self, r: &mut R) -> io::Result<Vec<GridCoord>> {
+        let mut imported = Vec::default();
+        let mut updates = DocumentUpdates::default();
+        let result = loop {
+            match ChunkRecord::read_from(r) {
+                Ok(Some(record)) => match self.import_chunk(record, &mut updates) {
+                    Ok(idx) => imported.push(idx),
+                    Err(e) => break Err(e),
+                },
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        // record the chunks which were imported, even if a later record failed
+        let applied = self
+            .apply_document_updates(updates)
+            .map_err(io::Error::other);
+        result.and(applied)?;
+        Ok(imported)
+    }
+
+    fn import_chunk(
+        &self,
+        record: ChunkRecord,
+        updates: &mut DocumentUpdates<T>,
+    ) -> io::Result<GridCoord> {
+        record
+            .header
+            .check_compatible(&self.metadata)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let idx: GridCoord = record.header.chunk.iter().copied().collect();
+        if !self
+            .chunk_in_grid(&idx)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Chunk index is outside the chunk grid",
+            ));
+        }
+        let stats = if self.metadata.records_chunk_stats() {
+            let chunk = self.metadata.codecs.decode_with(
+                record.data.as_slice(),
+                self.chunk_repr(&idx),
+                &self.decode_options,
+            )?;
+            ChunkStats::from_values(chunk.iter())
+        } else {
+            None
+        };
+        let key = self.metadata.chunk_key_encoding.chunk_key(&self.key, &idx);
+        self.store.set(&key, |w| w.write_all(&record.data))?;
+        if self.metadata.inline_chunk_max_size().is_some() {
+            updates.inline.insert(chunk_id(&idx), None);
+        }
+        self.defer_chunk_stats(&idx, stats, updates);
+        Ok(idx)
+    }
+
     pub fn write_chunk(&self, idx: &GridCoord, chunk: ArcArrayD<T>) -> Result<(), &'static str> {
-        let shape = self.metadata.chunk_grid.chunk_shape(idx);
-        if chunk
-            .shape()
-            .iter()
-            .zip(shape.iter())
-            .any(|(sh, exp)| *sh as u64 != *exp)
+        let mut updates = DocumentUpdates::default();
+        let result = self.write_chunk_deferred(idx, chunk, &mut updates);
+        let applied = self.apply_document_updates(updates);
+        result.and(applied)
+    }
+
+    /// As [Array::write_chunk], collecting changes to the inline chunks and chunk statistics documents
+    /// rather than writing them.
+    fn write_chunk_deferred(
+        &self,
+        idx: &GridCoord,
+        chunk: ArcArrayD<T>,
+        updates: &mut DocumentUpdates<T>,
+    ) -> Result<(), &'static str> {
+        let shape = self
+            .metadata
+            .chunk_grid
+            .chunk_shape(idx)
+            .map_err(|_| "Chunk index has wrong dimensionality")?;
+        if chunk.ndim() != shape.len()
+            || chunk
+                .shape()
+                .iter()
+                .zip(shape.iter())
+                .any(|(sh, exp)| *sh as u64 != *exp)
         {
             return Err("Chunk is the wrong shape");
         }
+        if chunk.iter().all(|v| v.bitwise_eq(&self.fill_value)) {
+            return self.erase_chunk_deferred(idx, updates);
+        }
+
         let key = self.metadata.chunk_key_encoding.chunk_key(&self.key, idx);
-        if chunk.iter().all(|v| v == &self.fill_value) {
-            return self
-                .store
-                .erase(&key)
-                .map(|_| ())
-                .map_err(|_| "Could not erase chunk of fill value");
+        let stats = if self.metadata.records_chunk_stats() {
+            ChunkStats::from_values(chunk.iter())
+        } else {
+            None
+        };
+
+        if let Some(max_size) = self.metadata.inline_chunk_max_size() {
+            let mut buf = Vec::default();
+            self.metadata
+                .codecs
+                .encode_with_fill(chunk, self.fill_value, &mut buf)
+                .map_err(|_| "Could not encode chunk")?;
+            if buf.len() <= max_size {
+                updates
+                    .inline
+                    .insert(chunk_id(idx), Some(encode_payload(&buf)));
+                updates.inlined_keys.push(key);
+            } else {
+                self.store
+                    .set(&key, |w| w.write_all(&buf))
+                    .map_err(|_| "Could not get chunk writer")?;
+                updates.inline.insert(chunk_id(idx), None);
+            }
+            self.defer_chunk_stats(idx, stats, updates);
+            return Ok(());
+        }
+
+        if let Some(cache) = &self.write_dedup {
+            let mut buf = Vec::default();
+            self.metadata
+                .codecs
+                .encode_with_fill(chunk, self.fill_value, &mut buf)
+                .map_err(|_| "Could not encode chunk")?;
+            let crc = crc32c::crc32c(&buf);
+            if !self
+                .stored_chunk_matches(cache, &key, &buf, crc)
+                .map_err(|_| "Could not read existing chunk")?
+            {
+                self.store
+                    .set(&key, |w| w.write_all(&buf))
+                    .map_err(|_| "Could not get chunk writer")?;
+                let version = self
+                    .store
+                    .version(&key)
+                    .map_err(|_| "Could not read chunk version")?;
+                let mut cache = cache.lock().unwrap();
+                match version {
+                    Some(v) => cache.insert(key, (crc, buf.len(), v)),
+                    None => cache.remove(&key),
+                };
+            }
+            self.defer_chunk_stats(idx, stats, updates);
+            return Ok(());
         }
 
         self.store
             .set(&key, move |w| {
-                self.metadata.codecs.encode(chunk, w);
-                Ok(())
+                self.metadata
+                    .codecs
+                    .encode_with_fill(chunk, self.fill_value, w)
             })
-            .map_err(|_| "Could not get chunk writer")
+            .map_err(|_| "Could not write chunk")?;
+        self.defer_chunk_stats(idx, stats, updates);
+        Ok(())
+    }
+
+    /// Whether the stored chunk is identical to the given encoded bytes (with the given checksum).
+    ///
+    /// Uses the cached checksum if the stored version is unchanged,
+    /// otherwise reads the stored chunk.
+    fn stored_chunk_matches(
+        &self,
+        cache: &DedupCache,
+        key: &NodeKey,
+        encoded: &[u8],
+        crc: u32,
+    ) -> io::Result<bool> {
+        let cached = cache.lock().unwrap().get(key).cloned();
+        if let Some((cached_crc, cached_len, cached_version)) = cached {
+            if self.store.version(key)?.as_ref() == Some(&cached_version) {
+                return Ok(cached_crc == crc && cached_len == encoded.len());
+            }
+        }
+
+        let Some((mut r, version)) = self.store.get_versioned(key)? else {
+            return Ok(false);
+        };
+        let mut buf = vec![0; 64 * 1024];
+        let mut offset = 0;
+        loop {
+            let n = r.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            let end = offset + n;
+            if end > encoded.len() || buf[..n] != encoded[offset..end] {
+                return Ok(false);
+            }
+            offset = end;
+        }
+        if offset != encoded.len() {
+            return Ok(false);
+        }
+        if let Some(v) = version {
+            cache
+                .lock()
+                .unwrap()
+                .insert(key.clone(), (crc, encoded.len(), v));
+        }
+        Ok(true)
+    }
+
+    /// Remove a chunk, so that it reads as the fill value,
+    /// collecting changes to the shared documents rather than writing them.
+    fn erase_chunk_deferred(
+        &self,
+        idx: &GridCoord,
+        updates: &mut DocumentUpdates<T>,
+    ) -> Result<(), &'static str> {
+        let key = self.metadata.chunk_key_encoding.chunk_key(&self.key, idx);
+        self.store
+            .erase(&key)
+            .map_err(|_| "Could not erase chunk")?;
+        if self.metadata.inline_chunk_max_size().is_some() {
+            updates.inline.insert(chunk_id(idx), None);
+        }
+        self.defer_chunk_stats(idx, None, updates);
+        Ok(())
+    }
+
+    fn defer_chunk_stats(
+        &self,
+        idx: &GridCoord,
+        stats: Option<ChunkStats<T>>,
+        updates: &mut DocumentUpdates<T>,
+    ) {
+        if self.metadata.records_chunk_stats() {
+            updates.stats.insert(chunk_id(idx), stats);
+        }
+    }
+
+    /// Write the collected changes to the inline chunks and chunk statistics documents,
+    /// then erase the stored objects of chunks which were inlined.
+    fn apply_document_updates(&self, updates: DocumentUpdates<T>) -> Result<(), &'static str> {
+        if !updates.inline.is_empty() {
+            self.update_document(
+                &self.inline_chunks_key(),
+                |doc: &mut InlineChunksDocument| {
+                    let mut changed = false;
+                    for (id, payload) in updates.inline.iter() {
+                        changed |= match payload {
+                            Some(p) => doc.insert(id.clone(), p.clone()).as_ref() != Some(p),
+                            None => doc.remove(id).is_some(),
+                        };
+                    }
+                    changed
+                },
+            )
+            .map_err(|_| "Could not write inline chunks")?;
+        }
+        for key in updates.inlined_keys.iter() {
+            self.store.erase(key).map_err(|_| "Could not erase chunk")?;
+        }
+        if !updates.stats.is_empty() {
+            self.update_document(
+                &self.chunk_stats_key(),
+                |doc: &mut ChunkStatsDocument<T>| {
+                    let mut changed = false;
+                    for (id, stats) in updates.stats.iter() {
+                        changed |= match stats {
+                            Some(s) => doc.insert(id.clone(), *s) != Some(*s),
+                            None => doc.remove(id).is_some(),
+                        };
+                    }
+                    changed
+                },
+            )
+            .map_err(|_| "Could not write chunk statistics")?;
+        }
+        Ok(())
+    }
+
+    /// Read the JSON document at the given key (or the default, if there is none),
+    /// apply `modify`, and write it back if `modify` returns `true`.
+    ///
+    /// The document is written with [WriteableStore::set_if_match],
+    /// and re-read and modified again if another writer changed it in the meantime,
+    /// so that concurrent writers do not lose each others' changes.
+    /// Stores which do not support versioning cannot detect this, and the document is overwritten.
+    fn update_document<D, F>(&self, key: &NodeKey, mut modify: F) -> io::Result<()>
+    where
+        D: Default + Serialize + serde::de::DeserializeOwned,
+        F: FnMut(&mut D) -> bool,
+    {
+        for _ in 0..DOCUMENT_UPDATE_ATTEMPTS {
+            let (mut doc, version, exists) = match self.store.get_versioned(key)? {
+                Some((r, version)) => (
+                    serde_json::from_reader(r)
+                        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+                    version,
+                    true,
+                ),
+                None => (D::default(), None, false),
+            };
+            if !modify(&mut doc) {
+                return Ok(());
+            }
+            let write =
+                |w: &mut S::Writeable| serde_json::to_writer(w, &doc).map_err(io::Error::other);
+            if exists && version.is_none() {
+                return self.store.set(key, write);
+            }
+            if self.store.set_if_match(key, version.as_ref(), write)? {
+                return Ok(());
+            }
+        }
+        Err(io::Error::new(
+            ErrorKind::Interrupted,
+            "Document was repeatedly changed by other writers",
+        ))
     }
 
-    fn write_partial_chunk(
+    /// Write part of a chunk, reading the existing chunk with an already-read inline chunks document
+    /// and collecting changes to the shared documents rather than writing them.
+    fn write_partial_chunk_deferred(
         &self,
         chunk_idx: &GridCoord,
         chunk_region: &ArrayRegion,
         sub_chunk: ArcArrayD<T>,
+        inline: Option<&InlineChunksDocument>,
+        updates: &mut DocumentUpdates<T>,
     ) -> Result<(), &'static str> {
         let mut chunk = self
-            .read_chunk(chunk_idx)
+            .read_chunk_with(chunk_idx, inline)
             .map_err(|_e| "IO error")?
             .unwrap();
         let chunk_slice = chunk_region.slice_info();
         sub_chunk.assign_to(chunk.slice_mut(chunk_slice));
-        self.write_chunk(chunk_idx, chunk)?;
-        Ok(())
+        self.write_chunk_deferred(chunk_idx, chunk, updates)
+    }
+
+    /// Shape the array would need to contain the given region, if larger than its current shape.
+    fn grown_shape(&self, offset: &GridCoord, array: &ArcArrayD<T>) -> Option<GridCoord> {
+        let needed: GridCoord = offset
+            .iter()
+            .zip(array.shape().iter())
+            .zip(self.metadata.shape.iter())
+            .map(|((o, s), current)| (o + *s as u64).max(*current))
+            .collect();
+        (needed != self.metadata.shape).then_some(needed)
+    }
+
+    /// Apply the given [OutOfBoundsPolicy] to a region to be written.
+    fn check_out_of_bounds(
+        &self,
+        offset: &GridCoord,
+        array: &ArcArrayD<T>,
+        policy: OutOfBoundsPolicy,
+    ) -> Result<(), &'static str> {
+        if policy == OutOfBoundsPolicy::Clip || self.grown_shape(offset, array).is_none() {
+            return Ok(());
+        }
+        Err("Region extends beyond the array")
     }
 
+    /// Write a region of the array, starting at the given offset.
+    ///
+    /// Regions extending beyond the array are handled according to the
+    /// [OutOfBoundsPolicy] set with [Array::with_out_of_bounds_policy];
+    /// to grow the array, use [Array::write_region_growing].
     pub fn write_region(
         &self,
         offset: &GridCoord,
         array: ArcArrayD<T>,
     ) -> Result<(), &'static str> {
+        self.write_region_with_progress(offset, array, |_| ControlFlow::Continue(()))
+            .map(|_| ())
+    }
+
+    /// As [Array::write_region], calling `progress` after each chunk is written.
+    ///
+    /// If `progress` returns [ControlFlow::Break], no further chunks are written
+    /// and the progress so far is returned;
+    /// chunks which have already been written are not reverted.
+    /// Use [WriteProgress::is_complete] to check whether the write was cancelled.
+    pub fn write_region_with_progress<F>(
+        &self,
+        offset: &GridCoord,
+        array: ArcArrayD<T>,
+        progress: F,
+    ) -> Result<WriteProgress, &'static str>
+    where
+        F: FnMut(&WriteProgress) -> ControlFlow<()>,
+    {
+        self.write_region_with(offset, array, self.out_of_bounds, progress)
+    }
+
+    fn write_region_with<F>(
+        &self,
+        offset: &GridCoord,
+        array: ArcArrayD<T>,
+        policy: OutOfBoundsPolicy,
+        mut progress: F,
+    ) -> Result<WriteProgress, &'static str>
+    where
+        F: FnMut(&WriteProgress) -> ControlFlow<()>,
+    {
+        if offset.len() != self.ndim() || array.ndim() != self.ndim() {
+            return Err("Offset or array has wrong dimensionality");
+        }
+        self.check_out_of_bounds(offset, &array, policy)?;
         let shape: GridCoord = array.shape().iter().map(|n| *n as u64).collect();
-        let region_opt = ArrayRegion::from_offset_shape(offset, shape.as_slice())
+        let region_opt = ArrayRegion::from_offset_shape_unchecked(offset, shape.as_slice())
             .limit_extent_unchecked(&self.metadata.shape);
 
-        if region_opt.is_none() {
-            return Ok(());
-        }
-        let region = region_opt.unwrap();
+        let Some(region) = region_opt else {
+            return Ok(WriteProgress::default());
+        };
 
         let slice_within = region.at_origin().slice_info();
         let array_within = array.slice(slice_within);
 
-        let _it = self.metadata.chunk_grid.chunks_in_region_unchecked(&region);
+        let mut state = WriteProgress {
+            chunks_total: self
+                .metadata
+                .chunk_grid
+                .chunks_in_region_unchecked(&region)
+                .count() as u64,
+            ..Default::default()
+        };
+        let nbytes = self.metadata.data_type.nbytes() as u64;
+        // each chunk is written at most once, so the document read here is current for every chunk read
+        let inline = self
+            .read_inline_chunks_document()
+            .map_err(|_| "Could not read inline chunks")?;
+        let mut updates = DocumentUpdates::default();
+        let mut result = Ok(());
         for pc in self.metadata.chunk_grid.chunks_in_region_unchecked(&region) {
             let arr_slice = pc.out_region.slice_info();
             let sub_arr = array_within.slice(arr_slice).to_shared();
+            let sub_bytes = sub_arr.len() as u64 * nbytes;
 
-            if pc.chunk_region.is_whole(
+            result = if pc.chunk_region.is_whole_unchecked(
                 &self
                     .metadata
                     .chunk_grid
                     .chunk_shape_unchecked(&pc.chunk_idx),
             ) {
                 // whole chunk
-                self.write_chunk(&pc.chunk_idx, sub_arr)?;
+                self.write_chunk_deferred(&pc.chunk_idx, sub_arr, &mut updates)
             } else {
                 // partial chunk
-                self.write_partial_chunk(&pc.chunk_idx, &pc.chunk_region, sub_arr)?;
+                self.write_partial_chunk_deferred(
+                    &pc.chunk_idx,
+                    &pc.chunk_region,
+                    sub_arr,
+                    inline.as_ref(),
+                    &mut updates,
+                )
+            };
+            if result.is_err() {
+                break;
+            }
+
+            state.chunks_done += 1;
+            state.bytes_written += sub_bytes;
+            if progress(&state).is_break() {
+                break;
             }
         }
+        // record the chunks which were written, even if a later chunk failed
+        let applied = self.apply_document_updates(updates);
+        result.and(applied)?;
+        Ok(state)
+    }
+
+    /// As [Array::write_region], with the given [OutOfBoundsPolicy] rather than the array's.
+    pub fn write_region_with_policy(
+        &self,
+        offset: &GridCoord,
+        array: ArcArrayD<T>,
+        policy: OutOfBoundsPolicy,
+    ) -> Result<(), &'static str> {
+        self.write_region_with(offset, array, policy, |_| ControlFlow::Continue(()))
+            .map(|_| ())
+    }
+
+    /// As [Array::write_region], but first resizing the array (see [Array::resize])
+    /// if the region extends beyond it, so that the whole region is written.
+    pub fn write_region_growing(
+        &mut self,
+        offset: &GridCoord,
+        array: ArcArrayD<T>,
+    ) -> Result<(), &'static str> {
+        if offset.len() != self.ndim() || array.ndim() != self.ndim() {
+            return Err("Offset or array has wrong dimensionality");
+        }
+        if let Some(shape) = self.grown_shape(offset, &array) {
+            self.resize(&shape)?;
+        }
+        self.write_region_with_policy(offset, array, OutOfBoundsPolicy::Error)
+    }
+
+    /// Change the shape of the array, and write the new metadata.
+    ///
+    /// When shrinking, chunks which lie entirely outside the new shape are erased,
+    /// and the parts of boundary chunks outside the new shape are reset to the fill value
+    /// so that old data does not reappear if the array grows again.
+    /// For sharded arrays, sub-chunks of boundary shards which lie outside the new shape
+    /// are removed from the shard's index without re-encoding the rest of the shard.
+    pub fn resize(&mut self, shape: &[u64]) -> Result<(), &'static str> {
+        if shape.len() != self.ndim() {
+            return Err("Shape has wrong dimensionality");
+        }
+        let shrinks = shape
+            .iter()
+            .zip(self.metadata.shape.iter())
+            .any(|(new, old)| new < old);
+        if shrinks {
+            self.truncate_chunks(shape)?;
+        }
+        self.metadata.shape = shape.iter().cloned().collect();
+        self.write_meta().map_err(|_| "Could not write metadata")
+    }
+
+    fn truncate_chunks(&self, shape: &[u64]) -> Result<(), &'static str> {
+        let grid_shape = self.metadata.chunk_grid.grid_shape_unchecked(shape);
+        let indices = self
+            .stored_chunk_indices()
+            .map_err(|_| "Could not list chunks")?;
+        let mut updates = DocumentUpdates::default();
+        let result = self.truncate_chunks_deferred(shape, &grid_shape, indices, &mut updates);
+        let applied = self.apply_document_updates(updates);
+        result.and(applied)
+    }
+
+    fn truncate_chunks_deferred(
+        &self,
+        shape: &[u64],
+        grid_shape: &[u64],
+        indices: Vec<GridCoord>,
+        updates: &mut DocumentUpdates<T>,
+    ) -> Result<(), &'static str> {
+        let inline = self
+            .read_inline_chunks_document()
+            .map_err(|_| "Could not read inline chunks")?;
+        for idx in indices {
+            if idx.iter().zip(grid_shape.iter()).any(|(i, n)| i >= n) {
+                self.erase_chunk_deferred(&idx, updates)?;
+                continue;
+            }
+
+            let chunk_shape = self.metadata.chunk_grid.chunk_shape_unchecked(&idx);
+            let keep: GridCoord = idx
+                .iter()
+                .zip(chunk_shape.iter())
+                .zip(shape.iter())
+                .map(|((i, c), s)| (*c).min(s - i * c))
+                .collect();
+            if keep == chunk_shape {
+                continue;
+            }
+            if self.truncate_shard(&idx, &keep, updates)? {
+                continue;
+            }
+            let Some(chunk) = self
+                .read_chunk_with(&idx, inline.as_ref())
+                .map_err(|_| "Could not read chunk")?
+            else {
+                continue;
+            };
+            let mut truncated = self.empty_chunk(&idx)?;
+            let keep_slice = ArrayRegion::from_offset_shape_unchecked(
+                &keep.iter().map(|_| 0).collect::<GridCoord>(),
+                &keep,
+            )
+            .slice_info();
+            chunk
+                .slice(&keep_slice)
+                .assign_to(truncated.slice_mut(&keep_slice));
+            self.write_chunk_deferred(&idx, truncated, updates)?;
+        }
         Ok(())
     }
 
+    /// If the chunk is a shard stored as its own object,
+    /// truncate it to the given shape by rewriting its index and straddling sub-chunks only
+    /// (see [ShardingIndexedCodec::truncate_shard](crate::codecs::ab::sharding_indexed::ShardingIndexedCodec::truncate_shard)), erasing it if nothing remains.
+    ///
+    /// `false` if the chunk must be truncated by decoding it in full, e.g. to update its statistics.
+    fn truncate_shard(
+        &self,
+        idx: &GridCoord,
+        keep: &[u64],
+        updates: &mut DocumentUpdates<T>,
+    ) -> Result<bool, &'static str> {
+        let codecs = &self.metadata.codecs;
+        let ABCodecType::ShardingIndexed(sharding) = codecs.ab_codec() else {
+            return Ok(false);
+        };
+        if !codecs.aa_codecs.is_empty()
+            || !codecs.bb_codecs.is_empty()
+            || self.metadata.records_chunk_stats()
+        {
+            return Ok(false);
+        }
+        let key = self.metadata.chunk_key_encoding.chunk_key(&self.key, idx);
+        let Some(mut r) = self.store.get(&key).map_err(|_| "Could not read chunk")? else {
+            // may be inlined
+            return Ok(false);
+        };
+        let mut shard = Vec::default();
+        r.read_to_end(&mut shard)
+            .map_err(|_| "Could not read chunk")?;
+        // release any lock held by the reader before writing
+        drop(r);
+
+        match sharding
+            .truncate_shard(&shard, self.chunk_repr(idx), keep, &self.decode_options)
+            .map_err(|_| "Could not truncate shard")?
+        {
+            Some(truncated) => self
+                .store
+                .set(&key, |w| w.write_all(&truncated))
+                .map_err(|_| "Could not write chunk")?,
+            None => self.erase_chunk_deferred(idx, updates)?,
+        }
+        Ok(true)
+    }
+
     pub fn erase(self) -> io::Result<()> {
         self.store.erase_prefix(&self.key)?;
         Ok(())
     }
 }
 
+#[cfg(feature = "parallel")]
+impl<'s, S: WriteableStore + Sync + ?Sized, T: ReflectedType> Array<'s, S, T> {
+    /// As [Array::write_region], but encoding and writing chunks on the array's thread pool.
+    ///
+    /// Arrays which record chunk statistics or inline chunks are written serially,
+    /// as those documents are shared between chunks.
+    pub fn par_write_region(
+        &self,
+        offset: &GridCoord,
+        array: ArcArrayD<T>,
+    ) -> Result<(), &'static str> {
+        let pool = match self.thread_pool.as_ref() {
+            Some(p)
+                if !self.metadata.records_chunk_stats()
+                    && self.metadata.inline_chunk_max_size().is_none() =>
+            {
+                p
+            }
+            _ => return self.write_region(offset, array),
+        };
+        if offset.len() != self.ndim() || array.ndim() != self.ndim() {
+            return Err("Offset or array has wrong dimensionality");
+        }
+        self.check_out_of_bounds(offset, &array, self.out_of_bounds)?;
+        let shape: GridCoord = array.shape().iter().map(|n| *n as u64).collect();
+        let Some(region) = ArrayRegion::from_offset_shape_unchecked(offset, shape.as_slice())
+            .limit_extent_unchecked(&self.metadata.shape)
+        else {
+            return Ok(());
+        };
+
+        let array_within = array.slice(region.at_origin().slice_info());
+        let jobs: Vec<_> = self
+            .metadata
+            .chunk_grid
+            .chunks_in_region_unchecked(&region)
+            .map(|pc| {
+                let sub_arr = array_within.slice(pc.out_region.slice_info()).to_shared();
+                (pc, sub_arr)
+            })
+            .collect();
+
+        pool.install(|| {
+            jobs.into_par_iter().try_for_each(|(pc, sub_arr)| {
+                if pc.chunk_region.is_whole_unchecked(
+                    &self
+                        .metadata
+                        .chunk_grid
+                        .chunk_shape_unchecked(&pc.chunk_idx),
+                ) {
+                    self.write_chunk(&pc.chunk_idx, sub_arr)
+                } else {
+                    // there are no shared documents to read or update
+                    self.write_partial_chunk_deferred(
+                        &pc.chunk_idx,
+                        &pc.chunk_region,
+                        sub_arr,
+                        None,
+                        &mut DocumentUpdates::default(),
+                    )
+                }
+            })
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "gzip")]
     use crate::{
         chunk_key_encoding::V2ChunkKeyEncoding,
         codecs::{aa::TransposeCodec, ab::bytes_codec::BytesCodec, bb::gzip_codec::GzipCodec},
     };
 
     use super::{ArrayMetadata, ArrayMetadataBuilder};
+    #[cfg(feature = "gzip")]
     use smallvec::smallvec;
 
+    #[cfg(feature = "gzip")]
     #[test]
     fn build_arraymeta() {
         let _meta: ArrayMetadata = ArrayMetadataBuilder::new(&[100, 200, 300])
@@ -638,4 +2930,108 @@ mod tests {
             .unwrap()
             .into();
     }
+
+    #[test]
+    fn build_arraymeta_presets() {
+        let meta: ArrayMetadata = ArrayMetadataBuilder::u8_image(&[100, 200], &[10, 20])
+            .unwrap()
+            .into();
+        meta.validate().unwrap();
+        // one compressor if any is enabled
+        let expected = usize::from(cfg!(any(feature = "blosc", feature = "gzip")));
+        assert_eq!(meta.codecs.bb_codecs.len(), expected);
+
+        let meta: ArrayMetadata = ArrayMetadataBuilder::label_volume(&[100, 200, 300], &[10; 3])
+            .unwrap()
+            .into();
+        meta.validate().unwrap();
+        assert_eq!(meta.get_effective_fill_value::<u64>().unwrap(), 0);
+
+        let meta: ArrayMetadata = ArrayMetadataBuilder::f32_image(&[100, 200], &[10, 20])
+            .unwrap()
+            .into();
+        meta.validate().unwrap();
+
+        assert!(ArrayMetadataBuilder::label_volume(&[100, 200], &[10; 3]).is_err());
+    }
+
+    #[test]
+    fn deser_must_understand() {
+        let meta = serde_json::json!({
+            "zarr_format": 3,
+            "shape": [10, 10],
+            "data_type": "uint8",
+            "chunk_grid": {
+                "name": "regular",
+                "configuration": {"chunk_shape": [5, 5]},
+                "must_understand": false
+            },
+            "chunk_key_encoding": {
+                "name": "default",
+                "configuration": {"separator": "/"},
+                "must_understand": true
+            },
+            "fill_value": 0,
+            "codecs": [
+                {"name": "bytes", "configuration": {}, "must_understand": false},
+                {"name": "future_checksum", "configuration": {}, "must_understand": false}
+            ]
+        });
+        let parsed: ArrayMetadata = serde_json::from_value(meta.clone()).unwrap();
+        parsed.validate().unwrap();
+        assert_eq!(parsed.codecs.len(), 1);
+
+        let mut required = meta.clone();
+        required["codecs"][1]
+            .as_object_mut()
+            .unwrap()
+            .remove("must_understand");
+        assert!(serde_json::from_value::<ArrayMetadata>(required).is_err());
+
+        // a supported codec with an invalid configuration is not ignored
+        let mut invalid = meta.clone();
+        invalid["codecs"][0]["configuration"]["endian"] = "middle".into();
+        assert!(serde_json::from_value::<ArrayMetadata>(invalid).is_err());
+
+        // an unknown grid cannot be ignored, as it determines where chunks are
+        let mut unknown_grid = meta.clone();
+        unknown_grid["chunk_grid"]["name"] = "future_grid".into();
+        assert!(serde_json::from_value::<ArrayMetadata>(unknown_grid).is_err());
+
+        let mut unknown_encoding = meta;
+        unknown_encoding["chunk_key_encoding"]["name"] = "future_encoding".into();
+        unknown_encoding["chunk_key_encoding"]["must_understand"] = false.into();
+        assert!(serde_json::from_value::<ArrayMetadata>(unknown_encoding).is_err());
+    }
+
+    #[test]
+    fn coerce_fill_value() {
+        let coerced = |data_type: &str, fill_value: serde_json::Value| {
+            let mut meta: ArrayMetadata = serde_json::from_value(serde_json::json!({
+                "zarr_format": 3,
+                "node_type": "array",
+                "shape": [10],
+                "data_type": data_type,
+                "chunk_grid": {"name": "regular", "configuration": {"chunk_shape": [5]}},
+                "chunk_key_encoding": {"name": "default", "configuration": {"separator": "/"}},
+                "fill_value": fill_value,
+                "codecs": [{"name": "bytes", "configuration": {"endian": "little"}}]
+            }))
+            .unwrap();
+            let changed = meta.coerce_fill_value();
+            (changed, meta.fill_value)
+        };
+
+        assert_eq!(coerced("bool", 1.into()), (true, true.into()));
+        assert_eq!(coerced("bool", 0.0.into()), (true, false.into()));
+        assert_eq!(coerced("bool", false.into()), (false, false.into()));
+        assert_eq!(coerced("bool", 2.into()), (false, 2.into()));
+        assert_eq!(coerced("int16", (-3.0).into()), (true, (-3).into()));
+        assert_eq!(coerced("uint8", 255.0.into()), (true, 255.into()));
+        // ambiguous or out of range values are left alone
+        assert_eq!(coerced("uint8", 256.0.into()), (false, 256.0.into()));
+        assert_eq!(coerced("int32", 1.5.into()), (false, 1.5.into()));
+        assert_eq!(coerced("int64", 1e30.into()), (false, 1e30.into()));
+        assert_eq!(coerced("float32", 1.into()), (false, 1.into()));
+    }
 }
@@ -0,0 +1,205 @@
+//! Optional limits on the attributes embedded in a node's metadata document.
+//!
+//! Attributes are stored inline in `zarr.json`, which is read whenever the node is opened;
+//! limits guard shared datasets against attributes growing large enough to slow that down
+//! (e.g. an embedded multi-megabyte blob), or containing keys which other tools handle badly.
+//!
+//! Nodes do not check attributes unless given limits
+//! (see [Array::with_attribute_limits](super::Array::with_attribute_limits)
+//! and [Group::with_attribute_limits](super::Group::with_attribute_limits)),
+//! after which the limits are checked before every metadata write.
+use super::JsonObject;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AttributeError {
+    #[error("Attributes are {size} bytes, above the limit of {max}")]
+    DocumentTooLarge { size: usize, max: usize },
+    #[error("Attribute key `{key}` is longer than {max} characters")]
+    KeyTooLong { key: String, max: usize },
+    #[error("Attribute `{key}` is {size} bytes, above the limit of {max}")]
+    ValueTooLarge {
+        key: String,
+        size: usize,
+        max: usize,
+    },
+    #[error("Attribute key `{0}` does not match the required pattern")]
+    InvalidKey(String),
+    #[error("Attribute `{0}` contains control or non-character code points")]
+    InvalidText(String),
+}
+
+/// Limits on a node's attributes; by default, nothing is limited.
+///
+/// Sizes are measured as compact (not pretty-printed) JSON.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AttributeLimits {
+    max_document_size: Option<usize>,
+    max_value_size: Option<usize>,
+    max_key_len: Option<usize>,
+    key_pattern: Option<fn(&str) -> bool>,
+    strict_utf8: bool,
+}
+
+impl AttributeLimits {
+    /// Maximum size of the whole attributes object.
+    pub fn max_document_size(mut self, bytes: usize) -> Self {
+        self.max_document_size = Some(bytes);
+        self
+    }
+
+    /// Maximum size of any single top-level attribute value.
+    pub fn max_value_size(mut self, bytes: usize) -> Self {
+        self.max_value_size = Some(bytes);
+        self
+    }
+
+    /// Maximum length of top-level attribute keys, in characters.
+    pub fn max_key_len(mut self, chars: usize) -> Self {
+        self.max_key_len = Some(chars);
+        self
+    }
+
+    /// Predicate which every top-level attribute key must satisfy,
+    /// e.g. [is_recommended_key].
+    pub fn key_pattern(mut self, pattern: fn(&str) -> bool) -> Self {
+        self.key_pattern = Some(pattern);
+        self
+    }
+
+    /// Reject keys and strings (at any depth) containing code points which are valid UTF-8
+    /// but commonly mangled by other tools:
+    /// control characters other than tab, line feed and carriage return,
+    /// and Unicode non-characters (e.g. `U+FFFF`).
+    pub fn strict_utf8(mut self, strict: bool) -> Self {
+        self.strict_utf8 = strict;
+        self
+    }
+
+    /// Check the attributes against these limits, returning the first violation.
+    pub fn validate_attributes(&self, attributes: &JsonObject) -> Result<(), AttributeError> {
+        if let Some(max) = self.max_document_size {
+            let size = json_size(attributes);
+            if size > max {
+                return Err(AttributeError::DocumentTooLarge { size, max });
+            }
+        }
+        let mut keys: Vec<_> = attributes.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = &attributes[key];
+            if let Some(max) = self.max_key_len {
+                if key.chars().count() > max {
+                    return Err(AttributeError::KeyTooLong {
+                        key: key.clone(),
+                        max,
+                    });
+                }
+            }
+            if let Some(pattern) = self.key_pattern {
+                if !pattern(key) {
+                    return Err(AttributeError::InvalidKey(key.clone()));
+                }
+            }
+            if let Some(max) = self.max_value_size {
+                let size = json_size(value);
+                if size > max {
+                    return Err(AttributeError::ValueTooLarge {
+                        key: key.clone(),
+                        size,
+                        max,
+                    });
+                }
+            }
+            if self.strict_utf8 && !(is_strict_text(key) && is_strict_value(value)) {
+                return Err(AttributeError::InvalidText(key.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the key uses only the characters recommended for node names:
+/// `a-z`, `A-Z`, `0-9`, `-`, `_`, `.`.
+pub fn is_recommended_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn json_size<V: serde::Serialize + ?Sized>(value: &V) -> usize {
+    serde_json::to_vec(value).map(|v| v.len()).unwrap_or(0)
+}
+
+fn is_strict_text(s: &str) -> bool {
+    s.chars().all(|c| {
+        let cp = c as u32;
+        let control = c.is_control() && !matches!(c, '\t' | '\n' | '\r');
+        let nonchar = (0xFDD0..=0xFDEF).contains(&cp) || cp & 0xFFFE == 0xFFFE;
+        !control && !nonchar
+    })
+}
+
+fn is_strict_value(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::String(s) => is_strict_text(s),
+        serde_json::Value::Array(a) => a.iter().all(is_strict_value),
+        serde_json::Value::Object(o) => o
+            .iter()
+            .all(|(k, v)| is_strict_text(k) && is_strict_value(v)),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn validate_attributes() {
+        let mut attrs = JsonObject::default();
+        attrs.insert("description".into(), json!("raw data"));
+        attrs.insert("nested".into(), json!({"values": [1, 2, 3]}));
+
+        assert!(AttributeLimits::default()
+            .validate_attributes(&attrs)
+            .is_ok());
+        let limits = AttributeLimits::default()
+            .max_document_size(100)
+            .max_value_size(30)
+            .max_key_len(12)
+            .key_pattern(is_recommended_key)
+            .strict_utf8(true);
+        assert!(limits.validate_attributes(&attrs).is_ok());
+
+        attrs.insert("blob".into(), json!("x".repeat(200)));
+        assert!(matches!(
+            limits.validate_attributes(&attrs),
+            Err(AttributeError::DocumentTooLarge { .. })
+        ));
+        assert!(matches!(
+            limits.max_document_size(1000).validate_attributes(&attrs),
+            Err(AttributeError::ValueTooLarge { size: 202, .. })
+        ));
+        attrs.remove("blob");
+
+        attrs.insert("a key".into(), json!(1));
+        assert_eq!(
+            limits.validate_attributes(&attrs),
+            Err(AttributeError::InvalidKey("a key".into()))
+        );
+        attrs.remove("a key");
+
+        attrs.insert("text".into(), json!({"inner": ["\t", "\u{7}"]}));
+        assert_eq!(
+            limits.validate_attributes(&attrs),
+            Err(AttributeError::InvalidText("text".into()))
+        );
+        assert!(limits
+            .strict_utf8(false)
+            .validate_attributes(&attrs)
+            .is_ok());
+    }
+}
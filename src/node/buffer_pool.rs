@@ -0,0 +1,174 @@
+//! Re-usable byte buffers for staging chunks' stored bytes while they are decoded.
+//!
+//! Reading a region of many small chunks otherwise allocates (and grows) a fresh buffer per chunk.
+//! Each [Array](super::Array) keeps a small pool, which is shared by all of its reads
+//! (including parallel ones); see [Array::with_buffer_pool](super::Array::with_buffer_pool).
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Mutex,
+};
+
+/// Counters describing how well an array's buffer pool is working, for tuning its size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers handed out.
+    pub acquired: u64,
+    /// Buffers handed out which were taken from the pool with enough capacity.
+    pub reused: u64,
+    /// Buffers handed out which needed a new allocation (or reallocation).
+    pub allocated: u64,
+    /// Buffers dropped rather than returned because the pool was full.
+    pub discarded: u64,
+    /// Buffers currently in the pool.
+    pub pooled: usize,
+    /// Total capacity of the buffers currently in the pool.
+    pub pooled_bytes: usize,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    buffers: Vec<Vec<u8>>,
+    stats: PoolStats,
+}
+
+/// A bounded pool of byte buffers, handed out as [PooledBuffer]s which return themselves when dropped.
+#[derive(Debug, Default)]
+pub(crate) struct BufferPool {
+    max_buffers: usize,
+    state: Mutex<PoolState>,
+}
+
+impl BufferPool {
+    /// Pool which holds up to `max_buffers` idle buffers; with 0, every buffer is freshly allocated.
+    pub(crate) fn new(max_buffers: usize) -> Self {
+        Self {
+            max_buffers,
+            state: Default::default(),
+        }
+    }
+
+    pub(crate) fn max_buffers(&self) -> usize {
+        self.max_buffers
+    }
+
+    /// Get an empty buffer with at least the given capacity.
+    pub(crate) fn acquire(&self, capacity: usize) -> PooledBuffer<'_> {
+        let mut state = self.state.lock().unwrap();
+        state.stats.acquired += 1;
+        // prefer the smallest buffer which is big enough, so that big buffers stay available
+        let best = state
+            .buffers
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.capacity() >= capacity)
+            .min_by_key(|(_, b)| b.capacity())
+            .map(|(idx, _)| idx);
+        let taken = match best {
+            Some(idx) => {
+                state.stats.reused += 1;
+                Some(state.buffers.remove(idx))
+            }
+            None => {
+                state.stats.allocated += 1;
+                // grow the largest pooled buffer rather than leaving it to be evicted
+                state.buffers.pop()
+            }
+        };
+        let buf = match taken {
+            Some(mut b) => {
+                state.stats.pooled_bytes -= b.capacity();
+                b.reserve_exact(capacity);
+                b
+            }
+            None => Vec::with_capacity(capacity),
+        };
+        state.stats.pooled = state.buffers.len();
+        PooledBuffer {
+            pool: self,
+            buf: Some(buf),
+        }
+    }
+
+    fn release(&self, mut buf: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        if state.buffers.len() >= self.max_buffers {
+            state.stats.discarded += 1;
+            return;
+        }
+        buf.clear();
+        state.stats.pooled_bytes += buf.capacity();
+        state.buffers.push(buf);
+        state.buffers.sort_unstable_by_key(|b| b.capacity());
+        state.stats.pooled = state.buffers.len();
+    }
+
+    pub(crate) fn stats(&self) -> PoolStats {
+        self.state.lock().unwrap().stats
+    }
+}
+
+/// A buffer borrowed from a [BufferPool], which is cleared and returned to it on drop.
+pub(crate) struct PooledBuffer<'p> {
+    pool: &'p BufferPool,
+    buf: Option<Vec<u8>>,
+}
+
+impl Deref for PooledBuffer<'_> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        self.buf.as_ref().expect("buffer is only taken on drop")
+    }
+}
+
+impl DerefMut for PooledBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buf.as_mut().expect("buffer is only taken on drop")
+    }
+}
+
+impl Drop for PooledBuffer<'_> {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.pool.release(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_buffers() {
+        let pool = BufferPool::new(2);
+        {
+            let mut a = pool.acquire(100);
+            a.extend_from_slice(&[1; 150]);
+            let _b = pool.acquire(10);
+        }
+        let stats = pool.stats();
+        assert_eq!(stats.acquired, 2);
+        assert_eq!(stats.allocated, 2);
+        assert_eq!(stats.pooled, 2);
+
+        {
+            let small = pool.acquire(10);
+            assert!(small.is_empty());
+            assert!(small.capacity() < 100);
+            let big = pool.acquire(120);
+            assert!(big.capacity() >= 150);
+            let _extra = pool.acquire(10);
+        }
+        let stats = pool.stats();
+        assert_eq!(stats.reused, 2);
+        assert_eq!(stats.allocated, 3);
+        assert_eq!(stats.discarded, 1);
+        assert_eq!(stats.pooled, 2);
+
+        let unpooled = BufferPool::new(0);
+        drop(unpooled.acquire(10));
+        assert_eq!(unpooled.stats().discarded, 1);
+        assert_eq!(unpooled.stats().pooled_bytes, 0);
+    }
+}
@@ -0,0 +1,149 @@
+//! A self-describing file format for transferring encoded chunks between copies of an array,
+//! e.g. to move a subset of a massive array by sneaker-net and re-assemble it elsewhere.
+//!
+//! A file is a sequence of records, each of which is:
+//!
+//! - the magic bytes [CHUNK_RECORD_MAGIC]
+//! - the length of the header as a little-endian `u32`
+//! - the [ChunkRecordHeader] as UTF-8 JSON
+//! - the chunk's encoded bytes, exactly as stored
+//!
+//! The header includes the parts of the array metadata needed to decode the chunk,
+//! so that imports can check that the destination array encodes chunks identically.
+//! Export with [Array::export_chunks](super::Array::export_chunks)
+//! and import with [Array::import_chunks](super::Array::import_chunks).
+use std::io::{self, ErrorKind, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::ArrayMetadata;
+
+/// Bytes at the start of every record.
+pub const CHUNK_RECORD_MAGIC: &[u8; 8] = b"ZARRCHNK";
+pub const CHUNK_RECORD_VERSION: u32 = 1;
+
+/// Array metadata fields which must match for a chunk's bytes to be interchangeable.
+const ENCODING_FIELDS: &[&str] = &["data_type", "chunk_grid", "codecs", "fill_value"];
+/// Array metadata fields recorded for information.
+const INFO_FIELDS: &[&str] = &["shape", "chunk_key_encoding"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChunkRecordHeader {
+    pub version: u32,
+    /// Index of the chunk in the grid.
+    pub chunk: Vec<u64>,
+    /// Fields of the source array's metadata document.
+    pub array: serde_json::Map<String, serde_json::Value>,
+    /// Length of the encoded bytes.
+    pub nbytes: u64,
+    /// CRC32C checksum of the encoded bytes.
+    pub crc32c: u32,
+}
+
+impl ChunkRecordHeader {
+    /// Whether chunks encoded for an array with the given metadata can be used as-is by this record's array.
+    ///
+    /// `Err` describes the first field which differs.
+    pub fn check_compatible(&self, metadata: &ArrayMetadata) -> Result<(), String> {
+        let other = metadata_fields(metadata);
+        for field in ENCODING_FIELDS {
+            if self.array.get(*field) != other.get(*field) {
+                return Err(format!("Arrays have different \"{field}\""));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single encoded chunk with a description of how it was encoded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkRecord {
+    pub header: ChunkRecordHeader,
+    pub data: Vec<u8>,
+}
+
+impl ChunkRecord {
+    pub fn new(metadata: &ArrayMetadata, chunk: &[u64], data: Vec<u8>) -> Self {
+        Self {
+            header: ChunkRecordHeader {
+                version: CHUNK_RECORD_VERSION,
+                chunk: chunk.to_vec(),
+                array: metadata_fields(metadata),
+                nbytes: data.len() as u64,
+                crc32c: crc32c::crc32c(&data),
+            },
+            data,
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let header = serde_json::to_vec(&self.header)?;
+        let header_len: u32 = header
+            .len()
+            .try_into()
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "Header is too long"))?;
+        w.write_all(CHUNK_RECORD_MAGIC)?;
+        w.write_all(&header_len.to_le_bytes())?;
+        w.write_all(&header)?;
+        w.write_all(&self.data)
+    }
+
+    /// Read the next record; `Ok(None)` at the end of the input.
+    ///
+    /// Malformed records, unsupported versions and checksum mismatches are [ErrorKind::InvalidData] errors.
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<Option<Self>> {
+        let mut magic = [0; 8];
+        let mut filled = 0;
+        while filled < magic.len() {
+            match r.read(&mut magic[filled..])? {
+                0 if filled == 0 => return Ok(None),
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => filled += n,
+            }
+        }
+        if &magic != CHUNK_RECORD_MAGIC {
+            return Err(invalid_data("Not a chunk record"));
+        }
+
+        let mut len = [0; 4];
+        r.read_exact(&mut len)?;
+        let header_len = u32::from_le_bytes(len) as u64;
+        // grows with the input, rather than trusting the length up front
+        let mut header = Vec::default();
+        r.take(header_len).read_to_end(&mut header)?;
+        if header.len() as u64 != header_len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let header: ChunkRecordHeader = serde_json::from_slice(&header)?;
+        if header.version != CHUNK_RECORD_VERSION {
+            return Err(invalid_data(format!(
+                "Unsupported chunk record version {}",
+                header.version
+            )));
+        }
+
+        let mut data = Vec::default();
+        r.take(header.nbytes).read_to_end(&mut data)?;
+        if data.len() as u64 != header.nbytes {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        if crc32c::crc32c(&data) != header.crc32c {
+            return Err(invalid_data("Chunk record checksum mismatch"));
+        }
+        Ok(Some(Self { header, data }))
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(e: E) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}
+
+fn metadata_fields(metadata: &ArrayMetadata) -> serde_json::Map<String, serde_json::Value> {
+    let serde_json::Value::Object(mut doc) =
+        serde_json::to_value(metadata).expect("metadata is serializable")
+    else {
+        unreachable!("metadata serializes to an object")
+    };
+    doc.retain(|k, _| ENCODING_FIELDS.contains(&k.as_str()) || INFO_FIELDS.contains(&k.as_str()));
+    doc
+}
@@ -0,0 +1,94 @@
+//! Per-chunk summary statistics, recorded on write so that they can be queried without reading chunk data.
+//!
+//! Arrays opt in with the [CHUNK_STATS_EXTENSION] metadata extension
+//! (see [ArrayMetadataBuilder::chunk_stats](super::ArrayMetadataBuilder::chunk_stats)).
+//! Statistics are stored in a sidecar JSON document alongside the array metadata,
+//! mapping `.`-separated chunk indices to [ChunkStats].
+//! Chunks which consist only of the fill value are not stored, and so have no entry.
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data_type::ReflectedType, GridCoord};
+
+/// Name of the array metadata extension which indicates that chunk statistics are recorded.
+pub const CHUNK_STATS_EXTENSION: &str = "chunk_statistics";
+
+/// Name of the sidecar document within the array node.
+pub(crate) const CHUNK_STATS_NAME: &str = "chunk_stats.json";
+
+/// Summary statistics for the values in a single chunk.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ChunkStats<T> {
+    pub min: T,
+    pub max: T,
+}
+
+impl<T: ReflectedType> ChunkStats<T> {
+    /// `None` if the data type is unordered or there are no comparable values.
+    pub fn from_values<'a, I: IntoIterator<Item = &'a T>>(values: I) -> Option<Self> {
+        T::min_max(values).map(|(min, max)| Self { min, max })
+    }
+
+    /// Combine several statistics into one covering all of them.
+    pub fn merge<I: IntoIterator<Item = Self>>(stats: I) -> Option<Self> {
+        let vals: Vec<_> = stats.into_iter().flat_map(|s| [s.min, s.max]).collect();
+        Self::from_values(vals.iter())
+    }
+
+    /// Whether every value in the chunk is equal.
+    pub fn is_uniform(&self) -> bool {
+        self.min == self.max
+    }
+}
+
+pub(crate) type ChunkStatsDocument<T> = BTreeMap<String, ChunkStats<T>>;
+
+pub(crate) fn chunk_id(idx: &GridCoord) -> String {
+    idx.iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+pub(crate) fn parse_chunk_id(s: &str) -> Option<GridCoord> {
+    s.split('.').map(|i| i.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smallvec::smallvec;
+
+    #[test]
+    fn chunk_id_roundtrip() {
+        let idx: GridCoord = smallvec![1, 20, 300];
+        let s = chunk_id(&idx);
+        assert_eq!(s, "1.20.300");
+        assert_eq!(parse_chunk_id(&s).unwrap(), idx);
+    }
+
+    #[test]
+    fn stats_skip_nan() {
+        let vals = [f32::NAN, 3.0, -1.0, f32::NAN];
+        let stats = ChunkStats::from_values(vals.iter()).unwrap();
+        assert_eq!(
+            stats,
+            ChunkStats {
+                min: -1.0,
+                max: 3.0
+            }
+        );
+        assert!(ChunkStats::<f32>::from_values([f32::NAN].iter()).is_none());
+    }
+
+    #[test]
+    fn stats_merge() {
+        let merged = ChunkStats::merge([
+            ChunkStats { min: 2u8, max: 5 },
+            ChunkStats { min: 0, max: 3 },
+        ])
+        .unwrap();
+        assert_eq!(merged, ChunkStats { min: 0, max: 5 });
+    }
+}
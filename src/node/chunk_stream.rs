@@ -0,0 +1,175 @@
+//! Decoding and encoding single chunks as streams of raw values,
+//! for composing with other tools in Unix pipelines, e.g.
+//!
+//! ```sh
+//! curl https://example.com/data.zarr/my_array/c/0/0 \
+//!     | cargo run --example chunk_pipe -- decode --meta zarr.json > chunk.bin
+//! ```
+//!
+//! Decoded values are written in C order with the given endianness,
+//! without any header; the chunk's shape and data type are given by the array metadata.
+//! Any reader and writer can be used, typically [std::io::stdin] and [std::io::stdout].
+use std::io::{self, ErrorKind, Read, Write};
+
+use super::ArrayMetadata;
+use crate::{
+    codecs::{ab::bytes_codec::Endian, DecodeOptions},
+    data_type::{c128, c64, ComplexSize, DataType, FloatSize, IntSize, NBytes, ReflectedType},
+    to_usize, GridCoord,
+};
+
+/// Call a generic function with the [ReflectedType] matching a [DataType].
+macro_rules! with_reflected_type {
+    ($data_type:expr, $func:ident($($arg:expr),*)) => {
+        match $data_type {
+            DataType::Bool => $func::<bool>($($arg),*),
+            DataType::Int(IntSize::b8) => $func::<i8>($($arg),*),
+            DataType::Int(IntSize::b16) => $func::<i16>($($arg),*),
+            DataType::Int(IntSize::b32) => $func::<i32>($($arg),*),
+            DataType::Int(IntSize::b64) => $func::<i64>($($arg),*),
+            DataType::UInt(IntSize::b8) => $func::<u8>($($arg),*),
+            DataType::UInt(IntSize::b16) => $func::<u16>($($arg),*),
+            DataType::UInt(IntSize::b32) => $func::<u32>($($arg),*),
+            DataType::UInt(IntSize::b64) => $func::<u64>($($arg),*),
+            DataType::Float(FloatSize::b32) => $func::<f32>($($arg),*),
+            DataType::Float(FloatSize::b64) => $func::<f64>($($arg),*),
+            DataType::Complex(ComplexSize::b64) => $func::<c64>($($arg),*),
+            DataType::Complex(ComplexSize::b128) => $func::<c128>($($arg),*),
+            DataType::Raw(8) => $func::<[u8; 1]>($($arg),*),
+            DataType::Raw(16) => $func::<[u8; 2]>($($arg),*),
+            DataType::Raw(24) => $func::<[u8; 3]>($($arg),*),
+            DataType::Raw(32) => $func::<[u8; 4]>($($arg),*),
+            DataType::Raw(40) => $func::<[u8; 5]>($($arg),*),
+            DataType::Raw(48) => $func::<[u8; 6]>($($arg),*),
+            DataType::Raw(56) => $func::<[u8; 7]>($($arg),*),
+            DataType::Raw(64) => $func::<[u8; 8]>($($arg),*),
+            DataType::Raw(72) => $func::<[u8; 9]>($($arg),*),
+            DataType::Raw(80) => $func::<[u8; 10]>($($arg),*),
+            DataType::Raw(88) => $func::<[u8; 11]>($($arg),*),
+            DataType::Raw(96) => $func::<[u8; 12]>($($arg),*),
+            DataType::Raw(104) => $func::<[u8; 13]>($($arg),*),
+            DataType::Raw(112) => $func::<[u8; 14]>($($arg),*),
+            DataType::Raw(120) => $func::<[u8; 15]>($($arg),*),
+            DataType::Raw(128) => $func::<[u8; 16]>($($arg),*),
+            DataType::Raw(_) => Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "Raw data types are only supported up to r128",
+            )),
+        }
+    };
+}
+
+/// Decode one encoded chunk read from `r`, writing its raw values to `w`.
+pub fn decode_chunk_stream<R: Read, W: Write>(
+    metadata: &ArrayMetadata,
+    chunk_idx: &GridCoord,
+    r: R,
+    w: W,
+    endian: Endian,
+) -> io::Result<()> {
+    with_reflected_type!(
+        metadata.data_type(),
+        decode_typed(metadata, chunk_idx, r, w, endian)
+    )
+}
+
+/// Encode one chunk's raw values read from `r`, writing the encoded chunk to `w`.
+///
+/// `Err` with [ErrorKind::InvalidData] if `r` does not contain exactly one chunk's values.
+pub fn encode_chunk_stream<R: Read, W: Write>(
+    metadata: &ArrayMetadata,
+    chunk_idx: &GridCoord,
+    r: R,
+    w: W,
+    endian: Endian,
+) -> io::Result<()> {
+    with_reflected_type!(
+        metadata.data_type(),
+        encode_typed(metadata, chunk_idx, r, w, endian)
+    )
+}
+
+fn decode_typed<T: ReflectedType>(
+    metadata: &ArrayMetadata,
+    chunk_idx: &GridCoord,
+    r: impl Read,
+    w: impl Write,
+    endian: Endian,
+) -> io::Result<()> {
+    let chunk = metadata.decode_chunk::<T, _>(chunk_idx, r, &DecodeOptions::default())?;
+    T::write_array_to(chunk, w, endian)
+}
+
+fn encode_typed<T: ReflectedType>(
+    metadata: &ArrayMetadata,
+    chunk_idx: &GridCoord,
+    mut r: impl Read,
+    w: impl Write,
+    endian: Endian,
+) -> io::Result<()> {
+    let shape = to_usize(metadata.chunk_shape(chunk_idx)?.as_slice());
+    let mut buf = Vec::default();
+    r.read_to_end(&mut buf)?;
+    let expected = shape.iter().product::<usize>() * metadata.data_type().nbytes();
+    if buf.len() != expected {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Expected {expected} bytes for chunk of shape {shape:?}, got {}",
+                buf.len()
+            ),
+        ));
+    }
+    let chunk = T::read_array_from(buf.as_slice(), endian, &shape)?;
+    metadata.encode_chunk(chunk_idx, chunk, w)
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use smallvec::smallvec;
+
+    use super::*;
+    use crate::{codecs::bb::gzip_codec::GzipCodec, node::ArrayMetadataBuilder};
+
+    #[test]
+    fn roundtrip() {
+        let meta: ArrayMetadata = ArrayMetadataBuilder::<u16>::new(&[5, 3])
+            .chunk_grid(vec![2, 3].as_slice())
+            .unwrap()
+            .push_bb_codec(GzipCodec::default())
+            .into();
+        let idx: GridCoord = smallvec![2, 0];
+        let raw: Vec<u8> = (0..12).collect();
+
+        let mut encoded = Vec::default();
+        encode_chunk_stream(&meta, &idx, raw.as_slice(), &mut encoded, Endian::Little).unwrap();
+        let mut decoded = Vec::default();
+        decode_chunk_stream(
+            &meta,
+            &idx,
+            encoded.as_slice(),
+            &mut decoded,
+            Endian::Little,
+        )
+        .unwrap();
+        assert_eq!(decoded, raw);
+
+        let chunk = meta
+            .decode_chunk::<u16, _>(&idx, encoded.as_slice(), &Default::default())
+            .unwrap();
+        assert_eq!(chunk[[1, 2]], u16::from_le_bytes([10, 11]));
+
+        let err = encode_chunk_stream(&meta, &idx, &raw[1..], Vec::default(), Endian::Little)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = decode_chunk_stream(
+            &meta,
+            &smallvec![3, 0],
+            encoded.as_slice(),
+            Vec::default(),
+            Endian::Little,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
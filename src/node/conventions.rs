@@ -0,0 +1,161 @@
+//! Typed access to common attribute conventions describing an array's physical space.
+//!
+//! All keys are optional and stored at the top level of the node's attributes:
+//!
+//! - [UNITS_KEY]: per-axis unit strings (e.g. `"nanometer"`), or `null` for unitless axes
+//! - [VOXEL_SIZE_KEY]: per-axis distance between adjacent voxels, in those units
+//! - [OFFSET_KEY]: per-axis physical position of the voxel at index 0
+//! - [COORDINATES_KEY]: mapping from dimension name to the path (relative to this node's parent)
+//!   of a 1D array holding explicit coordinates for that dimension
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{ReadableMetadata, WriteableMetadata};
+
+pub const UNITS_KEY: &str = "units";
+pub const VOXEL_SIZE_KEY: &str = "voxel_size";
+pub const OFFSET_KEY: &str = "offset";
+pub const COORDINATES_KEY: &str = "coordinates";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AxisConventions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub units: Option<Vec<Option<String>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voxel_size: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<BTreeMap<String, String>>,
+}
+
+impl AxisConventions {
+    /// Read whichever convention keys are present in the node's attributes.
+    ///
+    /// Fails if a key is present but has an unexpected JSON shape.
+    pub fn from_metadata<M: ReadableMetadata + ?Sized>(
+        meta: &M,
+    ) -> Result<Self, serde_json::Error> {
+        let attrs = meta.get_attributes();
+        let mut out = Self::default();
+        if let Some(v) = attrs.get(UNITS_KEY) {
+            out.units = Some(serde_json::from_value(v.clone())?);
+        }
+        if let Some(v) = attrs.get(VOXEL_SIZE_KEY) {
+            out.voxel_size = Some(serde_json::from_value(v.clone())?);
+        }
+        if let Some(v) = attrs.get(OFFSET_KEY) {
+            out.offset = Some(serde_json::from_value(v.clone())?);
+        }
+        if let Some(v) = attrs.get(COORDINATES_KEY) {
+            out.coordinates = Some(serde_json::from_value(v.clone())?);
+        }
+        Ok(out)
+    }
+
+    /// Write the convention keys into the node's attributes.
+    ///
+    /// Keys which are [None] are left untouched.
+    /// All keys are written in a single attribute mutation.
+    pub fn write_to<M: WriteableMetadata + ?Sized>(
+        &self,
+        meta: &mut M,
+    ) -> Result<(), serde_json::Error> {
+        let serde_json::Value::Object(map) = serde_json::to_value(self)? else {
+            unreachable!("conventions serialise to an object");
+        };
+        meta.mutate_attributes(|attrs| attrs.extend(map));
+        Ok(())
+    }
+
+    /// Check that all per-axis values have the given dimensionality,
+    /// and that voxel sizes are positive and finite.
+    pub fn validate(&self, ndim: usize) -> Result<(), &'static str> {
+        let lens = [
+            self.units.as_ref().map(|v| v.len()),
+            self.voxel_size.as_ref().map(|v| v.len()),
+            self.offset.as_ref().map(|v| v.len()),
+        ];
+        if lens.iter().flatten().any(|n| *n != ndim) {
+            return Err("Inconsistent dimensionality");
+        }
+        if let Some(vs) = &self.voxel_size {
+            if vs.iter().any(|s| !(s.is_finite() && *s > 0.0)) {
+                return Err("Voxel size must be positive and finite");
+            }
+        }
+        if let Some(os) = &self.offset {
+            if os.iter().any(|o| !o.is_finite()) {
+                return Err("Offset must be finite");
+            }
+        }
+        Ok(())
+    }
+
+    /// Physical position of the voxel at the given index,
+    /// using the voxel size (default 1) and offset (default 0).
+    ///
+    /// `None` if the index is not of the same dimensionality as the conventions.
+    pub fn physical_coord(&self, idx: &[u64]) -> Option<Vec<f64>> {
+        let ndim = idx.len();
+        let vs = self.voxel_size.clone().unwrap_or_else(|| vec![1.0; ndim]);
+        let os = self.offset.clone().unwrap_or_else(|| vec![0.0; ndim]);
+        if vs.len() != ndim || os.len() != ndim {
+            return None;
+        }
+        Some(
+            idx.iter()
+                .zip(vs.iter().zip(os.iter()))
+                .map(|(i, (s, o))| o + *i as f64 * s)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::GroupMetadata;
+
+    #[test]
+    fn conventions_roundtrip() {
+        let conv = AxisConventions {
+            units: Some(vec![Some("nanometer".to_owned()), None]),
+            voxel_size: Some(vec![4.0, 1.0]),
+            offset: None,
+            coordinates: Some([("t".to_owned(), "t_coords".to_owned())].into()),
+        };
+        conv.validate(2).unwrap();
+
+        let mut meta = GroupMetadata::default();
+        meta.set_attribute(OFFSET_KEY, [1.0, 2.0]).unwrap();
+        conv.write_to(&mut meta).unwrap();
+        assert_eq!(meta.get_attributes().len(), 4);
+
+        let read = AxisConventions::from_metadata(&meta).unwrap();
+        assert_eq!(read.units, conv.units);
+        assert_eq!(read.coordinates, conv.coordinates);
+        assert_eq!(read.offset, Some(vec![1.0, 2.0]));
+        assert_eq!(read.physical_coord(&[2, 3]), Some(vec![9.0, 5.0]));
+    }
+
+    #[test]
+    fn conventions_invalid() {
+        let mut meta = GroupMetadata::default();
+        assert_eq!(
+            AxisConventions::from_metadata(&meta).unwrap(),
+            AxisConventions::default()
+        );
+        meta.set_attribute(VOXEL_SIZE_KEY, "big").unwrap();
+        assert!(AxisConventions::from_metadata(&meta).is_err());
+
+        let conv = AxisConventions {
+            voxel_size: Some(vec![1.0, 0.0]),
+            ..Default::default()
+        };
+        assert!(conv.validate(3).is_err());
+        assert!(conv.validate(2).is_err());
+        assert_eq!(conv.physical_coord(&[1]), None);
+    }
+}
@@ -0,0 +1,181 @@
+//! Deterministic fingerprints of an array's contents, for comparing copies without holding them in memory.
+//!
+//! See [Array::content_digest](super::Array::content_digest) for how the digest is defined.
+use std::{collections::HashMap, fmt::Display, hash::Hasher};
+
+use twox_hash::XxHash64;
+
+use crate::GridCoord;
+
+/// Number of elements in the segments of rows along the last axis which are hashed independently.
+pub(crate) const DIGEST_SEGMENT_LEN: u64 = 1024;
+
+/// Hash function used to compute a [ContentDigest].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DigestAlgorithm {
+    /// CRC32C, as used by the `crc32c` codec; fast, but only 32 bits.
+    Crc32c,
+    /// 64-bit xxHash with a seed of 0.
+    XxHash64,
+}
+
+impl Display for DigestAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Crc32c => write!(f, "crc32c"),
+            Self::XxHash64 => write!(f, "xxh64"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContentDigest {
+    pub algorithm: DigestAlgorithm,
+    /// Digest of the decoded values.
+    pub decoded: u64,
+    /// Digest of the stored (encoded) chunks, if requested.
+    pub encoded: Option<u64>,
+}
+
+/// Incremental state of a [DigestAlgorithm].
+pub(crate) enum DigestState {
+    Crc32c(u32),
+    XxHash64(XxHash64),
+}
+
+impl DigestState {
+    pub(crate) fn new(algorithm: DigestAlgorithm) -> Self {
+        match algorithm {
+            DigestAlgorithm::Crc32c => Self::Crc32c(0),
+            DigestAlgorithm::XxHash64 => Self::XxHash64(XxHash64::with_seed(0)),
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, bytes),
+            Self::XxHash64(h) => h.write(bytes),
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        match self {
+            Self::Crc32c(crc) => *crc as u64,
+            Self::XxHash64(h) => h.finish(),
+        }
+    }
+}
+
+/// Digest of an array's values which can be fed with rows from its chunks in any order,
+/// as long as the pieces of each row along the last axis are given in order.
+///
+/// Rows are split into segments of [DIGEST_SEGMENT_LEN] elements from the array's origin,
+/// each of which is hashed along with its position;
+/// the digest is the (wrapping) sum of the segments' hashes.
+/// Only segments which straddle the pieces given so far are buffered.
+pub(crate) struct SegmentedDigest {
+    algorithm: DigestAlgorithm,
+    row_len: u64,
+    nbytes: usize,
+    pending: HashMap<(GridCoord, u64), Vec<u8>>,
+    sum: u64,
+}
+
+impl SegmentedDigest {
+    /// For rows of `row_len` elements of `nbytes` bytes each.
+    pub(crate) fn new(algorithm: DigestAlgorithm, row_len: u64, nbytes: usize) -> Self {
+        Self {
+            algorithm,
+            row_len,
+            nbytes,
+            pending: HashMap::default(),
+            sum: 0,
+        }
+    }
+
+    /// Add part of a row, given the coordinates of the row on the leading axes,
+    /// the index of the part's first element along the last axis, and the part's values as bytes.
+    pub(crate) fn update_row(&mut self, leading: &[u64], start: u64, mut bytes: &[u8]) {
+        let mut pos = start;
+        while !bytes.is_empty() {
+            let segment = pos / DIGEST_SEGMENT_LEN;
+            let seg_start = segment * DIGEST_SEGMENT_LEN;
+            let seg_end = (seg_start + DIGEST_SEGMENT_LEN).min(self.row_len);
+            let n = ((seg_end - pos) as usize * self.nbytes).min(bytes.len());
+            let (piece, rest) = bytes.split_at(n);
+            let piece_start = pos;
+            pos += (n / self.nbytes) as u64;
+            bytes = rest;
+
+            if piece_start == seg_start && pos == seg_end {
+                self.add_segment(leading, segment, piece);
+                continue;
+            }
+            let key = (GridCoord::from_slice(leading), segment);
+            let buf = self.pending.entry(key.clone()).or_default();
+            buf.extend_from_slice(piece);
+            if pos == seg_end {
+                let buf = self.pending.remove(&key).expect("segment is pending");
+                self.add_segment(leading, segment, &buf);
+            }
+        }
+    }
+
+    fn add_segment(&mut self, leading: &[u64], segment: u64, bytes: &[u8]) {
+        let mut state = DigestState::new(self.algorithm);
+        for c in leading {
+            state.update(&c.to_le_bytes());
+        }
+        state.update(&segment.to_le_bytes());
+        state.update(bytes);
+        self.sum = self.sum.wrapping_add(state.finish());
+    }
+
+    /// The sum of the segments' hashes; any incomplete segments are ignored.
+    pub(crate) fn finish(&self) -> u64 {
+        debug_assert!(self.pending.is_empty(), "Incomplete digest segments");
+        self.sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streaming_matches_whole() {
+        for algorithm in [DigestAlgorithm::Crc32c, DigestAlgorithm::XxHash64] {
+            let mut whole = DigestState::new(algorithm);
+            whole.update(b"hello world");
+            let mut parts = DigestState::new(algorithm);
+            parts.update(b"hello");
+            parts.update(b" world");
+            assert_eq!(whole.finish(), parts.finish());
+        }
+        let mut crc = DigestState::new(DigestAlgorithm::Crc32c);
+        crc.update(b"hello world");
+        assert_eq!(crc.finish(), crc32c::crc32c(b"hello world") as u64);
+    }
+
+    #[test]
+    fn segments_in_any_order() {
+        let row_len = DIGEST_SEGMENT_LEN * 2 + 5;
+        let bytes: Vec<u8> = (0..row_len * 2).map(|n| n as u8).collect();
+        let mut whole = SegmentedDigest::new(DigestAlgorithm::XxHash64, row_len, 2);
+        whole.update_row(&[0], 0, &bytes);
+        let mut parts = SegmentedDigest::new(DigestAlgorithm::XxHash64, row_len, 2);
+        let split = (DIGEST_SEGMENT_LEN as usize + 3) * 2;
+        parts.update_row(&[0], 0, &bytes[..split]);
+        parts.update_row(&[1], 0, &bytes);
+        parts.update_row(&[0], split as u64 / 2, &bytes[split..]);
+        assert!(parts.pending.is_empty());
+        whole.update_row(&[1], 0, &bytes);
+        assert_eq!(whole.finish(), parts.finish());
+
+        // the position of each segment contributes
+        let mut moved = SegmentedDigest::new(DigestAlgorithm::XxHash64, row_len, 2);
+        moved.update_row(&[0], 0, &bytes);
+        moved.update_row(&[2], 0, &bytes);
+        assert_ne!(moved.finish(), whole.finish());
+    }
+}
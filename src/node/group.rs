@@ -1,16 +1,23 @@
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
-    io::{self, ErrorKind},
+    io::{self, ErrorKind, Read},
 };
 
 use crate::{
     data_type::ReflectedType,
-    store::{ListableStore, NodeKey, NodeName, ReadableStore, Store, WriteableStore},
+    metadata::parse_group_meta,
+    store::{naming, ListableStore, NodeKey, NodeName, ReadableStore, Store, WriteableStore},
     ZARR_FORMAT,
 };
 
-use super::{array::Array, ArrayMetadata, JsonObject, ReadableMetadata, WriteableMetadata};
+use super::{
+    array::{Array, ExtensionPolicy},
+    attributes::AttributeLimits,
+    create_meta,
+    manifest::Manifest,
+    ArrayMetadata, JsonObject, ReadableMetadata, WriteableMetadata,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GroupMetadata {
@@ -52,14 +59,20 @@ impl Default for GroupMetadata {
     }
 }
 
-pub struct Group<'s, S: Store> {
+/// Handle to a group in a store.
+///
+/// Groups are [Send] and [Sync] if their store is [Sync].
+pub struct Group<'s, S: Store + ?Sized> {
     store: &'s S,
     key: NodeKey,
     meta_key: NodeKey,
     metadata: GroupMetadata,
+    attribute_limits: Option<AttributeLimits>,
+    extension_policy: ExtensionPolicy,
+    lenient_fill_values: bool,
 }
 
-impl<'s, S: Store> Group<'s, S> {
+impl<'s, S: Store + ?Sized> Group<'s, S> {
     pub(crate) fn new(store: &'s S, key: NodeKey, metadata: GroupMetadata) -> Self {
         let mut meta_key = key.clone();
         meta_key.with_metadata();
@@ -68,6 +81,64 @@ impl<'s, S: Store> Group<'s, S> {
             key,
             meta_key,
             metadata,
+            attribute_limits: None,
+            extension_policy: ExtensionPolicy::default(),
+            lenient_fill_values: false,
+        }
+    }
+
+    /// Which extensions arrays opened through this group may use
+    /// (see [Array::from_store_with_extensions]).
+    ///
+    /// Inherited by child groups created or opened through this group.
+    pub fn with_extension_policy(mut self, policy: ExtensionPolicy) -> Self {
+        self.extension_policy = policy;
+        self
+    }
+
+    pub fn extension_policy(&self) -> &ExtensionPolicy {
+        &self.extension_policy
+    }
+
+    /// Whether arrays opened through this group coerce sloppily-encoded fill values
+    /// (see [Array::from_store_lenient]).
+    ///
+    /// Off by default; inherited by child groups created or opened through this group.
+    pub fn with_lenient_fill_values(mut self, lenient: bool) -> Self {
+        self.lenient_fill_values = lenient;
+        self
+    }
+
+    pub fn lenient_fill_values(&self) -> bool {
+        self.lenient_fill_values
+    }
+
+    /// Limits checked by [Group::validate_attributes] before every metadata write,
+    /// including those of child nodes created or opened through this group (which inherit the limits).
+    ///
+    /// By default, attributes are not checked.
+    pub fn with_attribute_limits(mut self, limits: AttributeLimits) -> Self {
+        self.attribute_limits = Some(limits);
+        self
+    }
+
+    pub fn attribute_limits(&self) -> Option<&AttributeLimits> {
+        self.attribute_limits.as_ref()
+    }
+
+    /// Check the attributes against this group's [AttributeLimits], if any.
+    ///
+    /// Violations are [ErrorKind::InvalidInput] errors.
+    pub fn validate_attributes(&self) -> io::Result<()> {
+        self.check_attributes(&self.metadata)
+    }
+
+    fn check_attributes<M: ReadableMetadata>(&self, metadata: &M) -> io::Result<()> {
+        match &self.attribute_limits {
+            Some(limits) => limits
+                .validate_attributes(metadata.get_attributes())
+                .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e)),
+            None => Ok(()),
         }
     }
 
@@ -81,6 +152,7 @@ impl<'s, S: Store> Group<'s, S> {
         key
     }
 
+    #[allow(dead_code)]
     fn descendant_key(&self, subkey: &NodeKey) -> NodeKey {
         let mut key = self.key.clone();
         for n in subkey.as_slice().iter() {
@@ -92,16 +164,20 @@ impl<'s, S: Store> Group<'s, S> {
     fn meta_key(&self) -> &NodeKey {
         &self.meta_key
     }
+}
 
-    fn store(&self) -> &'s S {
-        self.store
-    }
+/// `Err` with [ErrorKind::InvalidData] if the document is not valid group metadata.
+fn read_group_meta<R: Read>(mut r: R) -> io::Result<GroupMetadata> {
+    let mut bytes = Vec::default();
+    r.read_to_end(&mut bytes)?;
+    parse_group_meta(&bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
 }
 
-impl<'s, S: ReadableStore> Group<'s, S> {
+impl<'s, S: ReadableStore + ?Sized> Group<'s, S> {
+    #[allow(dead_code)]
     pub(crate) fn read_meta(&mut self) -> io::Result<()> {
         if let Some(r) = self.store.get(self.meta_key())? {
-            let meta: GroupMetadata = serde_json::from_reader(r).expect("deser error");
+            let meta = read_group_meta(r)?;
             self.metadata = meta;
             Ok(())
         } else {
@@ -116,7 +192,7 @@ impl<'s, S: ReadableStore> Group<'s, S> {
         let mut meta_key = key.clone();
         meta_key.with_metadata();
         if let Some(r) = store.get(&meta_key)? {
-            let meta: GroupMetadata = serde_json::from_reader(r).expect("deser error");
+            let meta = read_group_meta(r)?;
             Ok(Self::new(store, key, meta))
         } else {
             Err(io::Error::new(
@@ -130,57 +206,98 @@ impl<'s, S: ReadableStore> Group<'s, S> {
         let mut key = self.key().clone();
         key.extend(subkey);
         match Self::from_store(self.store, key) {
-            Ok(s) => Ok(Some(s)),
+            Ok(mut s) => {
+                s.attribute_limits = self.attribute_limits;
+                s.extension_policy = self.extension_policy.clone();
+                s.lenient_fill_values = self.lenient_fill_values;
+                Ok(Some(s))
+            }
             Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
             Err(e) => Err(e),
         }
     }
 
-    pub fn get_array<T: ReflectedType>(&self, subkey: NodeKey) -> io::Result<Option<Array<S, T>>> {
+    pub fn get_array<T: ReflectedType>(
+        &self,
+        subkey: NodeKey,
+    ) -> io::Result<Option<Array<'s, S, T>>> {
         let mut key = self.key().clone();
         key.extend(subkey);
-        match Array::from_store(self.store, key) {
-            Ok(s) => Ok(Some(s)),
+        let opened = if self.lenient_fill_values {
+            Array::from_store_lenient(self.store, key, &self.extension_policy)
+        } else {
+            Array::from_store_with_extensions(self.store, key, &self.extension_policy)
+        };
+        match opened {
+            Ok(s) => Ok(Some(match self.attribute_limits {
+                Some(limits) => s.with_attribute_limits(limits),
+                None => s,
+            })),
             Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
             Err(e) => Err(e),
         }
     }
 }
 
-impl<'s, S: ListableStore> Group<'s, S> {
+impl<'s, S: ListableStore + ?Sized> Group<'s, S> {
     pub fn child_keys(&self) -> io::Result<Vec<NodeKey>> {
         let (_, keys) = self.store.list_dir(&self.key)?;
         Ok(keys)
     }
 }
 
-impl<'s, S: WriteableStore> Group<'s, S> {
-    pub(crate) fn write_meta(&self) -> io::Result<()> {
-        self.store.set(&self.meta_key, |w| {
-            serde_json::to_writer_pretty(w, &self.metadata).expect("could not serialise metadata");
-            Ok(())
-        })
+impl<'s, S: ReadableStore + ListableStore + ?Sized> Group<'s, S> {
+    /// List the size (and optionally checksum) of every key in this group and its descendants.
+    pub fn manifest(&self, checksums: bool) -> io::Result<Manifest> {
+        Manifest::from_store(self.store, &self.key, checksums)
     }
+}
 
-    /// Deletes any existing group.
-    pub fn create_group(&self, name: NodeName) -> io::Result<Self> {
+/// Check the name of a node to be created against the global naming policy.
+fn check_name(name: &NodeName) -> io::Result<()> {
+    naming::check_global(name).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))
+}
+
+impl<'s, S: WriteableStore + ?Sized> Group<'s, S> {
+    /// Create a child group.
+    ///
+    /// If `overwrite`, any existing node with this name is deleted;
+    /// otherwise, an existing node is an [ErrorKind::AlreadyExists] error.
+    /// A name which violates the [global naming policy](naming::set_global_naming_policy)
+    /// is an [ErrorKind::InvalidInput] error.
+    pub fn create_group(&self, name: NodeName, overwrite: bool) -> io::Result<Self> {
+        check_name(&name)?;
         let key = self.child_key(name);
-        self.store.erase_prefix(&key)?;
-        let g = Self::new(self.store, key, GroupMetadata::default());
-        g.write_meta()?;
+        let mut g = Self::new(self.store, key, GroupMetadata::default());
+        g.attribute_limits = self.attribute_limits;
+        g.extension_policy = self.extension_policy.clone();
+        g.lenient_fill_values = self.lenient_fill_values;
+        create_meta(self.store, &g.key, &g.metadata, overwrite)?;
         Ok(g)
     }
 
+    /// Create a child array.
+    ///
+    /// If `overwrite`, any existing node with this name is deleted;
+    /// otherwise, an existing node is an [ErrorKind::AlreadyExists] error.
+    /// A name which violates the [global naming policy](naming::set_global_naming_policy)
+    /// is an [ErrorKind::InvalidInput] error.
     pub fn create_array<T: ReflectedType>(
         &self,
         name: NodeName,
         metadata: ArrayMetadata,
-    ) -> io::Result<Array<S, T>> {
+        overwrite: bool,
+    ) -> io::Result<Array<'s, S, T>> {
+        check_name(&name)?;
         let mut key = self.key.clone();
         key.push(name);
-        let arr = Array::new(self.store, key.clone(), metadata).expect("Bad array arguments");
-        self.store.erase_prefix(&key)?;
-        arr.write_meta()?;
+        metadata.validate_sharding()?;
+        self.check_attributes(&metadata)?;
+        let mut arr = Array::new(self.store, key.clone(), metadata).expect("Bad array arguments");
+        if let Some(limits) = self.attribute_limits {
+            arr = arr.with_attribute_limits(limits);
+        }
+        create_meta(self.store, &key, arr.metadata(), overwrite)?;
         Ok(arr)
     }
 
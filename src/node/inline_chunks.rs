@@ -0,0 +1,57 @@
+//! Experimental storage of small encoded chunks inline in a sidecar document,
+//! rather than as one object per chunk.
+//!
+//! Useful for arrays with very many tiny chunks (e.g. coordinate arrays) on object stores,
+//! where per-object overhead dominates.
+//! Arrays opt in with the [INLINE_CHUNKS_EXTENSION] metadata extension
+//! (see [ArrayMetadataBuilder::inline_chunks](super::ArrayMetadataBuilder::inline_chunks)).
+//! Encoded chunks no larger than the configured `max_size` are stored base64-encoded
+//! in a JSON document alongside the array metadata,
+//! mapping `.`-separated chunk indices to payloads; larger chunks are stored as usual.
+//!
+//! The extension must be understood, as other implementations would otherwise
+//! read inlined chunks as the fill value.
+use std::{collections::BTreeMap, io};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+
+/// Name of the array metadata extension which indicates that small chunks are inlined.
+pub const INLINE_CHUNKS_EXTENSION: &str = "inline_chunks";
+
+/// Name of the sidecar document within the array node.
+pub(crate) const INLINE_CHUNKS_NAME: &str = "inline_chunks.json";
+
+/// Configuration of the [INLINE_CHUNKS_EXTENSION].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlineChunksConfig {
+    /// Largest encoded chunk, in bytes, which is stored inline.
+    pub max_size: usize,
+}
+
+/// Ordered so that the serialised document is deterministic.
+pub(crate) type InlineChunksDocument = BTreeMap<String, String>;
+
+pub(crate) fn encode_payload(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+pub(crate) fn decode_payload(payload: &str) -> io::Result<Vec<u8>> {
+    STANDARD
+        .decode(payload)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_roundtrip() {
+        let bytes = [0, 1, 2, 255, 254];
+        let payload = encode_payload(&bytes);
+        assert_eq!(payload, "AAEC//4=");
+        assert_eq!(decode_payload(&payload).unwrap(), bytes);
+        assert!(decode_payload("not base64!").is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Listings of every key below a node, for verifying that a copy of a hierarchy is complete.
+//!
+//! A [Manifest] maps keys (relative to the node, in their encoded form)
+//! to their size in bytes and optionally a CRC32C checksum of their contents.
+//! Build one for the source and destination with [Array::manifest](super::Array::manifest)
+//! or [Group::manifest](super::Group::manifest) and compare them with [Manifest::mismatches].
+use std::{
+    collections::BTreeMap,
+    io::{self, Read},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::store::{ListableStore, NodeKey, ReadableStore};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub size: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crc32c: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Manifest(pub BTreeMap<String, ManifestEntry>);
+
+impl Manifest {
+    /// List all keys below the given node; if `checksums`, every value is read in full.
+    pub fn from_store<S: ReadableStore + ListableStore + ?Sized>(
+        store: &S,
+        key: &NodeKey,
+        checksums: bool,
+    ) -> io::Result<Self> {
+        let mut entries = BTreeMap::default();
+        let mut buf = vec![0; BUF_SIZE];
+        for k in store.list_prefix(key)? {
+            let Some(mut r) = store.get(&k)? else {
+                // deleted since listing
+                continue;
+            };
+            let mut size = 0;
+            let mut crc = 0;
+            loop {
+                let n = r.read(&mut buf)?;
+                if n == 0 {
+                    break;
+                }
+                size += n as u64;
+                if checksums {
+                    crc = crc32c::crc32c_append(crc, &buf[..n]);
+                }
+            }
+            let rel: NodeKey = k.as_slice()[key.len()..].iter().cloned().collect();
+            entries.insert(
+                rel.encode(),
+                ManifestEntry {
+                    size,
+                    crc32c: checksums.then_some(crc),
+                },
+            );
+        }
+        Ok(Self(entries))
+    }
+
+    /// Keys which are missing from either manifest, or whose entries differ.
+    ///
+    /// Checksums are only compared if present in both.
+    pub fn mismatches(&self, other: &Manifest) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .0
+            .iter()
+            .filter(|(k, e)| match other.0.get(*k) {
+                None => true,
+                Some(o) => {
+                    e.size != o.size || matches!((e.crc32c, o.crc32c), (Some(a), Some(b)) if a != b)
+                }
+            })
+            .map(|(k, _)| k.clone())
+            .collect();
+        out.extend(other.0.keys().filter(|k| !self.0.contains_key(*k)).cloned());
+        out.sort();
+        out
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.0.values().map(|e| e.size).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{HashMapStore, WriteableStore};
+    use std::io::Write;
+
+    fn populate(store: &HashMapStore, vals: &[(&str, &[u8])]) {
+        for (k, v) in vals {
+            store.set(&k.parse().unwrap(), |w| w.write_all(v)).unwrap();
+        }
+    }
+
+    #[test]
+    fn manifest_roundtrip() {
+        let store = HashMapStore::default();
+        populate(
+            &store,
+            &[
+                ("a/zarr.json", b"{}"),
+                ("a/c/0", b"abc"),
+                ("b/zarr.json", b"{}"),
+            ],
+        );
+        let m = Manifest::from_store(&store, &"a".parse().unwrap(), true).unwrap();
+        assert_eq!(m.0.len(), 2);
+        assert_eq!(m.0["c/0"].size, 3);
+        assert_eq!(m.0["c/0"].crc32c, Some(crc32c::crc32c(b"abc")));
+        assert_eq!(m.total_size(), 5);
+
+        let s = serde_json::to_string(&m).unwrap();
+        let m2: Manifest = serde_json::from_str(&s).unwrap();
+        assert_eq!(m, m2);
+    }
+
+    #[test]
+    fn manifest_mismatches() {
+        let store = HashMapStore::default();
+        populate(&store, &[("zarr.json", b"{}"), ("c/0", b"abc")]);
+        let m1 = Manifest::from_store(&store, &NodeKey::default(), true).unwrap();
+
+        populate(&store, &[("c/0", b"abd"), ("c/1", b"abc")]);
+        let m2 = Manifest::from_store(&store, &NodeKey::default(), true).unwrap();
+        assert_eq!(m1.mismatches(&m2), vec!["c/0", "c/1"]);
+
+        // sizes only
+        let m3 = Manifest::from_store(&store, &NodeKey::default(), false).unwrap();
+        assert_eq!(m2.mismatches(&m3), Vec::<String>::new());
+    }
+}
@@ -1,12 +1,34 @@
 mod array;
-use std::collections::HashMap;
+pub mod attributes;
+pub mod buffer_pool;
+pub mod chunk_exchange;
+pub mod chunk_stats;
+pub mod chunk_stream;
+pub mod conventions;
+pub mod digest;
+pub mod inline_chunks;
+pub mod manifest;
+mod prefetch;
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Write},
+};
 
-pub use array::{Array, ArrayMetadata, ArrayMetadataBuilder, Extension, StorageTransformer};
+pub use array::{
+    Array, ArrayMetadata, ArrayMetadataBuilder, Extension, ExtensionError, ExtensionPolicy,
+    InterleavedImage, MetadataLint, OutOfBoundsPolicy, SparseRun, StorageTransformer, WritePlan,
+    WriteProgress,
+};
 mod group;
 pub use group::{Group, GroupMetadata};
+mod virtual_array;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+pub use virtual_array::VirtualArray;
 
-use crate::variant_from_data;
+use crate::{
+    store::{NodeKey, WriteableStore},
+    variant_from_data,
+};
 
 pub type JsonObject = HashMap<String, serde_json::Value>;
 
@@ -68,6 +90,7 @@ pub trait WriteableMetadata {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "node_type", rename_all = "lowercase")]
+#[allow(clippy::large_enum_variant)]
 pub enum Metadata {
     Array(ArrayMetadata),
     Group(GroupMetadata),
@@ -114,11 +137,42 @@ impl WriteableMetadata for Metadata {
 variant_from_data!(Metadata, Array, ArrayMetadata);
 variant_from_data!(Metadata, Group, GroupMetadata);
 
+/// Write the metadata document of a new node.
+///
+/// If `overwrite`, any existing node at this key (including its children) is erased first.
+/// Otherwise, an [ErrorKind::AlreadyExists] error is returned if the metadata document exists;
+/// this uses the store's create-if-absent operation ([WriteableStore::set_if_match]),
+/// so that at most one of several concurrent creators succeeds.
+pub(crate) fn create_meta<S: WriteableStore + ?Sized, M: Serialize>(
+    store: &S,
+    key: &NodeKey,
+    metadata: &M,
+    overwrite: bool,
+) -> io::Result<()> {
+    let mut meta_key = key.clone();
+    meta_key.with_metadata();
+    let buf = serde_json::to_vec_pretty(metadata).expect("could not serialise metadata");
+
+    if overwrite {
+        store.erase_prefix(key)?;
+        return store.set(&meta_key, |w| w.write_all(&buf));
+    }
+    if store.set_if_match(&meta_key, None, |w| w.write_all(&buf))? {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            ErrorKind::AlreadyExists,
+            "Node already exists",
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     // from the spec, although with "extensions" removed
+    #[cfg(feature = "gzip")]
     const EXAMPLE_ARRAY_META: &str = r#"
         {
             "zarr_format": 3,
@@ -170,6 +224,7 @@ mod tests {
         }
     "#;
 
+    #[cfg(feature = "gzip")]
     #[test]
     fn array_meta_roundtrip() {
         let meta: Metadata =
@@ -181,6 +236,39 @@ mod tests {
         let _s2 = serde_json::to_string(&meta).expect("Couldn't serialize array metadata");
     }
 
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn array_meta_lint() {
+        let meta: ArrayMetadata =
+            serde_json::from_str(EXAMPLE_ARRAY_META).expect("Could not deserialise array metadata");
+        // "NaN" is a valid float fill value
+        assert_eq!(meta.lint(), vec![]);
+
+        let meta: ArrayMetadata = ArrayMetadataBuilder::<f32>::new(&[10, 20])
+            .dimension_names(smallvec::smallvec![Some("x".to_owned()), None])
+            .unwrap()
+            .into();
+        assert_eq!(meta.lint(), vec![MetadataLint::UnnamedDimensions(vec![1])]);
+
+        let meta: ArrayMetadata = ArrayMetadataBuilder::<f32>::new(&[10, 20]).into();
+        assert_eq!(meta.lint(), vec![MetadataLint::MissingDimensionNames]);
+
+        // ignored extensions are reported in name order
+        let mut value: serde_json::Value = serde_json::from_str(EXAMPLE_ARRAY_META).unwrap();
+        let names = ["ext_d", "ext_a", "ext_c", "ext_e", "ext_b"];
+        for name in names {
+            value[name] = serde_json::json!({"must_understand": false});
+        }
+        let meta: ArrayMetadata = serde_json::from_value(value).unwrap();
+        let mut sorted = names;
+        sorted.sort();
+        let expected: Vec<_> = sorted
+            .iter()
+            .map(|n| MetadataLint::IgnoredExtension(n.to_string()))
+            .collect();
+        assert_eq!(meta.lint(), expected);
+    }
+
     #[test]
     fn group_meta_roundtrip() {
         let meta: Metadata =
@@ -197,8 +285,11 @@ mod tests {
         use crate::{
             chunk_grid::ArrayRegion,
             data_type::ReflectedType,
-            node::{array::Array, group::Group},
-            store::{filesystem::FileSystemStore, NodeKey, ReadableStore},
+            node::{
+                array::Array, chunk_exchange::CHUNK_RECORD_MAGIC, chunk_stats::ChunkStats,
+                group::Group,
+            },
+            store::{filesystem::FileSystemStore, ListableStore, NodeKey, ReadableStore},
             ArcArrayD, GridCoord,
         };
         use smallvec::smallvec;
@@ -211,9 +302,8 @@ mod tests {
             let path = tmp.path().join("root.zarr");
             let store = FileSystemStore::create(path, true).unwrap();
 
-            let g = Group::new(&store, Default::default(), Default::default());
-            g.write_meta().unwrap();
-            let g2 = g.create_group("child".parse().unwrap()).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+            let g2 = g.create_group("child".parse().unwrap(), false).unwrap();
 
             let ameta = ArrayMetadataBuilder::<f32>::new(&[30, 40])
                 .chunk_grid(vec![5, 10].as_slice())
@@ -221,7 +311,7 @@ mod tests {
                 .into();
 
             let arr = g2
-                .create_array::<f32>("array".parse().unwrap(), ameta)
+                .create_array::<f32>("array".parse().unwrap(), ameta, false)
                 .unwrap();
             let chunk = ArcArrayD::from_elem(vec![5, 10].as_slice(), 1.0);
             arr.write_chunk(&smallvec![0, 0], chunk.clone()).unwrap();
@@ -256,8 +346,7 @@ mod tests {
             let path = tmp.path().join("root.zarr");
             let store = FileSystemStore::create(path, true).unwrap();
 
-            let g = Group::new(&store, Default::default(), Default::default());
-            g.write_meta().unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
 
             let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
                 .chunk_grid(vec![2, 2].as_slice())
@@ -265,7 +354,7 @@ mod tests {
                 .into();
 
             let arr = g
-                .create_array::<i32>("array".parse().unwrap(), ameta)
+                .create_array::<i32>("array".parse().unwrap(), ameta, false)
                 .unwrap();
             let offset = smallvec![1, 1];
 
@@ -279,14 +368,1565 @@ mod tests {
             assert_eq!(chunk_contents(&arr, &[1, 1]), vec![1, 0, 0, 0]);
         }
 
+        #[test]
+        fn chunk_tiles() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .into();
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta).unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![4, 4], (0..16).collect()).unwrap();
+            arr.write_chunk(&smallvec![0, 0], data).unwrap();
+
+            let regions = vec![
+                ArrayRegion::from_offset_shape(&[0, 0], &[2, 2]),
+                ArrayRegion::from_offset_shape(&[1, 2], &[3, 2]),
+            ];
+            let tiles = arr
+                .read_chunk_tiles(&smallvec![0, 0], &regions)
+                .unwrap()
+                .unwrap();
+            assert_eq!(tiles.len(), 2);
+            for (reg, tile) in regions.into_iter().zip(tiles) {
+                let expected = arr.read_region(reg).unwrap().unwrap();
+                assert_eq!(tile, expected);
+            }
+
+            assert!(arr
+                .read_chunk_tiles(
+                    &smallvec![0, 0],
+                    &[ArrayRegion::from_offset_shape(&[3, 3], &[2, 2])]
+                )
+                .is_err());
+        }
+
+        #[test]
+        fn open_invalid_metadata() {
+            use crate::store::WriteableStore;
+            use std::io::{ErrorKind, Write};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+            g.create_group("grp".parse().unwrap(), Default::default())
+                .unwrap();
+            store
+                .set(&"bad/zarr.json".parse().unwrap(), |w| w.write_all(b"{"))
+                .unwrap();
+
+            for name in ["grp", "bad"] {
+                let Err(err) = g.get_array::<i32>(name.parse().unwrap()) else {
+                    panic!("Opened invalid array metadata");
+                };
+                assert_eq!(err.kind(), ErrorKind::InvalidData);
+            }
+            let Err(err) = g.get_group("bad".parse().unwrap()) else {
+                panic!("Opened invalid group metadata");
+            };
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+
+        #[test]
+        fn skip_checksum_verification() {
+            use crate::codecs::bb::BBCodecType;
+            use crate::store::WriteableStore;
+            use std::io::{Read, Write};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .push_bb_codec(BBCodecType::Crc32c(None))
+                .into();
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta).unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![4, 4], (0..16).collect()).unwrap();
+            arr.write_chunk(&smallvec![0, 0], data.clone()).unwrap();
+
+            // corrupt the checksum
+            let key: NodeKey = "c/0/0".parse().unwrap();
+            let mut buf = Vec::default();
+            store
+                .get(&key)
+                .unwrap()
+                .unwrap()
+                .read_to_end(&mut buf)
+                .unwrap();
+            let last = buf.pop().unwrap();
+            buf.push(last.wrapping_add(1));
+            store.set(&key, |w| w.write_all(&buf)).unwrap();
+
+            let arr = arr.with_checksum_verification(false);
+            assert!(!arr.verifies_checksums());
+            let out = arr.read_chunk(&smallvec![0, 0]).unwrap().unwrap();
+            assert_eq!(out, data);
+        }
+
+        #[test]
+        fn skip_checksum_verification_sharded() {
+            use crate::codecs::{ab::sharding_indexed::ShardingIndexedCodec, bb::BBCodecType};
+            use crate::store::WriteableStore;
+            use std::io::{Read, Write};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .ab_codec(
+                    ShardingIndexedCodec::new(vec![2, 2]).push_bb_codec(BBCodecType::Crc32c(None)),
+                )
+                .unwrap()
+                .into();
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta).unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![4, 4], (0..16).collect()).unwrap();
+            arr.write_chunk(&smallvec![0, 0], data.clone()).unwrap();
+
+            // corrupt the checksums of the first sub-chunk and of the index
+            let key: NodeKey = "c/0/0".parse().unwrap();
+            let mut buf = Vec::default();
+            store
+                .get(&key)
+                .unwrap()
+                .unwrap()
+                .read_to_end(&mut buf)
+                .unwrap();
+            buf[2 * 2 * 4 + 3] = buf[2 * 2 * 4 + 3].wrapping_add(1);
+            let last = buf.pop().unwrap();
+            buf.push(last.wrapping_add(1));
+            store.set(&key, |w| w.write_all(&buf)).unwrap();
+
+            assert!(arr.read_chunk(&smallvec![0, 0]).is_err());
+            let arr = arr.with_checksum_verification(false);
+            let out = arr.read_chunk(&smallvec![0, 0]).unwrap().unwrap();
+            assert_eq!(out, data);
+        }
+
+        #[cfg(feature = "parallel")]
+        #[test]
+        fn parallel_region_roundtrip() {
+            use std::sync::Arc;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[10, 10])
+                .chunk_grid(vec![3, 3].as_slice())
+                .unwrap()
+                .into();
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(2)
+                .build()
+                .unwrap();
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta)
+                .unwrap()
+                .with_thread_pool(Arc::new(pool));
+
+            let data = ArcArrayD::from_shape_vec(vec![8, 8], (0..64).collect()).unwrap();
+            arr.par_write_region(&smallvec![1, 1], data.clone())
+                .unwrap();
+
+            let out = arr
+                .par_read_region(ArrayRegion::from_offset_shape(&[1, 1], &[8, 8]))
+                .unwrap()
+                .unwrap();
+            assert_eq!(out, data);
+            assert_eq!(
+                out,
+                arr.read_region(ArrayRegion::from_offset_shape(&[1, 1], &[8, 8]))
+                    .unwrap()
+                    .unwrap()
+            );
+        }
+
+        #[test]
+        fn read_interleaved() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            // channel-first, 3 x 4 x 5
+            let ameta = ArrayMetadataBuilder::<u8>::new(&[3, 4, 5])
+                .chunk_grid(vec![1, 2, 2].as_slice())
+                .unwrap()
+                .into();
+            let arr: Array<_, u8> = Array::new(&store, Default::default(), ameta).unwrap();
+            let data = ArcArrayD::from_shape_fn(vec![3, 4, 5], |idx| {
+                (idx[0] * 100 + idx[1] * 10 + idx[2]) as u8
+            });
+            arr.write_region(&smallvec![0, 0, 0], data).unwrap();
+
+            let img = arr
+                .read_interleaved(&ArrayRegion::from_offset_shape(&[1, 2], &[2, 2]), 0)
+                .unwrap()
+                .unwrap();
+            assert_eq!(img.shape.as_slice(), &[2, 2]);
+            assert_eq!(img.channels, 3);
+            assert_eq!(
+                img.data,
+                vec![12, 112, 212, 13, 113, 213, 22, 122, 222, 23, 123, 223]
+            );
+
+            assert!(arr
+                .read_interleaved(&ArrayRegion::from_offset_shape(&[0, 0], &[2, 2]), 2)
+                .is_err());
+        }
+
+        #[test]
+        fn create_exclusive() {
+            use std::io::ErrorKind;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+            assert_eq!(
+                crate::prelude::create_root_group(&store, Default::default())
+                    .err()
+                    .unwrap()
+                    .kind(),
+                ErrorKind::AlreadyExists
+            );
+
+            let ameta: ArrayMetadata = ArrayMetadataBuilder::<i32>::new(&[4, 4]).into();
+            let arr = g
+                .create_array::<i32>("array".parse().unwrap(), ameta.clone(), false)
+                .unwrap();
+            arr.write_chunk(&smallvec![0, 0], ArcArrayD::from_elem(vec![4, 4], 1))
+                .unwrap();
+
+            let err = g
+                .create_group("array".parse().unwrap(), false)
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+            // existing data is untouched
+            assert_eq!(
+                arr.read_chunk(&smallvec![0, 0]).unwrap().unwrap()[[0, 0]],
+                1
+            );
+
+            let arr = g
+                .create_array::<i32>("array".parse().unwrap(), ameta, true)
+                .unwrap();
+            assert_eq!(
+                arr.read_chunk(&smallvec![0, 0]).unwrap().unwrap()[[0, 0]],
+                0
+            );
+        }
+
+        #[test]
+        fn virtual_array() {
+            use crate::node::VirtualArray;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let mut arrays = Vec::default();
+            for (name, len) in [("t0", 2), ("t1", 3)] {
+                let ameta = ArrayMetadataBuilder::<i32>::new(&[len, 4])
+                    .chunk_grid(vec![2, 2].as_slice())
+                    .unwrap()
+                    .into();
+                let arr = g
+                    .create_array::<i32>(name.parse().unwrap(), ameta, false)
+                    .unwrap();
+                let data = ArcArrayD::from_elem(vec![len as usize, 4], len as i32);
+                arr.write_region(&smallvec![0, 0], data).unwrap();
+                arrays.push(arr);
+            }
+
+            let varr = VirtualArray::new(arrays, 0).unwrap();
+            assert_eq!(varr.shape(), &[5, 4]);
+            assert_eq!(varr.locate(&[3, 1]).unwrap(), Some((1, smallvec![1, 1])));
+
+            let out = varr
+                .read_region(ArrayRegion::from_offset_shape(&[1, 1], &[3, 2]))
+                .unwrap()
+                .unwrap();
+            let expected = ArcArrayD::from_shape_vec(vec![3, 2], vec![2, 2, 3, 3, 3, 3]).unwrap();
+            assert_eq!(out, expected);
+        }
+
+        #[test]
+        fn with_store() {
+            use crate::{node::OutOfBoundsPolicy, store::HashMapStore};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .into();
+            let arr = crate::prelude::create_root_array::<i32, _>(&store, ameta)
+                .unwrap()
+                .with_out_of_bounds_policy(OutOfBoundsPolicy::Error);
+            let data = ArcArrayD::from_elem(vec![4, 4], 1);
+            arr.write_region(&smallvec![0, 0], data.clone()).unwrap();
+
+            // mirror without any metadata
+            let mirror = HashMapStore::default();
+            let marr = arr.with_store(&mirror);
+            assert_eq!(marr.out_of_bounds_policy(), OutOfBoundsPolicy::Error);
+            assert_eq!(marr.stored_chunk_count().unwrap(), 0);
+            marr.write_region(&smallvec![0, 0], data.clone()).unwrap();
+
+            let region = ArrayRegion::from_offset_shape(&[0, 0], &[4, 4]);
+            assert_eq!(marr.read_region(region.clone()).unwrap().unwrap(), data);
+            assert_eq!(
+                arr.with_store(&mirror)
+                    .read_region(region)
+                    .unwrap()
+                    .unwrap(),
+                data
+            );
+            assert!(!mirror.has_key(marr.meta_key()).unwrap());
+        }
+
+        #[test]
+        fn plan_write_region() {
+            use crate::{codecs::ab::sharding_indexed::ShardingIndexedCodec, node::WritePlan};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[10, 10])
+                .chunk_grid(vec![4, 5].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<i32>("plain".parse().unwrap(), ameta, false)
+                .unwrap();
+
+            let plan = arr.plan_write_region(&smallvec![0, 0], &[4, 10]).unwrap();
+            assert_eq!(
+                plan,
+                WritePlan {
+                    whole_chunks: 2,
+                    partial_chunks: 0,
+                    shards: 0,
+                    bytes_read: 0,
+                    bytes_written: 2 * 20 * 4,
+                }
+            );
+
+            let plan = arr.plan_write_region(&smallvec![2, 3], &[4, 4]).unwrap();
+            assert_eq!(plan.partial_chunks, 4);
+            assert_eq!(plan.bytes_read, plan.bytes_written);
+
+            // edge chunks overhang the array, so are never whole; the region is clipped
+            let plan = arr.plan_write_region(&smallvec![8, 0], &[4, 10]).unwrap();
+            assert_eq!((plan.whole_chunks, plan.partial_chunks), (0, 2));
+            assert_eq!(
+                arr.plan_write_region(&smallvec![10, 0], &[1, 1]).unwrap(),
+                WritePlan::default()
+            );
+            assert!(arr.plan_write_region(&smallvec![0], &[1]).is_err());
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[20, 20])
+                .chunk_grid(vec![10, 10].as_slice())
+                .unwrap()
+                .ab_codec(ShardingIndexedCodec::new(vec![5, 5]))
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<i32>("sharded".parse().unwrap(), ameta, false)
+                .unwrap();
+            let plan = arr.plan_write_region(&smallvec![0, 0], &[10, 15]).unwrap();
+            assert_eq!(plan.shards, 2);
+            assert_eq!(plan.chunks(), 2);
+            assert_eq!(plan.partial_chunks, 1);
+            assert_eq!(arr.stored_chunk_count().unwrap(), 0);
+        }
+
+        #[test]
+        fn chunk_counts() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[10, 10])
+                .chunk_grid(vec![4, 5].as_slice())
+                .unwrap()
+                .chunk_stats()
+                .into();
+            let arr = crate::prelude::create_root_array::<i32, _>(&store, ameta).unwrap();
+            assert_eq!(arr.expected_chunk_count(), 6);
+            assert_eq!(arr.stored_chunk_count().unwrap(), 0);
+
+            arr.write_region(&smallvec![0, 0], ArcArrayD::from_elem(vec![5, 5], 1))
+                .unwrap();
+            assert_eq!(arr.stored_chunk_count().unwrap(), 2);
+            assert!((arr.completeness().unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn out_of_bounds_writes() {
+            use crate::node::OutOfBoundsPolicy;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[6, 6])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .into();
+            let mut arr = crate::prelude::create_root_array::<i32, _>(&store, ameta)
+                .unwrap()
+                .with_out_of_bounds_policy(OutOfBoundsPolicy::Error);
+            let data = ArcArrayD::from_elem(vec![4, 4], 1);
+
+            assert!(arr.write_region(&smallvec![4, 4], data.clone()).is_err());
+            assert_eq!(arr.stored_chunk_count().unwrap(), 0);
+
+            arr.write_region_with_policy(&smallvec![4, 4], data.clone(), OutOfBoundsPolicy::Clip)
+                .unwrap();
+            assert_eq!(arr.shape(), &[6, 6]);
+
+            arr.write_region_growing(&smallvec![4, 4], data).unwrap();
+            assert_eq!(arr.shape(), &[8, 8]);
+            let again = Array::<_, i32>::from_store(&store, Default::default()).unwrap();
+            assert_eq!(again.shape(), &[8, 8]);
+            let out = again
+                .read_region(ArrayRegion::from_offset_shape(&[4, 4], &[4, 4]))
+                .unwrap()
+                .unwrap();
+            assert!(out.iter().all(|v| *v == 1));
+        }
+
+        #[test]
+        fn resize_shrink() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[8, 8])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .into();
+            let mut arr = crate::prelude::create_root_array::<i32, _>(&store, ameta).unwrap();
+            arr.write_region(&smallvec![0, 0], ArcArrayD::from_elem(vec![8, 8], 1))
+                .unwrap();
+
+            arr.resize(&[3, 6]).unwrap();
+            assert_eq!(arr.stored_chunk_count().unwrap(), 2);
+
+            arr.resize(&[8, 8]).unwrap();
+            let out = arr
+                .read_region(ArrayRegion::from_offset_shape(&[0, 0], &[8, 8]))
+                .unwrap()
+                .unwrap();
+            let expected =
+                ArcArrayD::from_shape_fn(vec![8, 8], |idx| (idx[0] < 3 && idx[1] < 6) as i32);
+            assert_eq!(out, expected);
+        }
+
+        #[test]
+        fn resize_sharded() {
+            use crate::codecs::ab::sharding_indexed::{ChunkSpec, ShardingIndexedCodec};
+            use std::io::{Cursor, Read};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[20, 20])
+                .chunk_grid(vec![10, 10].as_slice())
+                .unwrap()
+                .ab_codec(ShardingIndexedCodec::new(vec![5, 5]))
+                .unwrap()
+                .into();
+            let mut arr = crate::prelude::create_root_array::<i32, _>(&store, ameta).unwrap();
+            arr.write_region(&smallvec![0, 0], ArcArrayD::from_elem(vec![20, 20], 1))
+                .unwrap();
+            let shard_index = |key: &str| {
+                let mut buf = Vec::default();
+                store
+                    .get(&key.parse().unwrap())
+                    .unwrap()
+                    .unwrap()
+                    .read_to_end(&mut buf)
+                    .unwrap();
+                let spec = ChunkSpec::from_shard(&mut Cursor::new(buf), smallvec![2, 2]).unwrap();
+                [[0u64, 0], [0, 1], [1, 0], [1, 1]].map(|idx| {
+                    !spec
+                        .get_idx(&GridCoord::from_slice(&idx))
+                        .unwrap()
+                        .unwrap()
+                        .is_empty()
+                })
+            };
+            assert_eq!(shard_index("c/1/0"), [true; 4]);
+
+            arr.resize(&[12, 7]).unwrap();
+            assert_eq!(arr.stored_chunk_count().unwrap(), 2);
+            // the straddling column is kept, the row beyond the edge is dropped
+            assert_eq!(shard_index("c/0/0"), [true, true, true, true]);
+            assert_eq!(shard_index("c/1/0"), [true, true, false, false]);
+
+            arr.resize(&[20, 20]).unwrap();
+            let out = arr
+                .read_region(ArrayRegion::from_offset_shape(&[0, 0], &[20, 20]))
+                .unwrap()
+                .unwrap();
+            let expected =
+                ArcArrayD::from_shape_fn(vec![20, 20], |idx| (idx[0] < 12 && idx[1] < 7) as i32);
+            assert_eq!(out, expected);
+
+            // growing does not touch the stored shards
+            arr.resize(&[30, 30]).unwrap();
+            assert_eq!(arr.stored_chunk_count().unwrap(), 2);
+            arr.resize(&[3, 3]).unwrap();
+            assert_eq!(shard_index("c/0/0"), [true, false, false, false]);
+            arr.resize(&[3, 0]).unwrap();
+            assert_eq!(arr.stored_chunk_count().unwrap(), 0);
+        }
+
+        #[test]
+        fn sharded_overhanging_edges() {
+            use crate::codecs::ab::sharding_indexed::ShardingIndexedCodec;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            assert!(ArrayMetadataBuilder::<i32>::new(&[25, 35])
+                .chunk_grid(vec![10, 20].as_slice())
+                .unwrap()
+                .ab_codec(ShardingIndexedCodec::new(vec![3, 10]))
+                .is_err());
+
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![25, 35], (0..25 * 35).collect()).unwrap();
+            // array shape is not a whole number of shards,
+            // with and without (opted-in) sub-chunks overhanging the shards
+            for (name, sub_chunks) in [("aligned", vec![5, 10]), ("overhanging", vec![3, 7])] {
+                let overhanging = name == "overhanging";
+                let mut builder = ArrayMetadataBuilder::<i32>::new(&[25, 35]);
+                if overhanging {
+                    builder = builder.overhanging_sub_chunks();
+                }
+                let ameta = builder
+                    .chunk_grid(vec![10, 20].as_slice())
+                    .unwrap()
+                    .ab_codec(ShardingIndexedCodec::new(sub_chunks))
+                    .unwrap()
+                    .into();
+                let arr = g
+                    .create_array::<i32>(name.parse().unwrap(), ameta, false)
+                    .unwrap();
+                assert_eq!(
+                    arr.metadata()
+                        .lint()
+                        .contains(&MetadataLint::OverhangingSubChunks(vec![10, 20])),
+                    overhanging
+                );
+                arr.write_region(&smallvec![0, 0], data.clone()).unwrap();
+                let out = arr
+                    .read_region(ArrayRegion::from_offset_shape(&[0, 0], &[25, 35]))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(out, data);
+
+                let out = arr
+                    .read_region(ArrayRegion::from_offset_shape(&[8, 18], &[17, 17]))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(out, data.slice(ndarray::s![8.., 18..]).into_dyn());
+
+                let opened = Array::<_, i32>::from_store(&store, name.parse().unwrap());
+                assert_eq!(opened.is_err(), overhanging);
+                let reopened = Array::<_, i32>::from_store_lenient(
+                    &store,
+                    name.parse().unwrap(),
+                    &Default::default(),
+                )
+                .unwrap();
+                let chunk = reopened.read_chunk(&smallvec![2, 1]).unwrap().unwrap();
+                assert_eq!(
+                    chunk.slice(ndarray::s![..5, ..15]),
+                    data.slice(ndarray::s![20.., 20..])
+                );
+            }
+
+            // misaligned metadata written by another implementation is rejected on open
+            let err = Array::<_, i32>::from_store(&store, "overhanging".parse().unwrap())
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("[10, 20]"));
+            assert!(err.to_string().contains("[3, 7]"));
+
+            // sub-chunks which cannot be made from the shard are rejected even when lenient
+            let arr = Array::<_, i32>::from_store(&store, "aligned".parse().unwrap()).unwrap();
+            let mut meta = serde_json::to_value(arr.metadata()).unwrap();
+            meta["codecs"][0]["configuration"]["chunk_shape"] = serde_json::json!([3, 0]);
+            store
+                .set(&"aligned/zarr.json".parse().unwrap(), |w| {
+                    serde_json::to_writer(w, &meta).map_err(io::Error::from)
+                })
+                .unwrap();
+            let err = Array::<_, i32>::from_store_lenient(
+                &store,
+                "aligned".parse().unwrap(),
+                &Default::default(),
+            )
+            .err()
+            .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("[3, 0]"));
+        }
+
+        #[test]
+        fn inline_chunks() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            // 4 bytes per chunk
+            let ameta: ArrayMetadata = ArrayMetadataBuilder::<u8>::new(&[20])
+                .chunk_grid(vec![4].as_slice())
+                .unwrap()
+                .inline_chunks(4)
+                .into();
+            ameta.validate().unwrap();
+            let mut arr = crate::prelude::create_root_array::<u8, _>(&store, ameta).unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![10], (1..11).collect()).unwrap();
+            arr.write_region(&smallvec![0], data.clone()).unwrap();
+
+            // only the metadata and sidecar documents are stored
+            assert_eq!(store.list_prefix(&NodeKey::default()).unwrap().len(), 2);
+            assert_eq!(arr.stored_chunk_count().unwrap(), 3);
+            let out = arr
+                .read_region(ArrayRegion::from_offset_shape(&[0], &[10]))
+                .unwrap()
+                .unwrap();
+            assert_eq!(out, data);
+
+            arr.resize(&[4]).unwrap();
+            assert_eq!(arr.stored_chunk_count().unwrap(), 1);
+            let reopened = Array::<_, u8>::from_store(&store, NodeKey::default()).unwrap();
+            let out = reopened
+                .read_region(ArrayRegion::from_offset_shape(&[0], &[4]))
+                .unwrap()
+                .unwrap();
+            assert_eq!(out.as_slice().unwrap(), &[1, 2, 3, 4]);
+
+            // concurrent writers do not lose each others' inlined chunks
+            let ameta: ArrayMetadata = ArrayMetadataBuilder::<u8>::new(&[64])
+                .chunk_grid(vec![4].as_slice())
+                .unwrap()
+                .inline_chunks(4)
+                .into();
+            let store = FileSystemStore::create(tmp.path().join("concurrent.zarr"), true).unwrap();
+            let arr = crate::prelude::create_root_array::<u8, _>(&store, ameta).unwrap();
+            std::thread::scope(|s| {
+                for i in 0..4u64 {
+                    let arr = &arr;
+                    s.spawn(move || {
+                        for j in 0..4 {
+                            let chunk = ArcArrayD::from_elem(vec![4], (i * 4 + j + 1) as u8);
+                            arr.write_chunk(&smallvec![i * 4 + j], chunk).unwrap();
+                        }
+                    });
+                }
+            });
+            assert_eq!(arr.stored_chunk_count().unwrap(), 16);
+            let out = arr
+                .read_region(ArrayRegion::from_offset_shape(&[0], &[64]))
+                .unwrap()
+                .unwrap();
+            assert!(out
+                .iter()
+                .enumerate()
+                .all(|(i, v)| *v as usize == i / 4 + 1));
+        }
+
+        #[test]
+        fn dimension_mismatch_errors() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .into();
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta).unwrap();
+
+            let err = arr.read_chunk(&smallvec![0, 0, 0]).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+            let err = arr
+                .read_region(ArrayRegion::from_offset_shape(&[0], &[2]))
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+            let data = ArcArrayD::from_elem(vec![2, 2, 2], 1);
+            assert!(arr.write_region(&smallvec![0, 0, 0], data.clone()).is_err());
+            assert!(arr.write_chunk(&smallvec![0, 0], data).is_err());
+        }
+
+        #[test]
+        fn padding_overflow_errors() {
+            use crate::codecs::bb::padding_codec::PaddingCodec;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            // 2x2 i32 chunks encode to 16 bytes, which do not fit in a 16-byte envelope
+            let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .push_bb_codec(PaddingCodec::new(16).unwrap())
+                .into();
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta).unwrap();
+
+            let chunk = ArcArrayD::from_elem(vec![2, 2], 1);
+            assert!(arr.write_chunk(&smallvec![0, 0], chunk).is_err());
+            assert_eq!(arr.stored_chunk_count().unwrap(), 0);
+        }
+
+        #[test]
+        fn chunk_stats() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let ameta: ArrayMetadata = ArrayMetadataBuilder::<i32>::new(&[4, 4])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .chunk_stats()
+                .into();
+            assert!(ameta.records_chunk_stats());
+            let arr: Array<_, i32> = Array::new(&store, Default::default(), ameta).unwrap();
+
+            let data = ArcArrayD::from_shape_vec(vec![2, 4], (-4..4).collect()).unwrap();
+            arr.write_region(&smallvec![0, 0], data).unwrap();
+
+            assert_eq!(
+                arr.chunk_stats(&smallvec![0, 0]).unwrap().unwrap(),
+                ChunkStats { min: -4, max: 1 }
+            );
+            assert_eq!(
+                arr.chunk_stats(&smallvec![0, 1]).unwrap().unwrap(),
+                ChunkStats { min: -2, max: 3 }
+            );
+            assert!(arr.chunk_stats(&smallvec![1, 0]).unwrap().is_none());
+            assert_eq!(arr.all_chunk_stats().unwrap().len(), 2);
+
+            let whole = ArrayRegion::from_offset_shape(&[0, 0], &[4, 4]);
+            assert_eq!(
+                arr.region_stats(&whole).unwrap().unwrap(),
+                ChunkStats { min: -4, max: 3 }
+            );
+            let lower = ArrayRegion::from_offset_shape(&[2, 0], &[2, 4]);
+            assert!(arr.region_stats(&lower).unwrap().unwrap().is_uniform());
+
+            // overwriting with the fill value removes the statistics
+            arr.write_chunk(&smallvec![0, 0], ArcArrayD::from_elem(vec![2, 2], 0))
+                .unwrap();
+            assert!(arr.chunk_stats(&smallvec![0, 0]).unwrap().is_none());
+
+            // concurrent writers do not lose each others' statistics
+            let ameta: ArrayMetadata = ArrayMetadataBuilder::<i32>::new(&[8, 8])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .chunk_stats()
+                .into();
+            let store = FileSystemStore::create(tmp.path().join("concurrent.zarr"), true).unwrap();
+            let arr = crate::prelude::create_root_array::<i32, _>(&store, ameta).unwrap();
+            std::thread::scope(|s| {
+                for i in 0..4 {
+                    let arr = &arr;
+                    s.spawn(move || {
+                        for j in 0..4 {
+                            let chunk = ArcArrayD::from_elem(vec![2, 2], (i * 4 + j + 1) as i32);
+                            arr.write_chunk(&smallvec![i, j], chunk).unwrap();
+                        }
+                    });
+                }
+            });
+            assert_eq!(arr.all_chunk_stats().unwrap().len(), 16);
+        }
+
+        #[cfg(feature = "gzip")]
+        #[test]
+        fn read_values() {
+            use crate::codecs::{ab::bytes_codec::BytesCodec, bb::gzip_codec::GzipCodec};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let coords: Vec<GridCoord> = vec![
+                smallvec![9, 9],
+                smallvec![0, 0],
+                smallvec![5, 7],
+                smallvec![0, 1],
+                smallvec![5, 7],
+                smallvec![3, 4],
+            ];
+            let data = ArcArrayD::from_shape_fn(vec![8, 10], |idx| (idx[0] * 10 + idx[1]) as u16);
+
+            // byte ranges of uncompressed chunks, and whole compressed chunks
+            for (name, gzip) in [("raw", false), ("gzip", true)] {
+                let mut builder = ArrayMetadataBuilder::<u16>::new(&[10, 10])
+                    .chunk_grid(vec![4, 4].as_slice())
+                    .unwrap()
+                    .fill_value(7)
+                    .ab_codec(BytesCodec::new_big())
+                    .unwrap();
+                if gzip {
+                    builder = builder.push_bb_codec(GzipCodec::default());
+                }
+                let arr = g
+                    .create_array::<u16>(name.parse().unwrap(), builder.into(), false)
+                    .unwrap();
+                // last rows, including chunk (2, 2), are left empty
+                arr.write_region(&smallvec![0, 0], data.clone()).unwrap();
+
+                let vals = arr.read_values(&coords).unwrap();
+                assert_eq!(vals, vec![7, 0, 57, 1, 57, 34]);
+                // absent chunks are filled directly in the output
+                let dense = arr
+                    .read_region(ArrayRegion::from_offset_shape(&[6, 6], &[4, 4]))
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(dense[[1, 1]], 77);
+                assert_eq!(dense[[2, 1]], 7);
+                assert_eq!(dense[[3, 3]], 7);
+                assert_eq!(arr.read_values(&[]).unwrap(), Vec::<u16>::new());
+
+                let err = arr.read_values(&[smallvec![10, 0]]).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+                assert!(arr.read_values(&[smallvec![1]]).is_err());
+            }
+        }
+
+        #[test]
+        fn attribute_limits() {
+            use crate::node::attributes::AttributeLimits;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let limits = AttributeLimits::default().max_document_size(64);
+            let g = crate::prelude::create_root_group(&store, Default::default())
+                .unwrap()
+                .with_attribute_limits(limits);
+
+            let big = ArrayMetadataBuilder::<u8>::new(&[4])
+                .set_attribute("blob".into(), "x".repeat(100))
+                .unwrap()
+                .into();
+            let Err(err) = g.create_array::<u8>("big".parse().unwrap(), big, false) else {
+                panic!("attributes should be rejected");
+            };
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(!store.has_key(&"big/zarr.json".parse().unwrap()).unwrap());
+
+            let ameta = ArrayMetadataBuilder::<u8>::new(&[4]).into();
+            let mut arr = g
+                .create_array::<u8>("array".parse().unwrap(), ameta, false)
+                .unwrap();
+            arr.set_attribute("small", 1).unwrap();
+
+            let err = arr
+                .try_mutate_attributes(|a| a.insert("blob".into(), "x".repeat(100).into()))
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(!arr.has_attribute("blob"));
+
+            let arr_again = g
+                .get_array::<u8>("array".parse().unwrap())
+                .unwrap()
+                .unwrap();
+            assert!(arr_again.attribute_limits().is_some());
+            assert!(arr_again.has_attribute("small"));
+            assert!(!arr_again.has_attribute("blob"));
+        }
+
+        #[test]
+        fn read_region_sparse() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u32>::new(&[8, 8])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u32>("array".parse().unwrap(), ameta, false)
+                .unwrap();
+
+            // a label spanning the boundary between chunks (0, 0) and (0, 1)
+            let label = ArcArrayD::from_elem(vec![1, 4].as_slice(), 3u32);
+            arr.write_region(&smallvec![1, 2], label).unwrap();
+            arr.write_region(
+                &smallvec![2, 5],
+                ArcArrayD::from_elem(vec![1, 1].as_slice(), 4u32),
+            )
+            .unwrap();
+
+            let region = ArrayRegion::from_offset_shape(&[1, 1], &[7, 7]);
+            let runs = arr.read_region_sparse(region.clone()).unwrap().unwrap();
+            assert_eq!(
+                runs,
+                vec![
+                    SparseRun {
+                        start: smallvec![1, 2],
+                        len: 2,
+                        value: 3
+                    },
+                    SparseRun {
+                        start: smallvec![1, 4],
+                        len: 2,
+                        value: 3
+                    },
+                    SparseRun {
+                        start: smallvec![2, 5],
+                        len: 1,
+                        value: 4
+                    },
+                ]
+            );
+
+            let dense = arr.read_region(region).unwrap().unwrap();
+            let nonfill = dense.iter().filter(|v| **v != 0).count();
+            assert_eq!(runs.iter().map(|r| r.len as usize).sum::<usize>(), nonfill);
+            for (coord, value) in runs.iter().flat_map(|r| r.voxels()) {
+                assert_eq!(dense[[coord[0] as usize - 1, coord[1] as usize - 1]], value);
+            }
+
+            let empty = ArrayRegion::from_offset_shape(&[4, 0], &[4, 8]);
+            assert!(arr.read_region_sparse(empty).unwrap().unwrap().is_empty());
+            let outside = ArrayRegion::from_offset_shape(&[9, 9], &[1, 1]);
+            assert!(arr.read_region_sparse(outside).unwrap().is_none());
+            // fill values are compared bitwise, so -0.0 is not the fill value 0.0
+            let farr = g
+                .create_array::<f32>(
+                    "float".parse().unwrap(),
+                    ArrayMetadataBuilder::<f32>::new(&[4, 4])
+                        .chunk_grid(vec![2, 2].as_slice())
+                        .unwrap()
+                        .into(),
+                    false,
+                )
+                .unwrap();
+            farr.write_chunk(&smallvec![0, 0], ArcArrayD::from_elem(vec![2, 2], -0.0))
+                .unwrap();
+            assert_eq!(farr.stored_chunk_count().unwrap(), 1);
+            let runs = farr
+                .read_region_sparse(ArrayRegion::from_offset_shape(&[0, 0], &[4, 4]))
+                .unwrap()
+                .unwrap();
+            assert_eq!(runs.len(), 2);
+            assert!(runs
+                .iter()
+                .all(|r| r.len == 2 && r.value.is_sign_negative()));
+        }
+
+        #[cfg(feature = "gzip")]
+        #[test]
+        fn content_digest() {
+            use crate::codecs::{ab::bytes_codec::BytesCodec, bb::gzip_codec::GzipCodec};
+            use crate::node::digest::DigestAlgorithm;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            // overhanging chunks, and a chunk which is never written
+            let data = ArcArrayD::from_shape_fn(vec![6, 5], |idx| (idx[0] * 5 + idx[1]) as i32);
+            let mut digests = Vec::default();
+            for (name, gzip) in [("raw", false), ("gzip", true)] {
+                let mut builder = ArrayMetadataBuilder::<i32>::new(&[10, 5])
+                    .chunk_grid(vec![4, 4].as_slice())
+                    .unwrap()
+                    .ab_codec(BytesCodec::new_big())
+                    .unwrap();
+                if gzip {
+                    builder = builder.push_bb_codec(GzipCodec::default());
+                }
+                let arr = g
+                    .create_array::<i32>(name.parse().unwrap(), builder.into(), false)
+                    .unwrap();
+                arr.write_region(&smallvec![0, 0], data.clone()).unwrap();
+                digests.push(arr.content_digest(DigestAlgorithm::XxHash64, true).unwrap());
+            }
+            assert_eq!(digests[0].decoded, digests[1].decoded);
+            assert_ne!(digests[0].encoded.unwrap(), digests[1].encoded.unwrap());
+
+            // the decoded digest does not depend on the chunk grid
+            let regridded = g
+                .create_array::<i32>(
+                    "regridded".parse().unwrap(),
+                    ArrayMetadataBuilder::<i32>::new(&[10, 5])
+                        .chunk_grid(vec![3, 2].as_slice())
+                        .unwrap()
+                        .into(),
+                    false,
+                )
+                .unwrap();
+            regridded
+                .write_region(&smallvec![0, 0], data.clone())
+                .unwrap();
+            let regridded_digest = regridded
+                .content_digest(DigestAlgorithm::XxHash64, true)
+                .unwrap();
+            assert_eq!(regridded_digest.decoded, digests[0].decoded);
+            assert_ne!(regridded_digest.encoded, digests[0].encoded);
+
+            // rows spanning several segments, split differently by each grid
+            let wide =
+                ArcArrayD::from_shape_fn(vec![3, 2500], |idx| (idx[0] * 2500 + idx[1]) as i32);
+            let wide_digests: Vec<_> = [vec![2, 700], vec![3, 2500], vec![1, 1024]]
+                .into_iter()
+                .enumerate()
+                .map(|(i, chunk_shape)| {
+                    let arr = g
+                        .create_array::<i32>(
+                            format!("wide{i}").parse().unwrap(),
+                            ArrayMetadataBuilder::<i32>::new(&[3, 2500])
+                                .chunk_grid(chunk_shape.as_slice())
+                                .unwrap()
+                                .into(),
+                            false,
+                        )
+                        .unwrap();
+                    arr.write_region(&smallvec![0, 0], wide.clone()).unwrap();
+                    arr.content_digest(DigestAlgorithm::XxHash64, false)
+                        .unwrap()
+                        .decoded
+                })
+                .collect();
+            assert_eq!(wide_digests[0], wide_digests[1]);
+            assert_eq!(wide_digests[0], wide_digests[2]);
+
+            let arr = g.get_array::<i32>("raw".parse().unwrap()).unwrap().unwrap();
+            assert_eq!(
+                arr.content_digest(DigestAlgorithm::XxHash64, true).unwrap(),
+                digests[0]
+            );
+            let crc = arr.content_digest(DigestAlgorithm::Crc32c, false).unwrap();
+            assert!(crc.encoded.is_none());
+            assert!(crc.decoded <= u32::MAX as u64);
+
+            // values beyond the written data are the fill value either way
+            arr.write_region(&smallvec![8, 0], ArcArrayD::from_elem(vec![1, 1], 0))
+                .unwrap();
+            assert_eq!(
+                arr.content_digest(DigestAlgorithm::XxHash64, false)
+                    .unwrap()
+                    .decoded,
+                digests[0].decoded
+            );
+            arr.write_region(&smallvec![9, 4], ArcArrayD::from_elem(vec![1, 1], 1))
+                .unwrap();
+            assert_ne!(
+                arr.content_digest(DigestAlgorithm::XxHash64, false)
+                    .unwrap()
+                    .decoded,
+                digests[0].decoded
+            );
+        }
+
+        #[test]
+        fn extension_policy() {
+            use crate::store::WriteableStore;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u8>::new(&[4]).into();
+            let arr = g
+                .create_array::<u8>("array".parse().unwrap(), ameta, false)
+                .unwrap();
+
+            // add extensions which this crate does not implement
+            let mut value: serde_json::Value =
+                serde_json::from_reader(store.get(arr.meta_key()).unwrap().unwrap()).unwrap();
+            value["inhouse_tiling"] = serde_json::json!({"must_understand": true, "size": 2});
+            value["inhouse_note"] = serde_json::json!({"must_understand": false});
+            store
+                .set(arr.meta_key(), |w| {
+                    serde_json::to_writer(w, &value).map_err(io::Error::from)
+                })
+                .unwrap();
+
+            let key = arr.key().clone();
+            let Err(err) = Array::<_, u8>::from_store(&store, key.clone()) else {
+                panic!("unknown extension should be rejected");
+            };
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains("inhouse_tiling"));
+
+            let g = g.with_extension_policy(ExtensionPolicy::default().allow("inhouse_tiling"));
+            let mut arr = g
+                .get_array::<u8>("array".parse().unwrap())
+                .unwrap()
+                .unwrap();
+            arr.set_attribute("a", 1).unwrap();
+            let written: serde_json::Value =
+                serde_json::from_reader(store.get(arr.meta_key()).unwrap().unwrap()).unwrap();
+            assert_eq!(written["inhouse_tiling"]["size"], 2);
+
+            let policy = ExtensionPolicy::default()
+                .allow("inhouse_tiling")
+                .deny("inhouse_note");
+            let Err(err) = Array::<_, u8>::from_store_with_extensions(&store, key, &policy) else {
+                panic!("denied extension should be rejected");
+            };
+            let err = err
+                .into_inner()
+                .unwrap()
+                .downcast::<ExtensionError>()
+                .unwrap();
+            assert_eq!(*err, ExtensionError::Denied("inhouse_note".into()));
+        }
+
+        #[test]
+        fn write_dedup() {
+            use std::time::{Duration, SystemTime};
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path.clone(), true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u16>::new(&[4, 4])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u16>("array".parse().unwrap(), ameta, false)
+                .unwrap()
+                .with_write_dedup(true);
+            let idx: GridCoord = smallvec![0, 1];
+            let data = ArcArrayD::from_shape_vec(vec![2, 2], vec![1, 2, 3, 4]).unwrap();
+            arr.write_chunk(&idx, data.clone()).unwrap();
+
+            // a rewrite would update the modification time
+            let chunk_path = path.join("array/c/0/1");
+            let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+            let mtime = || std::fs::metadata(&chunk_path).unwrap().modified().unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&chunk_path)
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+
+            // compared by reading, then by cached checksum
+            arr.write_chunk(&idx, data.clone()).unwrap();
+            assert_eq!(mtime(), old);
+            arr.write_chunk(&idx, data.clone()).unwrap();
+            assert_eq!(mtime(), old);
+
+            let changed = data.mapv(|v| v * 2).into_shared();
+            arr.write_chunk(&idx, changed.clone()).unwrap();
+            assert_ne!(mtime(), old);
+            assert_eq!(arr.read_chunk(&idx).unwrap().unwrap(), changed);
+        }
+
+        #[test]
+        fn write_region_progress() {
+            use std::ops::ControlFlow;
+
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let meta: ArrayMetadata = ArrayMetadataBuilder::<u16>::new(&[8, 8])
+                .chunk_grid(vec![4, 4].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u16>("array".parse().unwrap(), meta.clone(), false)
+                .unwrap();
+            let data = ArcArrayD::from_elem(vec![6, 8], 1u16);
+
+            let mut seen = Vec::default();
+            let done = arr
+                .write_region_with_progress(&smallvec![2, 0], data.clone(), |p| {
+                    seen.push(*p);
+                    ControlFlow::Continue(())
+                })
+                .unwrap();
+            assert!(done.is_complete());
+            assert_eq!(seen.len(), 4);
+            assert!(seen.iter().all(|p| p.chunks_total == 4));
+            assert_eq!(done.bytes_written, 6 * 8 * 2);
+
+            // cancel after the second chunk
+            let arr = g
+                .create_array::<u16>("cancelled".parse().unwrap(), meta, false)
+                .unwrap();
+            let cancelled = arr
+                .write_region_with_progress(&smallvec![2, 0], data, |p| {
+                    if p.chunks_done == 2 {
+                        ControlFlow::Break(())
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                })
+                .unwrap();
+            assert!(!cancelled.is_complete());
+            assert_eq!(cancelled.chunks_done, 2);
+            assert_eq!(arr.stored_chunk_count().unwrap(), 2);
+        }
+
+        #[test]
+        fn read_region_permuted() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u32>::new(&[4, 6, 5])
+                .chunk_grid(vec![2, 4, 3].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u32>("zyx".parse().unwrap(), ameta, false)
+                .unwrap();
+            let data = ArcArrayD::from_shape_fn(vec![4, 5, 5], |idx| {
+                (idx[0] * 100 + idx[1] * 10 + idx[2]) as u32
+            });
+            arr.write_region(&smallvec![0, 0, 0], data).unwrap();
+
+            let region = ArrayRegion::from_offset_shape(&[1, 2, 1], &[3, 4, 4]);
+            let dense = arr.read_region(region.clone()).unwrap().unwrap();
+            for order in [[2, 1, 0], [1, 2, 0], [0, 1, 2]] {
+                let permuted = arr
+                    .read_region_permuted(region.clone(), &order)
+                    .unwrap()
+                    .unwrap();
+                assert!(permuted.is_standard_layout());
+                assert_eq!(permuted, dense.clone().permuted_axes(order.as_slice()));
+            }
+            // xyz of the last voxel, including the unwritten (fill) row
+            let xyz = arr
+                .read_region_permuted(region.clone(), &[2, 1, 0])
+                .unwrap()
+                .unwrap();
+            assert_eq!(xyz.shape(), &[4, 4, 3]);
+            assert_eq!(xyz[[3, 2, 2]], 344);
+            assert_eq!(xyz[[3, 3, 2]], 0);
+
+            let err = arr
+                .read_region_permuted(region.clone(), &[0, 0, 1])
+                .unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(arr.read_region_permuted(region, &[1, 0]).is_err());
+        }
+
+        #[test]
+        fn buffer_pool() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u32>::new(&[12, 12])
+                .chunk_grid(vec![3, 4].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u32>("array".parse().unwrap(), ameta, false)
+                .unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![12, 12], (0..144).collect()).unwrap();
+            arr.write_region(&smallvec![0, 0], data.clone()).unwrap();
+
+            let whole = ArrayRegion::from_offset_shape(&[0, 0], &[12, 12]);
+            assert_eq!(arr.read_region(whole.clone()).unwrap().unwrap(), data);
+            let stats = arr.buffer_pool_stats();
+            assert_eq!(stats.acquired, 12);
+            assert_eq!(stats.allocated, 1);
+            assert_eq!(stats.reused, 11);
+            assert_eq!(stats.pooled, 1);
+            assert!(stats.pooled_bytes >= 3 * 4 * 4);
+
+            let unpooled = arr.with_buffer_pool(0);
+            assert_eq!(unpooled.read_region(whole).unwrap().unwrap(), data);
+            let stats = unpooled.buffer_pool_stats();
+            assert_eq!(stats.allocated, 12);
+            assert_eq!(stats.discarded, 12);
+        }
+
+        #[test]
+        fn prefetch() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path.clone(), true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u8>::new(&[2, 20])
+                .chunk_grid(vec![2, 2].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u8>("array".parse().unwrap(), ameta, false)
+                .unwrap();
+            let data = ArcArrayD::from_shape_vec(vec![2, 20], (0..40).collect()).unwrap();
+            arr.write_region(&smallvec![0, 0], data).unwrap();
+
+            let cache =
+                crate::store::CachingStore::new(FileSystemStore::open(path).unwrap(), 1 << 20);
+            let cached = arr.with_store(&cache).with_prefetch(2);
+            assert_eq!(cached.prefetch_distance(), Some(2));
+            for col in 0..3 {
+                cached.read_chunk(&smallvec![0, col]).unwrap().unwrap();
+            }
+            let start = std::time::Instant::now();
+            while cache.stats().prefetched < 2 {
+                assert!(start.elapsed() < std::time::Duration::from_secs(10));
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            let chunk = cached.read_chunk(&smallvec![0, 3]).unwrap().unwrap();
+            assert_eq!(chunk[[1, 1]], 27);
+            let stats = cache.stats();
+            assert_eq!(stats.misses, 3);
+            assert_eq!(stats.hits, 1);
+
+            // the scan stops at the edge of the grid
+            for col in 4..10 {
+                let start = std::time::Instant::now();
+                while cache.stats().prefetched < (col - 1).min(7) {
+                    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                }
+                cached.read_chunk(&smallvec![0, col]).unwrap().unwrap();
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            let stats = cache.stats();
+            assert_eq!(stats.prefetched, 7);
+            assert_eq!(stats.misses, 3);
+            assert_eq!(arr.prefetch_distance(), None);
+        }
+
+        #[test]
+        fn chunk_exchange() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let src_store = FileSystemStore::create(tmp.path().join("src.zarr"), true).unwrap();
+            let dst_store = FileSystemStore::create(tmp.path().join("dst.zarr"), true).unwrap();
+            let src_group =
+                crate::prelude::create_root_group(&src_store, Default::default()).unwrap();
+            let dst_group =
+                crate::prelude::create_root_group(&dst_store, Default::default()).unwrap();
+
+            let builder = || {
+                ArrayMetadataBuilder::<u16>::new(&[6, 6])
+                    .chunk_grid(vec![3, 3].as_slice())
+                    .unwrap()
+            };
+            let src = src_group
+                .create_array::<u16>("array".parse().unwrap(), builder().into(), false)
+                .unwrap();
+            let mut data = ArcArrayD::<u16>::zeros(vec![6, 6]);
+            data.slice_mut(ndarray::s![..3, ..]).fill(7);
+            data[[4, 4]] = 9;
+            src.write_region(&smallvec![0, 0], data.clone()).unwrap();
+
+            // chunk [1, 0] is not stored
+            let idxs: Vec<GridCoord> = vec![smallvec![0, 1], smallvec![1, 0], smallvec![1, 1]];
+            let mut file = Vec::default();
+            let exported = src.export_chunks(&idxs, &mut file).unwrap();
+            assert_eq!(exported, vec![idxs[0].clone(), idxs[2].clone()]);
+            assert!(src
+                .export_chunks(&[smallvec![2, 0]], &mut Vec::default())
+                .is_err());
+
+            // the destination may be larger
+            let dst_meta: ArrayMetadata = builder().into();
+            let mut dst_meta = serde_json::to_value(dst_meta).unwrap();
+            dst_meta["shape"] = serde_json::json!([9, 6]);
+            let dst = dst_group
+                .create_array::<u16>(
+                    "array".parse().unwrap(),
+                    serde_json::from_value(dst_meta).unwrap(),
+                    false,
+                )
+                .unwrap();
+            let imported = dst.import_chunks(&mut file.as_slice()).unwrap();
+            assert_eq!(imported, exported);
+            for idx in exported.iter() {
+                assert_eq!(
+                    dst.read_chunk(idx).unwrap().unwrap(),
+                    src.read_chunk(idx).unwrap().unwrap()
+                );
+            }
+            assert_eq!(
+                dst.read_chunk(&smallvec![0, 0]).unwrap().unwrap()[[0, 0]],
+                0
+            );
+
+            // statistics are recomputed for imported chunks
+            let with_stats = dst_group
+                .create_array::<u16>(
+                    "with_stats".parse().unwrap(),
+                    builder().chunk_stats().into(),
+                    false,
+                )
+                .unwrap();
+            with_stats.import_chunks(&mut file.as_slice()).unwrap();
+            assert_eq!(
+                with_stats.chunk_stats(&smallvec![0, 1]).unwrap(),
+                Some(ChunkStats { min: 7, max: 7 })
+            );
+            let stats = with_stats
+                .region_stats(&ArrayRegion::from_offset_shape(&[3, 3], &[3, 3]))
+                .unwrap();
+            assert_eq!(stats, Some(ChunkStats { min: 0, max: 9 }));
+
+            // incompatible encoding
+            let other = dst_group
+                .create_array::<u16>(
+                    "other".parse().unwrap(),
+                    ArrayMetadataBuilder::<u16>::new(&[6, 6])
+                        .chunk_grid(vec![2, 3].as_slice())
+                        .unwrap()
+                        .into(),
+                    false,
+                )
+                .unwrap();
+            let err = other.import_chunks(&mut file.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            // corrupted
+            let last = file.len() - 1;
+            file[last] ^= 0xff;
+            let err = dst.import_chunks(&mut file.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            // truncated, with an implausible header length
+            let mut truncated = CHUNK_RECORD_MAGIC.to_vec();
+            truncated.extend_from_slice(&u32::MAX.to_le_bytes());
+            truncated.extend_from_slice(b"{}");
+            let err = dst.import_chunks(&mut truncated.as_slice()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+
+        #[test]
+        fn chunk_uris() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("my root.zarr");
+            let store = FileSystemStore::create(path.clone(), true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<u8>::new(&[4, 5])
+                .chunk_grid(vec![2, 3].as_slice())
+                .unwrap()
+                .into();
+            let arr = g
+                .create_array::<u8>("array".parse().unwrap(), ameta, false)
+                .unwrap();
+            let data = ArcArrayD::from_elem(vec![2, 3], 1);
+            arr.write_chunk(&smallvec![1, 0], data.clone()).unwrap();
+            arr.write_chunk(&smallvec![0, 1], data).unwrap();
+
+            let uris = arr.chunk_uris().unwrap();
+            let indices: Vec<_> = uris.iter().map(|(idx, _)| idx.to_vec()).collect();
+            assert_eq!(
+                indices,
+                vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]]
+            );
+            let base = path.canonicalize().unwrap();
+            let expected = format!(
+                "file://{}/array/c/0/1",
+                base.to_str().unwrap().replace(' ', "%20")
+            );
+            assert_eq!(uris[1].1, expected);
+
+            let stored = arr.stored_chunk_uris().unwrap();
+            assert_eq!(stored.len(), 2);
+            assert_eq!(stored[0], uris[1]);
+            assert_eq!(stored[1], uris[2]);
+            for (_, uri) in stored {
+                let p = uri.strip_prefix("file://").unwrap().replace("%20", " ");
+                assert!(std::path::Path::new(&p).is_file());
+            }
+
+            let mem = crate::store::HashMapStore::default();
+            let mem_arr = arr.with_store(&mem);
+            assert_eq!(
+                mem_arr.chunk_uris().unwrap_err().kind(),
+                io::ErrorKind::Unsupported
+            );
+        }
+
+        #[test]
+        fn lenient_fill_values() {
+            let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+            let path = tmp.path().join("root.zarr");
+            let store = FileSystemStore::create(path, true).unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+
+            let ameta = ArrayMetadataBuilder::<bool>::new(&[4])
+                .fill_value(true)
+                .into();
+            let arr = g
+                .create_array::<bool>("mask".parse().unwrap(), ameta, false)
+                .unwrap();
+            // as written by some other implementations
+            let mut meta = serde_json::to_value(arr.metadata()).unwrap();
+            meta["fill_value"] = 1.into();
+            store
+                .set(&"mask/zarr.json".parse().unwrap(), |w| {
+                    serde_json::to_writer(w, &meta).map_err(io::Error::from)
+                })
+                .unwrap();
+
+            let err = g.get_array::<bool>("mask".parse().unwrap()).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            let g = g.with_lenient_fill_values(true);
+            let arr = g
+                .get_array::<bool>("mask".parse().unwrap())
+                .unwrap()
+                .unwrap();
+            assert!(arr.metadata().get_effective_fill_value::<bool>().unwrap());
+            let out = arr
+                .read_region(ArrayRegion::from_offset_shape(&[0], &[4]))
+                .unwrap()
+                .unwrap();
+            assert!(out.iter().all(|v| *v));
+        }
+
         #[test]
         fn partial_read() {
             let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
             let path = tmp.path().join("root.zarr");
             let store = FileSystemStore::create(path, true).unwrap();
 
-            let g = Group::new(&store, Default::default(), Default::default());
-            g.write_meta().unwrap();
+            let g = crate::prelude::create_root_group(&store, Default::default()).unwrap();
 
             let ameta = ArrayMetadataBuilder::<i32>::new(&[4, 4])
                 .chunk_grid(vec![2, 2].as_slice())
@@ -294,7 +1934,7 @@ mod tests {
                 .into();
 
             let arr = g
-                .create_array::<i32>("array".parse().unwrap(), ameta)
+                .create_array::<i32>("array".parse().unwrap(), ameta, false)
                 .unwrap();
 
             let middle = ArcArrayD::from_elem(vec![2, 2].as_slice(), 1i32);
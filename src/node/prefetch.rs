@@ -0,0 +1,107 @@
+//! Detection of predictable chunk access patterns, for prefetching.
+use std::collections::VecDeque;
+
+use crate::GridCoord;
+
+/// How many recently-predicted chunks are remembered, so that they are not hinted again.
+const HINTED_MEMORY: usize = 64;
+
+/// Detects scans which step through chunks with a constant stride
+/// (e.g. `[0, 0]`, `[0, 1]`, `[0, 2]`), and predicts the next chunks of the scan.
+#[derive(Debug)]
+pub(crate) struct StrideDetector {
+    distance: usize,
+    previous: Option<GridCoord>,
+    stride: Option<Vec<i64>>,
+    hinted: VecDeque<GridCoord>,
+}
+
+impl StrideDetector {
+    /// Predict up to `distance` chunks ahead.
+    pub(crate) fn new(distance: usize) -> Self {
+        Self {
+            distance,
+            previous: None,
+            stride: None,
+            hinted: VecDeque::with_capacity(HINTED_MEMORY),
+        }
+    }
+
+    pub(crate) fn distance(&self) -> usize {
+        self.distance
+    }
+
+    /// Record an access, returning chunks which should be prefetched
+    /// (and have not been predicted recently).
+    ///
+    /// A scan is detected once two consecutive steps have the same (non-zero) stride.
+    /// Predictions are not limited to the array's grid.
+    pub(crate) fn record(&mut self, idx: &GridCoord) -> Vec<GridCoord> {
+        let stride: Option<Vec<i64>> = self.previous.as_ref().and_then(|prev| {
+            (prev.len() == idx.len() && prev != idx).then(|| {
+                idx.iter()
+                    .zip(prev.iter())
+                    .map(|(i, p)| *i as i64 - *p as i64)
+                    .collect()
+            })
+        });
+        let detected = stride.is_some() && stride == self.stride;
+        self.previous = Some(idx.clone());
+        self.stride = stride;
+        if !detected {
+            return Vec::default();
+        }
+        let stride = self.stride.as_ref().unwrap();
+
+        let mut out = Vec::default();
+        let mut next: Vec<i64> = idx.iter().map(|i| *i as i64).collect();
+        for _ in 0..self.distance {
+            for (n, s) in next.iter_mut().zip(stride.iter()) {
+                *n += s;
+            }
+            if next.iter().any(|n| *n < 0) {
+                break;
+            }
+            let predicted: GridCoord = next.iter().map(|n| *n as u64).collect();
+            if self.hinted.contains(&predicted) {
+                continue;
+            }
+            if self.hinted.len() == HINTED_MEMORY {
+                self.hinted.pop_front();
+            }
+            self.hinted.push_back(predicted.clone());
+            out.push(predicted);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smallvec::smallvec;
+
+    use super::*;
+
+    fn coords(cs: &[[u64; 2]]) -> Vec<GridCoord> {
+        cs.iter().map(|c| c.iter().copied().collect()).collect()
+    }
+
+    #[test]
+    fn detects_strides() {
+        let mut d = StrideDetector::new(2);
+        assert!(d.record(&smallvec![0, 0]).is_empty());
+        assert!(d.record(&smallvec![0, 1]).is_empty());
+        assert_eq!(d.record(&smallvec![0, 2]), coords(&[[0, 3], [0, 4]]));
+        // already hinted
+        assert_eq!(d.record(&smallvec![0, 3]), coords(&[[0, 5]]));
+
+        // a jump breaks the pattern, until it is re-established
+        assert!(d.record(&smallvec![5, 0]).is_empty());
+        assert!(d.record(&smallvec![4, 0]).is_empty());
+        assert_eq!(d.record(&smallvec![3, 0]), coords(&[[2, 0], [1, 0]]));
+        assert_eq!(d.record(&smallvec![2, 0]), coords(&[[0, 0]]));
+        // repeated reads of the same chunk are not a scan
+        assert!(d.record(&smallvec![2, 0]).is_empty());
+        assert!(d.record(&smallvec![2, 0]).is_empty());
+    }
+}
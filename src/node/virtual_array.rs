@@ -0,0 +1,128 @@
+use std::io;
+
+use crate::{
+    chunk_grid::ArrayRegion, data_type::ReflectedType, store::ReadableStore, to_usize, ArcArrayD,
+    DimensionMismatch, GridCoord, Ndim,
+};
+
+use super::Array;
+
+/// A read-only view of several arrays concatenated along one axis,
+/// e.g. one array per timepoint.
+///
+/// Constituents must have the same data type and chunk grid,
+/// and the same shape on every axis except the concatenation axis.
+pub struct VirtualArray<'s, S: ReadableStore + ?Sized, T: ReflectedType> {
+    arrays: Vec<Array<'s, S, T>>,
+    axis: usize,
+    /// Offset of each constituent along the concatenation axis, plus the total length.
+    offsets: Vec<u64>,
+    shape: GridCoord,
+}
+
+impl<'s, S: ReadableStore + ?Sized, T: ReflectedType> Ndim for VirtualArray<'s, S, T> {
+    fn ndim(&self) -> usize {
+        self.shape.len()
+    }
+}
+
+impl<'s, S: ReadableStore + ?Sized, T: ReflectedType> VirtualArray<'s, S, T> {
+    pub fn new(arrays: Vec<Array<'s, S, T>>, axis: usize) -> Result<Self, &'static str> {
+        let first = arrays.first().ok_or("No arrays to concatenate")?;
+        if axis >= first.ndim() {
+            return Err("Concatenation axis out of range");
+        }
+        let mut shape: GridCoord = first.shape().iter().cloned().collect();
+        let mut offsets = Vec::with_capacity(arrays.len() + 1);
+        offsets.push(0);
+        shape[axis] = 0;
+
+        for arr in arrays.iter() {
+            if arr.metadata().chunk_grid() != first.metadata().chunk_grid() {
+                return Err("Arrays have different chunk grids");
+            }
+            if arr.ndim() != shape.len()
+                || arr
+                    .shape()
+                    .iter()
+                    .zip(first.shape().iter())
+                    .enumerate()
+                    .any(|(idx, (a, b))| idx != axis && a != b)
+            {
+                return Err("Arrays have incompatible shapes");
+            }
+            shape[axis] += arr.shape()[axis];
+            offsets.push(shape[axis]);
+        }
+
+        Ok(Self {
+            arrays,
+            axis,
+            offsets,
+            shape,
+        })
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    pub fn axis(&self) -> usize {
+        self.axis
+    }
+
+    pub fn arrays(&self) -> &[Array<'s, S, T>] {
+        &self.arrays
+    }
+
+    /// Read a region spanning any number of the constituent arrays.
+    ///
+    /// `Ok(None)` if the region is out of bounds.
+    pub fn read_region(&self, region: ArrayRegion) -> io::Result<Option<ArcArrayD<T>>> {
+        let Some(reg) = region.limit_extent(&self.shape)? else {
+            return Ok(None);
+        };
+        let mut out =
+            ArcArrayD::from_elem(to_usize(reg.shape().as_slice()).as_slice(), T::default());
+        let start = reg.offset()[self.axis];
+        let end = reg.end()[self.axis];
+
+        for (arr, bounds) in self.arrays.iter().zip(self.offsets.windows(2)) {
+            let (arr_start, arr_end) = (bounds[0], bounds[1]);
+            if arr_end <= start || arr_start >= end {
+                continue;
+            }
+            let lo = start.max(arr_start);
+            let hi = end.min(arr_end);
+
+            let mut sub_offset = reg.offset();
+            sub_offset[self.axis] = lo - arr_start;
+            let mut sub_shape = reg.shape();
+            sub_shape[self.axis] = hi - lo;
+            let sub_region = ArrayRegion::from_offset_shape_unchecked(&sub_offset, &sub_shape);
+
+            let Some(sub_arr) = arr.read_region(sub_region)? else {
+                continue;
+            };
+
+            let mut out_offset: GridCoord = sub_shape.iter().map(|_| 0).collect();
+            out_offset[self.axis] = lo - start;
+            let out_region = ArrayRegion::from_offset_shape_unchecked(&out_offset, &sub_shape);
+            sub_arr.assign_to(out.slice_mut(out_region.slice_info()));
+        }
+        Ok(Some(out))
+    }
+
+    /// Which constituent array contains the given voxel, and its index within that array.
+    pub fn locate(&self, idx: &[u64]) -> Result<Option<(usize, GridCoord)>, DimensionMismatch> {
+        DimensionMismatch::check_coords(idx.len(), self.ndim())?;
+        if idx.iter().zip(self.shape.iter()).any(|(i, s)| i >= s) {
+            return Ok(None);
+        }
+        let along = idx[self.axis];
+        let arr_idx = self.offsets.partition_point(|o| *o <= along) - 1;
+        let mut inner: GridCoord = idx.iter().cloned().collect();
+        inner[self.axis] -= self.offsets[arr_idx];
+        Ok(Some((arr_idx, inner)))
+    }
+}
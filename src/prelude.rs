@@ -1,4 +1,4 @@
-use std::io::{self, ErrorKind};
+use std::io;
 
 pub use crate::chunk_grid::ArrayRegion;
 pub use crate::data_type::ReflectedType;
@@ -6,44 +6,28 @@ pub use crate::node::{
     Array, ArrayMetadata, ArrayMetadataBuilder, Group, GroupMetadata, ReadableMetadata,
     WriteableMetadata,
 };
-use crate::store::NodeKey;
 pub use crate::store::{ListableStore, ReadableStore, WriteableStore};
+use crate::{node::create_meta, store::NodeKey};
 
 pub use ndarray;
 pub use serde::{Deserialize, Serialize};
 pub use serde_json;
 pub use smallvec;
 
-pub fn create_root_group<S: WriteableStore>(
+pub fn create_root_group<S: WriteableStore + ?Sized>(
     store: &S,
     metadata: GroupMetadata,
-) -> io::Result<Group<S>> {
-    let mut key = NodeKey::default();
-    key.with_metadata();
-    if store.has_key(&key)? {
-        return Err(io::Error::new(
-            ErrorKind::AlreadyExists,
-            "Node exists at root",
-        ));
-    }
-    let g = Group::new(store, Default::default(), metadata);
-    g.write_meta()?;
-    Ok(g)
+) -> io::Result<Group<'_, S>> {
+    create_meta(store, &NodeKey::default(), &metadata, false)?;
+    Ok(Group::new(store, Default::default(), metadata))
 }
 
-pub fn create_root_array<T: ReflectedType, S: WriteableStore>(
+pub fn create_root_array<T: ReflectedType, S: WriteableStore + ?Sized>(
     store: &S,
     metadata: ArrayMetadata,
-) -> io::Result<Array<S, T>> {
-    let mut key = NodeKey::default();
-    key.with_metadata();
-    if store.has_key(&key)? {
-        return Err(io::Error::new(
-            ErrorKind::AlreadyExists,
-            "Node exists at root",
-        ));
-    }
+) -> io::Result<Array<'_, S, T>> {
+    metadata.validate_sharding()?;
     let a = Array::new(store, Default::default(), metadata).unwrap();
-    a.write_meta()?;
+    create_meta(store, a.key(), a.metadata(), false)?;
     Ok(a)
 }
@@ -0,0 +1,285 @@
+//! Declarative descriptions of whole hierarchies, which can be applied to a store in one call.
+//!
+//! A [NodeSchema] is the node's metadata document (as it would be stored, including `node_type`)
+//! plus an optional `members` object describing its children by name.
+//!
+//! ```json
+//! {
+//!     "zarr_format": 3,
+//!     "node_type": "group",
+//!     "attributes": {"description": "raw data"},
+//!     "members": {
+//!         "volume": {"zarr_format": 3, "node_type": "array", "shape": [100, 100], ...}
+//!     }
+//! }
+//! ```
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind, Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    metadata::parse_meta,
+    node::{Metadata, ReadableMetadata},
+    store::{naming, InvalidNodeName, NodeKey, NodeName, ReadableStore, WriteableStore},
+};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NodeSchema {
+    #[serde(flatten)]
+    pub metadata: Metadata,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub members: BTreeMap<String, NodeSchema>,
+}
+
+/// Node keys are given in their encoded form.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SchemaError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid member name: {0}")]
+    InvalidName(#[from] InvalidNodeName),
+    #[error("Array at \"{0}\" cannot have members")]
+    ArrayHasMembers(String),
+    #[error("Invalid array metadata at \"{0}\": {1}")]
+    InvalidMetadata(String, &'static str),
+    #[error("Existing node at \"{0}\" is a different node type")]
+    NodeTypeMismatch(String),
+    #[error("Existing node at \"{0}\" has different metadata")]
+    MetadataMismatch(String),
+}
+
+/// Keys of the nodes touched by [NodeSchema::apply].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApplyReport {
+    pub created: Vec<NodeKey>,
+    pub verified: Vec<NodeKey>,
+}
+
+/// Read the metadata document for a node, if it exists.
+///
+/// Tolerates documents without `node_type`, as written by earlier versions of this crate.
+fn read_metadata<S: ReadableStore + ?Sized>(
+    store: &S,
+    key: &NodeKey,
+) -> io::Result<Option<Metadata>> {
+    let mut meta_key = key.clone();
+    meta_key.with_metadata();
+    let Some(mut r) = store.get(&meta_key)? else {
+        return Ok(None);
+    };
+    let mut buf = Vec::default();
+    r.read_to_end(&mut buf)?;
+    parse_meta(&buf)
+        .map(Some)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Whether the existing metadata satisfies the expected metadata:
+/// everything except the attributes must be equal,
+/// and all expected attributes must be present with the same value.
+fn metadata_matches(expected: &Metadata, existing: &Metadata) -> bool {
+    let strip = |m: &Metadata| {
+        let mut v = serde_json::to_value(m).expect("could not serialise metadata");
+        if let Some(obj) = v.as_object_mut() {
+            obj.remove("attributes");
+        }
+        v
+    };
+    if strip(expected) != strip(existing) {
+        return false;
+    }
+    let existing_attrs = existing.get_attributes();
+    expected
+        .get_attributes()
+        .iter()
+        .all(|(k, v)| existing_attrs.get(k) == Some(v))
+}
+
+impl NodeSchema {
+    /// Check that names are valid (including against the [global naming policy](crate::store::naming::set_global_naming_policy)),
+    /// arrays have no members, and array metadata is valid.
+    pub fn validate(&self, key: &NodeKey) -> Result<(), SchemaError> {
+        if let Metadata::Array(m) = &self.metadata {
+            if !self.members.is_empty() {
+                return Err(SchemaError::ArrayHasMembers(key.encode()));
+            }
+            m.validate()
+                .map_err(|e| SchemaError::InvalidMetadata(key.encode(), e))?;
+        }
+        for (name, member) in self.members.iter() {
+            let mut child = key.clone();
+            let name = name.parse::<NodeName>()?;
+            naming::check_global(&name)?;
+            child.push(name);
+            member.validate(&child)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the schema with its root at the given key,
+    /// creating any missing nodes and verifying any existing ones.
+    ///
+    /// Existing nodes must be of the same type and have the same metadata,
+    /// although they may have additional attributes.
+    /// The whole schema is validated before anything is written,
+    /// but a mismatch may be found after some nodes have been created.
+    /// Existing nodes which are not described by the schema are left alone.
+    /// Nodes are created only if they do not exist ([WriteableStore::set_if_match]),
+    /// so a node created concurrently by another writer is verified rather than overwritten.
+    pub fn apply<S: WriteableStore + ?Sized>(
+        &self,
+        store: &S,
+        key: &NodeKey,
+    ) -> Result<ApplyReport, SchemaError> {
+        self.validate(key)?;
+        let mut report = ApplyReport::default();
+        self.apply_inner(store, key, &mut report)?;
+        Ok(report)
+    }
+
+    /// Check that the existing node satisfies this schema's metadata.
+    fn verify(
+        &self,
+        key: &NodeKey,
+        existing: &Metadata,
+        report: &mut ApplyReport,
+    ) -> Result<(), SchemaError> {
+        if existing.is_array() != self.metadata.is_array() {
+            return Err(SchemaError::NodeTypeMismatch(key.encode()));
+        }
+        if !metadata_matches(&self.metadata, existing) {
+            return Err(SchemaError::MetadataMismatch(key.encode()));
+        }
+        report.verified.push(key.clone());
+        Ok(())
+    }
+
+    fn apply_inner<S: WriteableStore + ?Sized>(
+        &self,
+        store: &S,
+        key: &NodeKey,
+        report: &mut ApplyReport,
+    ) -> Result<(), SchemaError> {
+        match read_metadata(store, key)? {
+            Some(existing) => self.verify(key, &existing, report)?,
+            None => {
+                let mut meta_key = key.clone();
+                meta_key.with_metadata();
+                let buf = serde_json::to_vec_pretty(&self.metadata).map_err(io::Error::other)?;
+                if store.set_if_match(&meta_key, None, |w| w.write_all(&buf))? {
+                    report.created.push(key.clone());
+                } else {
+                    // created concurrently by another writer
+                    let existing = read_metadata(store, key)?.ok_or_else(|| {
+                        io::Error::new(ErrorKind::NotFound, "Node was removed while applying")
+                    })?;
+                    self.verify(key, &existing, report)?;
+                }
+            }
+        }
+
+        for (name, member) in self.members.iter() {
+            let mut child = key.clone();
+            child.push(name.parse()?);
+            member.apply_inner(store, &child, report)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::HashMapStore;
+
+    const SCHEMA: &str = r#"
+        {
+            "zarr_format": 3,
+            "node_type": "group",
+            "attributes": {"description": "example"},
+            "members": {
+                "raw": {
+                    "zarr_format": 3,
+                    "node_type": "array",
+                    "shape": [100, 100],
+                    "data_type": "uint8",
+                    "chunk_grid": {"name": "regular", "configuration": {"chunk_shape": [10, 10]}},
+                    "chunk_key_encoding": {"name": "default", "configuration": {"separator": "/"}},
+                    "fill_value": 0,
+                    "codecs": [{"name": "bytes", "configuration": {"endian": "little"}}]
+                },
+                "labels": {
+                    "zarr_format": 3,
+                    "node_type": "group"
+                }
+            }
+        }
+    "#;
+
+    #[test]
+    fn apply_schema() {
+        let schema: NodeSchema = serde_json::from_str(SCHEMA).unwrap();
+        let store = HashMapStore::default();
+        let root = NodeKey::default();
+
+        let report = schema.apply(&store, &root).unwrap();
+        assert_eq!(report.created.len(), 3);
+        assert!(report.verified.is_empty());
+
+        let report = schema.apply(&store, &root).unwrap();
+        assert!(report.created.is_empty());
+        assert_eq!(report.verified.len(), 3);
+    }
+
+    #[test]
+    fn apply_schema_mismatch() {
+        let schema: NodeSchema = serde_json::from_str(SCHEMA).unwrap();
+        let store = HashMapStore::default();
+        let root = NodeKey::default();
+        schema.apply(&store, &root).unwrap();
+
+        let mut changed: NodeSchema = serde_json::from_str(SCHEMA).unwrap();
+        let labels = changed.members.remove("labels").unwrap();
+        changed.members.insert("raw".to_owned(), labels);
+        assert!(matches!(
+            changed.apply(&store, &root),
+            Err(SchemaError::NodeTypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn apply_schema_concurrently() {
+        let schema: NodeSchema = serde_json::from_str(SCHEMA).unwrap();
+        let store = HashMapStore::default();
+        let root = NodeKey::default();
+
+        let reports: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| schema.apply(&store, &root).unwrap()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        // each node is created exactly once
+        let created: usize = reports.iter().map(|r| r.created.len()).sum();
+        assert_eq!(created, 3);
+        assert!(reports
+            .iter()
+            .all(|r| r.created.len() + r.verified.len() == 3));
+    }
+
+    #[test]
+    fn array_with_members() {
+        let mut schema: NodeSchema = serde_json::from_str(SCHEMA).unwrap();
+        let mut raw = schema.members.remove("raw").unwrap();
+        raw.members.insert("child".to_owned(), schema.clone());
+        assert!(matches!(
+            raw.validate(&NodeKey::default()),
+            Err(SchemaError::ArrayHasMembers(_))
+        ));
+    }
+}
@@ -0,0 +1,451 @@
+//! A write-behind buffer in front of another store, for workloads which write many small values.
+use std::{
+    collections::HashMap,
+    io::{self, Cursor, Read, Write},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use log::warn;
+
+use crate::RangeRequest;
+
+use super::{
+    ListableStore, NodeKey, ReadableStore, Store, VersionToken, Versioned, WriteableStore,
+};
+
+const DEFAULT_MAX_BYTES: usize = 1 << 20;
+const DEFAULT_MAX_KEYS: usize = 1000;
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(1);
+
+/// A buffered write, waiting to be flushed.
+#[derive(Debug, Clone)]
+enum Pending {
+    Set(Bytes),
+    Erase,
+}
+
+impl Pending {
+    fn nbytes(&self) -> usize {
+        match self {
+            Self::Set(b) => b.len(),
+            Self::Erase => 0,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buffer {
+    pending: HashMap<NodeKey, Pending>,
+    /// Writes taken by a flush which is in progress, which are still visible to readers.
+    in_flight: HashMap<NodeKey, Pending>,
+    bytes: usize,
+    oldest: Option<Instant>,
+}
+
+impl Buffer {
+    fn get(&self, key: &NodeKey) -> Option<&Pending> {
+        self.pending.get(key).or_else(|| self.in_flight.get(key))
+    }
+
+    fn insert(&mut self, key: NodeKey, value: Pending) {
+        self.bytes += value.nbytes();
+        if let Some(old) = self.pending.insert(key, value) {
+            self.bytes -= old.nbytes();
+        }
+        self.oldest.get_or_insert_with(Instant::now);
+    }
+}
+
+/// Buffers writes and erasures in memory, writing them to the underlying store
+/// when the buffer exceeds a total size, a number of keys or an age, or when [BufferedStore::flush]ed.
+///
+/// Repeated writes to the same key (e.g. successive attribute updates) are coalesced,
+/// and flushes may write several keys concurrently ([BufferedStore::with_flush_threads]),
+/// saving round trips on high-latency stores.
+/// Reads through this store see buffered writes.
+///
+/// Thresholds are checked when writing, not in the background:
+/// call [BufferedStore::flush] at the end of a batch of writes.
+/// Remaining writes are flushed when the store is dropped, but errors can only be logged.
+/// Values larger than the size threshold are written through immediately.
+///
+/// Buffered values have no version, so [ReadableStore::version] gives `None` for them;
+/// [WriteableStore::set_if_match] flushes the buffer first.
+/// Listing and [WriteableStore::erase_prefix] also flush the buffer first.
+pub struct BufferedStore<S: WriteableStore> {
+    inner: S,
+    buffer: Mutex<Buffer>,
+    /// Held while flushing, so that flushes are applied in order.
+    flushing: Mutex<()>,
+    max_bytes: usize,
+    max_keys: usize,
+    max_age: Duration,
+    flush_threads: usize,
+}
+
+impl<S: WriteableStore> BufferedStore<S> {
+    /// Buffer up to 1MiB, 1000 keys or 1 second of writes, flushing on a single thread.
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buffer: Default::default(),
+            flushing: Default::default(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_keys: DEFAULT_MAX_KEYS,
+            max_age: DEFAULT_MAX_AGE,
+            flush_threads: 1,
+        }
+    }
+
+    /// Flush when buffered values total more than this many bytes.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Flush when more than this many keys have buffered writes.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    /// Flush when writing, if the oldest buffered write is older than this.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Write up to this many keys concurrently when flushing (at least 1, the default).
+    pub fn with_flush_threads(mut self, threads: usize) -> Self {
+        self.flush_threads = threads.max(1);
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Number of keys with buffered writes.
+    pub fn pending_keys(&self) -> usize {
+        self.buffer.lock().unwrap().pending.len()
+    }
+
+    /// Total size of buffered values.
+    pub fn pending_bytes(&self) -> usize {
+        self.buffer.lock().unwrap().bytes
+    }
+}
+
+impl<S: WriteableStore + Sync> BufferedStore<S> {
+    /// Write all buffered writes to the underlying store.
+    ///
+    /// Writes which fail remain buffered (unless they have since been replaced),
+    /// and the first error is returned.
+    pub fn flush(&self) -> io::Result<()> {
+        let _flushing = self.flushing.lock().unwrap();
+        let batch: Vec<_> = {
+            let mut buffer = self.buffer.lock().unwrap();
+            buffer.in_flight = std::mem::take(&mut buffer.pending);
+            buffer.bytes = 0;
+            buffer.oldest = None;
+            buffer
+                .in_flight
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect()
+        };
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        let per_thread = batch.len().div_ceil(self.flush_threads);
+        let failed: Vec<_> = if per_thread == batch.len() {
+            self.write_batch(&batch)
+        } else {
+            std::thread::scope(|s| {
+                let handles: Vec<_> = batch
+                    .chunks(per_thread)
+                    .map(|part| s.spawn(|| self.write_batch(part)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|h| h.join().expect("flush thread panicked"))
+                    .collect()
+            })
+        };
+
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.in_flight.clear();
+        let mut first_error = None;
+        for (key, value, e) in failed {
+            if !buffer.pending.contains_key(&key) {
+                buffer.insert(key, value);
+            }
+            first_error.get_or_insert(e);
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn write_batch(&self, batch: &[(NodeKey, Pending)]) -> Vec<(NodeKey, Pending, io::Error)> {
+        let mut failed = Vec::default();
+        for (key, value) in batch {
+            let result = match value {
+                Pending::Set(b) => self.inner.set(key, |w| w.write_all(b)),
+                Pending::Erase => self.inner.erase(key).map(|_| ()),
+            };
+            if let Err(e) = result {
+                failed.push((key.clone(), value.clone(), e));
+            }
+        }
+        failed
+    }
+
+    fn buffer_write(&self, key: &NodeKey, value: Pending) -> io::Result<()> {
+        let should_flush = {
+            let mut buffer = self.buffer.lock().unwrap();
+            buffer.insert(key.clone(), value);
+            buffer.bytes > self.max_bytes
+                || buffer.pending.len() > self.max_keys
+                || buffer.oldest.is_some_and(|t| t.elapsed() > self.max_age)
+        };
+        if should_flush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: WriteableStore> Store for BufferedStore<S> {}
+
+impl<S: WriteableStore> ReadableStore for BufferedStore<S>
+where
+    S::Readable: 'static,
+{
+    type Readable = Box<dyn Read>;
+
+    fn has_key(&self, key: &NodeKey) -> io::Result<bool> {
+        match self.buffer.lock().unwrap().get(key) {
+            Some(Pending::Set(_)) => return Ok(true),
+            Some(Pending::Erase) => return Ok(false),
+            None => (),
+        }
+        self.inner.has_key(key)
+    }
+
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Self::Readable>> {
+        match self.buffer.lock().unwrap().get(key) {
+            Some(Pending::Set(b)) => return Ok(Some(Box::new(Cursor::new(b.clone())))),
+            Some(Pending::Erase) => return Ok(None),
+            None => (),
+        }
+        Ok(self.inner.get(key)?.map(|r| Box::new(r) as Box<dyn Read>))
+    }
+
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        if self.buffer.lock().unwrap().get(key).is_some() {
+            return Ok(None);
+        }
+        self.inner.version(key)
+    }
+
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Self::Readable>>> {
+        match self.buffer.lock().unwrap().get(key) {
+            Some(Pending::Set(b)) => return Ok(Some((Box::new(Cursor::new(b.clone())), None))),
+            Some(Pending::Erase) => return Ok(None),
+            None => (),
+        }
+        Ok(self
+            .inner
+            .get_versioned(key)?
+            .map(|(r, v)| (Box::new(r) as Box<dyn Read>, v)))
+    }
+
+    fn get_partial_values(
+        &self,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+        let mut out: Vec<Option<Box<dyn Read>>> = Vec::with_capacity(key_ranges.len());
+        let mut unbuffered = Vec::default();
+        {
+            let buffer = self.buffer.lock().unwrap();
+            for (idx, (key, range)) in key_ranges.iter().enumerate() {
+                match buffer.get(key) {
+                    Some(Pending::Set(b)) => out.push(Some(Box::new(Cursor::new(
+                        b.slice(range.to_range(b.len())),
+                    )))),
+                    Some(Pending::Erase) => out.push(None),
+                    None => {
+                        out.push(None);
+                        unbuffered.push(idx);
+                    }
+                }
+            }
+        }
+        if unbuffered.is_empty() {
+            return Ok(out);
+        }
+        let requests: Vec<_> = unbuffered
+            .iter()
+            .map(|idx| key_ranges[*idx].clone())
+            .collect();
+        for (idx, r) in unbuffered
+            .into_iter()
+            .zip(self.inner.get_partial_values(&requests)?)
+        {
+            out[idx] = r;
+        }
+        Ok(out)
+    }
+
+    fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        self.inner.uri(key)
+    }
+
+    fn prefetch(&self, keys: &[NodeKey]) {
+        self.inner.prefetch(keys)
+    }
+}
+
+impl<S: WriteableStore + Sync> ListableStore for BufferedStore<S> {
+    fn list(&self) -> io::Result<Vec<NodeKey>> {
+        self.flush()?;
+        self.inner.list()
+    }
+
+    fn list_prefix(&self, prefix: &NodeKey) -> io::Result<Vec<NodeKey>> {
+        self.flush()?;
+        self.inner.list_prefix(prefix)
+    }
+
+    fn list_dir(&self, prefix: &NodeKey) -> io::Result<(Vec<NodeKey>, Vec<NodeKey>)> {
+        self.flush()?;
+        self.inner.list_dir(prefix)
+    }
+}
+
+impl<S: WriteableStore + Sync> WriteableStore for BufferedStore<S>
+where
+    S::Readable: 'static,
+{
+    type Writeable = Vec<u8>;
+
+    fn set<F>(&self, key: &NodeKey, value: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        let mut buf = Vec::default();
+        value(&mut buf)?;
+        if buf.len() > self.max_bytes {
+            // flush an older write to this key first, so that it cannot overwrite this one later
+            if self.buffer.lock().unwrap().get(key).is_some() {
+                self.flush()?;
+            }
+            return self.inner.set(key, |w| w.write_all(&buf));
+        }
+        self.buffer_write(key, Pending::Set(buf.into()))
+    }
+
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        self.flush()?;
+        let mut buf = Vec::default();
+        value(&mut buf)?;
+        self.inner
+            .set_if_match(key, expected, |w| w.write_all(&buf))
+    }
+
+    fn erase(&self, key: &NodeKey) -> io::Result<bool> {
+        self.buffer_write(key, Pending::Erase)?;
+        Ok(false)
+    }
+
+    fn erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
+        self.flush()?;
+        self.inner.erase_prefix(key_prefix)
+    }
+}
+
+impl<S: WriteableStore> Drop for BufferedStore<S> {
+    fn drop(&mut self) {
+        let buffer = std::mem::take(self.buffer.get_mut().unwrap());
+        for (key, value) in buffer.pending {
+            let result = match value {
+                Pending::Set(b) => self.inner.set(&key, |w| w.write_all(&b)),
+                Pending::Erase => self.inner.erase(&key).map(|_| ()),
+            };
+            if let Err(e) = result {
+                warn!("Could not write buffered value for {key} on drop: {e}");
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "filesystem"))]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::store::filesystem::FileSystemStore;
+
+    fn read<S: ReadableStore>(store: &S, key: &NodeKey) -> Option<Vec<u8>> {
+        store.get(key).unwrap().map(|mut r| {
+            let mut buf = Vec::default();
+            r.read_to_end(&mut buf).unwrap();
+            buf
+        })
+    }
+
+    #[test]
+    fn buffers_writes() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let path = tmp.path().join("root.zarr");
+        let store = BufferedStore::new(FileSystemStore::create(path.clone(), true).unwrap())
+            .with_max_keys(3)
+            .with_max_age(Duration::from_secs(3600))
+            .with_flush_threads(2);
+        let keys: Vec<NodeKey> = (0..4).map(|i| format!("k{i}").parse().unwrap()).collect();
+
+        store.set(&keys[0], |w| w.write_all(b"a")).unwrap();
+        store.set(&keys[0], |w| w.write_all(b"aa")).unwrap();
+        store.set(&keys[1], |w| w.write_all(b"b")).unwrap();
+        assert_eq!(store.pending_keys(), 2);
+        assert_eq!(store.pending_bytes(), 3);
+        assert!(!store.inner().has_key(&keys[0]).unwrap());
+        assert_eq!(read(&store, &keys[0]).unwrap(), b"aa");
+
+        store.erase(&keys[1]).unwrap();
+        assert!(!store.has_key(&keys[1]).unwrap());
+        store.set(&keys[2], |w| w.write_all(b"c")).unwrap();
+        assert_eq!(store.pending_keys(), 3);
+
+        // exceeds the key threshold
+        store.set(&keys[3], |w| w.write_all(b"d")).unwrap();
+        assert_eq!(store.pending_keys(), 0);
+        assert_eq!(read(store.inner(), &keys[0]).unwrap(), b"aa");
+        assert!(!store.inner().has_key(&keys[1]).unwrap());
+        assert_eq!(read(store.inner(), &keys[3]).unwrap(), b"d");
+
+        // flushed explicitly, or on drop
+        store.set(&keys[1], |w| w.write_all(b"b")).unwrap();
+        store.flush().unwrap();
+        assert_eq!(read(store.inner(), &keys[1]).unwrap(), b"b");
+        store.erase(&keys[1]).unwrap();
+        store.set(&keys[2], |w| w.write_all(b"cc")).unwrap();
+        drop(store);
+        let reopened = FileSystemStore::open(path).unwrap();
+        assert!(!reopened.has_key(&keys[1]).unwrap());
+        assert_eq!(read(&reopened, &keys[2]).unwrap(), b"cc");
+    }
+}
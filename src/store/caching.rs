@@ -0,0 +1,463 @@
+//! An in-memory read cache in front of another store, which can fetch values in the background.
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{self, Cursor, Read},
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
+};
+
+use bytes::Bytes;
+use log::debug;
+
+use crate::RangeRequest;
+
+use super::{
+    ListableStore, NodeKey, ReadableStore, Store, VersionToken, Versioned, WriteableStore,
+};
+
+/// Nominal size of a cached record that a key does not exist.
+const ABSENT_ENTRY_SIZE: usize = 64;
+/// Batches of prefetch hints which may wait for the background thread before further hints are dropped.
+const PREFETCH_QUEUE: usize = 16;
+
+/// Counters describing a [CachingStore]'s effectiveness.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads which went to the underlying store.
+    pub misses: u64,
+    /// Values fetched in the background in response to prefetch hints.
+    pub prefetched: u64,
+    /// Values evicted to make space for others.
+    pub evicted: u64,
+    /// Total size of cached values.
+    pub size: usize,
+}
+
+struct Entry {
+    /// `None` records that the key does not exist.
+    value: Option<Bytes>,
+    last_used: u64,
+}
+
+impl Entry {
+    fn size(&self) -> usize {
+        self.value.as_ref().map_or(ABSENT_ENTRY_SIZE, |v| v.len())
+    }
+}
+
+/// Least-recently-used cache of values, bounded by their total size.
+struct ValueCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<NodeKey, Entry>,
+    by_use: BTreeMap<u64, NodeKey>,
+    /// Incremented by every write, so that background fetches which raced with a write are discarded.
+    epoch: u64,
+    stats: CacheStats,
+}
+
+impl ValueCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::default(),
+            by_use: BTreeMap::default(),
+            epoch: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    fn get(&mut self, key: &NodeKey) -> Option<Option<Bytes>> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(key)?;
+        self.by_use.remove(&entry.last_used);
+        entry.last_used = self.tick;
+        self.by_use.insert(self.tick, key.clone());
+        Some(entry.value.clone())
+    }
+
+    fn insert(&mut self, key: NodeKey, value: Option<Bytes>) {
+        self.remove(&key);
+        self.tick += 1;
+        let entry = Entry {
+            value,
+            last_used: self.tick,
+        };
+        if entry.size() > self.capacity {
+            return;
+        }
+        self.stats.size += entry.size();
+        while self.stats.size > self.capacity {
+            let (_, oldest) = self.by_use.pop_first().expect("cache is not empty");
+            let evicted = self.entries.remove(&oldest).expect("entry exists");
+            self.stats.size -= evicted.size();
+            self.stats.evicted += 1;
+        }
+        self.by_use.insert(self.tick, key.clone());
+        self.entries.insert(key, entry);
+    }
+
+    fn remove(&mut self, key: &NodeKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.by_use.remove(&entry.last_used);
+            self.stats.size -= entry.size();
+        }
+    }
+
+    fn invalidate(&mut self, key: &NodeKey) {
+        self.epoch += 1;
+        self.remove(key);
+    }
+
+    fn invalidate_prefix(&mut self, prefix: &NodeKey) {
+        self.epoch += 1;
+        let keys: Vec<_> = self
+            .entries
+            .keys()
+            .filter(|k| prefix.is_ancestor_of(k))
+            .cloned()
+            .collect();
+        for k in keys.iter() {
+            self.remove(k);
+        }
+    }
+}
+
+/// Caches whole values read from another store in memory,
+/// up to a total size in bytes, evicting the least recently used.
+///
+/// [ReadableStore::prefetch] hints are fetched into the cache by a background thread,
+/// which is started by the first hint and stops when the store is dropped.
+/// Hints are best-effort: they are dropped if the background thread falls behind.
+///
+/// Versions ([ReadableStore::version], [ReadableStore::get_versioned]) are not cached,
+/// and listing is passed through.
+/// Writes and erasures through this store invalidate the affected values,
+/// but changes made to the underlying store by other means are not noticed.
+pub struct CachingStore<S> {
+    inner: Arc<S>,
+    cache: Arc<Mutex<ValueCache>>,
+    prefetcher: Mutex<Option<SyncSender<Vec<NodeKey>>>>,
+}
+
+impl<S> CachingStore<S> {
+    /// Cache up to `capacity` bytes of values.
+    pub fn new(inner: S, capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            cache: Arc::new(Mutex::new(ValueCache::new(capacity))),
+            prefetcher: Mutex::new(None),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats
+    }
+
+    /// Drop all cached values.
+    pub fn clear(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.epoch += 1;
+        cache.entries.clear();
+        cache.by_use.clear();
+        cache.stats.size = 0;
+    }
+}
+
+impl<S: ReadableStore + Send + Sync + 'static> CachingStore<S> {
+    fn cached(&self, key: &NodeKey) -> Option<Option<Bytes>> {
+        let mut cache = self.cache.lock().unwrap();
+        let out = cache.get(key);
+        if out.is_some() {
+            cache.stats.hits += 1;
+        }
+        out
+    }
+
+    /// Read the key's value from the cache, or from the underlying store if it is not cached.
+    fn fetch(&self, key: &NodeKey) -> io::Result<Option<Bytes>> {
+        if let Some(v) = self.cached(key) {
+            return Ok(v);
+        }
+        let epoch = {
+            let mut cache = self.cache.lock().unwrap();
+            cache.stats.misses += 1;
+            cache.epoch
+        };
+        let value = read_value(self.inner.as_ref(), key)?;
+        let mut cache = self.cache.lock().unwrap();
+        if cache.epoch == epoch {
+            cache.insert(key.clone(), value.clone());
+        }
+        Ok(value)
+    }
+
+    fn spawn_prefetcher(&self) -> io::Result<SyncSender<Vec<NodeKey>>> {
+        let (tx, rx) = mpsc::sync_channel::<Vec<NodeKey>>(PREFETCH_QUEUE);
+        let inner = Arc::clone(&self.inner);
+        let cache = Arc::clone(&self.cache);
+        std::thread::Builder::new()
+            .name("zarr3-prefetch".into())
+            .spawn(move || {
+                for keys in rx {
+                    for key in keys {
+                        let epoch = {
+                            let cache = cache.lock().unwrap();
+                            if cache.entries.contains_key(&key) {
+                                continue;
+                            }
+                            cache.epoch
+                        };
+                        match read_value(inner.as_ref(), &key) {
+                            Ok(value) => {
+                                let mut cache = cache.lock().unwrap();
+                                // a foreground read may have got there first
+                                if cache.epoch == epoch && !cache.entries.contains_key(&key) {
+                                    cache.insert(key, value);
+                                    cache.stats.prefetched += 1;
+                                }
+                            }
+                            Err(e) => debug!("Could not prefetch {key}: {e}"),
+                        }
+                    }
+                }
+            })?;
+        Ok(tx)
+    }
+}
+
+fn read_value<S: ReadableStore + ?Sized>(store: &S, key: &NodeKey) -> io::Result<Option<Bytes>> {
+    match store.get(key)? {
+        Some(mut r) => {
+            let mut buf = Vec::default();
+            r.read_to_end(&mut buf)?;
+            Ok(Some(buf.into()))
+        }
+        None => Ok(None),
+    }
+}
+
+impl<S> Store for CachingStore<S> {}
+
+impl<S: ReadableStore + Send + Sync + 'static> ReadableStore for CachingStore<S> {
+    type Readable = Cursor<Bytes>;
+
+    fn has_key(&self, key: &NodeKey) -> io::Result<bool> {
+        match self.cached(key) {
+            Some(v) => Ok(v.is_some()),
+            None => self.inner.has_key(key),
+        }
+    }
+
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Self::Readable>> {
+        Ok(self.fetch(key)?.map(Cursor::new))
+    }
+
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        self.inner.version(key)
+    }
+
+    /// Not cached, so that the value and version are consistent.
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Self::Readable>>> {
+        match self.inner.get_versioned(key)? {
+            Some((mut r, version)) => {
+                let mut buf = Vec::default();
+                r.read_to_end(&mut buf)?;
+                Ok(Some((Cursor::new(buf.into()), version)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Ranges of cached values are served from the cache;
+    /// others are passed through without being cached.
+    fn get_partial_values(
+        &self,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+        let mut out: Vec<Option<Box<dyn Read>>> = Vec::with_capacity(key_ranges.len());
+        let mut uncached = Vec::default();
+        for (idx, (key, range)) in key_ranges.iter().enumerate() {
+            match self.cached(key) {
+                Some(v) => out.push(v.map(|b| {
+                    Box::new(Cursor::new(b.slice(range.to_range(b.len())))) as Box<dyn Read>
+                })),
+                None => {
+                    out.push(None);
+                    uncached.push(idx);
+                }
+            }
+        }
+        if uncached.is_empty() {
+            return Ok(out);
+        }
+        let requests: Vec<_> = uncached
+            .iter()
+            .map(|idx| key_ranges[*idx].clone())
+            .collect();
+        for (idx, r) in uncached
+            .into_iter()
+            .zip(self.inner.get_partial_values(&requests)?)
+        {
+            out[idx] = r;
+        }
+        Ok(out)
+    }
+
+    fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        self.inner.uri(key)
+    }
+
+    fn prefetch(&self, keys: &[NodeKey]) {
+        let keys: Vec<_> = {
+            let cache = self.cache.lock().unwrap();
+            keys.iter()
+                .filter(|k| !cache.entries.contains_key(k))
+                .cloned()
+                .collect()
+        };
+        if keys.is_empty() {
+            return;
+        }
+        let mut prefetcher = self.prefetcher.lock().unwrap();
+        if prefetcher.is_none() {
+            match self.spawn_prefetcher() {
+                Ok(tx) => *prefetcher = Some(tx),
+                Err(e) => {
+                    debug!("Could not start prefetch thread: {e}");
+                    return;
+                }
+            }
+        }
+        match prefetcher.as_ref().unwrap().try_send(keys) {
+            Ok(()) | Err(TrySendError::Full(_)) => (),
+            Err(TrySendError::Disconnected(_)) => *prefetcher = None,
+        }
+    }
+}
+
+impl<S: ListableStore> ListableStore for CachingStore<S> {
+    fn list(&self) -> io::Result<Vec<NodeKey>> {
+        self.inner.list()
+    }
+
+    fn list_prefix(&self, prefix: &NodeKey) -> io::Result<Vec<NodeKey>> {
+        self.inner.list_prefix(prefix)
+    }
+
+    fn list_dir(&self, prefix: &NodeKey) -> io::Result<(Vec<NodeKey>, Vec<NodeKey>)> {
+        self.inner.list_dir(prefix)
+    }
+}
+
+impl<S: WriteableStore + Send + Sync + 'static> WriteableStore for CachingStore<S> {
+    type Writeable = S::Writeable;
+
+    fn set<F>(&self, key: &NodeKey, value: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        self.cache.lock().unwrap().invalidate(key);
+        let out = self.inner.set(key, value);
+        self.cache.lock().unwrap().invalidate(key);
+        out
+    }
+
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        self.cache.lock().unwrap().invalidate(key);
+        let out = self.inner.set_if_match(key, expected, value);
+        self.cache.lock().unwrap().invalidate(key);
+        out
+    }
+
+    fn set_partial_values(
+        &self,
+        key_offset_values: Vec<(NodeKey, usize, Vec<u8>)>,
+    ) -> io::Result<()> {
+        let keys: Vec<_> = key_offset_values
+            .iter()
+            .map(|(k, _, _)| k.clone())
+            .collect();
+        let out = self.inner.set_partial_values(key_offset_values);
+        let mut cache = self.cache.lock().unwrap();
+        for k in keys.iter() {
+            cache.invalidate(k);
+        }
+        out
+    }
+
+    fn erase(&self, key: &NodeKey) -> io::Result<bool> {
+        let out = self.inner.erase(key);
+        self.cache.lock().unwrap().invalidate(key);
+        out
+    }
+
+    fn erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
+        let out = self.inner.erase_prefix(key_prefix);
+        self.cache.lock().unwrap().invalidate_prefix(key_prefix);
+        out
+    }
+}
+
+#[cfg(all(test, feature = "filesystem"))]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::store::filesystem::FileSystemStore;
+
+    #[test]
+    fn caches_and_evicts() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let fs = FileSystemStore::create(tmp.path().join("root.zarr"), true).unwrap();
+        let store = CachingStore::new(fs, 10);
+        let (a, b): (NodeKey, NodeKey) = ("a".parse().unwrap(), "b".parse().unwrap());
+        store.set(&a, |w| w.write_all(b"aaaaaa")).unwrap();
+        store.set(&b, |w| w.write_all(b"bbbbbb")).unwrap();
+
+        let read = |k: &NodeKey| {
+            let mut buf = Vec::default();
+            store
+                .get(k)
+                .unwrap()
+                .unwrap()
+                .read_to_end(&mut buf)
+                .unwrap();
+            buf
+        };
+        assert_eq!(read(&a), b"aaaaaa");
+        assert_eq!(read(&a), b"aaaaaa");
+        assert_eq!(store.stats().hits, 1);
+        assert_eq!(store.stats().misses, 1);
+
+        // both values do not fit
+        assert_eq!(read(&b), b"bbbbbb");
+        assert_eq!(store.stats().evicted, 1);
+        assert_eq!(store.stats().size, 6);
+
+        // writes invalidate
+        store.set(&b, |w| w.write_all(b"BB")).unwrap();
+        assert_eq!(read(&b), b"BB");
+        assert_eq!(store.stats().misses, 3);
+
+        store.erase(&b).unwrap();
+        assert!(!store.has_key(&b).unwrap());
+    }
+}
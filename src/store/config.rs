@@ -0,0 +1,544 @@
+//! Build stores from configuration files or environment variables,
+//! so that a backend and its tuning can be chosen without code changes.
+//!
+//! A [StoreConfig] is a JSON (or, with the `toml_config` feature, TOML) document
+//! whose `type` field selects the backend:
+//!
+//! ```
+//! # use zarr3::store::config::StoreConfig;
+//! let config = StoreConfig::from_json(r#"{"type": "memory"}"#).unwrap();
+//! let store = config.open_writeable().unwrap();
+//! ```
+//!
+//! [StoreConfig::from_env] reads the same settings from environment variables,
+//! which also override those in a configuration file;
+//! this keeps credentials out of files which may be shared.
+//! With the default prefix of `ZARR3_`:
+//!
+//! | Variable | Meaning |
+//! | --- | --- |
+//! | `ZARR3_STORE_CONFIG` | Path to a configuration file (`.toml` for TOML, otherwise JSON) |
+//! | `ZARR3_STORE` | `memory`, an `http://` or `https://` URL, or a filesystem path (optionally `file://`) |
+//! | `ZARR3_STORE_CREATE` | Create a filesystem store if it does not exist (`true`/`false`) |
+//! | `ZARR3_HANDLE_CACHE_CAPACITY` | Filesystem read handles to keep open |
+//! | `ZARR3_HANDLE_CACHE_TTL_SECS` | How long to keep filesystem read handles open |
+//! | `ZARR3_HTTP_USERNAME` | HTTP basic auth user name |
+//! | `ZARR3_HTTP_PASSWORD` | HTTP basic auth password |
+//! | `ZARR3_HTTP_TIMEOUT_SECS` | HTTP request timeout |
+//! | `ZARR3_HTTP_MAX_RETRIES` | Retries of transiently-failing HTTP requests |
+//! | `ZARR3_HTTP_RETRY_BACKOFF_MS` | Delay before the first HTTP retry |
+//!
+//! Settings for backends which the crate was compiled without are rejected.
+use std::{fmt::Debug, io, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::{DynReadableStore, DynWriteableStore, HashMapStore};
+
+/// Default prefix of the environment variables read by [StoreConfig::from_env].
+pub const DEFAULT_ENV_PREFIX: &str = "ZARR3_";
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    #[error("Could not read configuration file")]
+    Io(#[from] io::Error),
+    #[error("Invalid JSON configuration: {0}")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "toml_config")]
+    #[error("Invalid TOML configuration: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("No store configured; set `{0}STORE` or `{0}STORE_CONFIG`")]
+    MissingStore(String),
+    #[error("Invalid value of environment variable `{name}`: {value:?}")]
+    InvalidVar { name: String, value: String },
+    #[error("Invalid value of setting `{name}`: {value}")]
+    InvalidSetting { name: String, value: String },
+    #[error("Setting `{0}` does not apply to the configured store")]
+    Inapplicable(String),
+    #[error("Unsupported store configuration: {0}")]
+    Unsupported(&'static str),
+}
+
+impl From<ConfigError> for io::Error {
+    fn from(value: ConfigError) -> Self {
+        match value {
+            ConfigError::Io(e) => e,
+            e => Self::new(io::ErrorKind::InvalidInput, e),
+        }
+    }
+}
+
+/// Which store to build, and how to tune it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum StoreConfig {
+    /// An empty in-memory [HashMapStore].
+    Memory,
+    #[cfg(feature = "filesystem")]
+    Filesystem(FileSystemConfig),
+    #[cfg(feature = "http")]
+    Http(HttpConfig),
+}
+
+/// Configuration of a [FileSystemStore](super::filesystem::FileSystemStore).
+#[cfg(feature = "filesystem")]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileSystemConfig {
+    pub path: std::path::PathBuf,
+    /// Create the store (and any missing parents) if it does not exist.
+    #[serde(default)]
+    pub create: bool,
+    /// Levels of hashed subdirectories to fan files out into, if the store is created.
+    #[serde(default)]
+    pub fanout_levels: u8,
+    #[serde(default)]
+    pub handle_cache: Option<HandleCacheConfig>,
+}
+
+/// See [FileSystemStore::with_handle_cache](super::filesystem::FileSystemStore::with_handle_cache).
+#[cfg(feature = "filesystem")]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HandleCacheConfig {
+    pub capacity: usize,
+    #[serde(default = "default_handle_ttl_secs")]
+    pub ttl_secs: f64,
+}
+
+#[cfg(feature = "filesystem")]
+fn default_handle_ttl_secs() -> f64 {
+    60.0
+}
+
+/// Configuration of an [HttpStore](super::http::HttpStore).
+///
+/// Unset options take their values from [HttpOptions::default](super::http::HttpOptions).
+/// The password is redacted from the [Debug] representation.
+#[cfg(feature = "http")]
+#[derive(Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Base URL of the hierarchy; a trailing `/` is added if missing.
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<f64>,
+    #[serde(default)]
+    pub pool_max_idle_per_host: Option<usize>,
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    #[serde(default)]
+    pub retry_backoff_ms: Option<u64>,
+}
+
+#[cfg(feature = "http")]
+impl Debug for HttpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HttpConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("timeout_secs", &self.timeout_secs)
+            .field("pool_max_idle_per_host", &self.pool_max_idle_per_host)
+            .field("max_retries", &self.max_retries)
+            .field("retry_backoff_ms", &self.retry_backoff_ms)
+            .finish()
+    }
+}
+
+#[cfg(feature = "http")]
+impl HttpConfig {
+    /// Connection settings for the client.
+    ///
+    /// `Err` if the timeout is negative or not finite.
+    pub fn options(&self) -> Result<super::http::HttpOptions, ConfigError> {
+        let mut options = super::http::HttpOptions::default();
+        if let Some(t) = self.timeout_secs {
+            options.timeout = Some(std::time::Duration::try_from_secs_f64(t).map_err(|e| {
+                ConfigError::InvalidSetting {
+                    name: "timeout_secs".to_string(),
+                    value: format!("{t} ({e})"),
+                }
+            })?);
+        }
+        if let Some(n) = self.pool_max_idle_per_host {
+            options.pool_max_idle_per_host = n;
+        }
+        if let Some(n) = self.max_retries {
+            options.retry.max_retries = n;
+        }
+        if let Some(ms) = self.retry_backoff_ms {
+            options.retry.backoff = std::time::Duration::from_millis(ms);
+        }
+        Ok(options)
+    }
+}
+
+impl StoreConfig {
+    pub fn from_json(s: &str) -> Result<Self, ConfigError> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    #[cfg(feature = "toml_config")]
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// Read a configuration file; TOML if it has a `.toml` extension, otherwise JSON.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|e| e == "toml") {
+            #[cfg(feature = "toml_config")]
+            return Self::from_toml(&contents);
+            #[cfg(not(feature = "toml_config"))]
+            return Err(ConfigError::Unsupported(
+                "TOML configuration requires the `toml_config` feature",
+            ));
+        }
+        Self::from_json(&contents)
+    }
+
+    /// Read the configuration from environment variables with the [DEFAULT_ENV_PREFIX].
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Self::from_env_with_prefix(DEFAULT_ENV_PREFIX)
+    }
+
+    /// Read the configuration from environment variables with the given prefix;
+    /// see the [module documentation](self) for the variables used.
+    pub fn from_env_with_prefix(prefix: &str) -> Result<Self, ConfigError> {
+        Self::from_vars(prefix, |name| std::env::var(name).ok())
+    }
+
+    /// As [StoreConfig::from_env_with_prefix], looking up variables with the given function.
+    pub fn from_vars<F: Fn(&str) -> Option<String>>(
+        prefix: &str,
+        lookup: F,
+    ) -> Result<Self, ConfigError> {
+        let vars = Vars { prefix, lookup };
+        let mut config = if let Some(path) = vars.get("STORE_CONFIG") {
+            Self::from_file(path)?
+        } else if let Some(location) = vars.get("STORE") {
+            Self::from_location(&location)?
+        } else {
+            return Err(ConfigError::MissingStore(prefix.to_owned()));
+        };
+        config.apply_overrides(&vars)?;
+        Ok(config)
+    }
+
+    /// `memory`, an `http://` or `https://` URL, or a filesystem path (optionally `file://`).
+    fn from_location(location: &str) -> Result<Self, ConfigError> {
+        if location == "memory" {
+            return Ok(Self::Memory);
+        }
+        if location.starts_with("http://") || location.starts_with("https://") {
+            #[cfg(feature = "http")]
+            return Ok(Self::Http(HttpConfig {
+                url: location.to_owned(),
+                ..Default::default()
+            }));
+            #[cfg(not(feature = "http"))]
+            return Err(ConfigError::Unsupported(
+                "HTTP stores require the `http` feature",
+            ));
+        }
+        #[cfg(feature = "filesystem")]
+        return Ok(Self::Filesystem(FileSystemConfig {
+            path: location.strip_prefix("file://").unwrap_or(location).into(),
+            create: false,
+            fanout_levels: 0,
+            handle_cache: None,
+        }));
+        #[cfg(not(feature = "filesystem"))]
+        Err(ConfigError::Unsupported(
+            "Filesystem stores require the `filesystem` feature",
+        ))
+    }
+
+    fn apply_overrides<F: Fn(&str) -> Option<String>>(
+        &mut self,
+        vars: &Vars<F>,
+    ) -> Result<(), ConfigError> {
+        #[allow(unused_mut)]
+        let mut applicable: Vec<&str> = Vec::default();
+        match self {
+            Self::Memory => (),
+            #[cfg(feature = "filesystem")]
+            Self::Filesystem(c) => {
+                applicable.extend([
+                    "STORE_CREATE",
+                    "HANDLE_CACHE_CAPACITY",
+                    "HANDLE_CACHE_TTL_SECS",
+                ]);
+                if let Some(create) = vars.parse("STORE_CREATE")? {
+                    c.create = create;
+                }
+                let capacity = vars.parse("HANDLE_CACHE_CAPACITY")?;
+                let ttl_secs = vars.parse("HANDLE_CACHE_TTL_SECS")?;
+                if capacity.is_some() || ttl_secs.is_some() {
+                    let cache = c.handle_cache.get_or_insert(HandleCacheConfig {
+                        capacity: 0,
+                        ttl_secs: default_handle_ttl_secs(),
+                    });
+                    cache.capacity = capacity.unwrap_or(cache.capacity);
+                    cache.ttl_secs = ttl_secs.unwrap_or(cache.ttl_secs);
+                }
+            }
+            #[cfg(feature = "http")]
+            Self::Http(c) => {
+                applicable.extend([
+                    "HTTP_USERNAME",
+                    "HTTP_PASSWORD",
+                    "HTTP_TIMEOUT_SECS",
+                    "HTTP_MAX_RETRIES",
+                    "HTTP_RETRY_BACKOFF_MS",
+                ]);
+                if let Some(u) = vars.get("HTTP_USERNAME") {
+                    c.username = Some(u);
+                }
+                if let Some(p) = vars.get("HTTP_PASSWORD") {
+                    c.password = Some(p);
+                }
+                c.timeout_secs = vars.parse("HTTP_TIMEOUT_SECS")?.or(c.timeout_secs);
+                c.max_retries = vars.parse("HTTP_MAX_RETRIES")?.or(c.max_retries);
+                c.retry_backoff_ms = vars.parse("HTTP_RETRY_BACKOFF_MS")?.or(c.retry_backoff_ms);
+            }
+        }
+        for name in OVERRIDES {
+            if !applicable.contains(name) && vars.get(name).is_some() {
+                return Err(ConfigError::Inapplicable(format!("{}{name}", vars.prefix)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the configured store for reading.
+    pub fn open(&self) -> io::Result<Box<dyn DynReadableStore>> {
+        match self {
+            #[cfg(feature = "http")]
+            Self::Http(c) => Ok(Box::new(self.open_http(c)?)),
+            _ => Ok(self.open_writeable()?),
+        }
+    }
+
+    /// Build the configured store for reading and writing.
+    ///
+    /// Fails with [io::ErrorKind::Unsupported] for read-only backends.
+    pub fn open_writeable(&self) -> io::Result<Box<dyn DynWriteableStore>> {
+        match self {
+            Self::Memory => Ok(Box::<HashMapStore>::default()),
+            #[cfg(feature = "filesystem")]
+            Self::Filesystem(c) => {
+                use super::filesystem::FileSystemStore;
+
+                let mut store = if c.create && !c.path.exists() {
+                    if c.fanout_levels > 0 {
+                        FileSystemStore::create_fanout(c.path.clone(), true, c.fanout_levels)?
+                    } else {
+                        FileSystemStore::create(c.path.clone(), true)?
+                    }
+                } else {
+                    FileSystemStore::open(c.path.clone())?
+                };
+                if let Some(cache) = c.handle_cache {
+                    let ttl = std::time::Duration::try_from_secs_f64(cache.ttl_secs)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                    store = store.with_handle_cache(cache.capacity, ttl);
+                }
+                Ok(Box::new(store))
+            }
+            #[cfg(feature = "http")]
+            Self::Http(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "HTTP stores are read-only",
+            )),
+        }
+    }
+
+    #[cfg(feature = "http")]
+    fn open_http(&self, c: &HttpConfig) -> io::Result<super::http::HttpStore> {
+        let mut url = c.url.clone();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        let basic_auth = c
+            .username
+            .clone()
+            .map(|u| (u, c.password.clone().unwrap_or_default()));
+        super::http::HttpStore::with_options(url, basic_auth, &c.options()?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+}
+
+/// Backend-specific override variables, without prefix.
+const OVERRIDES: &[&str] = &[
+    "STORE_CREATE",
+    "HANDLE_CACHE_CAPACITY",
+    "HANDLE_CACHE_TTL_SECS",
+    "HTTP_USERNAME",
+    "HTTP_PASSWORD",
+    "HTTP_TIMEOUT_SECS",
+    "HTTP_MAX_RETRIES",
+    "HTTP_RETRY_BACKOFF_MS",
+];
+
+struct Vars<'a, F: Fn(&str) -> Option<String>> {
+    prefix: &'a str,
+    lookup: F,
+}
+
+impl<F: Fn(&str) -> Option<String>> Vars<'_, F> {
+    fn get(&self, name: &str) -> Option<String> {
+        (self.lookup)(&format!("{}{name}", self.prefix))
+    }
+
+    #[allow(dead_code)]
+    fn parse<T: FromStr>(&self, name: &str) -> Result<Option<T>, ConfigError> {
+        self.get(name)
+            .map(|value| {
+                value.parse().map_err(|_| ConfigError::InvalidVar {
+                    name: format!("{}{name}", self.prefix),
+                    value,
+                })
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::store::{ReadableStore, WriteableStore};
+    use std::io::Write;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |k| vars.get(k).cloned()
+    }
+
+    #[test]
+    fn from_env() {
+        assert!(matches!(
+            StoreConfig::from_vars("ZARR3_", lookup(&[])),
+            Err(ConfigError::MissingStore(_))
+        ));
+        let config =
+            StoreConfig::from_vars("ZARR3_", lookup(&[("ZARR3_STORE", "memory")])).unwrap();
+        assert_eq!(config, StoreConfig::Memory);
+        assert!(matches!(
+            StoreConfig::from_vars(
+                "ZARR3_",
+                lookup(&[("ZARR3_STORE", "memory"), ("ZARR3_HTTP_PASSWORD", "hunter2")])
+            ),
+            Err(ConfigError::Inapplicable(name)) if name == "ZARR3_HTTP_PASSWORD"
+        ));
+
+        let store = config.open_writeable().unwrap();
+        let key = "a/b".parse().unwrap();
+        store.set(&key, |w| w.write_all(b"value")).unwrap();
+        assert!(store.has_key(&key).unwrap());
+    }
+
+    #[cfg(feature = "filesystem")]
+    #[test]
+    fn filesystem_config() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let path = tmp.path().join("root.zarr");
+        let config_path = tmp.path().join("store.json");
+        std::fs::write(
+            &config_path,
+            serde_json::json!({
+                "type": "filesystem",
+                "path": path,
+                "handle_cache": {"capacity": 4},
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let vars = [
+            ("APP_STORE_CONFIG", config_path.to_str().unwrap()),
+            ("APP_STORE_CREATE", "true"),
+            ("APP_HANDLE_CACHE_TTL_SECS", "5"),
+        ];
+        let config = StoreConfig::from_vars("APP_", lookup(&vars)).unwrap();
+        let StoreConfig::Filesystem(fs_config) = &config else {
+            panic!("wrong store type");
+        };
+        assert!(fs_config.create);
+        assert_eq!(
+            fs_config.handle_cache,
+            Some(HandleCacheConfig {
+                capacity: 4,
+                ttl_secs: 5.0
+            })
+        );
+        config.open_writeable().unwrap();
+        assert!(path.is_dir());
+
+        let err = StoreConfig::from_vars(
+            "APP_",
+            lookup(&[("APP_STORE", "file:///tmp"), ("APP_STORE_CREATE", "maybe")]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid value of environment variable `APP_STORE_CREATE`: \"maybe\""
+        );
+        assert!(
+            StoreConfig::from_json(r#"{"type": "filesystem", "path": "/", "depth": 1}"#).is_err()
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http_config() {
+        let vars = [
+            ("ZARR3_STORE", "https://example.com/data"),
+            ("ZARR3_HTTP_USERNAME", "user"),
+            ("ZARR3_HTTP_PASSWORD", "hunter2"),
+            ("ZARR3_HTTP_MAX_RETRIES", "3"),
+        ];
+        let config = StoreConfig::from_vars("ZARR3_", lookup(&vars)).unwrap();
+        assert!(!format!("{config:?}").contains("hunter2"));
+        let StoreConfig::Http(http_config) = &config else {
+            panic!("wrong store type");
+        };
+        assert_eq!(http_config.options().unwrap().retry.max_retries, 3);
+        config.open().unwrap();
+        assert_eq!(
+            config.open_writeable().err().unwrap().kind(),
+            io::ErrorKind::Unsupported
+        );
+
+        let vars = [
+            ("ZARR3_STORE", "https://example.com/data"),
+            ("ZARR3_HTTP_TIMEOUT_SECS", "-1"),
+        ];
+        let config = StoreConfig::from_vars("ZARR3_", lookup(&vars)).unwrap();
+        let StoreConfig::Http(http_config) = &config else {
+            panic!("wrong store type");
+        };
+        assert!(matches!(
+            http_config.options(),
+            Err(ConfigError::InvalidSetting { .. })
+        ));
+        assert_eq!(
+            config.open().err().unwrap().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Object-safe counterparts of the store traits, so that a backend can be chosen at runtime.
+//!
+//! Every [ReadableStore] (whose readers are `'static`) is a [DynReadableStore],
+//! and every such [WriteableStore] is a [DynWriteableStore].
+//! In turn, `dyn DynReadableStore` and `dyn DynWriteableStore` implement the generic traits,
+//! so they can be used with [Array](crate::node::Array) and [Group](crate::node::Group):
+//!
+//! ```
+//! # use zarr3::store::{DynWriteableStore, HashMapStore};
+//! # use zarr3::prelude::*;
+//! let store: Box<dyn DynWriteableStore> = Box::new(HashMapStore::default());
+//! let group = create_root_group(store.as_ref(), GroupMetadata::default()).unwrap();
+//! ```
+//!
+//! The traits are also implemented for `dyn DynReadableStore + Send + Sync`
+//! and `dyn DynWriteableStore + Send + Sync`, which can be shared between threads
+//! (e.g. by the parallel methods of [Array](crate::node::Array)).
+use std::io::{self, Read, Write};
+
+use crate::RangeRequest;
+
+use super::{
+    ListableStore, NodeKey, ReadableStore, Store, VersionToken, Versioned, WriteableStore,
+};
+
+/// Object-safe version of [ReadableStore].
+///
+/// Methods are prefixed with `dyn_` so that they do not shadow those of [ReadableStore].
+pub trait DynReadableStore: Store {
+    fn dyn_get(&self, key: &NodeKey) -> io::Result<Option<Box<dyn Read>>>;
+
+    fn dyn_has_key(&self, key: &NodeKey) -> io::Result<bool>;
+
+    fn dyn_version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>>;
+
+    fn dyn_uri(&self, key: &NodeKey) -> io::Result<Option<String>>;
+
+    fn dyn_prefetch(&self, keys: &[NodeKey]);
+
+    fn dyn_get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Box<dyn Read>>>>;
+
+    fn dyn_get_partial_values(
+        &self,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>>;
+}
+
+impl<S: ReadableStore> DynReadableStore for S
+where
+    S::Readable: 'static,
+{
+    fn dyn_get(&self, key: &NodeKey) -> io::Result<Option<Box<dyn Read>>> {
+        Ok(self.get(key)?.map(|r| Box::new(r) as Box<dyn Read>))
+    }
+
+    fn dyn_has_key(&self, key: &NodeKey) -> io::Result<bool> {
+        self.has_key(key)
+    }
+
+    fn dyn_version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        self.version(key)
+    }
+
+    fn dyn_uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        self.uri(key)
+    }
+
+    fn dyn_prefetch(&self, keys: &[NodeKey]) {
+        self.prefetch(keys)
+    }
+
+    fn dyn_get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Box<dyn Read>>>> {
+        Ok(self
+            .get_versioned(key)?
+            .map(|(r, v)| (Box::new(r) as Box<dyn Read>, v)))
+    }
+
+    fn dyn_get_partial_values(
+        &self,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+        self.get_partial_values(key_ranges)
+    }
+}
+
+/// Object-safe version of [WriteableStore], where values are written from a byte slice.
+pub trait DynWriteableStore: DynReadableStore + ListableStore {
+    fn set_bytes(&self, key: &NodeKey, value: &[u8]) -> io::Result<()>;
+
+    fn set_bytes_if_match(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: &[u8],
+    ) -> io::Result<bool>;
+
+    fn dyn_erase(&self, key: &NodeKey) -> io::Result<bool>;
+
+    fn dyn_erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool>;
+}
+
+impl<S: WriteableStore> DynWriteableStore for S
+where
+    S::Readable: 'static,
+{
+    fn set_bytes(&self, key: &NodeKey, value: &[u8]) -> io::Result<()> {
+        self.set(key, |w| w.write_all(value))
+    }
+
+    fn set_bytes_if_match(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: &[u8],
+    ) -> io::Result<bool> {
+        self.set_if_match(key, expected, |w| w.write_all(value))
+    }
+
+    fn dyn_erase(&self, key: &NodeKey) -> io::Result<bool> {
+        self.erase(key)
+    }
+
+    fn dyn_erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
+        self.erase_prefix(key_prefix)
+    }
+}
+
+macro_rules! dyn_readable_store {
+    ($t:ty) => {
+        impl<'a> ReadableStore for $t {
+            type Readable = Box<dyn Read>;
+
+            fn has_key(&self, key: &NodeKey) -> io::Result<bool> {
+                self.dyn_has_key(key)
+            }
+
+            fn get(&self, key: &NodeKey) -> io::Result<Option<Self::Readable>> {
+                self.dyn_get(key)
+            }
+
+            fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+                self.dyn_version(key)
+            }
+
+            fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+                self.dyn_uri(key)
+            }
+
+            fn prefetch(&self, keys: &[NodeKey]) {
+                self.dyn_prefetch(keys)
+            }
+
+            fn get_versioned(
+                &self,
+                key: &NodeKey,
+            ) -> io::Result<Option<Versioned<Self::Readable>>> {
+                self.dyn_get_versioned(key)
+            }
+
+            fn get_partial_values(
+                &self,
+                key_ranges: &[(NodeKey, RangeRequest)],
+            ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+                self.dyn_get_partial_values(key_ranges)
+            }
+        }
+    };
+}
+
+dyn_readable_store!(dyn DynReadableStore + 'a);
+dyn_readable_store!(dyn DynReadableStore + Send + Sync + 'a);
+dyn_readable_store!(dyn DynWriteableStore + 'a);
+dyn_readable_store!(dyn DynWriteableStore + Send + Sync + 'a);
+
+/// Values are buffered in memory and written when the closure returns.
+macro_rules! dyn_writeable_store {
+    ($t:ty) => {
+        impl<'a> WriteableStore for $t {
+            type Writeable = Vec<u8>;
+
+            fn set<F>(&self, key: &NodeKey, value: F) -> io::Result<()>
+            where
+                F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+            {
+                let mut buf = Vec::default();
+                value(&mut buf)?;
+                self.set_bytes(key, &buf)
+            }
+
+            fn set_if_match<F>(
+                &self,
+                key: &NodeKey,
+                expected: Option<&VersionToken>,
+                value: F,
+            ) -> io::Result<bool>
+            where
+                F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+            {
+                let mut buf = Vec::default();
+                value(&mut buf)?;
+                self.set_bytes_if_match(key, expected, &buf)
+            }
+
+            fn erase(&self, key: &NodeKey) -> io::Result<bool> {
+                self.dyn_erase(key)
+            }
+
+            fn erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
+                self.dyn_erase_prefix(key_prefix)
+            }
+        }
+    };
+}
+
+dyn_writeable_store!(dyn DynWriteableStore + 'a);
+dyn_writeable_store!(dyn DynWriteableStore + Send + Sync + 'a);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        node::{Array, ArrayMetadataBuilder},
+        prelude::{create_root_group, ArrayRegion},
+        store::HashMapStore,
+        ArcArrayD,
+    };
+    use smallvec::smallvec;
+
+    #[test]
+    fn array_over_dyn_store() {
+        let store: Box<dyn DynWriteableStore> = Box::new(HashMapStore::default());
+        let store = store.as_ref();
+        let g = create_root_group(store, Default::default()).unwrap();
+        let ameta = ArrayMetadataBuilder::<u16>::new(&[4, 4])
+            .chunk_grid(vec![2, 2].as_slice())
+            .unwrap()
+            .into();
+        let arr = g
+            .create_array::<u16>("arr".parse().unwrap(), ameta, false)
+            .unwrap();
+        let data = ArcArrayD::from_shape_vec(vec![3, 3], (0..9).collect()).unwrap();
+        arr.write_region(&smallvec![1, 1], data.clone()).unwrap();
+
+        let arr: Array<'_, dyn DynWriteableStore, u16> =
+            Array::from_store(store, "arr".parse().unwrap()).unwrap();
+        let out = arr
+            .read_region(ArrayRegion::from_offset_shape(&[1, 1], &[3, 3]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(out, data);
+        assert!(store
+            .dyn_has_key(&"arr/zarr.json".parse().unwrap())
+            .unwrap());
+    }
+}
@@ -1,7 +1,13 @@
 use std::{
-    fs::{self, File},
-    io::{self, ErrorKind, Read, Seek, SeekFrom},
-    path::PathBuf,
+    collections::HashMap,
+    fs::{self, File, Metadata},
+    io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 
 use fs4::FileExt;
@@ -9,33 +15,202 @@ use log::warn;
 use walkdir::WalkDir;
 
 use super::{
-    list_from_list_prefix, list_prefix_from_list_dir, ListableStore, NodeKey, NodeName,
-    ReadableStore, Store, WriteableStore,
+    list_from_list_prefix, list_prefix_from_list_dir, InvalidNodeName, ListableStore, NodeKey,
+    NodeName, ReadableStore, Store, VersionToken, Versioned, WriteableStore, METADATA_NAME,
 };
 use crate::RangeRequest;
 
+/// Name of the store-level marker file recording the fan-out configuration.
+///
+/// The reserved prefix means it is never listed as a key.
+const FANOUT_MARKER: &str = "__fanout.json";
+/// Prefix of hashed bucket directories, which is reserved so that they are never listed as keys.
+const BUCKET_PREFIX: &str = "__";
+const MAX_FANOUT_LEVELS: u8 = 4;
+
+/// Distinguishes temporary files written concurrently by this process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Store backed by a directory on the local filesystem, which can be shared between threads.
+///
+/// Values are written to a temporary file beside their destination (with the reserved `__` prefix,
+/// so it is never listed) which is moved into place once complete.
+/// Readers therefore never see a partially written value,
+/// even if the writing closure returns an error or panics (e.g. in a codec);
+/// the temporary file is removed as the closure's error or panic propagates.
+/// Files may still be left behind if the process is killed mid-write.
 pub struct FileSystemStore {
     base_path: PathBuf,
+    fanout_levels: u8,
+    handle_cache: Option<HandleCache>,
+}
+
+struct CachedHandle {
+    file: File,
+    opened: Instant,
+    last_used: Instant,
+}
+
+/// Bounded pool of open read handles, keyed by path.
+///
+/// Handles are checked out for exclusive use, so that concurrent readers never share a file offset.
+/// Least recently used handles are evicted when full,
+/// and handles older than the TTL are re-opened so that files replaced by other processes are noticed.
+struct HandleCache {
+    capacity: usize,
+    ttl: Duration,
+    handles: Mutex<HashMap<PathBuf, CachedHandle>>,
+}
+
+impl HandleCache {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            handles: Mutex::new(HashMap::with_capacity(capacity)),
+        }
+    }
+
+    fn take(&self, path: &Path) -> Option<CachedHandle> {
+        let h = self.handles.lock().unwrap().remove(path)?;
+        (h.opened.elapsed() < self.ttl).then_some(h)
+    }
+
+    fn put(&self, path: PathBuf, mut handle: CachedHandle) {
+        if self.capacity == 0 || handle.opened.elapsed() >= self.ttl {
+            return;
+        }
+        handle.last_used = Instant::now();
+        let mut handles = self.handles.lock().unwrap();
+        if handles.len() >= self.capacity && !handles.contains_key(&path) {
+            let lru = handles
+                .iter()
+                .min_by_key(|(_, h)| h.last_used)
+                .map(|(p, _)| p.clone());
+            if let Some(p) = lru {
+                handles.remove(&p);
+            }
+        }
+        handles.insert(path, handle);
+    }
+
+    /// Drop handles to the given path and any paths below it.
+    fn invalidate(&self, path: &Path) {
+        self.handles
+            .lock()
+            .unwrap()
+            .retain(|p, _| !p.starts_with(path));
+    }
+
+    fn clear(&self) {
+        self.handles.lock().unwrap().clear();
+    }
+
+    fn len(&self) -> usize {
+        self.handles.lock().unwrap().len()
+    }
 }
 
 impl FileSystemStore {
     /// Does not check or modify path.
+    ///
+    /// Does not read any fan-out marker; see [FileSystemStore::open].
     pub fn new_unchecked(path: PathBuf) -> Self {
-        Self { base_path: path }
+        Self {
+            base_path: path,
+            fanout_levels: 0,
+            handle_cache: None,
+        }
+    }
+
+    /// Keep up to `capacity` read handles open between calls to
+    /// [get_partial_values](ReadableStore::get_partial_values),
+    /// so that reading many ranges of the same files (e.g. a region of a sharded array)
+    /// does not re-open them every time.
+    ///
+    /// Handles are re-opened after `ttl`; writes and erasures through this store
+    /// drop any handles they affect.
+    /// [get](ReadableStore::get) always opens a new handle, as it is handed to the caller.
+    pub fn with_handle_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.handle_cache = Some(HandleCache::new(capacity, ttl));
+        self
+    }
+
+    /// Close all cached read handles.
+    pub fn clear_handle_cache(&self) {
+        if let Some(c) = &self.handle_cache {
+            c.clear();
+        }
+    }
+
+    /// Number of read handles currently cached.
+    pub fn cached_handles(&self) -> usize {
+        self.handle_cache.as_ref().map_or(0, |c| c.len())
+    }
+
+    fn invalidate_handles(&self, path: &Path) {
+        if let Some(c) = &self.handle_cache {
+            c.invalidate(path);
+        }
     }
 
     /// Canonicalizes path and checks that it is an extant directory.
+    ///
+    /// Respects the fan-out configuration, if the store was created with one.
     pub fn open(path: PathBuf) -> io::Result<Self> {
         let base_path = path.canonicalize()?;
         let meta = fs::metadata(&base_path)?;
         if meta.is_file() {
-            Err(io::Error::new(
-                ErrorKind::Other,
-                "Path exists, but it is a file",
-            ))
+            Err(io::Error::other("Path exists, but it is a file"))
         } else {
-            Ok(Self { base_path })
+            Self::new_unchecked(base_path).with_marker()
+        }
+    }
+
+    /// Create a new store which fans files out into `levels` of hashed subdirectories
+    /// (of up to 256 buckets each), to keep directory sizes manageable for large arrays.
+    ///
+    /// Other files are placed in buckets below the store root by a hash of their whole key,
+    /// so nested chunk keys (e.g. `arr/c/0/1/2`) are spread out too,
+    /// and a file's location does not depend on which metadata documents exist.
+    /// Metadata documents are not fanned out, so the hierarchy remains discoverable by other tools;
+    /// but other tools will not find chunks.
+    /// The configuration is recorded in a marker file so that [FileSystemStore::open] respects it.
+    /// `levels` must be between 1 and 4.
+    pub fn create_fanout(path: PathBuf, parents: bool, levels: u8) -> io::Result<Self> {
+        if levels == 0 || levels > MAX_FANOUT_LEVELS {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Fan-out levels must be between 1 and 4",
+            ));
         }
+        let mut store = Self::create(path, parents)?;
+        fs::write(
+            store.base_path.join(FANOUT_MARKER),
+            serde_json::to_vec(&serde_json::json!({ "levels": levels }))?,
+        )?;
+        store.fanout_levels = levels;
+        Ok(store)
+    }
+
+    /// Number of levels of hashed subdirectories files are fanned out into; 0 if not fanned out.
+    pub fn fanout_levels(&self) -> u8 {
+        self.fanout_levels
+    }
+
+    fn with_marker(mut self) -> io::Result<Self> {
+        let contents = match fs::read(self.base_path.join(FANOUT_MARKER)) {
+            Ok(c) => c,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(self),
+            Err(e) => return Err(e),
+        };
+        let levels = serde_json::from_slice::<serde_json::Value>(&contents)?
+            .get("levels")
+            .and_then(|l| l.as_u64())
+            .filter(|l| *l <= MAX_FANOUT_LEVELS as u64)
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Invalid fan-out marker"))?;
+        self.fanout_levels = levels as u8;
+        Ok(self)
     }
 
     /// Canonicalizes path and checks that it is an extant directory.
@@ -47,9 +222,7 @@ impl FileSystemStore {
         } else {
             fs::create_dir(&path)?;
         }
-        Ok(Self {
-            base_path: path.canonicalize()?,
-        })
+        Ok(Self::new_unchecked(path.canonicalize()?))
     }
 
     /// Canonicalizes path and, if the directory does not exist, creates it.
@@ -58,21 +231,19 @@ impl FileSystemStore {
         if base_path.exists() {
             let meta = fs::metadata(&base_path)?;
             if meta.is_file() {
-                return Err(io::Error::new(
-                    ErrorKind::Other,
-                    "Path exists, but it is a file",
-                ));
+                return Err(io::Error::other("Path exists, but it is a file"));
             }
-            return Ok(Self { base_path });
+            return Self::new_unchecked(base_path).with_marker();
         } else if parents {
             fs::create_dir_all(path)?;
         } else {
             fs::create_dir(path)?;
         }
-        Ok(Self { base_path })
+        Ok(Self::new_unchecked(base_path))
     }
 
-    fn get_path(&self, key: &NodeKey) -> PathBuf {
+    /// Path of the directory representing a key prefix.
+    fn get_dir_path(&self, key: &NodeKey) -> PathBuf {
         let mut p = self.base_path.clone();
         for k in key.as_slice().iter() {
             p.push(k.as_ref());
@@ -80,6 +251,100 @@ impl FileSystemStore {
         p
     }
 
+    /// Path of the file representing a key, which may be within hashed bucket directories.
+    ///
+    /// Keys other than metadata documents are hashed in their entirety,
+    /// and stored at their usual path within the bucket directories under the store root.
+    fn get_path(&self, key: &NodeKey) -> PathBuf {
+        if !self.is_bucketed(key) {
+            return self.get_dir_path(key);
+        }
+        let encoded = key
+            .as_slice()
+            .iter()
+            .map(|n| n.as_ref())
+            .collect::<Vec<_>>()
+            .join("/");
+        let hash = crc32c::crc32c(encoded.as_bytes()).to_be_bytes();
+
+        let mut p = self.base_path.clone();
+        for b in hash.iter().take(self.fanout_levels as usize) {
+            p.push(format!("{BUCKET_PREFIX}{b:02x}"));
+        }
+        for n in key.as_slice() {
+            p.push(n.as_ref());
+        }
+        p
+    }
+
+    /// Whether the key's file is stored within the hashed buckets.
+    fn is_bucketed(&self, key: &NodeKey) -> bool {
+        self.fanout_levels > 0
+            && key
+                .as_slice()
+                .last()
+                .is_some_and(|n| n.as_ref() != METADATA_NAME)
+    }
+
+    /// Innermost hashed bucket directories under `dir`, `depth` levels down.
+    fn bucket_dirs(&self, dir: &Path, depth: u8) -> io::Result<Vec<PathBuf>> {
+        let mut out = Vec::default();
+        let entries = match fs::read_dir(dir) {
+            Ok(e) => e,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(out),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir()
+                || !entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(BUCKET_PREFIX)
+            {
+                continue;
+            }
+            if depth <= 1 {
+                out.push(entry.path());
+            } else {
+                out.extend(self.bucket_dirs(&entry.path(), depth - 1)?);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Directories within the hashed buckets which hold files directly below the given prefix.
+    fn bucketed_dirs(&self, prefix: &NodeKey) -> io::Result<Vec<PathBuf>> {
+        if self.fanout_levels == 0 {
+            return Ok(Vec::default());
+        }
+        let mut out = Vec::default();
+        for mut dir in self.bucket_dirs(&self.base_path, self.fanout_levels)? {
+            for n in prefix.as_slice() {
+                dir.push(n.as_ref());
+            }
+            out.push(dir);
+        }
+        Ok(out)
+    }
+
+    /// Remove empty directories from `dir` upwards, stopping before `stop` or the first non-empty directory.
+    fn remove_empty_dirs(&self, dir: &Path, stop: &Path) -> io::Result<()> {
+        let mut dir = dir.to_path_buf();
+        while dir.starts_with(stop) && dir != stop {
+            match fs::remove_dir(&dir) {
+                Ok(()) => (),
+                Err(e) if e.kind() == ErrorKind::NotFound => (),
+                // not empty, or in use
+                Err(_) => break,
+            }
+            if !dir.pop() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     fn file_reader(&self, key: &NodeKey) -> io::Result<Option<File>> {
         let target = self.get_path(key);
         match File::open(target) {
@@ -91,6 +356,189 @@ impl FileSystemStore {
             Err(e) => Err(e),
         }
     }
+
+    /// Read ranges through handles checked out of the cache,
+    /// each of which is used for every range in its file before being returned.
+    fn cached_partial_values(
+        &self,
+        cache: &HandleCache,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+        let mut checked_out: HashMap<PathBuf, Option<CachedHandle>> = HashMap::default();
+        let mut out = Vec::with_capacity(key_ranges.len());
+
+        let result = key_ranges.iter().try_for_each(|(key, range)| {
+            let path = self.get_path(key);
+            let handle = match checked_out.get_mut(&path) {
+                Some(h) => h,
+                None => {
+                    let h = match cache.take(&path) {
+                        Some(h) => Some(h),
+                        None => match File::open(&path) {
+                            Ok(file) => {
+                                let now = Instant::now();
+                                Some(CachedHandle {
+                                    file,
+                                    opened: now,
+                                    last_used: now,
+                                })
+                            }
+                            Err(e) if e.kind() == ErrorKind::NotFound => None,
+                            Err(e) => return Err(e),
+                        },
+                    };
+                    checked_out.entry(path).or_insert(h)
+                }
+            };
+            let Some(h) = handle else {
+                out.push(None);
+                return Ok(());
+            };
+
+            // the lock is held only while reading, so that cached handles do not block writers
+            h.file.lock_shared()?;
+            let read = read_range(&mut h.file, *range);
+            h.file.unlock()?;
+            let buf = read?;
+            out.push(Some(Box::new(Cursor::new(buf)) as Box<dyn Read>));
+            Ok(())
+        });
+
+        if result.is_ok() {
+            for (path, h) in checked_out {
+                if let Some(h) = h {
+                    cache.put(path, h);
+                }
+            }
+        }
+        result.map(|_| out)
+    }
+}
+
+/// Read the requested range of a file into memory.
+fn read_range(f: &mut File, range: RangeRequest) -> io::Result<Vec<u8>> {
+    let mut r = SubReader::new(f, range)?;
+    let mut buf = Vec::with_capacity(r.nbytes as usize);
+    r.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// `file://` URI of an absolute path, percent-encoding everything except unreserved characters and separators.
+fn file_uri(path: &str) -> String {
+    let mut out = String::from("file://");
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// A file being written beside its destination, which is removed on drop unless it has been moved into place.
+struct TempFile {
+    path: PathBuf,
+    file: File,
+    persisted: bool,
+}
+
+impl TempFile {
+    fn beside(dest: &Path) -> io::Result<Self> {
+        let name = dest
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let path = dest.with_file_name(format!(
+            "{BUCKET_PREFIX}tmp-{}-{}-{name}",
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let open = || {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+        };
+        let file = match open() {
+            // the parent may have been removed as empty by a concurrent erase
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                open()?
+            }
+            result => result?,
+        };
+        Ok(Self {
+            path,
+            file,
+            persisted: false,
+        })
+    }
+
+    /// Atomically replace the destination.
+    ///
+    /// The contents are flushed to disk first, so that a crash cannot leave a partial file in place.
+    fn persist(mut self, dest: &Path) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.path, dest)?;
+        self.persisted = true;
+        Ok(())
+    }
+
+    /// Atomically create the destination, returning `false` if it already exists.
+    fn persist_new(self, dest: &Path) -> io::Result<bool> {
+        self.file.sync_all()?;
+        // hard linking fails if the destination exists, unlike renaming;
+        // the temporary name is removed on drop
+        match fs::hard_link(&self.path, dest) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Open and exclusively lock the file at the given path, if it exists.
+///
+/// Writers hold this lock on the file they replace or remove,
+/// so that they cannot interleave with [WriteableStore::set_if_match]'s version check and write.
+fn lock_existing(path: &Path) -> io::Result<Option<File>> {
+    match File::open(path) {
+        Ok(f) => {
+            f.lock_exclusive()?;
+            Ok(Some(f))
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Version a file by its modification time and length,
+/// and on unix its inode, which changes whenever the file is replaced
+/// (so that writes within the modification time's resolution are still distinguished).
+fn file_version(meta: &Metadata) -> io::Result<VersionToken> {
+    let mtime = meta
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_err(io::Error::other)?;
+    #[cfg(unix)]
+    let token = {
+        use std::os::unix::fs::MetadataExt;
+        format!("{}-{}-{}", mtime.as_nanos(), meta.len(), meta.ino())
+    };
+    #[cfg(not(unix))]
+    let token = format!("{}-{}", mtime.as_nanos(), meta.len());
+    Ok(VersionToken::new(token))
 }
 
 impl ReadableStore for FileSystemStore {
@@ -101,10 +549,38 @@ impl ReadableStore for FileSystemStore {
         self.file_reader(key)
     }
 
+    /// A `file://` URI of the key's (absolute) path, including any fan-out directories.
+    ///
+    /// `Ok(None)` if the path is not valid UTF-8.
+    fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        Ok(self.get_path(key).to_str().map(file_uri))
+    }
+
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        match fs::metadata(self.get_path(key)) {
+            Ok(m) => Ok(Some(file_version(&m)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<File>>> {
+        if let Some(f) = self.file_reader(key)? {
+            // file is locked, so this is consistent with the contents
+            let version = file_version(&f.metadata()?)?;
+            Ok(Some((f, Some(version))))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn get_partial_values(
         &self,
         key_ranges: &[(NodeKey, RangeRequest)],
     ) -> Result<Vec<Option<Box<dyn Read>>>, std::io::Error> {
+        if let Some(cache) = &self.handle_cache {
+            return self.cached_partial_values(cache, key_ranges);
+        }
         let mut out = Vec::with_capacity(key_ranges.len());
 
         for (key, range) in key_ranges.iter() {
@@ -137,11 +613,34 @@ impl ListableStore for FileSystemStore {
         let mut keys = Vec::default();
         let mut prefixes = Vec::default();
 
-        let target = self.get_path(prefix);
-        for maybe_file in fs::read_dir(target)? {
-            let file = maybe_file?;
+        let target = self.get_dir_path(prefix);
+        let mut entries = Vec::default();
+        match fs::read_dir(target) {
+            Ok(dir_entries) => {
+                for maybe_file in dir_entries {
+                    let file = maybe_file?;
+                    entries.push((file.file_name(), fs::metadata(file.path())?.is_file()));
+                }
+            }
+            // prefixes below a node may only exist within its buckets
+            Err(e) if e.kind() == ErrorKind::NotFound && self.fanout_levels > 0 => (),
+            Err(e) => return Err(e),
+        }
+        for dir in self.bucketed_dirs(prefix)? {
+            let Ok(dir_entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for maybe_file in dir_entries {
+                let file = maybe_file?;
+                entries.push((file.file_name(), fs::metadata(file.path())?.is_file()));
+            }
+        }
+        // directories may be repeated within several buckets
+        entries.sort();
+        entries.dedup();
+
+        for (fname, is_file) in entries {
             let mut key = prefix.clone();
-            let fname = file.file_name();
             let name = if let Some(n) = fname.to_str() {
                 n
             } else {
@@ -152,12 +651,17 @@ impl ListableStore for FileSystemStore {
                 Ok(n) => {
                     key.push(n);
                 }
-                Err(_) => continue,
+                // the store's own files, e.g. fan-out buckets and temporary files
+                Err(InvalidNodeName::ReservedPrefix) => continue,
+                Err(e) => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Invalid node name {name:?}: {e}"),
+                    ))
+                }
             };
 
-            let meta = fs::metadata(file.path())?;
-
-            if meta.is_file() {
+            if is_file {
                 keys.push(key);
             } else {
                 prefixes.push(key)
@@ -183,46 +687,95 @@ impl WriteableStore for FileSystemStore {
             fs::create_dir_all(parent)?;
         }
 
-        let mut f = fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .create(true)
-            .open(path)?;
-        f.lock_exclusive()?;
-        value(&mut f)
+        let mut tmp = TempFile::beside(&path)?;
+        value(&mut tmp.file)?;
+        let _lock = lock_existing(&path)?;
+        tmp.persist(&path)?;
+        self.invalidate_handles(&path);
+        Ok(())
+    }
+
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        let path = self.get_path(key);
+
+        let written = if let Some(token) = expected {
+            // writers which replace or remove the file hold the lock on the file they replace,
+            // so the version must be that of the file now at the path
+            let Some(_lock) = lock_existing(&path)? else {
+                return Ok(false);
+            };
+            match fs::metadata(&path) {
+                Ok(m) if &file_version(&m)? == token => (),
+                Ok(_) => return Ok(false),
+                Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+                Err(e) => return Err(e),
+            }
+            let mut tmp = TempFile::beside(&path)?;
+            value(&mut tmp.file)?;
+            tmp.persist(&path)?;
+            true
+        } else {
+            if !key.is_root() {
+                let parent = path.parent().expect("Key is filesystem root");
+                fs::create_dir_all(parent)?;
+            }
+            if path.exists() {
+                return Ok(false);
+            }
+            let mut tmp = TempFile::beside(&path)?;
+            value(&mut tmp.file)?;
+            tmp.persist_new(&path)?
+        };
+        self.invalidate_handles(&path);
+        Ok(written)
     }
 
     fn erase(&self, key: &NodeKey) -> io::Result<bool> {
         let path = self.get_path(key);
+        self.invalidate_handles(&path);
 
-        match File::open(path.clone()) {
-            Ok(f) => {
-                f.lock_exclusive()?;
+        match lock_existing(&path)? {
+            Some(_lock) => {
                 fs::remove_file(&path)?;
+                match path.parent() {
+                    Some(parent) if self.is_bucketed(key) => {
+                        self.remove_empty_dirs(parent, &self.base_path)?
+                    }
+                    _ => (),
+                }
                 Ok(false)
             }
-            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
-            Err(e) => Err(e),
+            None => Ok(false),
         }
         // is this sufficient to guarantee that return is correct?
         // todo: what if it's a directory?
     }
 
     fn erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
-        let path = self.get_path(key_prefix);
+        let path = self.get_dir_path(key_prefix);
+        self.invalidate_handles(&path);
 
-        if path.exists() {
-            for entry in WalkDir::new(&path).contents_first(true).follow_links(true) {
-                // todo: follow_links(true) allows for recursion, not good
-                let entry = entry?;
-
-                if entry.file_type().is_dir() {
-                    fs::remove_dir(entry.path())?;
-                } else {
-                    let file = File::open(entry.path())?;
-                    file.lock_exclusive()?;
-                    fs::remove_file(entry.path())?;
-                }
+        // counterparts of the prefix within the buckets, which are all below the root
+        let bucketed = if key_prefix.is_root() {
+            Vec::default()
+        } else {
+            self.bucketed_dirs(key_prefix)?
+        };
+
+        remove_tree(&path)?;
+        for dir in bucketed {
+            self.invalidate_handles(&dir);
+            remove_tree(&dir)?;
+            if let Some(parent) = dir.parent() {
+                self.remove_empty_dirs(parent, &self.base_path)?;
             }
         }
 
@@ -230,6 +783,26 @@ impl WriteableStore for FileSystemStore {
     }
 }
 
+/// Remove a directory and everything below it, if it exists,
+/// locking each file before removing it.
+fn remove_tree(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        for entry in WalkDir::new(path).contents_first(true).follow_links(true) {
+            // todo: follow_links(true) allows for recursion, not good
+            let entry = entry?;
+
+            if entry.file_type().is_dir() {
+                fs::remove_dir(entry.path())?;
+            } else {
+                let file = File::open(entry.path())?;
+                file.lock_exclusive()?;
+                fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    Ok(())
+}
+
 struct SubReader<R: Read + Seek> {
     offset: u64,
     nbytes: u64,
@@ -247,16 +820,16 @@ fn stream_len<S: Seek>(s: &mut S) -> Result<u64, io::Error> {
 }
 
 impl<R: Read + Seek> SubReader<R> {
+    /// Positions the reader at the start of the range, which is clamped to the stream.
     pub fn new(mut reader: R, range: RangeRequest) -> std::io::Result<Self> {
-        let orig_len = Some(stream_len(&mut reader)? as usize);
-
-        let start = range.start(orig_len).unwrap() as u64;
-        let end = range.end(orig_len).unwrap() as u64;
-        // todo: handle before-start case
+        let len = stream_len(&mut reader)? as usize;
+        let start = range.start(Some(len)).unwrap().min(len);
+        let end = range.end(Some(len)).unwrap_or(len).clamp(start, len);
+        reader.seek(SeekFrom::Start(start as u64))?;
 
         Ok(Self {
-            offset: start,
-            nbytes: end - start,
+            offset: start as u64,
+            nbytes: (end - start) as u64,
             reader,
         })
     }
@@ -269,7 +842,7 @@ impl<R: Read + Seek> SubReader<R> {
 impl<R: Read + Seek> Read for SubReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         let pos = self.stream_position()?;
-        let max_len = (self.nbytes - pos) as usize;
+        let max_len = self.nbytes.saturating_sub(pos) as usize;
         if buf.len() > max_len {
             self.reader.read(&mut buf[..max_len])
         } else {
@@ -278,27 +851,376 @@ impl<R: Read + Seek> Read for SubReader<R> {
     }
 }
 
+/// Positions are relative to the start of the range.
 impl<R: Read + Seek> Seek for SubReader<R> {
     fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
-        let new_pos = match pos {
-            SeekFrom::Start(o) => self.reader.seek(SeekFrom::Start(self.offset + o))?,
-            SeekFrom::End(o) => self
-                .reader
-                .seek(SeekFrom::Start((self.end_offset() as i64 + o) as u64))?,
-            SeekFrom::Current(o) => {
-                let orig_pos = self.stream_position()?;
-                let new_pos = self.reader.seek(SeekFrom::Current(o))?;
-                if new_pos < self.offset {
-                    let out = Err(std::io::Error::new(
-                        ErrorKind::InvalidInput,
-                        "Seeked before start of SubReader",
-                    ));
-                    self.reader.seek(SeekFrom::Start(orig_pos))?;
-                    out?
-                }
-                new_pos
-            }
+        let target = match pos {
+            SeekFrom::Start(o) => Some(self.offset + o),
+            SeekFrom::End(o) => self.end_offset().checked_add_signed(o),
+            SeekFrom::Current(o) => self.reader.stream_position()?.checked_add_signed(o),
+        };
+        match target {
+            Some(t) if t >= self.offset => Ok(self.reader.seek(SeekFrom::Start(t))? - self.offset),
+            _ => Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Seeked before start of SubReader",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn set_if_match() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create(tmp.path().join("root.zarr"), true).unwrap();
+        let key: NodeKey = "a/b".parse().unwrap();
+
+        assert!(store
+            .set_if_match(&key, None, |w| w.write_all(b"first"))
+            .unwrap());
+        assert!(!store
+            .set_if_match(&key, None, |w| w.write_all(b"second"))
+            .unwrap());
+
+        let (_, version) = store.get_versioned(&key).unwrap().unwrap();
+        let version = version.unwrap();
+        assert_eq!(store.version(&key).unwrap().as_ref(), Some(&version));
+
+        // different length so that the version changes regardless of mtime resolution
+        assert!(store
+            .set_if_match(&key, Some(&version), |w| w.write_all(b"second!"))
+            .unwrap());
+        assert!(!store
+            .set_if_match(&key, Some(&version), |w| w.write_all(b"third"))
+            .unwrap());
+
+        let mut buf = Vec::default();
+        store
+            .get(&key)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"second!");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn version_same_length_writes() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create(tmp.path().join("root.zarr"), true).unwrap();
+        let key: NodeKey = "a/b".parse().unwrap();
+
+        store.set(&key, |w| w.write_all(b"first")).unwrap();
+        let first = store.version(&key).unwrap().unwrap();
+        store.set(&key, |w| w.write_all(b"other")).unwrap();
+        let second = store.version(&key).unwrap().unwrap();
+        assert_ne!(first, second);
+
+        assert!(!store
+            .set_if_match(&key, Some(&first), |w| w.write_all(b"third"))
+            .unwrap());
+        assert!(store
+            .set_if_match(&key, Some(&second), |w| w.write_all(b"third"))
+            .unwrap());
+    }
+
+    #[test]
+    fn set_waits_for_lock() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create(tmp.path().join("root.zarr"), true).unwrap();
+        let key: NodeKey = "a/b".parse().unwrap();
+        store.set(&key, |w| w.write_all(b"first")).unwrap();
+
+        // as held by a concurrent set_if_match between its version check and write
+        let lock = lock_existing(&store.get_path(&key)).unwrap().unwrap();
+        std::thread::scope(|s| {
+            let handle = s.spawn(|| store.set(&key, |w| w.write_all(b"second")));
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            assert!(!handle.is_finished());
+            let mut buf = Vec::default();
+            File::open(store.get_path(&key))
+                .unwrap()
+                .read_to_end(&mut buf)
+                .unwrap();
+            assert_eq!(buf, b"first");
+            drop(lock);
+            handle.join().unwrap().unwrap();
+        });
+
+        let mut buf = Vec::default();
+        store
+            .get(&key)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"second");
+    }
+
+    #[test]
+    fn failed_writes_leave_no_trace() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create(tmp.path().join("root.zarr"), true).unwrap();
+        let old: NodeKey = "a/old".parse().unwrap();
+        let new: NodeKey = "a/new".parse().unwrap();
+        store.set(&old, |w| w.write_all(b"intact")).unwrap();
+        let version = store.version(&old).unwrap();
+
+        let failing = |w: &mut File| {
+            w.write_all(b"partial")?;
+            Err(io::Error::other("codec failure"))
+        };
+        let panicking = |w: &mut File| -> io::Result<()> {
+            w.write_all(b"partial")?;
+            panic!("codec failure")
+        };
+        for key in [&old, &new] {
+            assert!(store.set(key, failing).is_err());
+            assert!(std::panic::catch_unwind(|| store.set(key, panicking)).is_err());
+        }
+        assert!(store.set_if_match(&new, None, failing).is_err());
+        assert!(std::panic::catch_unwind(|| store.set_if_match(&new, None, panicking)).is_err());
+        assert!(std::panic::catch_unwind(|| {
+            store.set_if_match(&old, version.as_ref(), panicking)
+        })
+        .is_err());
+
+        let mut buf = Vec::default();
+        store
+            .get(&old)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"intact");
+        assert!(!store.has_key(&new).unwrap());
+        // no temporary files are left behind
+        let dir: Vec<_> = fs::read_dir(tmp.path().join("root.zarr/a"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(dir, vec![std::ffi::OsString::from("old")]);
+    }
+
+    #[test]
+    fn partial_values() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create(tmp.path().join("root.zarr"), true).unwrap();
+        let key: NodeKey = "a/0".parse().unwrap();
+        store.set(&key, |w| w.write_all(b"abcdefgh")).unwrap();
+
+        let ranges = [
+            RangeRequest::new_range(2, Some(3)),
+            RangeRequest::new_range(5, None),
+            RangeRequest::Suffix(2),
+            RangeRequest::new_range(6, Some(10)),
+        ];
+        let key_ranges: Vec<_> = ranges.iter().map(|r| (key.clone(), *r)).collect();
+        let out: Vec<Vec<u8>> = store
+            .get_partial_values(&key_ranges)
+            .unwrap()
+            .into_iter()
+            .map(|r| {
+                let mut buf = Vec::default();
+                r.unwrap().read_to_end(&mut buf).unwrap();
+                buf
+            })
+            .collect();
+        assert_eq!(out, vec![&b"cde"[..], b"fgh", b"gh", b"gh"]);
+    }
+
+    #[test]
+    fn handle_cache() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create(tmp.path().join("root.zarr"), true)
+            .unwrap()
+            .with_handle_cache(2, Duration::from_secs(60));
+        let keys: Vec<NodeKey> = ["a/0", "a/1", "a/2"]
+            .iter()
+            .map(|k| k.parse().unwrap())
+            .collect();
+        for k in keys.iter() {
+            store
+                .set(k, |w| w.write_all(k.encode().as_bytes()))
+                .unwrap();
+        }
+
+        let read_all = |krs: &[(NodeKey, RangeRequest)]| -> Vec<Option<Vec<u8>>> {
+            store
+                .get_partial_values(krs)
+                .unwrap()
+                .into_iter()
+                .map(|r| {
+                    r.map(|mut r| {
+                        let mut buf = Vec::default();
+                        r.read_to_end(&mut buf).unwrap();
+                        buf
+                    })
+                })
+                .collect()
+        };
+
+        let out = read_all(&[
+            (keys[0].clone(), RangeRequest::new_range(0, Some(1))),
+            (keys[0].clone(), RangeRequest::Suffix(1)),
+            (keys[1].clone(), RangeRequest::new_range(2, None)),
+            ("a/missing".parse().unwrap(), RangeRequest::Suffix(1)),
+        ]);
+        assert_eq!(
+            out,
+            vec![
+                Some(b"a".to_vec()),
+                Some(b"0".to_vec()),
+                Some(b"1".to_vec()),
+                None
+            ]
+        );
+        assert_eq!(store.cached_handles(), 2);
+
+        // least recently used handle is evicted
+        read_all(&[(keys[2].clone(), RangeRequest::Suffix(1))]);
+        assert_eq!(store.cached_handles(), 2);
+
+        // writes drop the handle and are seen by subsequent reads
+        store.set(&keys[2], |w| w.write_all(b"new")).unwrap();
+        assert_eq!(store.cached_handles(), 1);
+        let out = read_all(&[(keys[2].clone(), RangeRequest::new_range(0, None))]);
+        assert_eq!(out, vec![Some(b"new".to_vec())]);
+
+        store.erase_prefix(&"a".parse().unwrap()).unwrap();
+        assert_eq!(store.cached_handles(), 0);
+        assert_eq!(
+            read_all(&[(keys[0].clone(), RangeRequest::Suffix(1))]),
+            vec![None]
+        );
+
+        store.clear_handle_cache();
+    }
+
+    #[test]
+    fn fanout() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let path = tmp.path().join("root.zarr");
+        let store = FileSystemStore::create_fanout(path.clone(), true, 2).unwrap();
+
+        let keys: Vec<NodeKey> = ["a/zarr.json", "a/0.0", "a/0.1", "a/c/1"]
+            .iter()
+            .map(|k| k.parse().unwrap())
+            .collect();
+        for k in keys.iter() {
+            store
+                .set(k, |w| w.write_all(k.encode().as_bytes()))
+                .unwrap();
+        }
+
+        assert!(path.join("a").join("zarr.json").is_file());
+        assert!(!path.join("a").join("0.0").exists());
+
+        // reopening picks up the marker
+        let store = FileSystemStore::open(path.clone()).unwrap();
+        assert_eq!(store.fanout_levels(), 2);
+
+        let mut buf = Vec::default();
+        store
+            .get(&keys[1])
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"a/0.0");
+
+        let (mut listed, prefixes) = store.list_dir(&"a".parse().unwrap()).unwrap();
+        listed.sort_by_key(|k| k.encode());
+        assert_eq!(listed.len(), 3);
+        assert_eq!(listed[0], keys[1]);
+        assert_eq!(prefixes, vec!["a/c".parse().unwrap()]);
+
+        let mut all = store.list().unwrap();
+        all.sort_by_key(|k| k.encode());
+        let mut expected = keys.clone();
+        expected.sort_by_key(|k| k.encode());
+        assert_eq!(all, expected);
+        // the whole chunk key is hashed below the node, so nested keys are fanned out too
+        assert!(!path.join("a").join("c").exists());
+        let (listed, prefixes) = store.list_dir(&"a/c".parse().unwrap()).unwrap();
+        assert_eq!(listed, vec![keys[3].clone()]);
+        assert!(prefixes.is_empty());
+
+        // empty buckets are removed
+        let no_empty_dirs = |p: &Path| {
+            WalkDir::new(p)
+                .into_iter()
+                .map(|e| e.unwrap())
+                .filter(|e| e.file_type().is_dir())
+                .all(|e| fs::read_dir(e.path()).unwrap().next().is_some())
+        };
+        let bucket = store.get_path(&keys[3]);
+        store.erase(&keys[3]).unwrap();
+        assert!(!bucket.parent().unwrap().exists());
+        assert!(no_empty_dirs(&path));
+        assert!(store.list_dir(&"a".parse().unwrap()).unwrap().1.is_empty());
+
+        store.set(&keys[3], |w| w.write_all(b"again")).unwrap();
+        store.erase_prefix(&"a/c".parse().unwrap()).unwrap();
+        assert!(no_empty_dirs(&path));
+        assert!(store.get(&keys[3]).unwrap().is_none());
+        assert!(store.get(&keys[1]).unwrap().is_some());
+    }
+
+    #[test]
+    fn fanout_chunks_before_metadata() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let store = FileSystemStore::create_fanout(tmp.path().join("root.zarr"), true, 2).unwrap();
+        let read = |k: &NodeKey| {
+            let mut buf = Vec::default();
+            store.get(k).unwrap()?.read_to_end(&mut buf).unwrap();
+            Some(buf)
         };
-        Ok(new_pos)
+
+        let chunk: NodeKey = "arr/c/0/1".parse().unwrap();
+        let meta: NodeKey = "arr/zarr.json".parse().unwrap();
+        store.set(&chunk, |w| w.write_all(b"chunk")).unwrap();
+        let path = store.get_path(&chunk);
+
+        // writing, then removing, the node's metadata does not move its chunks
+        store.set(&meta, |w| w.write_all(b"{}")).unwrap();
+        assert_eq!(store.get_path(&chunk), path);
+        assert_eq!(read(&chunk).unwrap(), b"chunk");
+        store.erase(&meta).unwrap();
+        assert_eq!(read(&chunk).unwrap(), b"chunk");
+
+        store.set(&meta, |w| w.write_all(b"{}")).unwrap();
+        let (listed, _) = store.list_dir(&"arr/c/0".parse().unwrap()).unwrap();
+        assert_eq!(listed, vec![chunk.clone()]);
+        store.erase_prefix(&"arr".parse().unwrap()).unwrap();
+        assert!(read(&chunk).is_none());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn list_invalid_names() {
+        let tmp = tempdir::TempDir::new("zarr3-test").unwrap();
+        let path = tmp.path().join("root.zarr");
+        let store = FileSystemStore::create(path.clone(), true).unwrap();
+        store
+            .set(&"a".parse().unwrap(), |w| w.write_all(b"a"))
+            .unwrap();
+
+        // the store's own reserved files are not listed
+        fs::write(path.join("__tmp"), b"").unwrap();
+        let (keys, _) = store.list_dir(&NodeKey::default()).unwrap();
+        assert_eq!(keys, vec!["a".parse().unwrap()]);
+
+        // other invalid names are an error rather than being hidden
+        fs::write(path.join("..."), b"").unwrap();
+        let err = store.list_dir(&NodeKey::default()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
     }
 }
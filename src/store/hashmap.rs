@@ -1,7 +1,7 @@
 use std::{
-    cell::RefCell,
     collections::HashMap,
     io::{self, Read},
+    sync::RwLock,
 };
 
 use bytes::{
@@ -11,13 +11,15 @@ use bytes::{
 
 use super::{
     list_dir_from_all_keys_ref, list_prefix_from_all_keys_ref, ListableStore, NodeKey,
-    ReadableStore, Store, WriteableStore,
+    ReadableStore, Store, VersionToken, Versioned, WriteableStore,
 };
 
+/// In-memory store, which can be shared between threads.
+#[derive(Default)]
 pub struct HashMapStore {
     // this locks whole map for read of single key
     // consider https://crates.io/crates/lockable
-    map: RefCell<HashMap<NodeKey, Bytes>>,
+    map: RwLock<HashMap<NodeKey, Bytes>>,
 }
 
 impl Store for HashMapStore {}
@@ -26,7 +28,7 @@ impl ReadableStore for HashMapStore {
     type Readable = Reader<Bytes>;
 
     fn get(&self, key: &NodeKey) -> Result<Option<Self::Readable>, std::io::Error> {
-        let map = self.map.borrow();
+        let map = self.map.read().unwrap();
 
         // not sure if this clone is expensive
         Ok(map.get(key).map(|b| b.clone().reader()))
@@ -36,7 +38,7 @@ impl ReadableStore for HashMapStore {
         &self,
         key_ranges: &[(NodeKey, crate::RangeRequest)],
     ) -> Result<Vec<Option<Box<dyn Read>>>, std::io::Error> {
-        let map = self.map.borrow();
+        let map = self.map.read().unwrap();
         let mut out = Vec::with_capacity(key_ranges.len());
         for (key, range) in key_ranges.iter() {
             let r = map.get(key).map(|v| {
@@ -49,24 +51,41 @@ impl ReadableStore for HashMapStore {
     }
 
     fn has_key(&self, key: &NodeKey) -> io::Result<bool> {
-        let map = self.map.borrow();
+        let map = self.map.read().unwrap();
         Ok(map.contains_key(key))
     }
+
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        let map = self.map.read().unwrap();
+        Ok(map.get(key).map(content_version))
+    }
+
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Self::Readable>>> {
+        let map = self.map.read().unwrap();
+        Ok(map
+            .get(key)
+            .map(|b| (b.clone().reader(), Some(content_version(b)))))
+    }
+}
+
+/// Values are versioned by their content, as there is no other metadata.
+fn content_version(b: &Bytes) -> VersionToken {
+    VersionToken::new(format!("{:08x}-{}", crc32c::crc32c(b), b.len()))
 }
 
 impl ListableStore for HashMapStore {
     fn list(&self) -> io::Result<Vec<NodeKey>> {
-        let map = self.map.borrow();
+        let map = self.map.read().unwrap();
         Ok(map.keys().cloned().collect::<Vec<_>>())
     }
 
     fn list_prefix(&self, prefix: &NodeKey) -> io::Result<Vec<NodeKey>> {
-        let map = self.map.borrow();
+        let map = self.map.read().unwrap();
         Ok(list_prefix_from_all_keys_ref(map.keys(), prefix))
     }
 
     fn list_dir(&self, prefix: &NodeKey) -> Result<(Vec<NodeKey>, Vec<NodeKey>), std::io::Error> {
-        let map = self.map.borrow();
+        let map = self.map.read().unwrap();
         Ok(list_dir_from_all_keys_ref(map.keys(), prefix))
     }
 }
@@ -78,23 +97,86 @@ impl WriteableStore for HashMapStore {
     where
         F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
     {
-        let mut map = self.map.borrow_mut();
-
         let mut w = BytesMut::new().writer();
         value(&mut w)?;
+        let mut map = self.map.write().unwrap();
         map.insert(key.clone(), w.into_inner().into());
         Ok(())
     }
 
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        // written before taking the lock, so that a panicking closure cannot poison it
+        let mut w = BytesMut::new().writer();
+        value(&mut w)?;
+
+        // hold the lock for the whole comparison and update so that nothing can intervene
+        let mut map = self.map.write().unwrap();
+        if map.get(key).map(content_version).as_ref() != expected {
+            return Ok(false);
+        }
+        map.insert(key.clone(), w.into_inner().into());
+        Ok(true)
+    }
+
     fn erase(&self, key: &NodeKey) -> Result<bool, io::Error> {
-        let mut map = self.map.borrow_mut();
+        let mut map = self.map.write().unwrap();
         map.remove(key);
         Ok(false)
     }
 
     fn erase_prefix(&self, key_prefix: &NodeKey) -> Result<bool, io::Error> {
-        let mut map = self.map.borrow_mut();
+        let mut map = self.map.write().unwrap();
         map.retain(|k, _v| !k.starts_with(key_prefix));
         Ok(false)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn set_if_match() {
+        let store = HashMapStore::default();
+        let key: NodeKey = "a/b".parse().unwrap();
+
+        assert!(store
+            .set_if_match(&key, None, |w| w.write_all(b"first"))
+            .unwrap());
+        // key now exists
+        assert!(!store
+            .set_if_match(&key, None, |w| w.write_all(b"second"))
+            .unwrap());
+
+        let (_, version) = store.get_versioned(&key).unwrap().unwrap();
+        let version = version.unwrap();
+        assert_eq!(store.version(&key).unwrap().as_ref(), Some(&version));
+
+        assert!(store
+            .set_if_match(&key, Some(&version), |w| w.write_all(b"second"))
+            .unwrap());
+        // stale version
+        assert!(!store
+            .set_if_match(&key, Some(&version), |w| w.write_all(b"third"))
+            .unwrap());
+
+        let mut buf = Vec::default();
+        store
+            .get(&key)
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, b"second");
+    }
+}
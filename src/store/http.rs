@@ -1,5 +1,6 @@
 use std::io::{ErrorKind, Read};
 use std::iter::repeat_with;
+use std::time::Duration;
 use std::{collections::HashMap, io};
 
 use bytes::{Buf, Bytes};
@@ -8,16 +9,106 @@ use itertools::Itertools;
 use reqwest::blocking::Response;
 use reqwest::Method;
 use reqwest::{
-    blocking::{Client, RequestBuilder},
+    blocking::{Client, ClientBuilder, RequestBuilder},
     IntoUrl, StatusCode, Url,
 };
 
-use super::{NodeKey, ReadableStore, Store};
+use super::{NodeKey, ReadableStore, Store, VersionToken, Versioned};
+
+/// Which HTTP versions the client may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HttpVersionPolicy {
+    /// HTTP/2 where the server offers it during the TLS handshake, otherwise HTTP/1.1.
+    #[default]
+    Negotiate,
+    /// Never use HTTP/2.
+    Http1Only,
+    /// Assume the server supports HTTP/2, including over plain-text connections.
+    Http2PriorKnowledge,
+}
+
+/// Connection settings for an [HttpStore] client.
+///
+/// Requests are blocking, but connections are kept alive and reused,
+/// so that reading many small chunks does not pay for a new connection each time.
+/// Over HTTP/2, requests from several threads sharing a store
+/// (e.g. [Array::par_read_region](crate::node::Array)) are multiplexed over one connection.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    /// Maximum number of idle connections kept open per host.
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle connection is kept open; [None] to keep it indefinitely.
+    pub pool_idle_timeout: Option<Duration>,
+    /// Interval of TCP keep-alive probes; [None] to disable.
+    pub tcp_keepalive: Option<Duration>,
+    /// Timeout for each request, including reading the body; [None] to disable.
+    pub timeout: Option<Duration>,
+    pub version: HttpVersionPolicy,
+    /// Use adaptive flow control on HTTP/2 connections,
+    /// which helps throughput of large responses on high-latency links.
+    pub http2_adaptive_window: bool,
+    pub retry: RetryPolicy,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            timeout: Some(Duration::from_secs(30)),
+            version: Default::default(),
+            http2_adaptive_window: true,
+            retry: Default::default(),
+        }
+    }
+}
+
+/// How an [HttpStore] retries requests which fail transiently:
+/// connection errors, timeouts, and `429` or `5xx` responses.
+///
+/// By default, requests are not retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry, doubling for each subsequent one.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            backoff: Duration::from_millis(100),
+        }
+    }
+}
 
+impl HttpOptions {
+    /// Apply these settings to a client builder,
+    /// e.g. one which has already been configured with default headers.
+    pub fn configure(&self, builder: ClientBuilder) -> ClientBuilder {
+        let mut builder = builder
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+            .timeout(self.timeout)
+            .http2_adaptive_window(self.http2_adaptive_window);
+        match self.version {
+            HttpVersionPolicy::Negotiate => (),
+            HttpVersionPolicy::Http1Only => builder = builder.http1_only(),
+            HttpVersionPolicy::Http2PriorKnowledge => builder = builder.http2_prior_knowledge(),
+        }
+        builder
+    }
+}
+
+/// Read-only store backed by an HTTP server, which can be shared between threads.
 pub struct HttpStore {
     client: Client,
     base_url: Url,
     basic_auth: Option<(String, String)>,
+    retry: RetryPolicy,
 }
 
 impl HttpStore {
@@ -33,9 +124,49 @@ impl HttpStore {
             client,
             base_url: base_url.into_url()?,
             basic_auth,
+            retry: Default::default(),
         })
     }
 
+    /// As [HttpStore::new], constructing a client with the given connection settings.
+    pub fn with_options<U: IntoUrl>(
+        base_url: U,
+        basic_auth: Option<(String, String)>,
+        options: &HttpOptions,
+    ) -> reqwest::Result<Self> {
+        let client = options.configure(Client::builder()).build()?;
+        Ok(Self::new(client, base_url, basic_auth)?.with_retry(options.retry))
+    }
+
+    /// Retry transiently-failing requests according to the given policy.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Send the request, retrying transient failures according to the store's [RetryPolicy].
+    ///
+    /// Error statuses on the final response (including once retries are exhausted) are returned as `Err`.
+    fn send(&self, builder: RequestBuilder) -> reqwest::Result<Response> {
+        let mut delay = self.retry.backoff;
+        for _ in 0..self.retry.max_retries {
+            // requests with streaming bodies cannot be repeated
+            let Some(attempt) = builder.try_clone() else {
+                break;
+            };
+            match attempt.send() {
+                Err(e) if e.is_connect() || e.is_timeout() => (),
+                Ok(r)
+                    if r.status() == StatusCode::TOO_MANY_REQUESTS
+                        || r.status().is_server_error() => {}
+                result => return result.and_then(Response::error_for_status),
+            }
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+        builder.send().and_then(Response::error_for_status)
+    }
+
     fn make_request_builder(
         &self,
         method: Method,
@@ -61,18 +192,37 @@ impl ReadableStore for HttpStore {
             .make_request_builder(Method::GET, key)
             .map_err(|_e| io::Error::new(ErrorKind::InvalidInput, "Could not create URL"))?;
 
-        match builder.send() {
-            Ok(r) => Ok(Some(r)),
-            Err(e) => {
-                if e.status().unwrap() == StatusCode::NOT_FOUND {
-                    Ok(None)
-                } else {
-                    Err(io::Error::new(ErrorKind::Other, e))
-                }
-            }
+        map_response_err(self.send(builder))
+    }
+
+    /// The key's URL, relative to the store's base URL.
+    fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        let url = self
+            .base_url
+            .join(&key.encode())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        Ok(Some(url.into()))
+    }
+
+    /// Uses the `ETag` header from a `HEAD` request, if the server provides one.
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        let builder = self
+            .make_request_builder(Method::HEAD, key)
+            .map_err(|_e| io::Error::new(ErrorKind::InvalidInput, "Could not create URL"))?;
+
+        match map_response_err(self.send(builder))? {
+            Some(r) if r.status().is_success() => Ok(etag(&r)),
+            _ => Ok(None),
         }
     }
 
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Self::Readable>>> {
+        Ok(self.get(key)?.map(|r| {
+            let version = etag(&r);
+            (r, version)
+        }))
+    }
+
     fn get_partial_values(
         &self,
         key_ranges: &[(NodeKey, crate::RangeRequest)],
@@ -96,7 +246,7 @@ impl ReadableStore for HttpStore {
                 .map_err(|_e| io::Error::new(ErrorKind::InvalidInput, "Could not create URL"))?
                 .header("range", &req);
 
-            if let Some(r) = map_response_err(builder.send())? {
+            if let Some(r) = map_response_err(self.send(builder))? {
                 let status = r.status();
 
                 // need content type first as .bytes consumes header
@@ -151,6 +301,14 @@ impl ReadableStore for HttpStore {
     }
 }
 
+fn etag(response: &Response) -> Option<VersionToken> {
+    response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|h| h.to_str().ok())
+        .map(|s| VersionToken::new(s.to_owned()))
+}
+
 fn get_boundary<'a>(content_type: &'a str) -> Option<&'a str> {
     let mut cti = content_type.split(';').map(|s| s.trim());
     let t = cti.next()?;
@@ -165,15 +323,24 @@ fn get_boundary<'a>(content_type: &'a str) -> Option<&'a str> {
     bi.next()
 }
 
+/// `Ok(None)` for `404 Not Found`; other error statuses, and errors without a status
+/// (e.g. connection failures), are `Err`.
 fn map_response_err(response: reqwest::Result<Response>) -> io::Result<Option<Response>> {
     match response {
         Ok(r) => Ok(Some(r)),
         Err(e) => {
-            if e.status().unwrap() == StatusCode::NOT_FOUND {
-                Ok(None)
-            } else {
-                Err(io::Error::new(ErrorKind::Other, e))
-            }
+            let kind = match e.status() {
+                Some(StatusCode::NOT_FOUND) => return Ok(None),
+                Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                    ErrorKind::PermissionDenied
+                }
+                Some(StatusCode::BAD_REQUEST | StatusCode::RANGE_NOT_SATISFIABLE) => {
+                    ErrorKind::InvalidInput
+                }
+                _ if e.is_timeout() => ErrorKind::TimedOut,
+                _ => ErrorKind::Other,
+            };
+            Err(io::Error::new(kind, e))
         }
     }
 }
@@ -238,3 +405,20 @@ fn split_multipart_bytes(content: Bytes, boundary: &str) -> Result<Vec<Bytes>, &
 
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_with_options() {
+        let options = HttpOptions {
+            version: HttpVersionPolicy::Http1Only,
+            pool_idle_timeout: None,
+            ..Default::default()
+        };
+        let store = HttpStore::with_options("http://localhost/data/", None, &options).unwrap();
+        assert_eq!(store.base_url.as_str(), "http://localhost/data/");
+        assert_eq!(store.retry, RetryPolicy::default());
+    }
+}
@@ -0,0 +1,374 @@
+//! A store adapter which presents chunks written with one chunk key encoding
+//! as if they had been written with another.
+//!
+//! This allows an array's `chunk_key_encoding` metadata to be changed
+//! (e.g. from v2 `.`-separated keys to default `/`-separated keys)
+//! without rewriting every chunk up front:
+//! chunks are read from whichever key exists,
+//! written under the new key (removing the old one),
+//! and can be moved over in bulk with [KeyTranslatingStore::migrate].
+use std::io::{self, Read, Write};
+
+use itertools::Itertools;
+
+use super::{
+    list_dir_from_list_prefix, ListableStore, NodeKey, ReadableStore, Store, VersionToken,
+    Versioned, WriteableStore,
+};
+use crate::chunk_key_encoding::{ChunkKeyEncoder, ChunkKeyEncoding};
+use crate::RangeRequest;
+
+/// How the chunk keys of a single array are translated.
+#[derive(Debug, Clone)]
+struct Translation {
+    node: NodeKey,
+    ndim: usize,
+    presented: ChunkKeyEncoding,
+    stored: ChunkKeyEncoding,
+}
+
+impl Translation {
+    fn to_stored(&self, key: &NodeKey) -> Option<NodeKey> {
+        let coord = self.presented.chunk_coord(&self.node, key, self.ndim)?;
+        Some(self.stored.chunk_key(&self.node, &coord))
+    }
+
+    fn to_presented(&self, key: &NodeKey) -> Option<NodeKey> {
+        let coord = self.stored.chunk_coord(&self.node, key, self.ndim)?;
+        Some(self.presented.chunk_key(&self.node, &coord))
+    }
+}
+
+/// Wraps a store so that chunks of the registered arrays are addressed
+/// using a different chunk key encoding to the one they are stored with.
+///
+/// Requests use the presented encoding.
+/// Reads prefer an object stored under the presented key,
+/// falling back to the equivalent key in the stored (legacy) encoding.
+/// Writes always go to the presented key, and remove any legacy object.
+/// Listings report legacy keys under their presented equivalent.
+///
+/// Keys which are not chunk keys of a registered array are passed through unchanged.
+pub struct KeyTranslatingStore<S: Store> {
+    inner: S,
+    translations: Vec<Translation>,
+}
+
+impl<S: Store> KeyTranslatingStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            translations: Vec::default(),
+        }
+    }
+
+    /// Translate the chunk keys of the array at `node`, with `ndim` dimensions,
+    /// which are requested with the `presented` encoding but may be stored with the `stored` encoding.
+    pub fn with_array(
+        mut self,
+        node: NodeKey,
+        ndim: usize,
+        presented: ChunkKeyEncoding,
+        stored: ChunkKeyEncoding,
+    ) -> Self {
+        if presented != stored {
+            self.translations.push(Translation {
+                node,
+                ndim,
+                presented,
+                stored,
+            });
+        }
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// The legacy key equivalent to the given presented key, if it is a translated chunk key.
+    fn legacy_key(&self, key: &NodeKey) -> Option<NodeKey> {
+        self.translations.iter().find_map(|t| t.to_stored(key))
+    }
+
+    /// The presented key equivalent to the given stored key.
+    fn presented_key(&self, key: &NodeKey) -> NodeKey {
+        self.translations
+            .iter()
+            .find_map(|t| t.to_presented(key))
+            .unwrap_or_else(|| key.clone())
+    }
+
+    /// The translation whose array contains the given (non-array) prefix, if any.
+    fn translation_within(&self, prefix: &NodeKey) -> Option<&Translation> {
+        self.translations
+            .iter()
+            .find(|t| t.node.is_ancestor_of(prefix))
+    }
+}
+
+impl<S: ReadableStore> KeyTranslatingStore<S> {
+    /// The key under which the object is actually stored:
+    /// the presented key if it exists, otherwise the legacy key if it exists.
+    fn resolve(&self, key: &NodeKey) -> io::Result<Option<NodeKey>> {
+        if self.inner.has_key(key)? {
+            return Ok(Some(key.clone()));
+        }
+        match self.legacy_key(key) {
+            Some(legacy) if self.inner.has_key(&legacy)? => Ok(Some(legacy)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<S: Store> Store for KeyTranslatingStore<S> {}
+
+impl<S: ReadableStore> ReadableStore for KeyTranslatingStore<S> {
+    type Readable = S::Readable;
+
+    fn has_key(&self, key: &NodeKey) -> io::Result<bool> {
+        self.resolve(key).map(|k| k.is_some())
+    }
+
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Self::Readable>> {
+        if let Some(r) = self.inner.get(key)? {
+            return Ok(Some(r));
+        }
+        match self.legacy_key(key) {
+            Some(legacy) => self.inner.get(&legacy),
+            None => Ok(None),
+        }
+    }
+
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        match self.resolve(key)? {
+            Some(k) => self.inner.version(&k),
+            None => Ok(None),
+        }
+    }
+
+    /// The URI of the key under which the value is actually stored, if it exists.
+    fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        match self.resolve(key)? {
+            Some(k) => self.inner.uri(&k),
+            None => self.inner.uri(key),
+        }
+    }
+
+    fn prefetch(&self, keys: &[NodeKey]) {
+        self.inner.prefetch(keys)
+    }
+
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Self::Readable>>> {
+        if let Some(v) = self.inner.get_versioned(key)? {
+            return Ok(Some(v));
+        }
+        match self.legacy_key(key) {
+            Some(legacy) => self.inner.get_versioned(&legacy),
+            None => Ok(None),
+        }
+    }
+
+    fn get_partial_values(
+        &self,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+        let mut translated = Vec::with_capacity(key_ranges.len());
+        for (key, range) in key_ranges.iter() {
+            let k = self.resolve(key)?.unwrap_or_else(|| key.clone());
+            translated.push((k, *range));
+        }
+        self.inner.get_partial_values(&translated)
+    }
+}
+
+impl<S: ListableStore> ListableStore for KeyTranslatingStore<S> {
+    fn list(&self) -> io::Result<Vec<NodeKey>> {
+        Ok(self
+            .inner
+            .list()?
+            .iter()
+            .map(|k| self.presented_key(k))
+            .unique()
+            .collect())
+    }
+
+    fn list_prefix(&self, key: &NodeKey) -> io::Result<Vec<NodeKey>> {
+        // a prefix inside an array may not match the legacy keys' components,
+        // so list the whole array and filter
+        let (listed, filter) = match self.translation_within(key) {
+            Some(t) => (self.inner.list_prefix(&t.node)?, true),
+            None => (self.inner.list_prefix(key)?, false),
+        };
+        Ok(listed
+            .iter()
+            .map(|k| self.presented_key(k))
+            .filter(|k| !filter || key.is_ancestor_of(k))
+            .unique()
+            .collect())
+    }
+
+    fn list_dir(&self, prefix: &NodeKey) -> io::Result<(Vec<NodeKey>, Vec<NodeKey>)> {
+        list_dir_from_list_prefix(self, prefix)
+    }
+}
+
+impl<S: WriteableStore> WriteableStore for KeyTranslatingStore<S> {
+    type Writeable = S::Writeable;
+
+    fn set<F>(&self, key: &NodeKey, value: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        self.inner.set(key, value)?;
+        if let Some(legacy) = self.legacy_key(key) {
+            self.inner.erase(&legacy)?;
+        }
+        Ok(())
+    }
+
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        match self.resolve(key)? {
+            Some(k) if &k != key => {
+                // only the legacy object exists: compare against its version,
+                // then create the presented key and drop the legacy one
+                if self.inner.version(&k)?.as_ref() != expected {
+                    return Ok(false);
+                }
+                if !self.inner.set_if_match(key, None, value)? {
+                    return Ok(false);
+                }
+                self.inner.erase(&k)?;
+                Ok(true)
+            }
+            _ => self.inner.set_if_match(key, expected, value),
+        }
+    }
+
+    fn erase(&self, key: &NodeKey) -> io::Result<bool> {
+        let mut out = self.inner.erase(key)?;
+        if let Some(legacy) = self.legacy_key(key) {
+            out |= self.inner.erase(&legacy)?;
+        }
+        Ok(out)
+    }
+
+    fn erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
+        if self.translation_within(key_prefix).is_none() {
+            return self.inner.erase_prefix(key_prefix);
+        }
+        for key in self.list_prefix(key_prefix)? {
+            self.erase(&key)?;
+        }
+        Ok(false)
+    }
+}
+
+impl<S: WriteableStore> KeyTranslatingStore<S> {
+    /// Move every chunk stored under a legacy key to its presented key.
+    ///
+    /// Chunks which already exist under the presented key are kept,
+    /// and their legacy counterpart removed.
+    /// Returns the number of chunks which were moved.
+    pub fn migrate(&self) -> io::Result<usize> {
+        let mut moved = 0;
+        for t in self.translations.iter() {
+            for legacy in self.inner.list_prefix(&t.node)? {
+                let Some(key) = t.to_presented(&legacy) else {
+                    continue;
+                };
+                if key == legacy {
+                    continue;
+                }
+                if !self.inner.has_key(&key)? {
+                    let mut buf = Vec::default();
+                    match self.inner.get(&legacy)? {
+                        // the reader is dropped before writing,
+                        // as some stores lock the object while it is open
+                        Some(mut r) => r.read_to_end(&mut buf)?,
+                        None => continue,
+                    };
+                    self.inner.set(&key, |w| w.write_all(&buf))?;
+                    moved += 1;
+                }
+                self.inner.erase(&legacy)?;
+            }
+        }
+        Ok(moved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_key_encoding::{DefaultChunkKeyEncoding, V2ChunkKeyEncoding};
+    use crate::store::HashMapStore;
+
+    fn read(store: &impl ReadableStore, key: &NodeKey) -> Option<Vec<u8>> {
+        store.get(key).unwrap().map(|mut r| {
+            let mut buf = Vec::default();
+            r.read_to_end(&mut buf).unwrap();
+            buf
+        })
+    }
+
+    fn key(s: &str) -> NodeKey {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn translates_v2_keys() {
+        let inner = HashMapStore::default();
+        inner
+            .set(&key("arr/zarr.json"), |w| w.write_all(b"{}"))
+            .unwrap();
+        inner.set(&key("arr/0.1"), |w| w.write_all(b"a")).unwrap();
+        inner.set(&key("arr/1.0"), |w| w.write_all(b"b")).unwrap();
+        inner.set(&key("other/0.1"), |w| w.write_all(b"c")).unwrap();
+
+        let store = KeyTranslatingStore::new(inner).with_array(
+            key("arr"),
+            2,
+            ChunkKeyEncoding::Default(DefaultChunkKeyEncoding::default()),
+            ChunkKeyEncoding::V2(V2ChunkKeyEncoding::default()),
+        );
+
+        // legacy chunks are visible under the new keys; other keys pass through
+        assert_eq!(read(&store, &key("arr/c/0/1")).unwrap(), b"a");
+        assert_eq!(read(&store, &key("arr/zarr.json")).unwrap(), b"{}");
+        assert_eq!(read(&store, &key("other/0.1")).unwrap(), b"c");
+        assert!(read(&store, &key("arr/c/1/1")).is_none());
+        let partial = store
+            .get_partial_values(&[(key("arr/c/1/0"), RangeRequest::Suffix(1))])
+            .unwrap();
+        assert!(partial[0].is_some());
+
+        let mut listed = store.list_prefix(&key("arr/c/0")).unwrap();
+        listed.sort_by_key(|k| k.encode());
+        assert_eq!(listed, vec![key("arr/c/0/1")]);
+        assert_eq!(store.list_prefix(&key("arr")).unwrap().len(), 3);
+
+        // writing replaces the legacy object
+        store.set(&key("arr/c/0/1"), |w| w.write_all(b"d")).unwrap();
+        assert!(!store.inner().has_key(&key("arr/0.1")).unwrap());
+        assert_eq!(read(&store, &key("arr/c/0/1")).unwrap(), b"d");
+
+        assert_eq!(store.migrate().unwrap(), 1);
+        let inner = store.into_inner();
+        assert!(!inner.has_key(&key("arr/1.0")).unwrap());
+        assert_eq!(read(&inner, &key("arr/c/1/0")).unwrap(), b"b");
+        assert!(inner.has_key(&key("other/0.1")).unwrap());
+    }
+}
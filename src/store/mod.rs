@@ -10,6 +10,15 @@ use std::{
 
 mod hashmap;
 pub use hashmap::HashMapStore;
+mod dyn_store;
+pub use dyn_store::{DynReadableStore, DynWriteableStore};
+mod key_translation;
+pub use key_translation::KeyTranslatingStore;
+mod caching;
+pub use caching::{CacheStats, CachingStore};
+mod buffered;
+pub use buffered::BufferedStore;
+pub mod naming;
 
 use crate::RangeRequest;
 
@@ -19,12 +28,14 @@ pub mod filesystem;
 #[cfg(feature = "http")]
 pub mod http;
 
+pub mod config;
+
 const NODE_KEY_SIZE: usize = 10;
-const METADATA_NAME: &str = "zarr.json";
+pub(crate) const METADATA_NAME: &str = "zarr.json";
 pub(crate) const KEY_SEP: &str = "/";
 
 fn metadata_name() -> NodeName {
-    METADATA_NAME.parse().unwrap()
+    NodeName::new_unchecked(METADATA_NAME.to_owned())
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -107,6 +118,7 @@ impl FromStr for NodeName {
 pub struct NodeKey(SmallVec<[NodeName; NODE_KEY_SIZE]>);
 
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum InvalidNodeName {
     #[error("Node name is empty string")]
     Empty,
@@ -116,6 +128,8 @@ pub enum InvalidNodeName {
     IsPeriods,
     #[error("Node name starts with reserved prefix '__'")]
     ReservedPrefix,
+    #[error("Node name violates naming policy: {0}")]
+    Policy(String),
 }
 
 impl FromIterator<NodeName> for NodeKey {
@@ -254,6 +268,34 @@ impl AsRef<[NodeName]> for NodeKey {
     }
 }
 
+/// Opaque token identifying a particular version of a stored value,
+/// such as a file's modification time or an HTTP ETag.
+///
+/// Tokens are only meaningful to the store which produced them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VersionToken(String);
+
+impl VersionToken {
+    pub fn new(s: String) -> Self {
+        Self(s)
+    }
+}
+
+impl Display for VersionToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for VersionToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A readable value paired with the version which was read, if known.
+pub type Versioned<R> = (R, Option<VersionToken>);
+
 pub trait Store {}
 
 pub trait ReadableStore: Store {
@@ -268,6 +310,26 @@ pub trait ReadableStore: Store {
     /// Get a [Read]er representing the contents of the key.
     fn get(&self, key: &NodeKey) -> Result<Option<Self::Readable>, Error>;
 
+    /// Get a token representing the current version of the key's value.
+    ///
+    /// `Ok(None)` if the key does not exist, or if the store does not support versioning
+    /// (the default).
+    fn version(&self, key: &NodeKey) -> Result<Option<VersionToken>, Error> {
+        let _ = key;
+        Ok(None)
+    }
+
+    /// Get a [Read]er representing the contents of the key,
+    /// along with a token representing the version which was read (if supported).
+    ///
+    /// The trait's default implementation makes separate requests for the version and value,
+    /// and so may pair a value with a newer version;
+    /// it should be replaced by implementors where possible.
+    fn get_versioned(&self, key: &NodeKey) -> Result<Option<Versioned<Self::Readable>>, Error> {
+        let version = self.version(key)?;
+        Ok(self.get(key)?.map(|r| (r, version)))
+    }
+
     /// Get a number of [Read]ers for partial reads.
     ///
     /// The trait's default implementation is inefficient in most cases
@@ -307,8 +369,23 @@ pub trait ReadableStore: Store {
         Ok(out)
     }
 
-    // /// TODO: not in zarr spec
-    // fn uri(&self, key: &NodeKey) -> Result<String, Error>;
+    /// A URI at which other tools can find the key's value, e.g. a `file://` or `https://` URL.
+    ///
+    /// The key need not exist.
+    /// `Ok(None)` if the store has no such location (the default), e.g. because it is in memory.
+    fn uri(&self, key: &NodeKey) -> Result<Option<String>, Error> {
+        let _ = key;
+        Ok(None)
+    }
+
+    /// Hint that the given keys are likely to be read soon.
+    ///
+    /// Stores which can fetch values ahead of time (e.g. [CachingStore]) may start doing so in the background;
+    /// the default is to do nothing.
+    /// Hints must not block on fetching, and any errors are ignored.
+    fn prefetch(&self, keys: &[NodeKey]) {
+        let _ = keys;
+    }
 }
 
 /// Calculate [ListableStore::list_prefix] result from all keys.
@@ -485,6 +562,43 @@ pub trait WriteableStore: ReadableStore + ListableStore {
     where
         F: FnOnce(&mut Self::Writeable) -> io::Result<()>;
 
+    /// Write the contents of a key's entire value using the given function,
+    /// but only if the key's current version matches `expected`.
+    ///
+    /// If `expected` is `None`, the key must not already exist.
+    /// Returns `Ok(false)` if the versions did not match and nothing was written.
+    ///
+    /// Stores which do not support versioning can only use `expected = None`;
+    /// otherwise, an [io::ErrorKind::Unsupported] error is returned.
+    ///
+    /// The trait's default implementation is not atomic
+    /// and should be replaced by implementors.
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        let current = if self.has_key(key)? {
+            Some(self.version(key)?.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Store does not support versioning",
+                )
+            })?)
+        } else {
+            None
+        };
+        if current.as_ref() != expected {
+            return Ok(false);
+        }
+        self.set(key, value)?;
+        Ok(true)
+    }
+
     /// Set partial regions with the given byte vecs.
     ///
     /// The trait's default implementation is inefficient in most cases
@@ -0,0 +1,333 @@
+//! Stricter naming conventions for nodes, on top of the rules of the zarr specification,
+//! e.g. for deployments whose names must be safe in S3 keys or URLs.
+//!
+//! A [NamingPolicy] can be installed for the whole process with [set_global_naming_policy],
+//! which is applied to the names of nodes as they are created,
+//! or for a single store by wrapping it in a [ValidatingStore], which rejects writes to non-conforming keys.
+//!
+//! Documents named by the crate itself (e.g. `zarr.json`) are exempt from policies.
+use std::{
+    io::{self, ErrorKind, Read},
+    sync::{Arc, RwLock},
+};
+
+use super::{
+    InvalidNodeName, ListableStore, NodeKey, NodeName, ReadableStore, Store, VersionToken,
+    Versioned, WriteableStore, METADATA_NAME,
+};
+use crate::node::{chunk_stats::CHUNK_STATS_NAME, inline_chunks::INLINE_CHUNKS_NAME};
+use crate::RangeRequest;
+
+/// Names of documents written by the crate, which policies do not apply to.
+const RESERVED_NAMES: &[&str] = &[METADATA_NAME, CHUNK_STATS_NAME, INLINE_CHUNKS_NAME];
+
+/// A rule which node names must follow, in addition to those of the zarr specification.
+///
+/// Closures taking a name and returning `Err` with a description of the violation are policies.
+pub trait NamingPolicy: Send + Sync {
+    /// `Err` describes why the name is not allowed.
+    fn check(&self, name: &str) -> Result<(), String>;
+}
+
+impl<F: Fn(&str) -> Result<(), String> + Send + Sync> NamingPolicy for F {
+    fn check(&self, name: &str) -> Result<(), String> {
+        self(name)
+    }
+}
+
+/// Every policy must be followed.
+impl NamingPolicy for Vec<Box<dyn NamingPolicy>> {
+    fn check(&self, name: &str) -> Result<(), String> {
+        self.iter().try_for_each(|p| p.check(name))
+    }
+}
+
+/// Names may be at most this many bytes long (e.g. 255 for most filesystems).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxLength(pub usize);
+
+impl NamingPolicy for MaxLength {
+    fn check(&self, name: &str) -> Result<(), String> {
+        if name.len() > self.0 {
+            Err(format!(
+                "name is {} bytes long, more than the maximum of {}",
+                name.len(),
+                self.0
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Names may only contain the given kind of characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterSet {
+    /// `a-z`, `A-Z`, `0-9`, `-`, `_` and `.`, as recommended by the zarr specification.
+    Recommended,
+    /// [CharacterSet::Recommended] plus `!`, `*`, `'`, `(` and `)`,
+    /// the characters which S3 considers safe in object keys.
+    S3Safe,
+    /// [CharacterSet::Recommended] plus `~`, the characters which never need percent-encoding in URLs.
+    UrlSafe,
+}
+
+impl CharacterSet {
+    pub fn allows(&self, c: char) -> bool {
+        if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            return true;
+        }
+        match self {
+            Self::Recommended => false,
+            Self::S3Safe => matches!(c, '!' | '*' | '\'' | '(' | ')'),
+            Self::UrlSafe => c == '~',
+        }
+    }
+}
+
+impl NamingPolicy for CharacterSet {
+    fn check(&self, name: &str) -> Result<(), String> {
+        match name.chars().find(|c| !self.allows(*c)) {
+            Some(c) => Err(format!("character {c:?} is not allowed by {self:?}")),
+            None => Ok(()),
+        }
+    }
+}
+
+static GLOBAL_POLICY: RwLock<Option<Arc<dyn NamingPolicy>>> = RwLock::new(None);
+
+/// Apply a policy to the names of nodes created in this process
+/// (with [Group::create_group](crate::node::Group::create_group),
+/// [Group::create_array](crate::node::Group::create_array) or a [schema](crate::schema)),
+/// or remove it with `None`.
+///
+/// Existing nodes can still be opened and listed whatever their names.
+pub fn set_global_naming_policy(policy: Option<Arc<dyn NamingPolicy>>) {
+    *GLOBAL_POLICY.write().unwrap() = policy;
+}
+
+pub fn global_naming_policy() -> Option<Arc<dyn NamingPolicy>> {
+    GLOBAL_POLICY.read().unwrap().clone()
+}
+
+/// Check the name of a node to be created against the global policy, if any.
+pub(crate) fn check_global(name: &NodeName) -> Result<(), InvalidNodeName> {
+    match GLOBAL_POLICY.read().unwrap().as_ref() {
+        Some(p) => check_name(p.as_ref(), name),
+        None => Ok(()),
+    }
+}
+
+fn check_name(policy: &dyn NamingPolicy, name: &NodeName) -> Result<(), InvalidNodeName> {
+    if RESERVED_NAMES.contains(&name.as_ref()) {
+        return Ok(());
+    }
+    policy.check(name.as_ref()).map_err(InvalidNodeName::Policy)
+}
+
+/// Check every name in the key against a policy, except the names of the crate's own documents.
+pub fn check_key(policy: &dyn NamingPolicy, key: &NodeKey) -> Result<(), InvalidNodeName> {
+    key.as_slice()
+        .iter()
+        .try_for_each(|n| check_name(policy, n))
+}
+
+/// Wraps a store so that writes to keys which do not follow a [NamingPolicy]
+/// fail with [ErrorKind::InvalidInput] before reaching the store.
+///
+/// Reads and listings are passed through unchecked.
+/// Chunk keys are checked like any other key, so the policy must allow the array's chunk key encoding.
+pub struct ValidatingStore<S: Store> {
+    inner: S,
+    policy: Box<dyn NamingPolicy>,
+}
+
+impl<S: Store> ValidatingStore<S> {
+    pub fn new<P: NamingPolicy + 'static>(inner: S, policy: P) -> Self {
+        Self {
+            inner,
+            policy: Box::new(policy),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    pub fn check(&self, key: &NodeKey) -> io::Result<()> {
+        check_key(self.policy.as_ref(), key).map_err(|e| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid key \"{key}\": {e}"),
+            )
+        })
+    }
+}
+
+impl<S: Store> Store for ValidatingStore<S> {}
+
+impl<S: ReadableStore> ReadableStore for ValidatingStore<S> {
+    type Readable = S::Readable;
+
+    fn has_key(&self, key: &NodeKey) -> io::Result<bool> {
+        self.inner.has_key(key)
+    }
+
+    fn get(&self, key: &NodeKey) -> io::Result<Option<Self::Readable>> {
+        self.inner.get(key)
+    }
+
+    fn version(&self, key: &NodeKey) -> io::Result<Option<VersionToken>> {
+        self.inner.version(key)
+    }
+
+    fn get_versioned(&self, key: &NodeKey) -> io::Result<Option<Versioned<Self::Readable>>> {
+        self.inner.get_versioned(key)
+    }
+
+    fn get_partial_values(
+        &self,
+        key_ranges: &[(NodeKey, RangeRequest)],
+    ) -> io::Result<Vec<Option<Box<dyn Read>>>> {
+        self.inner.get_partial_values(key_ranges)
+    }
+
+    fn uri(&self, key: &NodeKey) -> io::Result<Option<String>> {
+        self.inner.uri(key)
+    }
+
+    fn prefetch(&self, keys: &[NodeKey]) {
+        self.inner.prefetch(keys)
+    }
+}
+
+impl<S: ListableStore> ListableStore for ValidatingStore<S> {
+    fn list(&self) -> io::Result<Vec<NodeKey>> {
+        self.inner.list()
+    }
+
+    fn list_prefix(&self, prefix: &NodeKey) -> io::Result<Vec<NodeKey>> {
+        self.inner.list_prefix(prefix)
+    }
+
+    fn list_dir(&self, prefix: &NodeKey) -> io::Result<(Vec<NodeKey>, Vec<NodeKey>)> {
+        self.inner.list_dir(prefix)
+    }
+}
+
+impl<S: WriteableStore> WriteableStore for ValidatingStore<S> {
+    type Writeable = S::Writeable;
+
+    fn set<F>(&self, key: &NodeKey, value: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        self.check(key)?;
+        self.inner.set(key, value)
+    }
+
+    fn set_if_match<F>(
+        &self,
+        key: &NodeKey,
+        expected: Option<&VersionToken>,
+        value: F,
+    ) -> io::Result<bool>
+    where
+        F: FnOnce(&mut Self::Writeable) -> io::Result<()>,
+    {
+        self.check(key)?;
+        self.inner.set_if_match(key, expected, value)
+    }
+
+    fn set_partial_values(
+        &self,
+        key_offset_values: Vec<(NodeKey, usize, Vec<u8>)>,
+    ) -> io::Result<()> {
+        for (key, _, _) in key_offset_values.iter() {
+            self.check(key)?;
+        }
+        self.inner.set_partial_values(key_offset_values)
+    }
+
+    fn erase(&self, key: &NodeKey) -> io::Result<bool> {
+        self.inner.erase(key)
+    }
+
+    fn erase_prefix(&self, key_prefix: &NodeKey) -> io::Result<bool> {
+        self.inner.erase_prefix(key_prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::store::HashMapStore;
+
+    #[test]
+    fn policies() {
+        assert!(MaxLength(5).check("abcde").is_ok());
+        assert!(MaxLength(5).check("abcdef").is_err());
+        assert!(CharacterSet::Recommended.check("a-b_c.0").is_ok());
+        assert!(CharacterSet::Recommended.check("a(b)").is_err());
+        assert!(CharacterSet::S3Safe.check("a(b)").is_ok());
+        assert!(CharacterSet::UrlSafe.check("a~b").is_ok());
+        assert!(CharacterSet::UrlSafe.check("a b").is_err());
+
+        let both: Vec<Box<dyn NamingPolicy>> =
+            vec![Box::new(MaxLength(4)), Box::new(CharacterSet::UrlSafe)];
+        assert!(both.check("a~b").is_ok());
+        assert!(both.check("a~bcd").is_err());
+        assert!(both.check("a b").is_err());
+
+        let store = ValidatingStore::new(HashMapStore::default(), MaxLength(3));
+        let ok: NodeKey = "abc/def".parse().unwrap();
+        let bad: NodeKey = "abc/defg".parse().unwrap();
+        store.set(&ok, |w| w.write_all(b"x")).unwrap();
+        let err = store.set(&bad, |w| w.write_all(b"x")).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("abc/defg"));
+        assert!(!store.inner().has_key(&bad).unwrap());
+    }
+
+    #[test]
+    fn global_policy() {
+        let store = HashMapStore::default();
+        let root = crate::prelude::create_root_group(&store, Default::default()).unwrap();
+        let rejected: NodeName = "rejected-by-global-policy".parse().unwrap();
+        root.create_group(rejected.clone(), false).unwrap();
+
+        // other tests run concurrently, so only reject names which they do not create
+        set_global_naming_policy(Some(Arc::new(|name: &str| {
+            if name == "rejected-by-global-policy" || name == METADATA_NAME {
+                Err("test name".to_owned())
+            } else {
+                Ok(())
+            }
+        })));
+        let parsed = "rejected-by-global-policy".parse::<NodeName>();
+        let created = root.create_group(rejected.clone(), true);
+        let allowed = root.create_group("allowed".parse().unwrap(), false);
+        set_global_naming_policy(None);
+
+        // names are only checked for nodes being created, and never for the crate's own documents
+        assert!(parsed.is_ok());
+        assert_eq!(created.err().unwrap().kind(), ErrorKind::InvalidInput);
+        assert!(allowed.is_ok());
+        let mut children = root.child_keys().unwrap();
+        children.sort_by_key(|k| k.encode());
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[1].as_slice().last(), Some(&rejected));
+        assert!(global_naming_policy().is_none());
+
+        let store = ValidatingStore::new(HashMapStore::default(), MaxLength(3));
+        store
+            .set(&"abc/zarr.json".parse().unwrap(), |w| w.write_all(b"{}"))
+            .unwrap();
+    }
+}
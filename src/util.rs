@@ -53,6 +53,16 @@ macro_rules! variant_from_data {
     };
 }
 
+/// Whether a `name`/`configuration` metadata object must be understood to read the node.
+///
+/// Objects are assumed to be required unless `"must_understand": false` is given explicitly.
+pub(crate) fn must_understand(value: &serde_json::Value) -> bool {
+    value
+        .get("must_understand")
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(true)
+}
+
 #[derive(Error, Debug)]
 #[error("Got {other_ndim} dimensions when expecting {ref_ndim}")]
 pub struct DimensionMismatch {
@@ -72,6 +82,7 @@ impl DimensionMismatch {
         }
     }
 
+    #[allow(dead_code)]
     pub fn check_many(reference: usize, others: &[usize]) -> Result<(), Self> {
         for o in others.iter() {
             if o != &reference {
@@ -85,6 +96,12 @@ impl DimensionMismatch {
     }
 }
 
+impl From<DimensionMismatch> for std::io::Error {
+    fn from(value: DimensionMismatch) -> Self {
+        Self::new(std::io::ErrorKind::InvalidInput, value)
+    }
+}
+
 // /// Panic if any dimensions mismatch.
 // pub fn dimpanic(reference: usize, others: &[usize]) {
 //     DimensionMismatch::check_many(reference, others).unwrap()